@@ -0,0 +1,59 @@
+//! Compares reading a channel-backed body through the boxed [`Body::stream`]
+//! against the unboxed [`Body::from_channel_stream`].
+//!
+//! Run with `cargo bench --bench body_stream`.
+
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use bytes::Bytes;
+use ftl::body::{Body, BodyError};
+use http_body::Frame;
+use http_body_util::BodyExt;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+
+const ITERATIONS: u32 = 100_000;
+const FRAMES: usize = 16;
+
+type Rx = ReceiverStream<Result<Frame<Bytes>, BodyError>>;
+
+async fn read(make_body: fn(Rx) -> Body) -> Duration {
+    let start = Instant::now();
+
+    for _ in 0..ITERATIONS {
+        let (tx, rx) = mpsc::channel(FRAMES);
+
+        let body = make_body(ReceiverStream::new(rx));
+
+        for _ in 0..FRAMES {
+            tx.try_send(Ok(Frame::data(Bytes::from_static(b"chunk")))).unwrap();
+        }
+        drop(tx);
+
+        black_box(body.collect().await.unwrap().to_bytes());
+    }
+
+    start.elapsed()
+}
+
+fn main() {
+    let rt = tokio::runtime::Builder::new_current_thread().build().unwrap();
+
+    rt.block_on(async {
+        // warm up the allocator and caches before measuring
+        read(Body::from_channel_stream).await;
+
+        let boxed = read(Body::stream).await;
+        let unboxed = read(Body::from_channel_stream).await;
+
+        for (name, elapsed) in [("Body::stream", boxed), ("Body::from_channel_stream", unboxed)] {
+            println!(
+                "{name:<28} {:>8.1} ns/body",
+                elapsed.as_nanos() as f64 / f64::from(ITERATIONS)
+            );
+        }
+    });
+}
@@ -12,17 +12,18 @@ use ftl::{
         cloneable::Cloneable,
         compression::CompressionLayer,
         convert_body::ConvertBody,
+        cors::CorsLayer,
         deferred::DeferredEncoding,
         normalize::Normalize,
         rate_limit::{gcra::Quota, RateLimitLayerBuilder},
         resp_timing::RespTimingLayer,
     },
-    rewrite::RewriteService,
     router::Router,
     serve::{
         accept::NoDelayAcceptor,
         // tls_openssl::{OpenSSLAcceptor, OpenSSLConfig},
         tls_rustls::{RustlsAcceptor, RustlsConfig},
+        Http2Preset,
         Server,
         TlsConfig,
     },
@@ -72,18 +73,14 @@ async fn main() {
     server.handle().shutdown_on(async { _ = ctrl_c().await });
     server.handle().set_shutdown_timeout(Duration::from_secs(1));
 
-    // configure the server properties, such as HTTP/2 adaptive window and connect protocol
-    server
-        .http1()
-        .writev(true)
-        .pipeline_flush(true)
-        .http2()
-        .max_concurrent_streams(Some(400))
-        .adaptive_window(true)
-        .enable_connect_protocol(); // used for HTTP/2 Websockets
+    // enable HTTP/2 Websockets via the extended CONNECT protocol
+    server.enable_http2_websockets();
 
-    // create a redirect server to bind at localhost:8080, under http, whilst sharing the same underlying Handle and config
-    let redirect_server = server.rebind(["0.0.0.0:8080".parse().unwrap()]);
+    // tune HTTP/2 for throughput, such as with an adaptive window
+    server.http2_preset(Http2Preset::HighThroughput);
+
+    // configure the remaining server properties
+    server.http1().writev(true).pipeline_flush(true);
 
     // spawn the HTTPS server
     tokio::spawn({
@@ -107,34 +104,34 @@ async fn main() {
         // set acceptor to use the tls config, and set that acceptor to use NoDelay
         server.acceptor(acceptor).serve(
             (
-                RespTimingLayer::default(),  // logs the time taken to process each request
-                CatchPanic::default(),       // spawns each request in a separate task and catches panics
-                Cloneable::default(),        // makes the service layered below it cloneable
-                RealIpLayer::default(),      // extracts the real ip from the request
-                CompressionLayer::new(),     // compresses responses
-                Normalize::default(),        // normalizes the response structure
-                ConvertBody::default(),      // converts the body to the correct type
+                RespTimingLayer::new().slow_threshold(Duration::from_secs(1)), // logs the time taken to process each request, warning if slow
+                CatchPanic::default(), // spawns each request in a separate task and catches panics
+                Cloneable::default(),  // makes the service layered below it cloneable
+                RealIpLayer::default(), // extracts the real ip from the request
+                CompressionLayer::new(), // compresses responses
+                CorsLayer::permissive(), // answers CORS preflight requests and adds CORS headers
+                Normalize::default(),  // normalizes the response structure
+                ConvertBody::default(), // converts the body to the correct type
                 DeferredEncoding::default(), // encodes deferred responses
             )
                 .layer(router.route_layer(rate_limit)), // routing layer with per-path rate limiting
         )
     });
 
-    tokio::spawn(
-        // setup a redirect server to redirect all http traffic to https
-        redirect_server.serve(RewriteService::permanent(|parts| {
-            let host = parts.extensions.get::<http::uri::Authority>().unwrap();
-
-            format!("https://{}:8083{}", host.host(), parts.uri.path())
-        })),
-    );
+    // setup a redirect server at localhost:8080 to redirect all http traffic to https,
+    // whilst sharing the same underlying Handle
+    tokio::spawn(ftl::serve::redirect_https(
+        ["0.0.0.0:8080".parse().unwrap()],
+        8083,
+        &handle,
+    ));
 
     // wait for the servers to finish
     handle.wait().await;
 }
 
 async fn placeholder(
-    Extension(p): Extension<MatchedPath>,
+    p: MatchedPath,
     uri: http::Uri,
     Extension(real_ip): Extension<RealIp>,
     _body: ftl::body::Body,
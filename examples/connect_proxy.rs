@@ -0,0 +1,63 @@
+//! A minimal HTTP `CONNECT` proxy, tunneling each connection to the requested upstream.
+//!
+//! Try it with `curl -p -x http://localhost:8084 https://example.com`
+
+use std::time::Duration;
+
+use ftl::{
+    extract::Upgrade,
+    layers::{cloneable::Cloneable, convert_body::ConvertBody},
+    router::Router,
+    serve::Server,
+    IntoResponse, Layer, Response,
+};
+
+use http::{StatusCode, Uri};
+use tokio::{net::TcpStream, signal::ctrl_c};
+
+#[tokio::main]
+async fn main() {
+    tracing_subscriber::fmt::init();
+
+    let mut router = Router::<_, Response>::with_state(());
+
+    // authority-form CONNECT requests are routed as `/`
+    router.connect("/", tunnel);
+
+    let server = Server::bind(["0.0.0.0:8084".parse().unwrap()]);
+
+    server.handle().shutdown_on(async { _ = ctrl_c().await });
+    server.handle().set_shutdown_timeout(Duration::from_secs(1));
+
+    server.serve((Cloneable::default(), ConvertBody::default()).layer(router)).await.unwrap();
+}
+
+async fn tunnel(upgrade: Upgrade, uri: Uri) -> Response {
+    let Some(authority) = uri.authority() else {
+        return ("CONNECT target must be host:port", StatusCode::BAD_REQUEST).into_response();
+    };
+
+    // connect before responding, so failures can still be reported to the client
+    let mut upstream = match TcpStream::connect(authority.as_str()).await {
+        Ok(upstream) => upstream,
+        Err(e) => {
+            tracing::warn!("failed to connect to {authority}: {e}");
+            return StatusCode::BAD_GATEWAY.into_response();
+        }
+    };
+
+    let authority = authority.clone();
+
+    upgrade
+        .on_upgrade(move |io| async move {
+            let Ok(mut io) = io else { return };
+
+            match tokio::io::copy_bidirectional(&mut io, &mut upstream).await {
+                Ok((sent, received)) => {
+                    tracing::info!("{authority}: sent {sent} bytes, received {received} bytes")
+                }
+                Err(e) => tracing::warn!("{authority}: tunnel error: {e}"),
+            }
+        })
+        .into_response()
+}
@@ -31,8 +31,11 @@ impl<R> State<R> {
 /// HTTP `Body` created from an `AsyncRead`, reading in chunks of `Bytes`,
 /// up to a specified length or until the end.
 ///
-/// The body will return trailers with the server-timing header
-/// containing the duration of the request.
+/// The reader does not need to be seekable, so any reader such as a socket or pipe
+/// can be used, see [`Body::from_async_read`](crate::body::Body::from_async_read).
+///
+/// By default, the body will return trailers with the server-timing header
+/// containing the duration of the request, unless disabled with [`AsyncReadBody::without_trailers`].
 ///
 /// Furthermore, the capacity of the buffer can be specified to control the
 /// amount of memory allocated for each read operation.
@@ -46,6 +49,7 @@ pub struct AsyncReadBody<R> {
     len: u64,
 
     start: Instant,
+    trailers: bool,
 }
 
 impl<R: AsyncRead> AsyncReadBody<R> {
@@ -57,8 +61,16 @@ impl<R: AsyncRead> AsyncReadBody<R> {
             capacity,
             len,
             start,
+            trailers: true,
         }
     }
+
+    /// Do not send the server-timing trailers once the reader is exhausted.
+    #[must_use]
+    pub fn without_trailers(mut self) -> Self {
+        self.trailers = false;
+        self
+    }
 }
 
 impl<R: AsyncRead> Body for AsyncReadBody<R> {
@@ -73,7 +85,7 @@ impl<R: AsyncRead> Body for AsyncReadBody<R> {
     fn size_hint(&self) -> SizeHint {
         match self.reader {
             State::Reading(_) => {
-                if self.len != u64::MAX {
+                if self.len == u64::MAX {
                     SizeHint::default()
                 } else {
                     SizeHint::with_exact(self.len)
@@ -159,9 +171,26 @@ impl<R: AsyncRead> Body for AsyncReadBody<R> {
         // if length is empty we've read as much as requested,
         // or if the bytes read is 0 we hit EOF
         if *this.len == 0 || n == 0 {
-            self.project().reader.set(State::Trailers);
+            let next = if *this.trailers { State::Trailers } else { State::Finished };
+            self.project().reader.set(next);
         }
 
         Poll::Ready(Some(Ok(frame)))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_size_hint() {
+        let bounded = AsyncReadBody::new(&b"hello world"[..], 4, Instant::now(), 5);
+        assert_eq!(bounded.size_hint().exact(), Some(5));
+
+        // reading until EOF has no known length
+        let unbounded = AsyncReadBody::new(&b"hello world"[..], 4, Instant::now(), u64::MAX);
+        assert_eq!(unbounded.size_hint().exact(), None);
+        assert_eq!(unbounded.size_hint().lower(), 0);
+    }
+}
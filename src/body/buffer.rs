@@ -0,0 +1,88 @@
+//! Buffer used by streaming bodies to accumulate encoded output between frames.
+//!
+//! By default, each flushed frame takes ownership of the buffer's allocation, so a new
+//! one must be allocated for the next frame. With the `pooled-buffers` feature, the buffer
+//! is a [`BytesMut`](bytes::BytesMut) that is split on each flush instead, allowing it to reclaim
+//! its original allocation once the previously sent frames have been written and dropped.
+
+use std::{fmt, io};
+
+use bytes::Bytes;
+
+#[cfg(feature = "pooled-buffers")]
+type Inner = bytes::BytesMut;
+
+#[cfg(not(feature = "pooled-buffers"))]
+type Inner = Vec<u8>;
+
+#[derive(Default)]
+pub(crate) struct StreamBuffer(Inner);
+
+impl StreamBuffer {
+    #[inline]
+    pub fn reserve(&mut self, additional: usize) {
+        self.0.reserve(additional);
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    #[inline]
+    pub fn truncate(&mut self, len: usize) {
+        self.0.truncate(len);
+    }
+
+    #[inline]
+    pub fn push(&mut self, byte: u8) {
+        self.0.extend_from_slice(&[byte]);
+    }
+
+    #[inline]
+    pub fn push_str(&mut self, s: &str) {
+        self.0.extend_from_slice(s.as_bytes());
+    }
+
+    /// Takes the buffered bytes, leaving the buffer empty.
+    #[inline]
+    pub fn take(&mut self) -> Bytes {
+        #[cfg(feature = "pooled-buffers")]
+        return self.0.split().freeze();
+
+        #[cfg(not(feature = "pooled-buffers"))]
+        return Bytes::from(std::mem::take(&mut self.0));
+    }
+}
+
+impl io::Write for StreamBuffer {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    #[inline]
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.0.extend_from_slice(buf);
+        Ok(())
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl fmt::Write for StreamBuffer {
+    #[inline]
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.push_str(s);
+        Ok(())
+    }
+}
@@ -5,7 +5,7 @@ use futures::{Stream, StreamExt};
 use http::StatusCode;
 use hyper::body::Frame;
 
-use super::Body;
+use super::{buffer::StreamBuffer, Body};
 
 #[must_use]
 #[derive(Clone, Debug)]
@@ -71,7 +71,7 @@ where
 
 #[pin_project::pin_project]
 struct CborArrayBody<S> {
-    buffer: Vec<u8>,
+    buffer: StreamBuffer,
 
     #[pin]
     stream: S,
@@ -79,7 +79,6 @@ struct CborArrayBody<S> {
 
 use std::{
     convert::Infallible,
-    mem,
     pin::Pin,
     task::{Context, Poll},
 };
@@ -91,7 +90,7 @@ where
     E: std::error::Error,
 {
     return Body::wrap(CborArrayBody {
-        buffer: Vec::new(),
+        buffer: StreamBuffer::default(),
         stream,
     })
     .with_header(APPLICATION_CBOR.clone());
@@ -122,19 +121,19 @@ where
 
                 let pos = this.buffer.len();
 
-                if let Err(e) = ciborium::into_writer(&item, &mut this.buffer) {
+                if let Err(e) = ciborium::into_writer(&item, &mut *this.buffer) {
                     this.buffer.truncate(pos);
                     log::error!("Error encoding CBOR stream: {e}");
                     break;
                 }
 
                 if this.buffer.len() >= (1024 * 8) {
-                    return Poll::Ready(Some(Ok(Frame::data(Bytes::from(mem::take(this.buffer))))));
+                    return Poll::Ready(Some(Ok(Frame::data(this.buffer.take()))));
                 }
             }
 
             Poll::Ready(match this.buffer.is_empty() {
-                false => Some(Ok(Frame::data(Bytes::from(mem::take(this.buffer))))),
+                false => Some(Ok(Frame::data(this.buffer.take()))),
                 true => None,
             })
         }
@@ -2,7 +2,7 @@ use std::marker::PhantomData;
 
 use futures::stream::StreamExt;
 
-use crate::{IntoResponse, Response};
+use crate::{response::IntoResponseParts, IntoResponse, Response, ResponseParts};
 
 /// A type with an associated static value, which can be used to create a deferred response
 /// without needing to allocate a new value each time.
@@ -60,18 +60,59 @@ impl DeferredInner {
 ///
 /// Must be used in conjunction with the [`DeferredEncoding`] layer.
 ///
+/// A status code and headers can be attached with [`Deferred::with_response_parts`]
+/// or [`Deferred::parts_mut`], which take precedence over those set by the encoder,
+/// such as `Content-Type`.
+///
 /// [`DeferredEncoding`]: crate::layers::deferred::DeferredEncoding
-#[repr(transparent)]
-pub struct Deferred(pub(crate) DeferredInner);
+pub struct Deferred {
+    pub(crate) inner: DeferredInner,
+    parts: Option<Box<ResponseParts>>,
+}
 
 impl Deferred {
+    #[inline]
+    const fn from_inner(inner: DeferredInner) -> Self {
+        Deferred { inner, parts: None }
+    }
+
+    /// Returns a mutable reference to the response parts to be used
+    /// for the final encoded response, such as its status and headers.
+    pub fn parts_mut(&mut self) -> &mut ResponseParts {
+        self.parts.get_or_insert_with(|| Box::new(http::Response::new(()).into_parts().0))
+    }
+
+    /// Replace the response parts to be used for the final encoded response.
+    #[inline]
+    #[must_use]
+    pub fn with_parts(mut self, parts: ResponseParts) -> Self {
+        self.parts = Some(Box::new(parts));
+        self
+    }
+
+    /// Apply the given response parts, such as a status code or headers,
+    /// to the final encoded response.
+    ///
+    /// ```rust,ignore
+    /// Deferred::new(user).with_response_parts((
+    ///     StatusCode::CREATED,
+    ///     [(header::LOCATION, HeaderValue::from_static("/users/42"))],
+    /// ))
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn with_response_parts(mut self, parts: impl IntoResponseParts) -> Self {
+        parts.into_response_parts(self.parts_mut());
+        self
+    }
+
     /// Create a new deferred value.
     #[inline]
     pub fn new<T>(value: T) -> Self
     where
         T: serde::Serialize + Send + 'static,
     {
-        Self(DeferredInner::Single(Box::new(value)))
+        Self::from_inner(DeferredInner::Single(Box::new(value)))
     }
 
     /// Crate a new deferred value from a static value, notably without allocating.
@@ -109,7 +150,7 @@ impl Deferred {
         T: serde::Serialize + Send + Sync + 'static,
         E: std::error::Error + Send + Sync + 'static,
     {
-        Self(DeferredInner::Array(Box::new(Some(stream))))
+        Self::from_inner(DeferredInner::Array(Box::new(Some(stream))))
     }
 
     /// Simplified version of [`Deferred::stream`] for when the stream does not return errors.
@@ -123,8 +164,12 @@ impl Deferred {
 }
 
 impl IntoResponse for Deferred {
-    fn into_response(self) -> Response {
-        Response::new(super::Body(super::BodyInner::Deferred(self)))
+    fn into_response(mut self) -> Response {
+        // apply the parts now so they are visible to any layers before `DeferredEncoding`
+        match self.parts.take() {
+            Some(parts) => Response::from_parts(*parts, super::Body(super::BodyInner::Deferred(self))),
+            None => Response::new(super::Body(super::BodyInner::Deferred(self))),
+        }
     }
 }
 
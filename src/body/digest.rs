@@ -0,0 +1,183 @@
+//! `Content-Digest` integrity digests of response bodies, as per [RFC 9530](https://www.rfc-editor.org/rfc/rfc9530).
+//!
+//! Buffered bodies have their digest computed up-front and sent as a header, while streaming bodies
+//! are hashed as they are sent, with the digest sent as a trailer once the body ends. Either way,
+//! the body is never buffered to compute the digest.
+//!
+//! Note that trailers are only delivered over HTTP/2, or HTTP/1.1 chunked responses
+//! to clients that send `TE: trailers`, so clients must not rely on them.
+//!
+//! `Content-Digest` covers the content as sent, so a digest computed here no longer matches once
+//! the response is compressed. [`WithContentDigest`] marks its responses with [`NoCompression`]
+//! for this reason, which must also be done when using [`Body::with_content_digest`] directly.
+
+use std::{
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use aws_lc_rs::digest;
+use base64::Engine as _;
+use bytes::Bytes;
+use http::{header, HeaderMap, HeaderName, HeaderValue};
+use http_body::Body as HttpBody;
+use http_body_util::BodyExt as _;
+
+use super::{Body, BodyInner};
+use crate::{layers::NoCompression, IntoResponse, Response};
+
+/// The `Content-Digest` header name.
+pub const CONTENT_DIGEST: HeaderName = HeaderName::from_static("content-digest");
+
+/// Formats a SHA-256 digest as a `Content-Digest` value, `sha-256=:<base64>:`.
+fn header_value(digest: digest::Digest) -> HeaderValue {
+    let mut value = String::from("sha-256=:");
+    base64::engine::general_purpose::STANDARD.encode_string(digest.as_ref(), &mut value);
+    value.push(':');
+
+    HeaderValue::try_from(value).expect("base64 is a valid header value")
+}
+
+/// Computes the `Content-Digest` header value for the given content.
+#[must_use]
+pub fn content_digest(content: &[u8]) -> HeaderValue {
+    header_value(digest::digest(&digest::SHA256, content))
+}
+
+impl Body {
+    /// Adds a SHA-256 `Content-Digest` to the body.
+    ///
+    /// For buffered bodies, the digest is computed immediately and returned, to be sent as a header.
+    /// Other bodies are hashed as their data is streamed, with the digest sent as a trailer
+    /// once the body ends, merged with any trailers of the body itself, and `None` is returned.
+    ///
+    /// The digest is of the body as given, so the response must not be compressed afterwards,
+    /// see [`WithContentDigest`] to do this for a whole response.
+    pub fn with_content_digest(self) -> (Body, Option<HeaderValue>) {
+        match self.0 {
+            BodyInner::Empty => (self, Some(content_digest(&[]))),
+            BodyInner::Full(mut full) => {
+                // full bodies yield their data immediately, if not already taken
+                let mut cx = Context::from_waker(futures::task::noop_waker_ref());
+
+                let data = match Pin::new(&mut full).poll_frame(&mut cx) {
+                    Poll::Ready(Some(Ok(frame))) => frame.into_data().unwrap_or_default(),
+                    _ => Bytes::new(),
+                };
+
+                let digest = content_digest(&data);
+
+                (Body::from(data), Some(digest))
+            }
+            _ => {
+                let context = Arc::new(Mutex::new(Some(digest::Context::new(&digest::SHA256))));
+
+                let body = self.map_data({
+                    let context = context.clone();
+
+                    move |data| {
+                        if let Some(context) = &mut *context.lock().unwrap() {
+                            context.update(&data);
+                        }
+
+                        data
+                    }
+                });
+
+                // only polled once the body has ended
+                let trailers = async move {
+                    let context = context.lock().unwrap().take()?;
+
+                    let mut trailers = HeaderMap::with_capacity(1);
+                    trailers.insert(CONTENT_DIGEST, header_value(context.finish()));
+
+                    Some(Ok(trailers))
+                };
+
+                (Body::wrap(body.with_trailers(trailers)), None)
+            }
+        }
+    }
+}
+
+/// Adds a SHA-256 `Content-Digest` to the response, as a header for buffered bodies
+/// or as a trailer for streaming bodies, see [`Body::with_content_digest`].
+///
+/// Streaming responses also get a `Trailer: content-digest` header announcing the trailer.
+/// The response is marked with [`NoCompression`], as compressing it would invalidate the digest.
+///
+/// ```rust,ignore
+/// async fn download() -> impl IntoResponse {
+///     WithContentDigest(Body::from_async_read(file, 64 * 1024))
+/// }
+/// ```
+#[must_use]
+pub struct WithContentDigest<R>(pub R);
+
+impl<R: IntoResponse> IntoResponse for WithContentDigest<R> {
+    fn into_response(self) -> Response {
+        let (mut parts, body) = self.0.into_response().into_parts();
+
+        let (body, digest) = body.with_content_digest();
+
+        if let Some(digest) = digest {
+            parts.headers.insert(CONTENT_DIGEST, digest);
+        } else {
+            parts.headers.append(header::TRAILER, HeaderValue::from_static("content-digest"));
+        }
+
+        parts.extensions.insert(NoCompression);
+
+        Response::from_parts(parts, body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http_body::Frame;
+    use http_body_util::BodyExt;
+
+    use super::*;
+
+    // `echo -n "hello world" | openssl dgst -sha256 -binary | base64`
+    const HELLO_WORLD: &str = "sha-256=:uU0nuZNNPgilLlLX2n2r+sSE7+N6U4DukIj3rOLvzek=:";
+
+    #[tokio::test]
+    async fn test_full_digest() {
+        let resp = WithContentDigest("hello world").into_response();
+
+        assert_eq!(resp.headers()[CONTENT_DIGEST], HELLO_WORLD);
+        assert!(!resp.headers().contains_key(header::TRAILER));
+        assert!(resp.extensions().get::<NoCompression>().is_some());
+        assert_eq!(resp.into_body().to_string().await.unwrap(), "hello world");
+    }
+
+    #[tokio::test]
+    async fn test_stream_digest() {
+        let (body, tx) = Body::channel(4);
+
+        tokio::spawn(async move {
+            tx.send(Ok(Frame::data(Bytes::from_static(b"hello ")))).await.unwrap();
+            tx.send(Ok(Frame::data(Bytes::from_static(b"world")))).await.unwrap();
+
+            // trailers of the body itself are kept
+            let mut trailers = HeaderMap::new();
+            trailers.insert("x-checksum", HeaderValue::from_static("abc"));
+            tx.send(Ok(Frame::trailers(trailers))).await.unwrap();
+        });
+
+        let resp = WithContentDigest(body).into_response();
+
+        assert!(!resp.headers().contains_key(CONTENT_DIGEST));
+        assert_eq!(resp.headers()[header::TRAILER], "content-digest");
+        assert!(resp.extensions().get::<NoCompression>().is_some());
+
+        let collected = resp.into_body().collect().await.unwrap();
+
+        let trailers = collected.trailers().unwrap();
+        assert_eq!(trailers[CONTENT_DIGEST], HELLO_WORLD);
+        assert_eq!(trailers["x-checksum"], "abc");
+        assert_eq!(collected.to_bytes(), "hello world");
+    }
+}
@@ -1,5 +1,5 @@
 use crate::{
-    body::{Body, BodyError},
+    body::{buffer::StreamBuffer, Body, BodyError},
     IntoResponse, Response,
 };
 
@@ -20,7 +20,9 @@ use hyper::body::Frame;
 ///
 /// Use [`Json::stream_array`] or [`Json::stream_map`] to stream large JSON arrays or maps without
 /// needing to hold the entire array or map in memory. There are also [`Json::stream_simple_array`]
-/// and [`Json::stream_simple_map`] for streams that don't yield results.
+/// and [`Json::stream_simple_map`] for streams that don't yield results. The streams can be
+/// pretty-printed and their flush threshold tuned, see [`JsonArrayStream`] and [`JsonMapStream`].
+/// For newline-delimited JSON, use [`Json::stream_ndjson`].
 #[must_use]
 #[derive(Clone, Debug)]
 #[repr(transparent)]
@@ -39,25 +41,41 @@ impl Json {
     /// while encoding the JSON, the array will be truncated at the last
     /// successful element and the error logged.
     #[inline]
-    #[must_use]
-    pub fn stream_array<S, T, E>(stream: S) -> impl IntoResponse
+    pub fn stream_array<S, T, E>(stream: S) -> JsonArrayStream<S>
     where
         S: Stream<Item = Result<T, E>> + Send + 'static,
         T: serde::Serialize + Send + Sync + 'static,
         E: std::error::Error,
     {
-        stream_array(stream)
+        JsonArrayStream {
+            stream,
+            format: StreamFormat::default(),
+        }
     }
 
     /// Like [`stream_array`](Self::stream_array), but for streams that yield `T` instead of results.
     #[inline]
-    #[must_use]
-    pub fn stream_simple_array<S, T>(stream: S) -> impl IntoResponse
+    pub fn stream_simple_array<S, T>(stream: S) -> JsonArrayStream<impl Stream<Item = Result<T, Infallible>>>
     where
         S: Stream<Item = T> + Send + 'static,
         T: serde::Serialize + Send + Sync + 'static,
     {
-        stream_array(stream.map(Result::<_, Infallible>::Ok))
+        Json::stream_array(stream.map(Result::<_, Infallible>::Ok))
+    }
+
+    /// Stream values as newline-delimited JSON, one compact value per line, with
+    /// `Content-Type: application/x-ndjson`. If an error occurs while encoding the JSON,
+    /// the stream will be ended after the last successful line and the error logged.
+    ///
+    /// This is the same as [`NdJson::new`](super::ndjson::NdJson::new).
+    #[inline]
+    pub fn stream_ndjson<S, T, E>(stream: S) -> super::ndjson::NdJson<S>
+    where
+        S: Stream<Item = Result<T, E>> + Send + 'static,
+        T: serde::Serialize + Send + Sync + 'static,
+        E: std::error::Error,
+    {
+        super::ndjson::NdJson::new(stream)
     }
 
     /// Stream a JSON map. This is useful for streaming large JSON maps
@@ -65,31 +83,136 @@ impl Json {
     /// while encoding the JSON, the map will be truncated at the last
     /// successful element and the error logged.
     #[inline]
-    #[must_use]
-    pub fn stream_map<S, K, T, E>(stream: S) -> impl IntoResponse
+    pub fn stream_map<S, K, T, E>(stream: S) -> JsonMapStream<S>
     where
         S: Stream<Item = Result<(K, T), E>> + Send + 'static,
         K: Borrow<str>,
         T: serde::Serialize + Send + Sync + 'static,
         E: std::error::Error,
     {
-        stream_map(stream)
+        JsonMapStream {
+            stream,
+            format: StreamFormat::default(),
+        }
     }
 
     /// Like [`stream_map`](Self::stream_map), but for streams that yield `(String, T)` pairs
     /// instead of results.
     #[inline]
-    #[must_use]
-    pub fn stream_simple_map<S, K, T>(stream: S) -> impl IntoResponse
+    pub fn stream_simple_map<S, K, T>(stream: S) -> JsonMapStream<impl Stream<Item = Result<(K, T), Infallible>>>
     where
         S: Stream<Item = (K, T)> + Send + 'static,
         K: Borrow<str> + 'static,
         T: serde::Serialize + Send + Sync + 'static,
     {
-        stream_map(stream.map(Result::<_, Infallible>::Ok))
+        Json::stream_map(stream.map(Result::<_, Infallible>::Ok))
+    }
+}
+
+/// Buffered bytes are flushed as a frame once they exceed this many bytes, by default.
+pub(super) const DEFAULT_FLUSH_THRESHOLD: usize = 1024 * 8;
+
+#[derive(Clone, Copy, Debug)]
+struct StreamFormat {
+    pretty: bool,
+    flush_threshold: usize,
+}
+
+impl Default for StreamFormat {
+    fn default() -> Self {
+        StreamFormat {
+            pretty: false,
+            flush_threshold: DEFAULT_FLUSH_THRESHOLD,
+        }
+    }
+}
+
+macro_rules! impl_stream_format {
+    ($($ty:ident),*) => {$(
+        impl<S> $ty<S> {
+            /// Pretty-print the output with two-space indentation, such as for developer-facing endpoints.
+            ///
+            /// By default, the output is compact.
+            pub fn pretty(mut self, pretty: bool) -> Self {
+                self.format.pretty = pretty;
+                self
+            }
+
+            /// Set how many bytes to buffer before sending them as a frame. Lower values reduce latency
+            /// for slow streams, higher values reduce the number of frames and syscalls.
+            ///
+            /// By default, this is 8KiB.
+            pub fn flush_threshold(mut self, threshold: usize) -> Self {
+                self.format.flush_threshold = threshold;
+                self
+            }
+        }
+    )*};
+}
+
+/// A streaming JSON array response, created with [`Json::stream_array`] or [`Json::stream_simple_array`].
+#[must_use]
+pub struct JsonArrayStream<S> {
+    stream: S,
+    format: StreamFormat,
+}
+
+/// A streaming JSON map response, created with [`Json::stream_map`] or [`Json::stream_simple_map`].
+#[must_use]
+pub struct JsonMapStream<S> {
+    stream: S,
+    format: StreamFormat,
+}
+
+impl_stream_format!(JsonArrayStream, JsonMapStream);
+
+impl<S, T, E> IntoResponse for JsonArrayStream<S>
+where
+    S: Stream<Item = Result<T, E>> + Send + 'static,
+    T: serde::Serialize + Send + Sync + 'static,
+    E: std::error::Error,
+{
+    #[inline]
+    fn into_response(self) -> Response {
+        stream_array(self.stream, self.format).into_response()
     }
 }
 
+impl<S, K, T, E> IntoResponse for JsonMapStream<S>
+where
+    S: Stream<Item = Result<(K, T), E>> + Send + 'static,
+    K: Borrow<str>,
+    T: serde::Serialize + Send + Sync + 'static,
+    E: std::error::Error,
+{
+    #[inline]
+    fn into_response(self) -> Response {
+        stream_map(self.stream, self.format).into_response()
+    }
+}
+
+/// Writes the value to the buffer, indenting it as an element of a pretty-printed array or map.
+fn write_value<T: serde::Serialize>(
+    buffer: &mut StreamBuffer,
+    value: &T,
+    pretty: bool,
+) -> Result<(), json_impl::Error> {
+    if !pretty {
+        return json_impl::to_writer(buffer, value);
+    }
+
+    // newlines within strings are escaped, so any newline here is between tokens
+    for (i, line) in json_impl::to_string_pretty(value)?.split('\n').enumerate() {
+        if i > 0 {
+            buffer.push_str("\n  ");
+        }
+
+        buffer.push_str(line);
+    }
+
+    Ok(())
+}
+
 impl<T> IntoResponse for Json<T>
 where
     T: serde::Serialize,
@@ -108,7 +231,6 @@ where
 use std::{
     borrow::Borrow,
     convert::Infallible,
-    mem,
     pin::Pin,
     task::{Context, Poll},
 };
@@ -124,8 +246,9 @@ enum State {
 #[pin_project::pin_project]
 struct JsonArrayBody<S> {
     state: State,
+    format: StreamFormat,
 
-    buffer: Vec<u8>,
+    buffer: StreamBuffer,
 
     #[pin]
     stream: S,
@@ -134,15 +257,16 @@ struct JsonArrayBody<S> {
 #[pin_project::pin_project]
 struct JsonMapBody<S> {
     state: State,
+    format: StreamFormat,
 
-    buffer: String,
+    buffer: StreamBuffer,
 
     #[pin]
     stream: S,
 }
 
 #[allow(clippy::single_char_add_str)] // faster than push(char)
-fn stream_map<S, K, T, E>(stream: S) -> impl IntoResponse
+fn stream_map<S, K, T, E>(stream: S, format: StreamFormat) -> impl IntoResponse
 where
     S: Stream<Item = Result<(K, T), E>> + Send + 'static,
     K: Borrow<str>,
@@ -151,7 +275,8 @@ where
 {
     return Body::wrap(JsonMapBody {
         state: State::New,
-        buffer: String::new(),
+        format,
+        buffer: StreamBuffer::default(),
         stream,
     })
     .with_header(ContentType::json());
@@ -193,43 +318,52 @@ where
 
                 let pos = this.buffer.len();
                 let key = key.borrow();
+                let pretty = this.format.pretty;
 
                 // most keys will be well-behaved and not need escaping, so `,"key":`
                 // extra byte won't hurt anything when the value is serialized
                 this.buffer.reserve(key.len() + 4);
 
-                if let State::First = *this.state {
-                    this.buffer.push_str("\"");
-                    *this.state = State::Running;
-                } else {
-                    this.buffer.push_str(",\"");
-                }
+                let first = matches!(*this.state, State::First);
+
+                this.buffer.push_str(match (first, pretty) {
+                    (true, false) => "\"",
+                    (false, false) => ",\"",
+                    (true, true) => "\n  \"",
+                    (false, true) => ",\n  \"",
+                });
 
                 use std::fmt::Write;
                 write!(this.buffer, "{}", v_jsonescape::escape(key)).expect("Unable to write to buffer");
 
-                this.buffer.push_str("\":");
+                this.buffer.push_str(if pretty { "\": " } else { "\":" });
 
-                if let Err(e) = json_impl::to_writer(unsafe { this.buffer.as_mut_vec() }, &value) {
+                if let Err(e) = write_value(this.buffer, &value, pretty) {
                     this.buffer.truncate(pos); // revert back to previous element
                     log::error!("Error encoding JSON map stream: {e}");
                     break;
                 }
 
-                if this.buffer.len() >= (1024 * 8) {
-                    return Poll::Ready(Some(Ok(Frame::data(Bytes::from(mem::take(this.buffer))))));
+                *this.state = State::Running;
+
+                if this.buffer.len() >= this.format.flush_threshold {
+                    return Poll::Ready(Some(Ok(Frame::data(this.buffer.take()))));
                 }
             }
 
+            if this.format.pretty && matches!(*this.state, State::Running) {
+                this.buffer.push_str("\n");
+            }
+
             this.buffer.push_str("}");
             *this.state = State::Done;
 
-            Poll::Ready(Some(Ok(Frame::data(Bytes::from(mem::take(this.buffer))))))
+            Poll::Ready(Some(Ok(Frame::data(this.buffer.take()))))
         }
     }
 }
 
-fn stream_array<S, T, E>(stream: S) -> impl IntoResponse
+fn stream_array<S, T, E>(stream: S, format: StreamFormat) -> impl IntoResponse
 where
     S: Stream<Item = Result<T, E>> + Send + 'static,
     T: serde::Serialize + Send + Sync + 'static,
@@ -237,7 +371,8 @@ where
 {
     return Body::wrap(JsonArrayBody {
         state: State::New,
-        buffer: Vec::new(),
+        format,
+        buffer: StreamBuffer::default(),
         stream,
     })
     .with_header(ContentType::json());
@@ -277,28 +412,113 @@ where
                 };
 
                 let pos = this.buffer.len();
+                let pretty = this.format.pretty;
 
-                if let State::First = *this.state {
-                    *this.state = State::Running;
-                } else {
+                if !matches!(*this.state, State::First) {
                     this.buffer.push(b',');
                 }
 
-                if let Err(e) = json_impl::to_writer(&mut this.buffer, &item) {
+                if pretty {
+                    this.buffer.push_str("\n  ");
+                }
+
+                if let Err(e) = write_value(this.buffer, &item, pretty) {
                     this.buffer.truncate(pos); // revert back to previous element
                     log::error!("Error encoding JSON array stream: {e}");
                     break;
                 }
 
-                if this.buffer.len() >= (1024 * 8) {
-                    return Poll::Ready(Some(Ok(Frame::data(Bytes::from(mem::take(this.buffer))))));
+                *this.state = State::Running;
+
+                if this.buffer.len() >= this.format.flush_threshold {
+                    return Poll::Ready(Some(Ok(Frame::data(this.buffer.take()))));
                 }
             }
 
+            if this.format.pretty && matches!(*this.state, State::Running) {
+                this.buffer.push(b'\n');
+            }
+
             this.buffer.push(b']');
             *this.state = State::Done;
 
-            Poll::Ready(Some(Ok(Frame::data(Bytes::from(mem::take(this.buffer))))))
+            Poll::Ready(Some(Ok(Frame::data(this.buffer.take()))))
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Json;
+    use crate::IntoResponse;
+
+    #[tokio::test]
+    async fn test_stream_array_and_map() {
+        // enough items to flush several frames from the same buffer
+        let items = (0..2000u32).collect::<Vec<_>>();
+
+        let resp = Json::stream_simple_array(futures::stream::iter(items.clone())).into_response();
+        let body = resp.into_body().to_string().await.unwrap();
+
+        assert_eq!(body, serde_json::to_string(&items).unwrap());
+
+        let resp = Json::stream_simple_map(futures::stream::iter([("a\"", 1), ("b", 2)])).into_response();
+        let body = resp.into_body().to_string().await.unwrap();
+
+        assert_eq!(body, r#"{"a\"":1,"b":2}"#);
+    }
+
+    #[tokio::test]
+    async fn test_flush_threshold() {
+        use http_body_util::BodyExt;
+
+        let resp =
+            Json::stream_simple_array(futures::stream::iter(100..110u32)).flush_threshold(8).into_response();
+        let mut body = resp.into_body();
+
+        let mut frames = Vec::new();
+        while let Some(frame) = body.frame().await {
+            frames.push(frame.unwrap().into_data().unwrap());
+        }
+
+        assert_eq!(
+            frames,
+            ["[100,101", ",102,103", ",104,105", ",106,107", ",108,109", "]"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pretty_stream() {
+        use serde_json::{json, Value};
+
+        let items = [json!({"id": 1, "tags": ["a\nb"]}), json!({"id": 2, "tags": []})];
+
+        let resp = Json::stream_simple_array(futures::stream::iter(items.clone())).pretty(true).into_response();
+        let body = resp.into_body().to_string().await.unwrap();
+
+        assert!(body.starts_with("[\n  {\n    \"id\": 1,"), "{body}");
+        assert_eq!(
+            serde_json::from_str::<Value>(&body).unwrap(),
+            Value::from(items.to_vec())
+        );
+
+        let resp = Json::stream_simple_map(futures::stream::iter([
+            ("a", items[0].clone()),
+            ("b", items[1].clone()),
+        ]))
+        .pretty(true)
+        .into_response();
+        let body = resp.into_body().to_string().await.unwrap();
+
+        assert!(body.starts_with("{\n  \"a\": {\n    \"id\": 1,"), "{body}");
+        assert_eq!(
+            serde_json::from_str::<Value>(&body).unwrap(),
+            json!({"a": items[0], "b": items[1]})
+        );
+
+        // empty streams stay compact
+        let resp =
+            Json::stream_simple_array(futures::stream::iter(Vec::<u32>::new())).pretty(true).into_response();
+        assert_eq!(resp.into_body().to_string().await.unwrap(), "[]");
+    }
+}
@@ -3,6 +3,10 @@ use std::{
     borrow::Cow,
     error::Error,
     pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
     task::{Context, Poll},
 };
 
@@ -16,7 +20,9 @@ use tokio_stream::wrappers::ReceiverStream;
 #[cfg(feature = "json")]
 mod json;
 #[cfg(feature = "json")]
-pub use json::Json;
+pub use json::{Json, JsonArrayStream, JsonMapStream};
+#[cfg(feature = "json")]
+pub mod ndjson;
 
 #[cfg(feature = "cbor")]
 mod cbor;
@@ -33,9 +39,13 @@ use crate::IntoResponse;
 
 pub mod async_read;
 pub mod deferred;
+#[cfg(feature = "content-digest")]
+pub mod digest;
 pub mod wrap;
 
 mod arbitrary;
+#[cfg(any(feature = "json", feature = "cbor"))]
+mod buffer;
 mod limited;
 
 #[derive(Debug, thiserror::Error)]
@@ -43,6 +53,13 @@ pub enum BodyError {
     #[error("Hyper error: {0}")]
     HyperError(#[from] hyper::Error),
 
+    /// The client disconnected while the request body was being read.
+    ///
+    /// This is only produced when reading the incoming request body, so that errors from other
+    /// connections, such as to an upstream server, are not mistaken for the client going away.
+    #[error("Client disconnected: {0}")]
+    ClientDisconnected(hyper::Error),
+
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 
@@ -52,6 +69,9 @@ pub enum BodyError {
     #[error("Length Limit Exceeded")]
     LengthLimitError,
 
+    #[error("Malformed compressed body: {0}")]
+    Decompression(std::io::Error),
+
     #[error(transparent)]
     Generic(Box<dyn Error + Send + Sync + 'static>),
 
@@ -68,12 +88,44 @@ impl From<http_body_util::LengthLimitError> for BodyError {
     }
 }
 
+impl BodyError {
+    /// Returns true if this error was caused by the client disconnecting mid-request,
+    /// as per [`BodyError::ClientDisconnected`], including when wrapped in an IO error.
+    #[must_use]
+    pub fn is_client_disconnect(&self) -> bool {
+        match self {
+            BodyError::ClientDisconnected(_) => true,
+            BodyError::Io(e) => crate::error::is_io_client_disconnect(e),
+            _ => false,
+        }
+    }
+
+    /// Converts errors from reading the incoming request body.
+    fn from_incoming(err: hyper::Error) -> Self {
+        // hyper reports a client hanging up mid-body as an IO error from the body
+        let io_disconnect = std::error::Error::source(&err)
+            .and_then(|e| e.downcast_ref::<std::io::Error>())
+            .is_some_and(|e| e.kind() == std::io::ErrorKind::UnexpectedEof || crate::error::is_io_disconnect(e));
+
+        match io_disconnect || crate::error::is_hyper_disconnect(&err) {
+            true => BodyError::ClientDisconnected(err),
+            false => BodyError::HyperError(err),
+        }
+    }
+}
+
 impl IntoResponse for BodyError {
     fn into_response(self) -> crate::Response {
         use http::StatusCode;
         use std::borrow::Cow;
 
+        // there's no one to respond to
+        if self.is_client_disconnect() {
+            return crate::error::ClientDisconnected::response();
+        }
+
         IntoResponse::into_response(match self {
+            BodyError::ClientDisconnected(_) => unreachable!(),
             BodyError::Generic(e) => (
                 format!("An error occurred while reading the body: {e}").into(),
                 StatusCode::INTERNAL_SERVER_ERROR,
@@ -90,20 +142,14 @@ impl IntoResponse for BodyError {
                 Cow::Borrowed("Body too large, Length limit exceeded"),
                 StatusCode::PAYLOAD_TOO_LARGE,
             ),
+            BodyError::Decompression(e) => (
+                format!("The body could not be decompressed: {e}").into(),
+                StatusCode::BAD_REQUEST,
+            ),
             BodyError::HyperError(err) => match err {
                 _ if err.is_parse_too_large() => {
                     (Cow::Borrowed("The body was too large"), StatusCode::PAYLOAD_TOO_LARGE)
                 }
-                _ if err.is_body_write_aborted()
-                    || err.is_canceled()
-                    || err.is_closed()
-                    || err.is_incomplete_message() =>
-                {
-                    (
-                        Cow::Borrowed("The request was aborted"),
-                        StatusCode::UNPROCESSABLE_ENTITY,
-                    )
-                }
                 _ if err.is_timeout() => (Cow::Borrowed("The request timed out"), StatusCode::GATEWAY_TIMEOUT),
                 _ if err.is_parse() || err.is_parse_status() => (
                     Cow::Borrowed("An error occurred while parsing the body"),
@@ -139,7 +185,7 @@ pub(crate) enum BodyInner {
     Limited(#[pin] limited::Limited),
     Incoming(#[pin] hyper::body::Incoming),
     Full(#[pin] Full<Bytes>),
-    Channel(#[pin] StreamBody<ReceiverStream<Result<Frame<Bytes>, BodyError>>>),
+    Channel(#[pin] ChannelBody),
     Stream(#[pin] StreamBody<futures::stream::BoxStream<'static, Result<Frame<Bytes>, BodyError>>>),
     //Buf(#[pin] Full<Pin<Box<dyn Buf + Send + 'static>>>),
     Dyn(#[pin] Pin<Box<dyn HttpBody<Data = Bytes, Error = BodyError> + Send + 'static>>),
@@ -149,6 +195,36 @@ pub(crate) enum BodyInner {
     Arbitrary(arbitrary::SmallArbitraryData),
 }
 
+/// Formats the variant and size hint of the body, without polling it.
+impl std::fmt::Debug for Body {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        struct SizeHint(hyper::body::SizeHint);
+
+        impl std::fmt::Debug for SizeHint {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                match self.0.upper() {
+                    Some(upper) => write!(f, "{}..={upper}", self.0.lower()),
+                    None => write!(f, "{}..", self.0.lower()),
+                }
+            }
+        }
+
+        let name = match self.0 {
+            BodyInner::Empty => return f.write_str("Body::Empty"),
+            BodyInner::Deferred(_) => return f.write_str("Body::Deferred"),
+            BodyInner::Arbitrary(_) => return f.write_str("Body::Arbitrary"),
+            BodyInner::Limited(_) => "Body::Limited",
+            BodyInner::Incoming(_) => "Body::Incoming",
+            BodyInner::Full(_) => "Body::Full",
+            BodyInner::Channel(_) => "Body::Channel",
+            BodyInner::Stream(_) => "Body::Stream",
+            BodyInner::Dyn(_) => "Body::Dyn",
+        };
+
+        f.debug_struct(name).field("size_hint", &SizeHint(self.0.size_hint())).finish()
+    }
+}
+
 // assert Send
 const _: () = {
     const fn test_send<T: Send>() {}
@@ -190,7 +266,7 @@ impl HttpBody for BodyInner {
         match self.project() {
             BodyProj::Empty => Poll::Ready(None),
             BodyProj::Limited(inner) => inner.poll_frame(cx),
-            BodyProj::Incoming(incoming) => incoming.poll_frame(cx).map_err(BodyError::from),
+            BodyProj::Incoming(incoming) => incoming.poll_frame(cx).map_err(BodyError::from_incoming),
             BodyProj::Full(full) => full.poll_frame(cx).map_err(|_| unreachable!()),
             //BodyProj::Buf(buf) => buf.poll_frame(cx).map_err(|_| unreachable!()),
             BodyProj::Channel(stream) => stream.poll_frame(cx),
@@ -278,6 +354,13 @@ impl From<Cow<'static, str>> for Body {
     }
 }
 
+impl From<mpsc::Receiver<Result<Frame<Bytes>, BodyError>>> for Body {
+    #[inline]
+    fn from(rx: mpsc::Receiver<Result<Frame<Bytes>, BodyError>>) -> Self {
+        Body::from_channel_stream(ReceiverStream::new(rx))
+    }
+}
+
 impl From<Incoming> for Body {
     #[inline]
     fn from(incoming: Incoming) -> Self {
@@ -338,16 +421,46 @@ impl Body {
 
     /// Create a new bounded channel with the given capacity where
     /// the receiver will forward given frames to the HTTP Body.
+    ///
+    /// The body ends cleanly once the sender is dropped, see [`Body::channel_with_abort`]
+    /// to distinguish a completed body from one whose producer vanished.
     pub fn channel(capacity: usize) -> (Self, BodySender) {
+        Self::channel_inner(capacity, false)
+    }
+
+    /// Like [`Body::channel`], but the body only ends cleanly once trailers are sent or
+    /// [`BodySender::finish`] is called.
+    ///
+    /// If the sender is dropped otherwise, such as when the producing task panics or is cancelled,
+    /// the body yields a [`BodyError::StreamAborted`] error, so the connection is aborted rather than
+    /// the client receiving a truncated body that looks complete.
+    pub fn channel_with_abort(capacity: usize) -> (Self, BodySender) {
+        Self::channel_inner(capacity, true)
+    }
+
+    fn channel_inner(capacity: usize, abort_on_drop: bool) -> (Self, BodySender) {
         let (tx, rx) = mpsc::channel::<Result<Frame<Bytes>, BodyError>>(capacity);
 
-        (
-            Body(BodyInner::Channel(StreamBody::new(ReceiverStream::new(rx)))),
-            BodySender(tx),
-        )
+        let finished = abort_on_drop.then(|| Arc::new(AtomicBool::new(false)));
+
+        let body = Body(BodyInner::Channel(ChannelBody {
+            rx,
+            finished: finished.clone(),
+            done: false,
+        }));
+
+        (body, BodySender { tx, finished })
     }
 
     /// Creates an HTTP Body by wrapping a Stream of byte frames.
+    ///
+    /// Frames are yielded in the order the stream produces them, and the body ends
+    /// when the stream does. The stream is boxed, so it must be `Send + 'static`,
+    /// meaning it must own everything it uses rather than borrowing from the handler,
+    /// but it need not be `Sync` or `Unpin`.
+    ///
+    /// For streams backed by a Tokio channel, prefer [`Body::channel`] or
+    /// [`Body::from_channel_stream`], which avoid boxing entirely.
     pub fn stream<S>(stream: S) -> Body
     where
         S: futures::Stream<Item = Result<Frame<Bytes>, BodyError>> + Send + 'static,
@@ -355,6 +468,33 @@ impl Body {
         Body(BodyInner::Stream(StreamBody::new(Box::pin(stream))))
     }
 
+    /// Creates an HTTP Body from an existing channel receiver stream without boxing it,
+    /// similar to the body half of [`Body::channel`].
+    ///
+    /// As there is no [`BodySender`] to signal completion, the body ends cleanly
+    /// whenever all senders are dropped.
+    pub fn from_channel_stream(stream: ReceiverStream<Result<Frame<Bytes>, BodyError>>) -> Body {
+        Body(BodyInner::Channel(ChannelBody {
+            rx: stream.into_inner(),
+            finished: None,
+            done: false,
+        }))
+    }
+
+    /// Creates an HTTP Body that streams from any [`AsyncRead`](tokio::io::AsyncRead) until EOF,
+    /// such as a socket or pipe, reading up to `buf_size` bytes at a time.
+    ///
+    /// Read errors are yielded as [`BodyError::Io`]. The length is unknown, so the body
+    /// is sent chunked unless a `Content-Length` header is set explicitly.
+    pub fn from_async_read<R>(reader: R, buf_size: usize) -> Body
+    where
+        R: tokio::io::AsyncRead + Send + 'static,
+    {
+        let reader = async_read::AsyncReadBody::new(reader, buf_size.max(1), std::time::Instant::now(), u64::MAX);
+
+        Body::wrap(reader.without_trailers())
+    }
+
     pub fn wrap<B>(body: B) -> Body
     where
         B: HttpBody<Data = Bytes, Error: Into<BodyError>> + Send + 'static,
@@ -362,6 +502,20 @@ impl Body {
         Body(BodyInner::Dyn(Box::pin(wrap::WrappedBody { body })))
     }
 
+    /// Transform each data frame of the body with the given closure, passing trailers through unchanged.
+    ///
+    /// The size hint of the resulting body is unknown, as the closure may change the length of each chunk.
+    pub fn map_data<F>(self, f: F) -> Body
+    where
+        F: FnMut(Bytes) -> Bytes + Send + 'static,
+    {
+        if self.is_empty() {
+            return self;
+        }
+
+        Body(BodyInner::Dyn(Box::pin(wrap::MapData { body: self, f })))
+    }
+
     /// Create a new body from an arbitrary type to be accessed later,
     /// currently limited to payloads of 32 bytes or less.
     ///
@@ -397,24 +551,119 @@ impl Body {
             _ => self.size_hint(),
         }
     }
+
+    /// Returns the exact length of the body, if known ahead of time, such as for
+    /// full bodies or streams with an exact size hint.
+    ///
+    /// Limited bodies only have a known length if the original body does and fits within the limit,
+    /// as otherwise reading the body would fail.
+    #[must_use]
+    pub fn content_length(&self) -> Option<u64> {
+        match self.0 {
+            BodyInner::Empty => Some(0),
+            BodyInner::Limited(ref limited) => {
+                limited.inner.content_length().filter(|&len| len <= limited.remaining)
+            }
+            _ => self.size_hint().exact(),
+        }
+    }
+}
+
+/// The body half of [`Body::channel`].
+pub(crate) struct ChannelBody {
+    rx: mpsc::Receiver<Result<Frame<Bytes>, BodyError>>,
+
+    /// Set by [`BodySender::finish`], or `None` if closing the channel always ends the body cleanly,
+    /// as for [`Body::channel`].
+    finished: Option<Arc<AtomicBool>>,
+
+    done: bool,
+}
+
+impl HttpBody for ChannelBody {
+    type Data = Bytes;
+    type Error = BodyError;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        if self.done {
+            return Poll::Ready(None);
+        }
+
+        let res = futures::ready!(self.rx.poll_recv(cx));
+
+        Poll::Ready(match res {
+            // trailers are always the last frame
+            Some(Ok(frame)) if frame.is_trailers() => {
+                self.done = true;
+                Some(Ok(frame))
+            }
+            Some(Ok(frame)) => Some(Ok(frame)),
+            Some(Err(e)) => {
+                self.done = true;
+                Some(Err(e))
+            }
+            None => {
+                self.done = true;
+
+                match self.finished {
+                    Some(ref finished) if !finished.load(Ordering::Acquire) => Some(Err(BodyError::StreamAborted)),
+                    _ => None,
+                }
+            }
+        })
+    }
+
+    #[inline]
+    fn is_end_stream(&self) -> bool {
+        self.done
+    }
 }
 
-pub struct BodySender(mpsc::Sender<Result<Frame<Bytes>, BodyError>>);
+/// The sending half of [`Body::channel`], which dereferences to the underlying [`mpsc::Sender`].
+///
+/// With [`Body::channel_with_abort`], call [`BodySender::finish`] or send trailers to end the body
+/// cleanly, as dropping the sender otherwise aborts the body with a [`BodyError::StreamAborted`] error.
+pub struct BodySender {
+    tx: mpsc::Sender<Result<Frame<Bytes>, BodyError>>,
+    finished: Option<Arc<AtomicBool>>,
+}
 
 impl std::ops::Deref for BodySender {
     type Target = mpsc::Sender<Result<Frame<Bytes>, BodyError>>;
 
     #[inline(always)]
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.tx
     }
 }
 
 impl BodySender {
+    /// Ends the body cleanly once all frames sent so far have been received.
+    ///
+    /// This is equivalent to dropping the sender, unless created with [`Body::channel_with_abort`].
+    pub fn finish(self) {
+        if let Some(ref finished) = self.finished {
+            finished.store(true, Ordering::Release);
+        }
+    }
+
     /// Aborts the body stream with an [`BodyError::StreamAborted`] error
     pub async fn abort(self) -> bool {
         self.send(Err(BodyError::StreamAborted)).await.is_ok()
     }
+
+    /// Sends the contents of `buf` as a data frame, leaving it empty but with
+    /// any spare capacity intact.
+    ///
+    /// Reusing the same buffer for each frame allows it to reclaim its allocation
+    /// once the previously sent frames have been written and dropped, rather than
+    /// allocating a new buffer for every frame. Returns `false` if the body was dropped.
+    pub async fn send_buffer(&self, buf: &mut bytes::BytesMut) -> bool {
+        self.send(Ok(Frame::data(buf.split().freeze()))).await.is_ok()
+    }
 }
 
 impl Body {
@@ -450,3 +699,139 @@ impl Body {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_debug() {
+        assert_eq!(format!("{:?}", Body::empty()), "Body::Empty");
+        assert_eq!(
+            format!("{:?}", Body::from(Bytes::from_static(b"hello"))),
+            "Body::Full { size_hint: 5..=5 }"
+        );
+
+        let (body, _tx) = Body::channel(1);
+        assert_eq!(format!("{body:?}"), "Body::Channel { size_hint: 0.. }");
+    }
+
+    #[tokio::test]
+    async fn test_from_async_read() {
+        use http_body_util::BodyExt;
+        use tokio::io::AsyncWriteExt;
+
+        let (mut tx, rx) = tokio::io::duplex(64);
+
+        tokio::spawn(async move {
+            for chunk in ["streamed ", "from ", "a pipe"] {
+                tx.write_all(chunk.as_bytes()).await.unwrap();
+            }
+        });
+
+        let body = Body::from_async_read(rx, 4);
+        assert_eq!(body.size_hint().exact(), None);
+
+        let collected = body.collect().await.unwrap();
+        assert!(collected.trailers().is_none());
+        assert_eq!(collected.to_bytes(), "streamed from a pipe");
+    }
+
+    #[tokio::test]
+    async fn test_map_data() {
+        use http_body_util::BodyExt;
+
+        let (body, tx) = Body::channel(4);
+
+        tokio::spawn(async move {
+            for chunk in ["hello, ", "world"] {
+                tx.send(Ok(Frame::data(Bytes::from_static(chunk.as_bytes())))).await.unwrap();
+            }
+
+            let mut trailers = http::HeaderMap::new();
+            trailers.insert("x-checksum", http::HeaderValue::from_static("abc"));
+            tx.send(Ok(Frame::trailers(trailers))).await.unwrap();
+        });
+
+        let body = body.map_data(|chunk| Bytes::from(chunk.to_ascii_uppercase()));
+
+        let collected = body.collect().await.unwrap();
+        assert_eq!(collected.trailers().unwrap()["x-checksum"], "abc");
+        assert_eq!(collected.to_bytes(), "HELLO, WORLD");
+    }
+
+    #[tokio::test]
+    async fn test_channel_completion() {
+        use http_body_util::BodyExt;
+
+        let (mut body, tx) = Body::channel_with_abort(4);
+
+        tx.send(Ok(Frame::data(Bytes::from_static(b"done")))).await.unwrap();
+        tx.finish();
+
+        assert_eq!(body.frame().await.unwrap().unwrap().into_data().unwrap(), "done");
+        assert!(body.frame().await.is_none());
+        assert!(body.is_end_stream());
+
+        // the producer vanishes mid-stream
+        let (mut body, tx) = Body::channel_with_abort(4);
+
+        tokio::spawn(async move {
+            tx.send(Ok(Frame::data(Bytes::from_static(b"partial")))).await.unwrap();
+            panic!("producer failed");
+        });
+
+        assert_eq!(body.frame().await.unwrap().unwrap().into_data().unwrap(), "partial");
+        assert!(matches!(body.frame().await, Some(Err(BodyError::StreamAborted))));
+        assert!(body.frame().await.is_none());
+        assert!(body.is_end_stream());
+
+        // but dropping the sender of a plain channel is a clean end
+        let (mut body, tx) = Body::channel(4);
+
+        tx.send(Ok(Frame::data(Bytes::from_static(b"done")))).await.unwrap();
+        drop(tx);
+
+        assert_eq!(body.frame().await.unwrap().unwrap().into_data().unwrap(), "done");
+        assert!(body.frame().await.is_none());
+        assert!(body.is_end_stream());
+    }
+
+    #[tokio::test]
+    async fn test_content_length() {
+        let full = || Body::from(Bytes::from_static(b"hello"));
+
+        assert_eq!(Body::empty().content_length(), Some(0));
+        assert_eq!(full().content_length(), Some(5));
+
+        // limited bodies only have a length if the full body fits
+        assert_eq!(full().limit(16).unwrap().content_length(), Some(5));
+        assert_eq!(full().limit(5).unwrap().content_length(), Some(5));
+        assert_eq!(full().limit(4).unwrap().content_length(), None);
+
+        let (channel, _tx) = Body::channel(4);
+        assert_eq!(channel.content_length(), None);
+        assert_eq!(channel.limit(16).unwrap().content_length(), None);
+
+        let stream = Body::stream(futures::stream::iter([Ok(Frame::data(Bytes::from_static(b"hello")))]));
+        assert_eq!(stream.content_length(), None);
+    }
+
+    #[tokio::test]
+    async fn test_from_channel_stream() {
+        let (tx, rx) = mpsc::channel(4);
+
+        let mut body = Body::from_channel_stream(ReceiverStream::new(rx));
+        assert!(
+            matches!(body.0, BodyInner::Channel(_)),
+            "channel streams should not be boxed"
+        );
+
+        for chunk in ["a", "b", "c"] {
+            tx.send(Ok(Frame::data(Bytes::from_static(chunk.as_bytes())))).await.unwrap();
+        }
+        drop(tx);
+
+        assert_eq!(body.to_string().await.unwrap(), "abc");
+    }
+}
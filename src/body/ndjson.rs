@@ -0,0 +1,197 @@
+//! Newline-delimited JSON (NDJSON) streaming responses.
+
+use std::{
+    convert::Infallible,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+use hyper::body::Frame;
+
+use crate::{
+    body::{buffer::StreamBuffer, json::DEFAULT_FLUSH_THRESHOLD, Body, BodyError},
+    headers::APPLICATION_NDJSON,
+    IntoResponse, Response,
+};
+
+/// A response that streams values as [newline-delimited JSON](https://github.com/ndjson/ndjson-spec),
+/// one compact JSON value per line, with `Content-Type: application/x-ndjson`.
+///
+/// Unlike [`Json::stream_array`](super::Json::stream_array), each value is framed independently,
+/// so clients can parse the stream line by line, and a truncated stream still consists of valid lines.
+///
+/// If an error occurs while encoding a value or the stream yields an error, the stream
+/// is ended after the last successful line and the error is logged.
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct NdJson<S> {
+    stream: S,
+    flush_threshold: usize,
+}
+
+impl<S> NdJson<S> {
+    /// Create a new NDJSON response from a stream of results.
+    #[inline]
+    pub const fn new(stream: S) -> Self {
+        NdJson {
+            stream,
+            flush_threshold: DEFAULT_FLUSH_THRESHOLD,
+        }
+    }
+
+    /// Set how many bytes to buffer before sending them as a frame. Lower values reduce latency
+    /// for slow streams, higher values reduce the number of frames and syscalls.
+    ///
+    /// By default, this is 8KiB, the same as [`JsonArrayStream`](super::JsonArrayStream).
+    pub fn flush_threshold(mut self, threshold: usize) -> Self {
+        self.flush_threshold = threshold;
+        self
+    }
+}
+
+impl NdJson<()> {
+    /// Create a new NDJSON response from a stream that yields `T` instead of results.
+    #[inline]
+    pub fn simple<S, T>(stream: S) -> NdJson<impl Stream<Item = Result<T, Infallible>>>
+    where
+        S: Stream<Item = T>,
+    {
+        NdJson::new(stream.map(Ok))
+    }
+}
+
+impl<S, T, E> IntoResponse for NdJson<S>
+where
+    S: Stream<Item = Result<T, E>> + Send + 'static,
+    T: serde::Serialize + Send + Sync + 'static,
+    E: std::error::Error,
+{
+    fn into_response(self) -> Response {
+        Body::wrap(NdJsonBody {
+            done: false,
+            flush_threshold: self.flush_threshold,
+            buffer: StreamBuffer::default(),
+            stream: self.stream,
+        })
+        .with_header(APPLICATION_NDJSON.clone())
+        .into_response()
+    }
+}
+
+#[pin_project::pin_project]
+struct NdJsonBody<S> {
+    done: bool,
+    flush_threshold: usize,
+
+    buffer: StreamBuffer,
+
+    #[pin]
+    stream: S,
+}
+
+impl<S, T, E> hyper::body::Body for NdJsonBody<S>
+where
+    S: Stream<Item = Result<T, E>> + Send + 'static,
+    T: serde::Serialize + Send + Sync + 'static,
+    E: std::error::Error,
+{
+    type Data = Bytes;
+    type Error = BodyError;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let mut this = self.project();
+
+        if *this.done {
+            return Poll::Ready(None);
+        }
+
+        while let Some(item) = futures::ready!(this.stream.as_mut().poll_next(cx)) {
+            let item = match item {
+                Ok(item) => item,
+                Err(e) => {
+                    log::error!("Error sending NDJSON stream: {e}");
+                    break;
+                }
+            };
+
+            let pos = this.buffer.len();
+
+            if let Err(e) = json_impl::to_writer(&mut *this.buffer, &item) {
+                this.buffer.truncate(pos); // revert back to previous line
+                log::error!("Error encoding NDJSON stream: {e}");
+                break;
+            }
+
+            this.buffer.push(b'\n');
+
+            if this.buffer.len() >= *this.flush_threshold {
+                return Poll::Ready(Some(Ok(Frame::data(this.buffer.take()))));
+            }
+        }
+
+        *this.done = true;
+
+        if this.buffer.is_empty() {
+            return Poll::Ready(None);
+        }
+
+        Poll::Ready(Some(Ok(Frame::data(this.buffer.take()))))
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.done
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+    struct Event {
+        id: u32,
+        message: String,
+    }
+
+    #[tokio::test]
+    async fn test_ndjson() {
+        let events = (0..1000u32).map(|id| Event {
+            id,
+            message: format!("event\n{id}"),
+        });
+
+        let resp = NdJson::simple(futures::stream::iter(events)).into_response();
+        assert_eq!(resp.headers()[http::header::CONTENT_TYPE], "application/x-ndjson");
+
+        let body = resp.into_body().to_string().await.unwrap();
+        assert!(body.ends_with('\n'));
+
+        for (id, line) in body.lines().enumerate() {
+            let event: Event = serde_json::from_str(line).unwrap();
+            assert_eq!(event.id, id as u32);
+            assert_eq!(event.message, format!("event\n{id}"));
+        }
+
+        assert_eq!(body.lines().count(), 1000);
+    }
+
+    #[tokio::test]
+    async fn test_ndjson_flush_threshold() {
+        use http_body_util::BodyExt;
+
+        let resp = NdJson::simple(futures::stream::iter(100..105u32)).flush_threshold(8).into_response();
+        let mut body = resp.into_body();
+
+        let mut frames = Vec::new();
+        while let Some(frame) = body.frame().await {
+            frames.push(frame.unwrap().into_data().unwrap());
+        }
+
+        assert_eq!(frames, ["100\n101\n", "102\n103\n", "104\n"]);
+    }
+}
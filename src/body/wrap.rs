@@ -44,3 +44,42 @@ where
         self.body.size_hint()
     }
 }
+
+/// Body adapter that transforms each data frame with a closure,
+/// passing trailers through unchanged. See [`Body::map_data`](super::Body::map_data).
+#[pin_project::pin_project]
+pub struct MapData<B, F> {
+    #[pin]
+    pub body: B,
+    pub f: F,
+}
+
+impl<B, F> Body for MapData<B, F>
+where
+    B: Body<Data = Bytes, Error = BodyError>,
+    F: FnMut(Bytes) -> Bytes,
+{
+    type Data = Bytes;
+    type Error = BodyError;
+
+    #[inline]
+    fn is_end_stream(&self) -> bool {
+        self.body.is_end_stream()
+    }
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.project();
+        let f = this.f;
+
+        this.body.poll_frame(cx).map(|opt| opt.map(|res| res.map(|frame| frame.map_data(&mut *f))))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> SizeHint {
+        // the transformation may change the length of each chunk
+        SizeHint::default()
+    }
+}
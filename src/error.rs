@@ -36,6 +36,8 @@ pub enum Error {
     #[error("Request timed out")]
     TimedOut,
 
+    /// A bare `401 Unauthorized` without a `WWW-Authenticate` challenge, prefer
+    /// [`Unauthorized`](crate::headers::www_authenticate::Unauthorized) where possible.
     #[error("Unauthorized")]
     Unauthorized,
 
@@ -45,12 +47,16 @@ pub enum Error {
     #[error("Unsupported media type")]
     UnsupportedMediaType,
 
+    #[error("multipart/form-data is not supported by the `Form` extractor, use a multipart extractor instead")]
+    UnsupportedMultipartForm,
+
     #[error("Payload too large")]
     PayloadTooLarge,
 
     #[error("The request is missing a required extension")]
     MissingExtension,
 
+    #[deprecated(note = "no longer returned, `Query` treats a missing query string as empty")]
     #[error("The query is missing")]
     MissingQuery,
 
@@ -80,12 +86,136 @@ pub enum Error {
     #[error("Websocket error: {0}")]
     WebsocketError(#[from] crate::ws::WsError),
 
+    #[error("Upgrade error: {0}")]
+    Upgrade(#[from] crate::extract::upgrade::UpgradeError),
+
+    #[cfg(feature = "multipart")]
+    #[error("Multipart error: {0}")]
+    Multipart(#[from] crate::extract::multipart::MultipartError),
+
     #[error("Custom error: {0}")]
     Custom(Box<dyn core::error::Error + Send + Sync + 'static>),
+
+    #[error("Error response with status {}", .0.status())]
+    Response(ErrorResponse),
+}
+
+/// A complete response used as an [`Error`], such as the custom rejection
+/// of [`WithRejection`](crate::extract::WithRejection), which is returned as-is.
+pub struct ErrorResponse {
+    status: StatusCode,
+    response: SyncWrapper<Box<crate::Response>>,
+}
+
+/// Makes a `Send` value `Sync` by never handing out shared references to it, only ownership,
+/// as with the `sync_wrapper` crate. Response bodies are not `Sync`, but errors must be.
+struct SyncWrapper<T>(T);
+
+// SAFETY: No `&T` can be obtained through a `&SyncWrapper<T>`, only `T` by value,
+// so sharing a `&SyncWrapper<T>` across threads cannot be used to access `T` concurrently.
+unsafe impl<T: Send> Sync for SyncWrapper<T> {}
+
+impl<T> SyncWrapper<T> {
+    fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl ErrorResponse {
+    pub fn new(response: impl IntoResponse) -> Self {
+        let response = response.into_response();
+
+        ErrorResponse {
+            status: response.status(),
+            response: SyncWrapper(Box::new(response)),
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+}
+
+impl core::fmt::Debug for ErrorResponse {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ErrorResponse").field("status", &self.status).finish_non_exhaustive()
+    }
+}
+
+impl IntoResponse for ErrorResponse {
+    #[inline]
+    fn into_response(self) -> crate::Response {
+        *self.response.into_inner()
+    }
+}
+
+impl From<ErrorResponse> for Error {
+    #[inline]
+    fn from(e: ErrorResponse) -> Self {
+        Error::Response(e)
+    }
 }
 
 pub type BoxError = Box<dyn core::error::Error + Send + Sync>;
 
+/// Response extension marking that the client disconnected before the response could be sent.
+///
+/// Such responses are not sent, as there is no one to send them to. Instead, the
+/// [`Server`](crate::serve::Server) quietly closes the connection. These responses
+/// use the non-standard `499 Client Closed Request` status for the sake of any layers
+/// inspecting them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientDisconnected;
+
+impl ClientDisconnected {
+    pub(crate) fn response() -> crate::Response {
+        log::debug!("client disconnected mid-request");
+
+        let mut resp = StatusCode::from_u16(499).unwrap().into_response();
+        resp.extensions_mut().insert(ClientDisconnected);
+        resp
+    }
+}
+
+/// Returns true if the hyper error indicates the client went away.
+pub(crate) fn is_hyper_disconnect(err: &hyper::Error) -> bool {
+    err.is_canceled() || err.is_closed() || err.is_incomplete_message() || err.is_body_write_aborted()
+}
+
+/// Returns true if the IO error of the client connection indicates the client went away.
+pub(crate) fn is_io_disconnect(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        ErrorKind::ConnectionReset | ErrorKind::ConnectionAborted | ErrorKind::BrokenPipe
+    )
+}
+
+/// Returns true if the IO error wraps a [`BodyError::ClientDisconnected`], such as from
+/// reading the request body through an `AsyncRead`.
+pub(crate) fn is_io_client_disconnect(err: &io::Error) -> bool {
+    err.get_ref()
+        .and_then(|e| e.downcast_ref::<BodyError>())
+        .is_some_and(BodyError::is_client_disconnect)
+}
+
+impl Error {
+    /// Returns true if this error was caused by the client disconnecting mid-request.
+    ///
+    /// Only errors from reading the request body are considered, see [`BodyError::ClientDisconnected`].
+    /// Errors from other connections, such as an upstream server resetting the connection,
+    /// are still treated as server errors.
+    #[must_use]
+    pub fn is_client_disconnect(&self) -> bool {
+        match self {
+            Error::BodyError(e) => e.is_client_disconnect(),
+            Error::IoError(e) => is_io_client_disconnect(e),
+            _ => false,
+        }
+    }
+}
+
 #[allow(dead_code)] // only used when certain features are enabled
 pub(crate) fn io_other<E: Into<BoxError>>(error: E) -> io::Error {
     io::Error::new(ErrorKind::Other, error)
@@ -93,6 +223,10 @@ pub(crate) fn io_other<E: Into<BoxError>>(error: E) -> io::Error {
 
 impl IntoResponse for Error {
     fn into_response(self) -> crate::Response {
+        if self.is_client_disconnect() {
+            return ClientDisconnected::response();
+        }
+
         match self {
             Error::HyperError(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
             Error::IoError(e) => {
@@ -124,8 +258,15 @@ impl IntoResponse for Error {
             }
             Error::MethodNotAllowed => StatusCode::METHOD_NOT_ALLOWED.into_response(),
             Error::UnsupportedMediaType => StatusCode::UNSUPPORTED_MEDIA_TYPE.into_response(),
+            Error::UnsupportedMultipartForm => (
+                "multipart/form-data is not supported by the `Form` extractor, use a multipart extractor instead",
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            )
+                .into_response(),
+            #[allow(deprecated)]
             Error::MissingQuery => ("Missing URI query", StatusCode::BAD_REQUEST).into_response(),
-            Error::MissingMatchedPath => ("Missing matched path", StatusCode::BAD_REQUEST).into_response(),
+            // like a missing extension, this is a server misconfiguration rather than a bad request
+            Error::MissingMatchedPath => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
 
             #[cfg(feature = "cbor")]
             Error::Cbor(error) => {
@@ -139,11 +280,15 @@ impl IntoResponse for Error {
             Error::Scheme(scheme_error) => scheme_error.into_response(),
             Error::Authority(authority_error) => authority_error.into_response(),
             Error::WebsocketError(ws_error) => ws_error.into_response(),
+            Error::Upgrade(upgrade_error) => upgrade_error.into_response(),
+            #[cfg(feature = "multipart")]
+            Error::Multipart(multipart_error) => multipart_error.into_response(),
 
             Error::Custom(e) => {
                 log::error!("Custom error: {}", e);
                 StatusCode::INTERNAL_SERVER_ERROR.into_response()
             }
+            Error::Response(response) => response.into_response(),
         }
     }
 }
@@ -154,3 +299,125 @@ impl From<core::convert::Infallible> for Error {
         match e {}
     }
 }
+
+impl From<tokio::time::error::Elapsed> for Error {
+    #[inline]
+    fn from(_: tokio::time::error::Elapsed) -> Self {
+        Error::TimedOut
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{body::Body, service::Service, Response, Router};
+
+    #[cfg(feature = "json")]
+    crate::path_segment!(ItemId: u32);
+
+    #[cfg(feature = "json")]
+    #[derive(serde::Deserialize)]
+    struct Item {
+        name: String,
+    }
+
+    // extractors used manually within the handler body, rather than as parameters
+    #[cfg(feature = "json")]
+    async fn update_item(req: crate::Request) -> Result<String, Error> {
+        use crate::{
+            extract::{FromRequest, FromRequestParts, Json, Path},
+            headers::Header,
+            Request,
+        };
+
+        let (mut parts, body) = req.into_parts();
+
+        let Path(id) = Path::<ItemId>::from_request_parts(&mut parts, &()).await?;
+        let Header(content_type) = Header::<headers::ContentType>::from_request_parts(&mut parts, &()).await?;
+        let Json(item) = Json::<Item>::from_request(Request::from_parts(parts, body), &()).await?;
+
+        Ok(format!("{id} {} {content_type}", item.name))
+    }
+
+    #[cfg(feature = "json")]
+    #[tokio::test]
+    async fn test_question_mark() {
+        let mut router = Router::<(), Response>::with_state(());
+        router.put("/items/{item_id}", update_item);
+
+        let put = |path: &str, body: &'static str| {
+            http::Request::put(path)
+                .header(http::header::CONTENT_TYPE, "application/json")
+                .body(Body::from(bytes::Bytes::from_static(body.as_bytes())))
+                .unwrap()
+        };
+
+        let resp = router.call(put("/items/42", r#"{"name":"widget"}"#)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.into_body().to_string().await.unwrap(),
+            "42 widget application/json"
+        );
+
+        // invalid path segment
+        let resp = router.call(put("/items/abc", r#"{"name":"widget"}"#)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        // invalid body
+        let resp = router.call(put("/items/42", "{")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_upstream_reset_is_not_disconnect() {
+        // e.g. a proxied upstream resetting the connection, which is still our fault
+        async fn proxy() -> Result<String, Error> {
+            Err(io::Error::new(ErrorKind::ConnectionReset, "upstream reset").into())
+        }
+
+        let mut router = Router::<(), Response>::with_state(());
+        router.get("/proxy", proxy);
+
+        let resp = router.call(http::Request::get("/proxy").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(resp.extensions().get::<ClientDisconnected>().is_none());
+
+        let err = BodyError::Io(io::Error::new(ErrorKind::BrokenPipe, "upstream closed"));
+        assert!(!err.is_client_disconnect());
+        assert_eq!(err.into_response().status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        // but a client hanging up mid-body is, including through `AsyncRead`
+        let err = client_hangup().await;
+        assert!(matches!(err, BodyError::ClientDisconnected(_)));
+        assert!(Error::IoError(io::Error::other(err)).is_client_disconnect());
+    }
+
+    /// Reads the request body from a client that hangs up mid-body.
+    async fn client_hangup() -> BodyError {
+        use http_body_util::BodyExt;
+        use tokio::io::AsyncWriteExt;
+
+        let (client, server) = tokio::io::duplex(1024);
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let tx = std::sync::Mutex::new(Some(tx));
+
+        let conn = hyper::server::conn::http1::Builder::new().serve_connection(
+            hyper_util::rt::TokioIo::new(server),
+            hyper::service::service_fn(move |req: hyper::Request<hyper::body::Incoming>| {
+                let tx = tx.lock().unwrap().take();
+                async move {
+                    let err = Body::from(req.into_body()).collect().await.unwrap_err();
+                    _ = tx.unwrap().send(err);
+                    Ok::<_, core::convert::Infallible>(hyper::Response::new(String::new()))
+                }
+            }),
+        );
+        tokio::spawn(conn);
+
+        let mut client = client;
+        client.write_all(b"POST / HTTP/1.1\r\nhost: x\r\ncontent-length: 10\r\n\r\nabc").await.unwrap();
+        drop(client);
+
+        rx.await.unwrap()
+    }
+}
@@ -0,0 +1,91 @@
+use core::future::Future;
+use core::ops::Deref;
+
+use crate::RequestParts;
+
+use super::FromRequestParts;
+
+/// Memoizes the result of the extractor `T` in the request extensions.
+///
+/// The first extraction of `Cached<T>` runs `T`'s extractor and stores a clone
+/// of the result, and subsequent extractions on the same request return a clone of the
+/// stored value instead. This avoids repeating expensive extractions, such as parsing
+/// authentication, when both layers and the handler need the result.
+///
+/// Rejections are not cached, so a failed extraction is retried on the next attempt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct Cached<T>(pub T);
+
+/// Private wrapper so cached values never conflict with the same type inserted by something else.
+#[derive(Clone)]
+struct CachedEntry<T>(T);
+
+impl<T> Deref for Cached<T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<S, T> FromRequestParts<S> for Cached<T>
+where
+    S: Sync,
+    T: FromRequestParts<S> + Clone + Sync,
+{
+    type Rejection = T::Rejection;
+
+    fn from_request_parts(
+        parts: &mut RequestParts,
+        state: &S,
+    ) -> impl Future<Output = Result<Self, Self::Rejection>> + Send {
+        async move {
+            if let Some(CachedEntry(value)) = parts.extensions.get::<CachedEntry<T>>() {
+                return Ok(Cached(value.clone()));
+            }
+
+            let value = T::from_request_parts(parts, state).await?;
+
+            parts.extensions.insert(CachedEntry(value.clone()));
+
+            Ok(Cached(value))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::body::Body;
+
+    static EXTRACTIONS: AtomicUsize = AtomicUsize::new(0);
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Expensive(usize);
+
+    impl<S> FromRequestParts<S> for Expensive {
+        type Rejection = core::convert::Infallible;
+
+        fn from_request_parts(
+            _parts: &mut RequestParts,
+            _state: &S,
+        ) -> impl Future<Output = Result<Self, Self::Rejection>> + Send {
+            core::future::ready(Ok(Expensive(EXTRACTIONS.fetch_add(1, Ordering::SeqCst))))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cached_runs_once() {
+        let (mut parts, _) = http::Request::get("/").body(Body::empty()).unwrap().into_parts();
+
+        let Cached(first) = Cached::<Expensive>::from_request_parts(&mut parts, &()).await.unwrap();
+        let Cached(second) = Cached::<Expensive>::from_request_parts(&mut parts, &()).await.unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(EXTRACTIONS.load(Ordering::SeqCst), 1);
+    }
+}
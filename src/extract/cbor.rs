@@ -1,5 +1,6 @@
 use std::future::Future;
 
+use bytes::Bytes;
 use http_body_util::BodyExt as _;
 
 use crate::{FromRequest, Request};
@@ -28,3 +29,78 @@ where
         }
     }
 }
+
+/// Extractor that collects a CBOR request body into contiguous [`Bytes`] without parsing it,
+/// the CBOR counterpart to [`JsonBytes`](super::JsonBytes).
+///
+/// The CBOR deserializer cannot borrow from its input, so unlike [`JsonBytes::parse`](super::JsonBytes::parse),
+/// [`CborBytes::parse`] still copies strings out of the body. This is for handlers that need the raw body
+/// alongside the parsed value, such as to verify a signature over it or forward it unchanged.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CborBytes(pub Bytes);
+
+impl CborBytes {
+    /// Deserializes the body.
+    pub fn parse<T>(&self) -> Result<T, crate::Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        Ok(ciborium::de::from_reader(&self.0[..])?)
+    }
+
+    /// Returns the raw body.
+    #[must_use]
+    pub fn into_bytes(self) -> Bytes {
+        self.0
+    }
+}
+
+impl std::ops::Deref for CborBytes {
+    type Target = Bytes;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<S> FromRequest<S> for CborBytes {
+    type Rejection = crate::Error;
+
+    fn from_request(mut req: Request, _state: &S) -> impl Future<Output = Result<Self, Self::Rejection>> + Send {
+        async move { Ok(CborBytes(req.body_mut().take().collect().await?.to_bytes())) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::body::Body;
+
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct Message {
+        channel: String,
+        content: String,
+    }
+
+    #[tokio::test]
+    async fn test_cbor_bytes() {
+        let msg = Message {
+            channel: "general".to_owned(),
+            content: "hello".to_owned(),
+        };
+
+        let mut encoded = Vec::new();
+        ciborium::ser::into_writer(&msg, &mut encoded).unwrap();
+
+        let req = http::Request::post("/").body(Body::from(encoded.clone())).unwrap();
+
+        let cbor = CborBytes::from_request(req, &()).await.unwrap();
+        assert_eq!(cbor.parse::<Message>().unwrap(), msg);
+        assert_eq!(cbor.into_bytes(), encoded);
+
+        let req = http::Request::post("/").body(Body::from(Bytes::from_static(b"\xff"))).unwrap();
+        let cbor = CborBytes::from_request(req, &()).await.unwrap();
+        assert!(cbor.parse::<Message>().is_err());
+    }
+}
@@ -0,0 +1,121 @@
+//! Request contexts bundling several extractors into a single struct.
+
+/// Declares a struct whose fields are all extractors, and implements [`FromRequestParts`]
+/// for it by extracting each field in order, much like a `#[derive(FromRequestParts)]`.
+///
+/// This is useful for handlers that always need the same bundle of extractors, such as the state,
+/// the client address and some headers. As with tuples of extractors, any rejection is converted
+/// into [`Error`](crate::Error), and fields after it are not extracted.
+///
+/// The implementation is generic over the router state, so any state the fields
+/// can be extracted from is accepted.
+///
+/// ```rust,ignore
+/// use ftl::{extract::{real_ip::RealIp, State}, headers::Header};
+/// use headers::ContentType;
+///
+/// ftl::from_request_parts! {
+///     pub struct Context {
+///         pub state: State<AppState>,
+///         pub ip: RealIp,
+///         pub content_type: Header<ContentType>,
+///     }
+/// }
+///
+/// async fn handler(ctx: Context) -> String {
+///     format!("{} sent {}", ctx.ip, ctx.content_type.0)
+/// }
+/// ```
+///
+/// [`FromRequestParts`]: crate::extract::FromRequestParts
+#[macro_export]
+macro_rules! from_request_parts {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $($(#[$field_meta:meta])* $field_vis:vis $field:ident: $ty:ty),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis struct $name {
+            $($(#[$field_meta])* $field_vis $field: $ty,)*
+        }
+
+        impl<__S> $crate::extract::FromRequestParts<__S> for $name
+        where
+            __S: ::core::marker::Send + ::core::marker::Sync,
+            $($ty: $crate::extract::FromRequestParts<__S>,)*
+        {
+            type Rejection = $crate::Error;
+
+            fn from_request_parts(
+                parts: &mut $crate::RequestParts,
+                state: &__S,
+            ) -> impl ::core::future::Future<Output = ::core::result::Result<Self, Self::Rejection>>
+                   + ::core::marker::Send {
+                async move {
+                    ::core::result::Result::Ok($name {
+                        $($field: <$ty as $crate::extract::FromRequestParts<__S>>::from_request_parts(parts, state)
+                            .await
+                            .map_err(::core::convert::Into::into)?,)*
+                    })
+                }
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use headers::ContentType;
+    use http::StatusCode;
+
+    use crate::{
+        body::Body,
+        extract::{real_ip::RealIp, State},
+        headers::Header,
+        service::Service,
+        IntoResponse, Response, Router,
+    };
+
+    #[derive(Clone)]
+    struct AppState {
+        name: &'static str,
+    }
+
+    crate::from_request_parts! {
+        /// Everything the handler needs.
+        struct Context {
+            state: State<AppState>,
+            pub ip: RealIp,
+            content_type: Header<ContentType>,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_from_request_parts_macro() {
+        let mut router = Router::<AppState, Response>::with_state(AppState { name: "app" });
+        router.post("/", |ctx: Context| async move {
+            format!("{} {} {}", ctx.state.name, ctx.ip.0, ctx.content_type.0)
+        });
+
+        let req = http::Request::post("/")
+            .header("x-real-ip", "10.0.0.1")
+            .header(http::header::CONTENT_TYPE, "application/json")
+            .body(Body::empty())
+            .unwrap();
+        let resp = router.call(req).await.unwrap();
+        assert_eq!(
+            resp.into_body().to_string().await.unwrap(),
+            "app 10.0.0.1 application/json"
+        );
+
+        // a rejection from any field rejects the whole context
+        let req = http::Request::post("/")
+            .header(http::header::CONTENT_TYPE, "application/json")
+            .body(Body::empty())
+            .unwrap();
+        let resp = router.call(req).await.unwrap_or_else(IntoResponse::into_response);
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+}
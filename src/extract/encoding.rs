@@ -0,0 +1,42 @@
+use core::convert::Infallible;
+use core::future::Future;
+
+use crate::headers::accept_encoding::{AcceptEncoding, ContentEncoding, FilterEncoding};
+use crate::RequestParts;
+
+use super::FromRequestParts;
+
+/// The encoding negotiated from the request's `Accept-Encoding` header.
+///
+/// The preferred encoding is chosen using the [`FilterEncoding`] configured by
+/// the compression layer if present in the request extensions, otherwise the
+/// default filter is used. Handlers that serve pre-encoded content can use this
+/// to pick the right variant themselves and set `Content-Encoding`, which the
+/// compression layer will then respect and skip.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(transparent)]
+pub struct NegotiatedEncoding(pub ContentEncoding);
+
+impl core::ops::Deref for NegotiatedEncoding {
+    type Target = ContentEncoding;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<S> FromRequestParts<S> for NegotiatedEncoding {
+    type Rejection = Infallible;
+
+    fn from_request_parts(
+        parts: &mut RequestParts,
+        _state: &S,
+    ) -> impl Future<Output = Result<Self, Self::Rejection>> + Send {
+        let filter = parts.extensions.get::<FilterEncoding>().copied().unwrap_or_default();
+
+        let negotiated = AcceptEncoding::negotiate(&parts.headers, filter);
+
+        core::future::ready(Ok(NegotiatedEncoding(negotiated.encoding)))
+    }
+}
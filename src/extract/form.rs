@@ -1,8 +1,34 @@
 use std::future::Future;
 
+use headers::{ContentType, HeaderMapExt as _};
+use http::Method;
 use http_body_util::BodyExt as _;
 
-use crate::{body::Form, FromRequest, Request};
+use crate::{
+    body::{BodyError, Form},
+    Error, FromRequest, Request,
+};
+
+/// Maximum size in bytes of a urlencoded [`Form`] body.
+///
+/// Insert this into the request extensions, such as from a middleware, to override
+/// the [default limit](FormLimit::DEFAULT) for [`Form`] extraction. Bodies
+/// exceeding the limit are rejected with `413 Payload Too Large`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct FormLimit(pub u64);
+
+impl FormLimit {
+    /// 2 MiB
+    pub const DEFAULT: FormLimit = FormLimit(2 * 1024 * 1024);
+}
+
+impl Default for FormLimit {
+    #[inline]
+    fn default() -> Self {
+        FormLimit::DEFAULT
+    }
+}
 
 impl<S, T> FromRequest<S> for Form<T>
 where
@@ -10,10 +36,37 @@ where
 {
     type Rejection = crate::Error;
 
+    /// For `GET` and `HEAD` requests, the form is read from the query string, as there is no body.
+    /// For any other method, the form is read from the `application/x-www-form-urlencoded` body.
+    ///
+    /// `multipart/form-data` bodies are rejected with `415 Unsupported Media Type`, and bodies
+    /// larger than the [`FormLimit`] are rejected with `413 Payload Too Large`.
     fn from_request(mut req: Request, _state: &S) -> impl Future<Output = Result<Self, Self::Rejection>> + Send {
         async move {
+            if req.method() == Method::GET || req.method() == Method::HEAD {
+                // a missing query is treated as empty, so all-optional forms can still be parsed
+                return Ok(Form(crate::form_impl::from_str(req.uri().query().unwrap_or_default())?));
+            }
+
+            if let Some(content_type) = req.headers().typed_get::<ContentType>() {
+                let mime = mime::Mime::from(content_type);
+
+                if mime.type_() == mime::MULTIPART && mime.subtype() == mime::FORM_DATA {
+                    return Err(Error::UnsupportedMultipartForm);
+                }
+            }
+
+            let FormLimit(limit) = req.extensions().get::<FormLimit>().copied().unwrap_or_default();
+
+            let body = req.body_mut().take();
+
+            // reject early if the body is known to be too large
+            if body.original_size_hint().lower() > limit {
+                return Err(BodyError::LengthLimitError.into());
+            }
+
             // collect body in non-contiguous memory and then parse it
-            let body = req.body_mut().take().collect().await?;
+            let body = body.limit(limit)?.collect().await?;
 
             Ok(Form({
                 use bytes::Buf;
@@ -25,3 +78,70 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use http::header::CONTENT_TYPE;
+
+    use super::FormLimit;
+    use crate::{
+        body::{Body, BodyError, Form},
+        Error, FromRequest, Request,
+    };
+
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    struct Test {
+        a: u32,
+        b: u32,
+    }
+
+    async fn extract(req: Request) -> Result<Test, Error> {
+        Form::<Test>::from_request(req, &()).await.map(|Form(form)| form)
+    }
+
+    #[tokio::test]
+    async fn test_form_from_query() {
+        let req = http::Request::get("/?a=1&b=2").body(Body::from(Bytes::from_static(b"a=3&b=4"))).unwrap();
+
+        assert_eq!(extract(req).await.unwrap(), Test { a: 1, b: 2 });
+    }
+
+    #[tokio::test]
+    async fn test_form_from_body() {
+        let req = http::Request::post("/?a=1&b=2")
+            .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(Body::from(Bytes::from_static(b"a=3&b=4")))
+            .unwrap();
+
+        assert_eq!(extract(req).await.unwrap(), Test { a: 3, b: 4 });
+    }
+
+    #[tokio::test]
+    async fn test_form_rejects_multipart() {
+        let req = http::Request::post("/")
+            .header(CONTENT_TYPE, "multipart/form-data; boundary=X")
+            .body(Body::from(Bytes::from_static(b"--X--")))
+            .unwrap();
+
+        assert!(matches!(extract(req).await, Err(Error::UnsupportedMultipartForm)));
+    }
+
+    #[tokio::test]
+    async fn test_form_limit_exceeded() {
+        let mut req = http::Request::post("/")
+            .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(Body::from(Bytes::from_static(b"a=3&b=4")))
+            .unwrap();
+
+        req.extensions_mut().insert(FormLimit(4));
+
+        let err = extract(req).await.unwrap_err();
+
+        assert!(matches!(err, Error::BodyError(BodyError::LengthLimitError)));
+        assert_eq!(
+            crate::IntoResponse::into_response(err).status(),
+            http::StatusCode::PAYLOAD_TOO_LARGE
+        );
+    }
+}
@@ -0,0 +1,114 @@
+use core::future::Future;
+use std::convert::Infallible;
+use std::sync::LazyLock;
+
+use headers::HeaderMapExt as _;
+use mime::Mime;
+
+use crate::{headers::accept::Accept, RequestParts};
+
+use super::FromRequestParts;
+
+static APPLICATION_CBOR: LazyLock<Mime> = LazyLock::new(|| "application/cbor".parse().unwrap());
+
+/// The response format preferred by the client, negotiated from the `Accept` header.
+///
+/// JSON, CBOR and HTML are preferred in that order when the client accepts them equally,
+/// such as with `*/*`. If the client accepts none of them, its most preferred media range is
+/// given as [`ResponseFormat::Other`]. A missing or invalid `Accept` header accepts anything.
+///
+/// This pairs with [`OneOf`](super::one_of::OneOf) to build fully content-negotiated endpoints:
+///
+/// ```rust,ignore
+/// async fn get_user(format: ResponseFormat) -> Response {
+///     let user = load_user().await;
+///
+///     match format {
+///         ResponseFormat::Cbor => Cbor(user).into_response(),
+///         ResponseFormat::Html => render_user(&user).into_response(),
+///         _ => Json(user).into_response(),
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResponseFormat {
+    Json,
+    Cbor,
+    Html,
+    Other(Mime),
+}
+
+impl ResponseFormat {
+    /// Negotiates the response format from the given `Accept` header.
+    #[must_use]
+    pub fn negotiate(accept: &Accept) -> Self {
+        let available = [mime::APPLICATION_JSON, APPLICATION_CBOR.clone(), mime::TEXT_HTML];
+
+        match accept.negotiate(&available).map(|mime| available.iter().position(|m| m == mime)) {
+            Some(Some(0)) => ResponseFormat::Json,
+            Some(Some(1)) => ResponseFormat::Cbor,
+            Some(Some(2)) => ResponseFormat::Html,
+            _ => {
+                let mut best: Option<&(Mime, _)> = None;
+
+                for range in &accept.0 {
+                    if !range.1.is_zero() && best.is_none_or(|best| range.1 > best.1) {
+                        best = Some(range);
+                    }
+                }
+
+                ResponseFormat::Other(best.map_or(mime::STAR_STAR, |(mime, _)| mime.clone()))
+            }
+        }
+    }
+}
+
+impl<S> FromRequestParts<S> for ResponseFormat {
+    type Rejection = Infallible;
+
+    fn from_request_parts(
+        parts: &mut RequestParts,
+        _state: &S,
+    ) -> impl Future<Output = Result<Self, Self::Rejection>> + Send {
+        let accept = parts.headers.typed_try_get::<Accept>().ok().flatten().unwrap_or_default();
+
+        core::future::ready(Ok(ResponseFormat::negotiate(&accept)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn format(accept: Option<&'static str>) -> ResponseFormat {
+        let mut req = http::Request::get("/");
+
+        if let Some(accept) = accept {
+            req = req.header(http::header::ACCEPT, accept);
+        }
+
+        let (mut parts, _) = req.body(()).unwrap().into_parts();
+
+        let Ok(format) = ResponseFormat::from_request_parts(&mut parts, &()).await;
+        format
+    }
+
+    #[tokio::test]
+    async fn test_response_format() {
+        assert_eq!(format(Some("application/cbor")).await, ResponseFormat::Cbor);
+        assert_eq!(
+            format(Some("application/json, application/cbor")).await,
+            ResponseFormat::Json
+        );
+        assert_eq!(
+            format(Some("text/html, application/cbor;q=0.5")).await,
+            ResponseFormat::Html
+        );
+        assert_eq!(format(Some("*/*")).await, ResponseFormat::Json);
+        assert_eq!(format(None).await, ResponseFormat::Json);
+        assert_eq!(
+            format(Some("image/webp, image/png;q=0.9")).await,
+            ResponseFormat::Other("image/webp".parse().unwrap())
+        );
+    }
+}
@@ -0,0 +1,143 @@
+use core::str::FromStr;
+use std::{
+    fmt::{self, Display},
+    future::{self, Future},
+    ops::Deref,
+};
+
+use http::{uri::Authority, HeaderMap, HeaderName, Uri};
+
+use crate::RequestParts;
+
+use super::FromRequestParts;
+
+/// The hostname the client targeted, without any port or userinfo.
+///
+/// The host is taken from the following, in order:
+/// - the `host` field of the first `Forwarded` header entry
+/// - the first `X-Forwarded-Host` header value
+/// - the `Host` header
+/// - the request URI authority, as used by HTTP/2 requests
+///
+/// Invalid values are skipped, and if none of these are found, it will return a 400 Bad Request
+/// via [`Error::BadRequest`](crate::Error::BadRequest).
+///
+/// Note that the forwarding headers are trusted as-is, so only rely on this for virtual hosting
+/// when behind a proxy that sets or strips them. See [`Authority`] for the unmodified authority.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct Host(pub String);
+
+impl Deref for Host {
+    type Target = str;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Display for Host {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
+impl<S> FromRequestParts<S> for Host {
+    type Rejection = crate::Error;
+
+    fn from_request_parts(
+        parts: &mut RequestParts,
+        _: &S,
+    ) -> impl Future<Output = Result<Self, Self::Rejection>> + Send {
+        future::ready(request_host(&parts.headers, &parts.uri).ok_or(crate::Error::BadRequest))
+    }
+}
+
+/// Determines the host of a request, see [`Host`] for the order of precedence.
+pub(crate) fn request_host(headers: &HeaderMap, uri: &Uri) -> Option<Host> {
+    fn parse_forwarded(headers: &HeaderMap) -> Option<&str> {
+        // if there are multiple `Forwarded` `HeaderMap::get` will return the first one
+        let forwarded_values = headers.get(http::header::FORWARDED)?.to_str().ok()?;
+
+        // get the first set of values
+        let first_value = forwarded_values.split(',').next()?;
+
+        // find the value of the `host` field
+        first_value.split(';').find_map(|pair| {
+            let (key, value) = pair.split_once('=')?;
+            key.trim().eq_ignore_ascii_case("host").then(|| value.trim().trim_matches('"'))
+        })
+    }
+
+    fn parse_host(value: &str) -> Option<Host> {
+        match Authority::from_str(value) {
+            Ok(authority) if !authority.host().is_empty() => Some(Host(authority.host().to_owned())),
+            _ => None,
+        }
+    }
+
+    const X_FORWARDED_HOST: HeaderName = HeaderName::from_static("x-forwarded-host");
+
+    if let Some(host) = parse_forwarded(headers).and_then(parse_host) {
+        return Some(host);
+    }
+
+    let x_forwarded_host = headers.get(X_FORWARDED_HOST).and_then(|value| value.to_str().ok());
+
+    if let Some(host) =
+        x_forwarded_host.and_then(|value| value.split(',').next()).and_then(|v| parse_host(v.trim()))
+    {
+        return Some(host);
+    }
+
+    if let Some(host) = headers.get(http::header::HOST).and_then(|value| parse_host(value.to_str().ok()?)) {
+        return Some(host);
+    }
+
+    uri.host().filter(|host| !host.is_empty()).map(|host| Host(host.to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_host_precedence() {
+        let host = |headers: &[(&'static str, &'static str)], uri: &'static str| {
+            let headers = headers
+                .iter()
+                .map(|&(name, value)| (HeaderName::from_static(name), value.parse().unwrap()))
+                .collect::<HeaderMap>();
+
+            request_host(&headers, &Uri::from_static(uri)).map(|Host(host)| host)
+        };
+
+        let all = [
+            (
+                "forwarded",
+                r#"for=192.0.2.60;host="forwarded.example:8443";proto=https, host=second.example"#,
+            ),
+            ("x-forwarded-host", "x-forwarded.example, other.example"),
+            ("host", "user@host.example:8080"),
+        ];
+
+        assert_eq!(host(&all, "https://uri.example/").as_deref(), Some("forwarded.example"));
+        assert_eq!(
+            host(&all[1..], "https://uri.example/").as_deref(),
+            Some("x-forwarded.example")
+        );
+        assert_eq!(host(&all[2..], "https://uri.example/").as_deref(), Some("host.example"));
+        assert_eq!(host(&[], "https://uri.example:443/").as_deref(), Some("uri.example"));
+        assert_eq!(host(&[("host", "[::1]:8080")], "/").as_deref(), Some("[::1]"));
+
+        // invalid values are skipped
+        assert_eq!(
+            host(&[("x-forwarded-host", "bad host"), ("host", "ok.example")], "/").as_deref(),
+            Some("ok.example")
+        );
+
+        assert_eq!(host(&[], "/"), None);
+    }
+}
@@ -1,3 +1,4 @@
+use bytes::Bytes;
 use http_body_util::BodyExt as _;
 use std::future::Future;
 
@@ -33,3 +34,89 @@ where
         }
     }
 }
+
+/// Extractor that collects a JSON request body into contiguous [`Bytes`] without parsing it,
+/// so that it can be deserialized into borrowed data with [`JsonBytes::parse`].
+///
+/// Unlike [`Json`], which requires `DeserializeOwned` and therefore copies every string out of
+/// the body, this allows `&str` and `&[u8]` fields to reference the body directly:
+///
+/// ```rust,ignore
+/// #[derive(Deserialize)]
+/// struct Message<'a> {
+///     channel: &'a str,
+///     content: &'a str,
+/// }
+///
+/// async fn post_message(body: JsonBytes) -> Result<impl IntoResponse, Error> {
+///     let msg: Message<'_> = body.parse()?;
+///     // ...
+/// }
+/// ```
+///
+/// The parsed value borrows from the `JsonBytes`, so it cannot outlive it or be sent elsewhere
+/// without copying. Strings containing escape sequences cannot be borrowed, so fields that may
+/// contain them should use `Cow<'a, str>` with `#[serde(borrow)]` instead of `&'a str`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonBytes(pub Bytes);
+
+impl JsonBytes {
+    /// Deserializes the body, borrowing from it where possible.
+    pub fn parse<'a, T>(&'a self) -> Result<T, crate::Error>
+    where
+        T: serde::de::Deserialize<'a>,
+    {
+        Ok(json_impl::from_slice(&self.0)?)
+    }
+
+    /// Returns the raw body.
+    #[must_use]
+    pub fn into_bytes(self) -> Bytes {
+        self.0
+    }
+}
+
+impl std::ops::Deref for JsonBytes {
+    type Target = Bytes;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<S> FromRequest<S> for JsonBytes {
+    type Rejection = crate::Error;
+
+    fn from_request(mut req: Request, _state: &S) -> impl Future<Output = Result<Self, Self::Rejection>> + Send {
+        async move { Ok(JsonBytes(req.body_mut().take().collect().await?.to_bytes())) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::body::Body;
+
+    #[derive(serde::Deserialize)]
+    struct Message<'a> {
+        channel: &'a str,
+        content: &'a str,
+    }
+
+    #[tokio::test]
+    async fn test_json_bytes_borrowed() {
+        let body = Body::from(Bytes::from_static(br#"{"channel":"general","content":"hello"}"#));
+        let req = http::Request::post("/").body(body).unwrap();
+
+        let json = JsonBytes::from_request(req, &()).await.unwrap();
+        let msg: Message<'_> = json.parse().unwrap();
+
+        assert_eq!((msg.channel, msg.content), ("general", "hello"));
+
+        // the strings point into the body, rather than being copied out of it
+        let range = json.as_ptr_range();
+        assert!(range.contains(&msg.channel.as_ptr()));
+        assert!(range.contains(&msg.content.as_ptr()));
+    }
+}
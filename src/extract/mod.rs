@@ -80,29 +80,52 @@ where
 }
 
 pub mod body;
+pub mod cached;
+pub mod context;
+pub mod encoding;
 pub mod form;
+pub mod format;
+pub mod host;
 pub mod path;
 pub mod query;
 pub mod real_ip;
+pub mod rejection;
+pub mod request_id;
 pub mod scheme;
 pub mod timeout;
+pub mod upgrade;
+pub mod valid;
 
 pub use crate::body::Form;
+pub use form::FormLimit;
+pub use format::ResponseFormat;
 
 #[cfg(feature = "json")]
 mod json;
 #[cfg(feature = "json")]
-pub use json::Json;
+pub use json::{Json, JsonBytes};
 
 #[cfg(feature = "cbor")]
 mod cbor;
 #[cfg(feature = "cbor")]
-pub use cbor::Cbor;
+pub use cbor::{Cbor, CborBytes};
+
+#[cfg(feature = "multipart")]
+pub mod multipart;
+#[cfg(feature = "multipart")]
+pub use multipart::Multipart;
 
 pub mod one_of;
 
 pub use body::{CollectedBytes, Limited};
+pub use cached::Cached;
+pub use encoding::NegotiatedEncoding;
+pub use host::Host;
 pub use path::Path;
+pub use rejection::WithRejection;
+pub use request_id::RequestId;
+pub use upgrade::Upgrade;
+pub use valid::{Valid, Validate, ValidationErrors};
 
 macro_rules! impl_from_request {
     ([$($t:ident),*], $last:ident) => {
@@ -165,6 +188,40 @@ impl<S> Deref for State<S> {
     }
 }
 
+/// Extracts state shared via [`Router::with_shared_state`](crate::Router::with_shared_state),
+/// dereferencing directly to `T` rather than the `Arc<T>` it is stored in.
+///
+/// Extracting this is only a single atomic reference count increment per request,
+/// regardless of the size of `T`.
+#[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct SharedState<T>(pub Arc<T>);
+
+impl<T> Clone for SharedState<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        SharedState(self.0.clone())
+    }
+}
+
+impl<T> SharedState<T> {
+    /// Returns the inner `Arc`.
+    #[inline]
+    #[must_use]
+    pub fn into_inner(self) -> Arc<T> {
+        self.0
+    }
+}
+
+impl<T> Deref for SharedState<T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[repr(transparent)]
 pub struct Extension<E>(pub E);
@@ -192,6 +249,20 @@ where
     }
 }
 
+impl<T> FromRequestParts<Arc<T>> for SharedState<T>
+where
+    T: Send + Sync + 'static,
+{
+    type Rejection = Infallible;
+
+    fn from_request_parts(
+        _parts: &mut RequestParts,
+        state: &Arc<T>,
+    ) -> impl Future<Output = Result<Self, Self::Rejection>> + Send {
+        core::future::ready(Ok(SharedState(state.clone())))
+    }
+}
+
 impl<S, E> FromRequestParts<S> for Extension<E>
 where
     E: Clone + Send + Sync + 'static,
@@ -324,16 +395,16 @@ impl IntoResponse for AuthorityError {
     }
 }
 
-pub(crate) fn extract_authority(parts: &RequestParts) -> Result<Authority, AuthorityError> {
-    let from_uri = parts.uri.authority();
+pub(crate) fn extract_authority(uri: &Uri, headers: &HeaderMap) -> Result<Authority, AuthorityError> {
+    let from_uri = uri.authority();
 
-    let from_header =
-        parts.headers.get(HeaderName::from_static("host")).ok_or(AuthorityError::MissingAuthority).and_then(
-            |hdr| {
-                Authority::from_str(hdr.to_str().map_err(|_| AuthorityError::InvalidAuthority)?)
-                    .map_err(|_| AuthorityError::InvalidAuthority)
-            },
-        );
+    let from_header = headers
+        .get(HeaderName::from_static("host"))
+        .ok_or(AuthorityError::MissingAuthority)
+        .and_then(|hdr| {
+            Authority::from_str(hdr.to_str().map_err(|_| AuthorityError::InvalidAuthority)?)
+                .map_err(|_| AuthorityError::InvalidAuthority)
+        });
 
     match (from_uri, from_header) {
         (Some(_), Ok(b)) => Ok(b),          // defer to HOST as what the client intended
@@ -350,7 +421,7 @@ impl<S> FromRequestParts<S> for Authority {
         parts: &mut RequestParts,
         _state: &S,
     ) -> impl Future<Output = Result<Self, Self::Rejection>> + Send {
-        futures::future::ready(extract_authority(parts))
+        futures::future::ready(extract_authority(&parts.uri, &parts.headers))
     }
 }
 
@@ -387,6 +458,18 @@ impl<S> FromRequestParts<S> for Extensions {
     }
 }
 
+/// The route pattern that matched the request, such as `/users/{id}`, as registered with the router.
+///
+/// This is inserted into the request extensions by the router, and can be extracted directly:
+///
+/// ```rust,ignore
+/// async fn handler(path: MatchedPath) -> String {
+///     format!("matched {}", &*path)
+/// }
+/// ```
+///
+/// `Extension<MatchedPath>` also works, and both are rejected with a
+/// `500 Internal Server Error` if the handler isn't behind a router.
 #[derive(Clone, Debug)]
 pub struct MatchedPath(pub Arc<str>);
 
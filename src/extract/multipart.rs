@@ -0,0 +1,736 @@
+//! Streaming `multipart/form-data` request bodies, as per [RFC 7578](https://www.rfc-editor.org/rfc/rfc7578).
+//!
+//! The body is parsed incrementally as fields are read, so large uploads are never buffered in full.
+
+use std::{
+    future::{self, Future},
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bytes::{Bytes, BytesMut};
+use futures::Stream;
+use headers::{ContentType, HeaderMapExt as _};
+use http::{HeaderMap, HeaderName, HeaderValue, StatusCode};
+use http_body::Body as _;
+use memchr::memmem::Finder;
+use tokio::io::{AsyncRead, ReadBuf};
+
+use crate::{
+    body::{Body, BodyError},
+    FromRequest, IntoResponse, Request, Response,
+};
+
+/// Maximum size of the headers of a single field.
+const MAX_HEADERS_SIZE: usize = 8 * 1024;
+
+/// Size limits for [`Multipart`] bodies.
+///
+/// Insert this into the request extensions, such as from a middleware, to override
+/// the [default limits](MultipartLimits::DEFAULT) for [`Multipart`] extraction.
+/// Exceeding either limit is rejected with `413 Payload Too Large`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MultipartLimits {
+    /// Maximum size in bytes of the whole body.
+    pub total: u64,
+
+    /// Maximum size in bytes of the data of any single field.
+    pub field: u64,
+}
+
+impl MultipartLimits {
+    /// 32 MiB total, 8 MiB per field
+    pub const DEFAULT: MultipartLimits = MultipartLimits {
+        total: 32 * 1024 * 1024,
+        field: 8 * 1024 * 1024,
+    };
+}
+
+impl Default for MultipartLimits {
+    #[inline]
+    fn default() -> Self {
+        MultipartLimits::DEFAULT
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum MultipartError {
+    #[error("expected a multipart/form-data body")]
+    UnsupportedMediaType,
+
+    #[error("missing or invalid multipart boundary")]
+    InvalidBoundary,
+
+    #[error("incomplete multipart body")]
+    Incomplete,
+
+    #[error("malformed multipart body")]
+    Malformed,
+
+    #[error("invalid multipart field headers")]
+    InvalidHeaders,
+
+    #[error("multipart field exceeds the size limit")]
+    FieldTooLarge,
+
+    #[error("multipart field is not valid UTF-8: {0}")]
+    Utf8(#[from] std::str::Utf8Error),
+
+    #[error(transparent)]
+    Body(#[from] BodyError),
+}
+
+impl IntoResponse for MultipartError {
+    fn into_response(self) -> Response {
+        let status = match self {
+            MultipartError::Body(e) => return e.into_response(),
+            MultipartError::UnsupportedMediaType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            MultipartError::FieldTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            _ => StatusCode::BAD_REQUEST,
+        };
+
+        (self.to_string(), status).into_response()
+    }
+}
+
+impl From<MultipartError> for io::Error {
+    fn from(e: MultipartError) -> Self {
+        match e {
+            MultipartError::Body(BodyError::Io(e)) => e,
+            _ => io::Error::new(io::ErrorKind::InvalidData, e),
+        }
+    }
+}
+
+/// Extractor for streaming `multipart/form-data` bodies, read one [`Field`] at a time.
+///
+/// Requests without a `multipart/form-data` content type are rejected with `415 Unsupported Media Type`,
+/// and those with an invalid boundary with `400 Bad Request`. See [`MultipartLimits`] for size limits.
+///
+/// ```rust,ignore
+/// async fn upload(mut multipart: Multipart) -> Result<String, MultipartError> {
+///     let mut names = Vec::new();
+///
+///     while let Some(field) = multipart.next_field().await? {
+///         names.push(field.name().unwrap_or_default().to_owned());
+///
+///         let data = field.bytes().await?;
+///     }
+///
+///     Ok(names.join(", "))
+/// }
+/// ```
+pub struct Multipart {
+    body: Body,
+    buf: BytesMut,
+    /// `\r\n--boundary`
+    delimiter: Finder<'static>,
+    state: State,
+    field_limit: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// Skipping to the next delimiter, then reading whether it is the final one.
+    Boundary,
+    Headers,
+    Data,
+    End,
+}
+
+impl<S> FromRequest<S> for Multipart {
+    type Rejection = MultipartError;
+
+    fn from_request(req: Request, _state: &S) -> impl Future<Output = Result<Self, Self::Rejection>> + Send {
+        future::ready(Multipart::from_request_sync(req))
+    }
+}
+
+impl Multipart {
+    fn from_request_sync(req: Request) -> Result<Self, MultipartError> {
+        let Some(content_type) = req.headers().typed_get::<ContentType>() else {
+            return Err(MultipartError::UnsupportedMediaType);
+        };
+
+        let mime = mime::Mime::from(content_type);
+
+        if mime.type_() != mime::MULTIPART || mime.subtype() != mime::FORM_DATA {
+            return Err(MultipartError::UnsupportedMediaType);
+        }
+
+        let boundary = mime.get_param(mime::BOUNDARY).ok_or(MultipartError::InvalidBoundary)?;
+
+        // https://www.rfc-editor.org/rfc/rfc2046#section-5.1.1
+        if boundary.as_str().is_empty() || boundary.as_str().len() > 70 || boundary.as_str().ends_with(' ') {
+            return Err(MultipartError::InvalidBoundary);
+        }
+
+        let limits = req.extensions().get::<MultipartLimits>().copied().unwrap_or_default();
+
+        let body = req.into_body();
+
+        // reject early if the body is known to be too large
+        if body.original_size_hint().lower() > limits.total {
+            return Err(BodyError::LengthLimitError.into());
+        }
+
+        Ok(Multipart::new(
+            body.limit(limits.total)?,
+            boundary.as_str(),
+            limits.field,
+        ))
+    }
+
+    fn new(body: Body, boundary: &str, field_limit: u64) -> Self {
+        let delimiter = format!("\r\n--{boundary}");
+
+        Multipart {
+            body,
+            // the first delimiter may be at the very start of the body, without a preceding line break
+            buf: BytesMut::from(&b"\r\n"[..]),
+            delimiter: Finder::new(delimiter.as_bytes()).into_owned(),
+            state: State::Boundary,
+            field_limit,
+        }
+    }
+
+    /// Returns the next field, or `None` once all fields have been read.
+    ///
+    /// Any unread data of the previous field is skipped.
+    pub async fn next_field(&mut self) -> Result<Option<Field<'_>>, MultipartError> {
+        let Some(headers) = future::poll_fn(|cx| self.poll_next_field(cx)).await? else {
+            return Ok(None);
+        };
+
+        Ok(Some(Field::new(self, headers)))
+    }
+
+    /// Reads more of the body into the buffer, returning `false` at the end of the body.
+    fn poll_fill(&mut self, cx: &mut Context<'_>) -> Poll<Result<bool, MultipartError>> {
+        loop {
+            return Poll::Ready(match futures::ready!(Pin::new(&mut self.body).poll_frame(cx)) {
+                Some(Ok(frame)) => match frame.into_data() {
+                    Ok(data) if data.is_empty() => continue,
+                    Ok(data) => {
+                        self.buf.extend_from_slice(&data);
+                        Ok(true)
+                    }
+                    Err(_) => continue, // trailers
+                },
+                Some(Err(e)) => Err(e.into()),
+                None => Ok(false),
+            });
+        }
+    }
+
+    /// Polls for more data, failing if the body ended early.
+    fn poll_fill_required(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), MultipartError>> {
+        match futures::ready!(self.poll_fill(cx)) {
+            Ok(true) => Poll::Ready(Ok(())),
+            Ok(false) => Poll::Ready(Err(MultipartError::Incomplete)),
+            Err(e) => Poll::Ready(Err(e)),
+        }
+    }
+
+    fn poll_next_field(&mut self, cx: &mut Context<'_>) -> Poll<Result<Option<HeaderMap>, MultipartError>> {
+        loop {
+            match self.state {
+                State::End => return Poll::Ready(Ok(None)),
+                State::Data => {
+                    // skip the rest of the previous field
+                    while futures::ready!(self.poll_data(cx))?.is_some() {}
+                }
+                State::Boundary => futures::ready!(self.poll_boundary(cx))?,
+                State::Headers => return self.poll_headers(cx).map_ok(Some),
+            }
+        }
+    }
+
+    fn poll_boundary(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), MultipartError>> {
+        let len = self.delimiter.needle().len();
+
+        loop {
+            let Some(idx) = self.delimiter.find(&self.buf) else {
+                // discard anything before the delimiter, such as the preamble,
+                // keeping only what could be the start of a delimiter
+                let _ = self.buf.split_to(self.buf.len().saturating_sub(len - 1));
+
+                futures::ready!(self.poll_fill_required(cx))?;
+                continue;
+            };
+
+            let rest = &self.buf[idx + len..];
+
+            if rest.starts_with(b"--") {
+                self.state = State::End;
+                self.buf.clear();
+                return Poll::Ready(Ok(()));
+            }
+
+            // the delimiter is followed by optional whitespace, then a line break
+            match rest.iter().position(|&b| b != b' ' && b != b'\t') {
+                Some(ws) if rest[ws..].starts_with(b"\r\n") => {
+                    let _ = self.buf.split_to(idx + len + ws + 2);
+                    self.state = State::Headers;
+                    return Poll::Ready(Ok(()));
+                }
+                Some(ws) if rest.len() - ws >= 2 => return Poll::Ready(Err(MultipartError::Malformed)),
+                _ if rest.len() > MAX_HEADERS_SIZE => return Poll::Ready(Err(MultipartError::Malformed)),
+                _ => futures::ready!(self.poll_fill_required(cx))?,
+            }
+        }
+    }
+
+    fn poll_headers(&mut self, cx: &mut Context<'_>) -> Poll<Result<HeaderMap, MultipartError>> {
+        loop {
+            if self.buf.starts_with(b"\r\n") {
+                let _ = self.buf.split_to(2);
+                self.state = State::Data;
+                return Poll::Ready(Ok(HeaderMap::new()));
+            }
+
+            if let Some(end) = memchr::memmem::find(&self.buf, b"\r\n\r\n") {
+                let raw = self.buf.split_to(end + 4);
+                self.state = State::Data;
+                return Poll::Ready(parse_headers(&raw[..end]));
+            }
+
+            if self.buf.len() > MAX_HEADERS_SIZE {
+                return Poll::Ready(Err(MultipartError::InvalidHeaders));
+            }
+
+            futures::ready!(self.poll_fill_required(cx))?;
+        }
+    }
+
+    /// Polls for the next chunk of the current field's data, or `None` at the end of the field.
+    fn poll_data(&mut self, cx: &mut Context<'_>) -> Poll<Result<Option<Bytes>, MultipartError>> {
+        if self.state != State::Data {
+            return Poll::Ready(Ok(None));
+        }
+
+        let len = self.delimiter.needle().len();
+
+        loop {
+            match self.delimiter.find(&self.buf) {
+                Some(0) => {
+                    self.state = State::Boundary;
+                    return Poll::Ready(Ok(None));
+                }
+                Some(idx) => return Poll::Ready(Ok(Some(self.buf.split_to(idx).freeze()))),
+                None => {
+                    // keep what could be the start of the delimiter
+                    let safe = self.buf.len().saturating_sub(len - 1);
+
+                    if safe > 0 {
+                        return Poll::Ready(Ok(Some(self.buf.split_to(safe).freeze())));
+                    }
+
+                    futures::ready!(self.poll_fill_required(cx))?;
+                }
+            }
+        }
+    }
+}
+
+fn parse_headers(raw: &[u8]) -> Result<HeaderMap, MultipartError> {
+    let mut headers = HeaderMap::new();
+
+    for line in raw.split(|&b| b == b'\n') {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+
+        let Some(colon) = memchr::memchr(b':', line) else {
+            return Err(MultipartError::InvalidHeaders);
+        };
+
+        let name =
+            HeaderName::from_bytes(line[..colon].trim_ascii()).map_err(|_| MultipartError::InvalidHeaders)?;
+        let value =
+            HeaderValue::from_bytes(line[colon + 1..].trim_ascii()).map_err(|_| MultipartError::InvalidHeaders)?;
+
+        headers.append(name, value);
+    }
+
+    Ok(headers)
+}
+
+/// Parses the `name` and `filename` parameters of a `Content-Disposition: form-data` header,
+/// preferring `filename*` over `filename` when present.
+fn parse_disposition(value: &str) -> (Option<String>, Option<String>) {
+    let mut name = None;
+    let mut file_name = None;
+    let mut file_name_ext = None;
+
+    let mut rest = value;
+
+    // skip the disposition type
+    match rest.find(';') {
+        Some(idx) => rest = &rest[idx + 1..],
+        None => return (None, None),
+    }
+
+    while !rest.is_empty() {
+        let Some(eq) = rest.find('=') else { break };
+
+        let key = rest[..eq].trim().to_ascii_lowercase();
+        rest = rest[eq + 1..].trim_start();
+
+        let value = if let Some(quoted) = rest.strip_prefix('"') {
+            let mut value = String::new();
+            let mut chars = quoted.char_indices();
+            let mut end = quoted.len();
+
+            while let Some((idx, c)) = chars.next() {
+                match c {
+                    '\\' => value.extend(chars.next().map(|(_, c)| c)),
+                    '"' => {
+                        end = idx + 1;
+                        break;
+                    }
+                    c => value.push(c),
+                }
+            }
+
+            rest = &quoted[end..];
+            value
+        } else {
+            let end = rest.find(';').unwrap_or(rest.len());
+            let value = rest[..end].trim().to_owned();
+            rest = &rest[end..];
+            value
+        };
+
+        rest = rest.trim_start().strip_prefix(';').unwrap_or(rest);
+
+        match &*key {
+            "name" => name = Some(value),
+            "filename" => file_name = Some(value),
+            // charset'language'percent-encoded, see RFC 5987
+            "filename*" => {
+                file_name_ext = value
+                    .splitn(3, '\'')
+                    .nth(2)
+                    .and_then(|encoded| urlencoding::decode(encoded).ok())
+                    .map(|decoded| decoded.into_owned());
+            }
+            _ => {}
+        }
+    }
+
+    (name, file_name_ext.or(file_name))
+}
+
+/// A single field of a [`Multipart`] body.
+///
+/// The field data can be read as a [`Stream`] of chunks, as an [`AsyncRead`],
+/// or all at once with [`Field::bytes`] or [`Field::text`]. Reading stops at the
+/// end of the field, and fails once the field exceeds [`MultipartLimits::field`].
+pub struct Field<'a> {
+    multipart: &'a mut Multipart,
+    headers: HeaderMap,
+    name: Option<String>,
+    file_name: Option<String>,
+    content_type: Option<mime::Mime>,
+    remaining: u64,
+    /// Partially read chunk, for [`AsyncRead`].
+    chunk: Bytes,
+    done: bool,
+}
+
+impl<'a> Field<'a> {
+    fn new(multipart: &'a mut Multipart, headers: HeaderMap) -> Self {
+        let (name, file_name) = match headers.get(http::header::CONTENT_DISPOSITION).map(HeaderValue::to_str) {
+            Some(Ok(value)) => parse_disposition(value),
+            _ => (None, None),
+        };
+
+        let content_type = headers.typed_get::<ContentType>().map(mime::Mime::from);
+
+        Field {
+            remaining: multipart.field_limit,
+            multipart,
+            headers,
+            name,
+            file_name,
+            content_type,
+            chunk: Bytes::new(),
+            done: false,
+        }
+    }
+
+    /// The field name, from the `Content-Disposition` header.
+    #[must_use]
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// The file name of file fields, from the `Content-Disposition` header.
+    ///
+    /// This is provided by the client as-is, so must be sanitized before being used as a path.
+    #[must_use]
+    pub fn file_name(&self) -> Option<&str> {
+        self.file_name.as_deref()
+    }
+
+    /// The content type of the field, if given.
+    #[must_use]
+    pub fn content_type(&self) -> Option<&mime::Mime> {
+        self.content_type.as_ref()
+    }
+
+    /// All headers of the field.
+    #[must_use]
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// Returns the next chunk of the field data, or `None` at the end of the field.
+    pub async fn chunk(&mut self) -> Result<Option<Bytes>, MultipartError> {
+        future::poll_fn(|cx| self.poll_chunk(cx)).await
+    }
+
+    /// Reads the rest of the field data into memory.
+    pub async fn bytes(mut self) -> Result<Bytes, MultipartError> {
+        let mut data = BytesMut::new();
+
+        while let Some(chunk) = self.chunk().await? {
+            data.extend_from_slice(&chunk);
+        }
+
+        Ok(data.freeze())
+    }
+
+    /// Reads the rest of the field data into memory as UTF-8 text.
+    pub async fn text(self) -> Result<String, MultipartError> {
+        let data = self.bytes().await?;
+
+        Ok(std::str::from_utf8(&data)?.to_owned())
+    }
+
+    fn poll_chunk(&mut self, cx: &mut Context<'_>) -> Poll<Result<Option<Bytes>, MultipartError>> {
+        if !self.chunk.is_empty() {
+            return Poll::Ready(Ok(Some(std::mem::take(&mut self.chunk))));
+        }
+
+        if self.done {
+            return Poll::Ready(Ok(None));
+        }
+
+        let chunk = match futures::ready!(self.multipart.poll_data(cx)) {
+            Ok(Some(chunk)) => chunk,
+            Ok(None) => {
+                self.done = true;
+                return Poll::Ready(Ok(None));
+            }
+            Err(e) => {
+                self.done = true;
+                return Poll::Ready(Err(e));
+            }
+        };
+
+        match self.remaining.checked_sub(chunk.len() as u64) {
+            Some(remaining) => self.remaining = remaining,
+            None => {
+                self.done = true;
+                return Poll::Ready(Err(MultipartError::FieldTooLarge));
+            }
+        }
+
+        Poll::Ready(Ok(Some(chunk)))
+    }
+}
+
+impl Stream for Field<'_> {
+    type Item = Result<Bytes, MultipartError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().poll_chunk(cx).map(Result::transpose)
+    }
+}
+
+impl AsyncRead for Field<'_> {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        if this.chunk.is_empty() {
+            match futures::ready!(this.poll_chunk(cx)) {
+                Ok(Some(chunk)) => this.chunk = chunk,
+                Ok(None) => return Poll::Ready(Ok(())),
+                Err(e) => return Poll::Ready(Err(e.into())),
+            }
+        }
+
+        let n = this.chunk.len().min(buf.remaining());
+        buf.put_slice(&this.chunk.split_to(n));
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::header::CONTENT_TYPE;
+    use tokio::io::AsyncReadExt;
+
+    use super::*;
+
+    const BODY: &str = "preamble\r\n\
+        --XyZ\r\n\
+        Content-Disposition: form-data; name=\"title\"\r\n\
+        \r\n\
+        hello world\r\n\
+        --XyZ\r\n\
+        Content-Disposition: form-data; name=\"file\"; filename=\"a \\\"b\\\".txt\"\r\n\
+        Content-Type: text/plain\r\n\
+        \r\n\
+        line 1 --XyZ not a delimiter\r\n\r\nline 2\r\n\
+        --XyZ\r\n\
+        Content-Disposition: form-data; name=\"skipped\"\r\n\
+        \r\n\
+        unread\r\n\
+        --XyZ--\r\n\
+        epilogue";
+
+    fn request(body: Body, limits: Option<MultipartLimits>) -> Request {
+        let mut req = http::Request::post("/")
+            .header(CONTENT_TYPE, "multipart/form-data; boundary=XyZ")
+            .body(body)
+            .unwrap();
+
+        if let Some(limits) = limits {
+            req.extensions_mut().insert(limits);
+        }
+
+        req
+    }
+
+    /// Splits the body into small chunks, so delimiters span multiple chunks.
+    fn chunked(data: &'static str, size: usize) -> Body {
+        let chunks = data.as_bytes().chunks(size).map(|c| Ok(http_body::Frame::data(Bytes::from_static(c))));
+        Body::stream(futures::stream::iter(chunks.collect::<Vec<_>>()))
+    }
+
+    #[tokio::test]
+    async fn test_multipart() {
+        for size in [1, 3, 7, BODY.len()] {
+            let req = request(chunked(BODY, size), None);
+            let mut multipart = Multipart::from_request(req, &()).await.unwrap();
+
+            let field = multipart.next_field().await.unwrap().unwrap();
+            assert_eq!(field.name(), Some("title"));
+            assert_eq!(field.file_name(), None);
+            assert_eq!(field.text().await.unwrap(), "hello world");
+
+            let mut field = multipart.next_field().await.unwrap().unwrap();
+            assert_eq!(field.name(), Some("file"));
+            assert_eq!(field.file_name(), Some("a \"b\".txt"));
+            assert_eq!(field.content_type(), Some(&mime::TEXT_PLAIN));
+
+            let mut data = String::new();
+            field.read_to_string(&mut data).await.unwrap();
+            assert_eq!(data, "line 1 --XyZ not a delimiter\r\n\r\nline 2");
+
+            let field = multipart.next_field().await.unwrap().unwrap();
+            assert_eq!(field.name(), Some("skipped"));
+            drop(field);
+
+            assert!(multipart.next_field().await.unwrap().is_none());
+            assert!(multipart.next_field().await.unwrap().is_none());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_multipart_errors() {
+        async fn first_field(req: Request) -> Result<Bytes, MultipartError> {
+            let mut multipart = Multipart::from_request(req, &()).await?;
+            multipart.next_field().await?.unwrap().bytes().await
+        }
+
+        let status = |e: MultipartError| e.into_response().status();
+
+        // truncated within a field
+        let req = request(chunked(&BODY[..50], 8), None);
+        assert_eq!(status(first_field(req).await.unwrap_err()), StatusCode::BAD_REQUEST);
+
+        // per-field limit
+        let limits = MultipartLimits { total: 1024, field: 4 };
+        let req = request(chunked(BODY, 8), Some(limits));
+        assert_eq!(
+            status(first_field(req).await.unwrap_err()),
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+
+        // total limit
+        let limits = MultipartLimits { total: 16, field: 1024 };
+        let req = request(chunked(BODY, 8), Some(limits));
+        assert_eq!(
+            status(first_field(req).await.unwrap_err()),
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+
+        // bad boundary
+        let req = http::Request::post("/")
+            .header(CONTENT_TYPE, "multipart/form-data")
+            .body(Body::from(BODY.to_owned()))
+            .unwrap();
+        assert_eq!(status(first_field(req).await.unwrap_err()), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_multipart_large_field() {
+        const SIZE: usize = 4 * 1024 * 1024;
+
+        let mut data = BytesMut::new();
+        data.extend_from_slice(
+            b"--XyZ\r\nContent-Disposition: form-data; name=\"file\"; filename=\"big.bin\"\r\n\r\n",
+        );
+        data.extend((0..SIZE).map(|i| (i % 251) as u8));
+        data.extend_from_slice(
+            b"\r\n--XyZ\r\nContent-Disposition: form-data; name=\"after\"\r\n\r\nnext\r\n--XyZ--\r\n",
+        );
+        let data = data.freeze();
+
+        let body = || {
+            let chunks = (0..data.len()).step_by(64 * 1024).map(|i| {
+                let chunk = data.slice(i..data.len().min(i + 64 * 1024));
+                Ok(http_body::Frame::data(chunk))
+            });
+
+            Body::stream(futures::stream::iter(chunks.collect::<Vec<_>>()))
+        };
+
+        let limits = MultipartLimits {
+            total: 2 * SIZE as u64,
+            field: SIZE as u64,
+        };
+
+        let mut multipart = Multipart::from_request(request(body(), Some(limits)), &()).await.unwrap();
+
+        // streamed to the sink without being buffered, stopping at the boundary
+        let mut field = multipart.next_field().await.unwrap().unwrap();
+        let copied = tokio::io::copy(&mut field, &mut tokio::io::sink()).await.unwrap();
+        assert_eq!(copied, SIZE as u64);
+        drop(field);
+
+        let field = multipart.next_field().await.unwrap().unwrap();
+        assert_eq!(field.name(), Some("after"));
+        assert_eq!(field.text().await.unwrap(), "next");
+
+        // over the per-field cap, the copy fails partway through
+        let limits = MultipartLimits {
+            field: SIZE as u64 / 2,
+            ..limits
+        };
+
+        let mut multipart = Multipart::from_request(request(body(), Some(limits)), &()).await.unwrap();
+        let mut field = multipart.next_field().await.unwrap().unwrap();
+
+        let err = tokio::io::copy(&mut field, &mut tokio::io::sink()).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(matches!(
+            err.into_inner().unwrap().downcast::<MultipartError>().as_deref(),
+            Ok(MultipartError::FieldTooLarge)
+        ));
+    }
+}
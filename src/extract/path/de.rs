@@ -0,0 +1,311 @@
+//! Serde deserializer for named path parameters, used by [`Params`](super::Params)
+//! and the single-parameter [`PathSegments`](super::PathSegments) implementations.
+
+use std::{fmt, sync::Arc};
+
+use serde::de::{self, value::BorrowedStrDeserializer, DeserializeSeed, Visitor};
+
+use super::PathError;
+use crate::params::PercentDecodedStr;
+
+type Params = [(Arc<str>, PercentDecodedStr)];
+
+pub(super) fn deserialize<'de, T: de::Deserialize<'de>>(params: &'de Params) -> Result<T, PathError> {
+    T::deserialize(ParamsDeserializer { params }).map_err(|e| e.0)
+}
+
+#[derive(Debug)]
+struct Error(PathError);
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error(PathError::Deserialize(msg.to_string()))
+    }
+
+    fn missing_field(field: &'static str) -> Self {
+        Error(PathError::MissingSegment(field))
+    }
+}
+
+impl Error {
+    /// Attributes errors from deserializing a single value to its parameter.
+    fn with_key(self, key: &str) -> Self {
+        match self.0 {
+            PathError::Deserialize(message) => Error(PathError::InvalidParameter {
+                key: Arc::from(key),
+                message,
+            }),
+            e => Error(e),
+        }
+    }
+}
+
+/// Deserializes all parameters, as a map or struct by name, as a sequence or tuple by position,
+/// or as a single value if there is exactly one parameter.
+struct ParamsDeserializer<'de> {
+    params: &'de Params,
+}
+
+impl<'de> ParamsDeserializer<'de> {
+    fn single(self) -> Result<ValueDeserializer<'de>, Error> {
+        match self.params {
+            [(key, value)] => Ok(ValueDeserializer { key, value: &value.0 }),
+            _ => Err(Error(PathError::WrongNumberOfParameters {
+                expected: 1,
+                found: self.params.len(),
+            })),
+        }
+    }
+
+    fn expect_len(&self, expected: usize) -> Result<(), Error> {
+        match self.params.len() {
+            found if found == expected => Ok(()),
+            found => Err(Error(PathError::WrongNumberOfParameters { expected, found })),
+        }
+    }
+}
+
+macro_rules! forward_to_single {
+    ($($method:ident)*) => {$(
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            self.single()?.$method(visitor)
+        }
+    )*};
+}
+
+impl<'de> de::Deserializer<'de> for ParamsDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_map(MapAccess {
+            params: self.params.iter(),
+            value: None,
+        })
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_seq(SeqAccess {
+            params: self.params.iter(),
+        })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, Error> {
+        self.expect_len(len)?;
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.single()?.deserialize_enum(name, variants, visitor)
+    }
+
+    forward_to_single! {
+        deserialize_bool deserialize_char deserialize_str deserialize_string
+        deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64 deserialize_i128
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64 deserialize_u128
+        deserialize_f32 deserialize_f64 deserialize_bytes deserialize_byte_buf
+        deserialize_option deserialize_identifier deserialize_ignored_any
+    }
+}
+
+struct MapAccess<'de> {
+    params: std::slice::Iter<'de, (Arc<str>, PercentDecodedStr)>,
+    value: Option<ValueDeserializer<'de>>,
+}
+
+impl<'de> de::MapAccess<'de> for MapAccess<'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, Error> {
+        let Some((key, value)) = self.params.next() else {
+            return Ok(None);
+        };
+
+        self.value = Some(ValueDeserializer { key, value: &value.0 });
+
+        seed.deserialize(BorrowedStrDeserializer::new(key)).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        match self.value.take() {
+            Some(value) => seed.deserialize(value),
+            None => Err(de::Error::custom("value requested before key")),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.params.len())
+    }
+}
+
+struct SeqAccess<'de> {
+    params: std::slice::Iter<'de, (Arc<str>, PercentDecodedStr)>,
+}
+
+impl<'de> de::SeqAccess<'de> for SeqAccess<'de> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, Error> {
+        match self.params.next() {
+            Some((key, value)) => seed.deserialize(ValueDeserializer { key, value: &value.0 }).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.params.len())
+    }
+}
+
+/// Deserializes a single parameter value, parsing it as needed.
+struct ValueDeserializer<'de> {
+    key: &'de str,
+    value: &'de str,
+}
+
+macro_rules! parse_value {
+    ($($method:ident => $visit:ident),*) => {$(
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            match self.value.parse() {
+                Ok(value) => visitor.$visit(value).map_err(|e: Error| e.with_key(self.key)),
+                Err(e) => Err(Error(PathError::InvalidParameter {
+                    key: Arc::from(self.key),
+                    message: format!("{e}: {:?}", self.value),
+                })),
+            }
+        }
+    )*};
+}
+
+impl<'de> de::Deserializer<'de> for ValueDeserializer<'de> {
+    type Error = Error;
+
+    parse_value! {
+        deserialize_bool => visit_bool, deserialize_char => visit_char,
+        deserialize_i8 => visit_i8, deserialize_i16 => visit_i16, deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64, deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8, deserialize_u16 => visit_u16, deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64, deserialize_u128 => visit_u128,
+        deserialize_f32 => visit_f32, deserialize_f64 => visit_f64
+    }
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_borrowed_str(self.value).map_err(|e: Error| e.with_key(self.key))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let key = self.key;
+        visitor.visit_some(self).map_err(|e: Error| e.with_key(key))
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        let key = self.key;
+        visitor.visit_newtype_struct(self).map_err(|e: Error| e.with_key(key))
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor
+            .visit_enum(BorrowedStrDeserializer::new(self.value))
+            .map_err(|e: Error| e.with_key(self.key))
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Error> {
+        Err(Error(PathError::InvalidParameter {
+            key: Arc::from(self.key),
+            message: "cannot deserialize a single path parameter as a sequence or map".to_owned(),
+        }))
+    }
+
+    serde::forward_to_deserialize_any! {
+        str string bytes byte_buf identifier ignored_any unit_struct
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+}
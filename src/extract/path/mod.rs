@@ -0,0 +1,313 @@
+#![allow(private_interfaces)]
+
+use core::str::FromStr;
+use std::{
+    collections::HashMap, error::Error as StdError, future::Future, hash::BuildHasher, marker::PhantomData,
+    sync::Arc,
+};
+
+use crate::{params::UrlParams, RequestParts};
+
+use super::FromRequestParts;
+
+mod de;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct Path<P: PathSegments>(pub P::Output);
+
+pub trait PathSegments: Send + 'static {
+    type Output: Send + 'static;
+
+    #[doc(hidden)]
+    fn parse_segments(segments: &UrlParams) -> Result<Self::Output, PathError>;
+}
+
+pub trait PathSegment: Send + 'static {
+    const NAME: &'static str;
+    type Type: FromStr<Err: StdError> + Send + 'static;
+}
+
+impl<T> PathSegments for T
+where
+    T: PathSegment,
+{
+    type Output = T::Type;
+
+    fn parse_segments(segments: &UrlParams) -> Result<Self::Output, PathError> {
+        let segment = match segments {
+            UrlParams::InvalidUtf8InPathParam { key } => {
+                return Err(PathError::InvalidUtf8InPathParam { key: key.clone() });
+            }
+            UrlParams::Params(params) => {
+                params.iter().find_map(|(k, v)| if k.as_ref() == T::NAME { Some(v) } else { None })
+            }
+        };
+
+        let segment = segment.ok_or(PathError::MissingSegment(T::NAME))?;
+
+        match FromStr::from_str(&segment.0) {
+            Ok(value) => Ok(value),
+            Err(e) => Err(PathError::InvalidSegment(e.to_string())),
+        }
+    }
+}
+
+/// Defines a path segment that can be parsed from the URL path.
+#[macro_export]
+macro_rules! path_segment {
+    ($($vis:vis $name:ident $(as $alt:literal)?: $ty:ty),* $(,)?) => {$crate::paste::paste!{$(
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        $vis struct $name;
+
+        impl $crate::extract::path::PathSegment for $name {
+            const NAME: &'static str = ($($alt,)? stringify!([<$name:snake>]),).0;
+            type Type = $ty;
+        }
+    )*}};
+}
+
+macro_rules! decl_segments {
+    ($($t:ident),*) => {
+        impl<$($t: PathSegment,)*> PathSegments for ($($t,)*) {
+            type Output = ($($t::Type,)*);
+
+            fn parse_segments(segments: &$crate::params::UrlParams) -> Result<Self::Output, PathError> {
+                Ok(($($t::parse_segments(segments)?,)*))
+            }
+        }
+    };
+}
+
+all_the_tuples_no_last_special_case!(decl_segments);
+
+impl PathSegments for () {
+    type Output = ();
+
+    fn parse_segments(_segments: &UrlParams) -> Result<Self::Output, PathError> {
+        Ok(())
+    }
+}
+
+/// Captures all named path parameters as a map of name to value.
+///
+/// Note that the map does not preserve the order in which the parameters appear in the path.
+impl<H> PathSegments for HashMap<String, String, H>
+where
+    H: BuildHasher + Default + Send + 'static,
+{
+    type Output = Self;
+
+    fn parse_segments(segments: &UrlParams) -> Result<Self::Output, PathError> {
+        match segments {
+            UrlParams::InvalidUtf8InPathParam { key } => {
+                Err(PathError::InvalidUtf8InPathParam { key: key.clone() })
+            }
+            UrlParams::Params(params) => {
+                Ok(params.iter().map(|(k, v)| (String::from(&**k), String::from(&*v.0))).collect())
+            }
+        }
+    }
+}
+
+/// Deserializes all named path parameters into `T` with serde, by name for structs and maps,
+/// or by position for tuples, parsing each parameter as needed by its field type.
+///
+/// This is a marker for use with [`Path`], as `Path<Params<T>>`, which extracts as `Path(T)`.
+///
+/// ```rust,ignore
+/// #[derive(serde::Deserialize)]
+/// struct Repo {
+///     org: String,
+///     repo: u32,
+/// }
+///
+/// // GET /{org}/{repo}
+/// async fn repo(Path(repo): Path<Params<Repo>>) -> String {
+///     format!("{}/{}", repo.org, repo.repo)
+/// }
+/// ```
+///
+/// For routes with a single parameter, `Path<String>`, `Path<u64>`, `Path<(u64,)>` and so on
+/// can be used directly.
+pub struct Params<T>(PhantomData<fn() -> T>);
+
+fn params(segments: &UrlParams) -> Result<&[(Arc<str>, crate::params::PercentDecodedStr)], PathError> {
+    match segments {
+        UrlParams::InvalidUtf8InPathParam { key } => Err(PathError::InvalidUtf8InPathParam { key: key.clone() }),
+        UrlParams::Params(params) => Ok(params),
+    }
+}
+
+impl<T> PathSegments for Params<T>
+where
+    T: serde::de::DeserializeOwned + Send + 'static,
+{
+    type Output = T;
+
+    fn parse_segments(segments: &UrlParams) -> Result<Self::Output, PathError> {
+        de::deserialize(params(segments)?)
+    }
+}
+
+macro_rules! impl_single_segment {
+    ($($ty:ty),*) => {$(
+        /// Parses the single path parameter of the route.
+        impl PathSegments for $ty {
+            type Output = Self;
+
+            fn parse_segments(segments: &UrlParams) -> Result<Self::Output, PathError> {
+                de::deserialize(params(segments)?)
+            }
+        }
+
+        /// Parses the single path parameter of the route.
+        impl PathSegments for ($ty,) {
+            type Output = Self;
+
+            fn parse_segments(segments: &UrlParams) -> Result<Self::Output, PathError> {
+                de::deserialize(params(segments)?)
+            }
+        }
+    )*};
+}
+
+impl_single_segment!(String, bool, char, u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64);
+
+#[derive(Debug, thiserror::Error)]
+pub enum PathError {
+    #[error("missing path parameters")]
+    MissingParameters,
+
+    #[error("missing segment: {0}")]
+    MissingSegment(&'static str),
+
+    #[error("invalid segment: {0}")]
+    InvalidSegment(String),
+
+    #[error("invalid UTF-8 in path parameter: {key}")]
+    InvalidUtf8InPathParam { key: Arc<str> },
+
+    #[error("invalid path parameter `{key}`: {message}")]
+    InvalidParameter { key: Arc<str>, message: String },
+
+    #[error("expected {expected} path parameters, found {found}")]
+    WrongNumberOfParameters { expected: usize, found: usize },
+
+    #[error("invalid path parameters: {0}")]
+    Deserialize(String),
+}
+
+impl<P, S> FromRequestParts<S> for Path<P>
+where
+    P: PathSegments,
+{
+    type Rejection = PathError;
+
+    fn from_request_parts(
+        parts: &mut RequestParts,
+        _state: &S,
+    ) -> impl Future<Output = Result<Self, Self::Rejection>> + Send {
+        let params = parts.extensions.get::<UrlParams>().ok_or(PathError::MissingParameters);
+
+        async move { Ok(Path(P::parse_segments(params?)?)) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{body::Body, params::PercentDecodedStr};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_path() {
+        // https://example.com/{user_id}/{party_id}/{something}
+
+        // you define these manually before declaring your routes
+        path_segment! {
+            pub UserId as "user_id": u8,
+            pub PartyId: u16,
+            pub Something: u32,
+        }
+
+        // this is done automatically during routing, just here to simulate it for testing
+        #[rustfmt::skip]
+        let (mut parts, _) = http::request::Builder::new().extension(UrlParams::Params(vec![
+            (Arc::from("user_id"), PercentDecodedStr::new("1").unwrap()),
+            (Arc::from("party_id"), PercentDecodedStr::new("2").unwrap()),
+            (Arc::from("something"), PercentDecodedStr::new("3").unwrap()),
+        ])).body(Body::empty()).unwrap().into_parts();
+
+        // this is what you would do in your handler
+        fn fixture(Path((user_id, party_id, something)): Path<(UserId, PartyId, Something)>) {
+            assert_eq!(user_id, 1);
+            assert_eq!(party_id, 2);
+            assert_eq!(something, 3);
+        }
+
+        // test the handler
+        fixture(Path::from_request_parts(&mut parts, &()).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_path_map() {
+        use crate::{service::Service, Response, Router};
+
+        let mut router = Router::<(), Response>::with_state(());
+
+        router.get("/{a}/{b}", |Path(params): Path<HashMap<String, String>>| async move {
+            let mut params = Vec::from_iter(params);
+            params.sort();
+
+            format!("{params:?}")
+        });
+
+        let req = http::Request::get("/x/y").body(Body::empty()).unwrap();
+        let body = router.call(req).await.unwrap().into_body().to_string().await.unwrap();
+
+        assert_eq!(body, r#"[("a", "x"), ("b", "y")]"#);
+    }
+
+    #[tokio::test]
+    async fn test_path_params() {
+        use crate::{service::Service, Response, Router};
+
+        #[derive(serde::Deserialize)]
+        struct Repo {
+            org: String,
+            repo: u32,
+        }
+
+        let mut router = Router::<(), Response>::with_state(());
+
+        router.get("/repos/{org}/{repo}", |Path(repo): Path<Params<Repo>>| async move {
+            format!("{}/{}", repo.org, repo.repo)
+        });
+        router.get("/users/{id}", |Path(id): Path<u64>| async move { format!("user {id}") });
+        router.get("/tuple/{id}", |Path((id,)): Path<(u64,)>| async move {
+            format!("tuple {id}")
+        });
+        router.get("/names/{name}", |Path(name): Path<String>| async move { name });
+
+        async fn call(router: &Router<(), Response>, uri: &str) -> (http::StatusCode, String) {
+            let req = http::Request::get(uri).body(Body::empty()).unwrap();
+            let resp = router.call(req).await.unwrap_or_else(crate::IntoResponse::into_response);
+
+            (resp.status(), resp.into_body().to_string().await.unwrap())
+        }
+
+        assert_eq!(call(&router, "/repos/rust-lang/42").await.1, "rust-lang/42");
+        assert_eq!(call(&router, "/users/7").await.1, "user 7");
+        assert_eq!(call(&router, "/tuple/8").await.1, "tuple 8");
+        assert_eq!(call(&router, "/names/a%20b").await.1, "a b");
+
+        let (status, body) = call(&router, "/repos/rust-lang/nope").await;
+        assert_eq!(status, http::StatusCode::BAD_REQUEST);
+        assert!(body.contains("`repo`"), "{body}");
+
+        let (status, body) = call(&router, "/users/x").await;
+        assert_eq!(status, http::StatusCode::BAD_REQUEST);
+        assert!(body.contains("`id`"), "{body}");
+    }
+}
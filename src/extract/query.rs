@@ -2,6 +2,17 @@ use crate::{form_impl, Error, RequestParts};
 
 use super::FromRequestParts;
 
+/// Extracts the URI query string, deserialized as `application/x-www-form-urlencoded`.
+///
+/// A missing query string is treated as empty, the same as [`Form`](crate::body::Form) does for
+/// `GET` requests, so absent keys are handled by `T` alone: `Option` fields become `None`, fields
+/// with `#[serde(default)]` take their default, and other fields are rejected as missing.
+/// This is the same regardless of which form backend is enabled.
+///
+/// Previously a missing query string was rejected with the now deprecated [`Error::MissingQuery`],
+/// so an `Option<Query<T>>` extractor now gives `Some` for requests without a query string whenever
+/// `T` can be deserialized from an empty one. Use a required field or check
+/// [`Uri::query`](http::Uri::query) directly to tell the two apart.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[repr(transparent)]
 pub struct Query<T>(pub T);
@@ -25,10 +36,102 @@ where
         parts: &mut RequestParts,
         _state: &S,
     ) -> impl core::future::Future<Output = Result<Self, Self::Rejection>> + Send {
-        core::future::ready(match parts.uri.query().map(form_impl::from_str) {
-            Some(Ok(value)) => Ok(Query(value)),
-            Some(Err(e)) => Err(e.into()),
-            None => Err(Error::MissingQuery),
+        core::future::ready(match form_impl::from_str(parts.uri.query().unwrap_or_default()) {
+            Ok(value) => Ok(Query(value)),
+            Err(e) => Err(e.into()),
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{body::Body, body::Form, FromRequest};
+
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    struct Q {
+        page: Option<u32>,
+        #[serde(default)]
+        size: u32,
+    }
+
+    #[derive(Debug, serde::Deserialize)]
+    struct Required {
+        page: u32,
+    }
+
+    #[tokio::test]
+    async fn test_query_defaults() {
+        for uri in ["/", "/?", "/?other=1"] {
+            let (mut parts, _) = http::Request::get(uri).body(()).unwrap().into_parts();
+
+            let Query(q) = Query::<Q>::from_request_parts(&mut parts, &()).await.unwrap();
+            assert_eq!(q, Q { page: None, size: 0 });
+
+            let req = http::Request::get(uri).body(Body::empty()).unwrap();
+
+            let Form(q) = Form::<Q>::from_request(req, &()).await.unwrap();
+            assert_eq!(q, Q { page: None, size: 0 });
+        }
+
+        let (mut parts, _) = http::Request::get("/?page=2&size=10").body(()).unwrap().into_parts();
+        let Query(q) = Query::<Q>::from_request_parts(&mut parts, &()).await.unwrap();
+        assert_eq!(
+            q,
+            Q {
+                page: Some(2),
+                size: 10
+            }
+        );
+
+        // required fields are still required
+        let (mut parts, _) = http::Request::get("/").body(()).unwrap().into_parts();
+        assert!(Query::<Required>::from_request_parts(&mut parts, &()).await.is_err());
+    }
+
+    /// Checks the semantics of each form backend directly, as only one of them is used by [`Query`] and [`Form`].
+    macro_rules! check_backend {
+        ($backend:ident) => {{
+            for query in ["", "other=1"] {
+                assert_eq!(
+                    $backend::from_str::<Q>(query).unwrap(),
+                    Q { page: None, size: 0 },
+                    "{query}"
+                );
+                assert!($backend::from_str::<Required>(query).is_err(), "{query}");
+            }
+
+            assert_eq!(
+                $backend::from_str::<Q>("page=2&size=10").unwrap(),
+                Q {
+                    page: Some(2),
+                    size: 10
+                }
+            );
+        }};
+    }
+
+    #[test]
+    fn test_form_backends() {
+        check_backend!(serde_urlencoded);
+
+        #[cfg(feature = "serde_html_form")]
+        check_backend!(serde_html_form);
+    }
+
+    #[tokio::test]
+    async fn test_optional_query() {
+        // a missing query string is no longer `None` if `T` accepts an empty query
+        let (mut parts, _) = http::Request::get("/").body(()).unwrap().into_parts();
+        let q = Option::<Query<Q>>::from_request_parts(&mut parts, &()).await.unwrap();
+        assert_eq!(q.map(|Query(q)| q), Some(Q { page: None, size: 0 }));
+
+        let (mut parts, _) = http::Request::get("/").body(()).unwrap().into_parts();
+        let q = Option::<Query<Required>>::from_request_parts(&mut parts, &()).await.unwrap();
+        assert!(q.is_none());
+
+        let (mut parts, _) = http::Request::get("/?page=3").body(()).unwrap().into_parts();
+        let q = Option::<Query<Required>>::from_request_parts(&mut parts, &()).await.unwrap();
+        assert_eq!(q.map(|Query(q)| q.page), Some(3));
+    }
+}
@@ -0,0 +1,139 @@
+use core::future::Future;
+use core::marker::PhantomData;
+use core::ops::Deref;
+
+use crate::{error::ErrorResponse, IntoResponse, Request, RequestParts};
+
+use super::{FromRequest, FromRequestParts};
+
+/// Extractor wrapper that converts the rejection of `T` into `R`, which is then
+/// returned as the response instead of the default rejection response.
+///
+/// This allows different endpoints to use different error formats for the same extractor:
+///
+/// ```rust,ignore
+/// struct ApiError(String);
+///
+/// impl From<PathError> for ApiError {
+///     fn from(e: PathError) -> Self {
+///         ApiError(format!("invalid user id: {e}"))
+///     }
+/// }
+///
+/// impl IntoResponse for ApiError { /* ... */ }
+///
+/// async fn get_user(WithRejection(Path(id), _): WithRejection<Path<UserId>, ApiError>) { /* ... */ }
+/// ```
+pub struct WithRejection<T, R>(pub T, pub PhantomData<fn() -> R>);
+
+impl<T, R> WithRejection<T, R> {
+    /// Returns the inner extracted value.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T, R> Deref for WithRejection<T, R> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T: core::fmt::Debug, R> core::fmt::Debug for WithRejection<T, R> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_tuple("WithRejection").field(&self.0).finish()
+    }
+}
+
+impl<S, T, R> FromRequestParts<S> for WithRejection<T, R>
+where
+    S: Sync,
+    T: FromRequestParts<S>,
+    R: From<T::Rejection> + IntoResponse + 'static,
+{
+    type Rejection = ErrorResponse;
+
+    fn from_request_parts(
+        parts: &mut RequestParts,
+        state: &S,
+    ) -> impl Future<Output = Result<Self, Self::Rejection>> + Send {
+        async move {
+            match T::from_request_parts(parts, state).await {
+                Ok(value) => Ok(WithRejection(value, PhantomData)),
+                Err(rejection) => Err(ErrorResponse::new(R::from(rejection))),
+            }
+        }
+    }
+}
+
+impl<S, T, R> FromRequest<S> for WithRejection<T, R>
+where
+    S: Sync,
+    T: FromRequest<S>,
+    R: From<T::Rejection> + IntoResponse + 'static,
+{
+    type Rejection = ErrorResponse;
+
+    fn from_request(req: Request, state: &S) -> impl Future<Output = Result<Self, Self::Rejection>> + Send {
+        async move {
+            match T::from_request(req, state).await {
+                Ok(value) => Ok(WithRejection(value, PhantomData)),
+                Err(rejection) => Err(ErrorResponse::new(R::from(rejection))),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+
+    use super::*;
+    use crate::{
+        body::Body,
+        extract::{path::PathError, Path},
+        service::Service,
+        Response, Router,
+    };
+
+    /// Domain error with its own response format.
+    struct ApiError(String);
+
+    impl From<PathError> for ApiError {
+        fn from(e: PathError) -> Self {
+            ApiError(format!("invalid user id: {e}"))
+        }
+    }
+
+    impl IntoResponse for ApiError {
+        fn into_response(self) -> Response {
+            (format!(r#"{{"error":"{}"}}"#, self.0), StatusCode::UNPROCESSABLE_ENTITY).into_response()
+        }
+    }
+
+    crate::path_segment! {
+        UserId as "id": u64,
+    }
+
+    #[tokio::test]
+    async fn test_with_rejection() {
+        let mut router = Router::<(), Response>::with_state(());
+        router.get(
+            "/users/{id}",
+            |WithRejection(Path(id), _): WithRejection<Path<UserId>, ApiError>| async move { format!("user {id}") },
+        );
+
+        let req = http::Request::get("/users/42").body(Body::empty()).unwrap();
+        let resp = router.call(req).await.unwrap();
+        assert_eq!(resp.into_body().to_string().await.unwrap(), "user 42");
+
+        let req = http::Request::get("/users/abc").body(Body::empty()).unwrap();
+        let resp = router.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(resp.into_body().to_string().await.unwrap().starts_with(r#"{"error":"invalid user id: "#));
+    }
+}
@@ -0,0 +1,254 @@
+//! Request IDs, for correlating logs of a request across services.
+//!
+//! The [`RequestIdLayer`] reuses the `X-Request-Id` header of incoming requests, or generates
+//! a new ID, adding it to the request extensions as a [`RequestId`] and echoing it back on the response.
+
+use std::{
+    fmt::{self, Debug, Display},
+    future::{self, Future},
+    hash::{BuildHasher, Hasher},
+    ops::Deref,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, LazyLock,
+    },
+};
+
+use futures::FutureExt as _;
+use http::{header::HeaderName, HeaderValue, Request};
+
+use crate::{extract::FromRequestParts, service::ServiceFuture, Layer, RequestParts, Response, Service};
+
+/// The default `X-Request-Id` header name.
+pub const X_REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+/// The ID of the current request, added to the request extensions by the [`RequestIdLayer`].
+///
+/// This can be extracted directly or through `Extension<RequestId>`, and is rejected with
+/// a `500 Internal Server Error` if the [`RequestIdLayer`] was not applied.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct RequestId(pub Arc<str>);
+
+impl Deref for RequestId {
+    type Target = str;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Debug for RequestId {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&self.0, f)
+    }
+}
+
+impl Display for RequestId {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
+impl<S> FromRequestParts<S> for RequestId {
+    type Rejection = crate::Error;
+
+    fn from_request_parts(
+        parts: &mut RequestParts,
+        _: &S,
+    ) -> impl Future<Output = Result<Self, Self::Rejection>> + Send {
+        future::ready(parts.extensions.get::<RequestId>().cloned().ok_or(crate::Error::MissingExtension))
+    }
+}
+
+/// Generates a random 128-bit ID as 32 lowercase hex digits.
+///
+/// This is unique and unpredictable enough for tracing, but not suitable as a secret.
+#[must_use]
+pub fn random_request_id() -> RequestId {
+    static STATE: LazyLock<std::hash::RandomState> = LazyLock::new(std::hash::RandomState::new);
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+
+    let half = |i: u64| {
+        let mut hasher = STATE.build_hasher();
+        hasher.write_u64(n);
+        hasher.write_u64(i);
+        hasher.finish()
+    };
+
+    RequestId(Arc::from(format!("{:016x}{:016x}", half(0), half(1))))
+}
+
+/// Maximum length of an incoming request ID to reuse, longer IDs are replaced.
+const MAX_INCOMING_LEN: usize = 128;
+
+/// [`Layer`] that adds a [`RequestId`] extension to requests and echoes it back in a response header.
+///
+/// The ID of an incoming `X-Request-Id` header is reused if present and reasonable, being at most
+/// 128 visible ASCII characters, otherwise a new one is generated, by default with [`random_request_id`].
+#[derive(Clone)]
+#[must_use]
+pub struct RequestIdLayer {
+    header: HeaderName,
+    generate: Arc<dyn Fn() -> RequestId + Send + Sync + 'static>,
+}
+
+/// The service created by the [`RequestIdLayer`].
+#[derive(Clone)]
+pub struct PropagateRequestId<S> {
+    inner: S,
+    layer: RequestIdLayer,
+}
+
+impl Default for RequestIdLayer {
+    fn default() -> Self {
+        RequestIdLayer {
+            header: X_REQUEST_ID,
+            generate: Arc::new(random_request_id),
+        }
+    }
+}
+
+impl Debug for RequestIdLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RequestIdLayer").field("header", &self.header).finish_non_exhaustive()
+    }
+}
+
+impl<S: Debug> Debug for PropagateRequestId<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PropagateRequestId")
+            .field("inner", &self.inner)
+            .field("layer", &self.layer)
+            .finish()
+    }
+}
+
+impl RequestIdLayer {
+    /// Creates a new layer using the `X-Request-Id` header and random IDs.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the header to read the request ID from and echo it back in, instead of `X-Request-Id`.
+    pub fn with_header(mut self, header: HeaderName) -> Self {
+        self.header = header;
+        self
+    }
+
+    /// Sets the function used to generate new request IDs.
+    ///
+    /// IDs which are not valid header values are still added to the request, but not echoed back.
+    pub fn with_generator<F, R>(mut self, generate: F) -> Self
+    where
+        F: Fn() -> R + Send + Sync + 'static,
+        R: Into<Arc<str>>,
+    {
+        self.generate = Arc::new(move || RequestId(generate().into()));
+        self
+    }
+
+    fn request_id<B>(&self, req: &Request<B>) -> (RequestId, Option<HeaderValue>) {
+        if let Some(value) = req.headers().get(&self.header) {
+            if let Ok(id) = value.to_str() {
+                if !id.is_empty() && id.len() <= MAX_INCOMING_LEN && id.bytes().all(|b| b.is_ascii_graphic()) {
+                    return (RequestId(Arc::from(id)), Some(value.clone()));
+                }
+            }
+        }
+
+        let id = (self.generate)();
+        let value = HeaderValue::from_str(&id).ok();
+
+        (id, value)
+    }
+}
+
+impl<S> Layer<S> for RequestIdLayer {
+    type Service = PropagateRequestId<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        PropagateRequestId {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+impl<B, S> Service<Request<B>> for PropagateRequestId<S>
+where
+    S: Service<Request<B>, Response = Response>,
+{
+    type Response = Response;
+    type Error = S::Error;
+
+    #[inline]
+    fn call(&self, mut req: Request<B>) -> impl ServiceFuture<Self::Response, Self::Error> {
+        let (id, value) = self.layer.request_id(&req);
+
+        req.extensions_mut().insert(id);
+
+        self.inner.call(req).map(move |res| {
+            res.map(|mut resp| {
+                if let Some(value) = value {
+                    resp.headers_mut().insert(self.layer.header.clone(), value);
+                }
+
+                resp
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{body::Body, extract::Extension, Router};
+
+    #[tokio::test]
+    async fn test_request_id() {
+        let mut router = Router::<(), Response>::with_state(());
+        router.get("/", |id: RequestId, Extension(ext): Extension<RequestId>| async move {
+            assert_eq!(id, ext);
+            id.to_string()
+        });
+
+        let service = RequestIdLayer::new().layer(router);
+
+        let req = Request::get("/").header(X_REQUEST_ID, "abc-123").body(Body::empty()).unwrap();
+        let resp = service.call(req).await.unwrap();
+
+        assert_eq!(resp.headers()[X_REQUEST_ID], "abc-123");
+        assert_eq!(resp.into_body().to_string().await.unwrap(), "abc-123");
+
+        // generated when missing or unreasonable
+        let req = Request::get("/").header(X_REQUEST_ID, "a b").body(Body::empty()).unwrap();
+        let resp = service.call(req).await.unwrap();
+
+        let id = resp.headers()[X_REQUEST_ID].to_str().unwrap().to_owned();
+        assert_eq!(id.len(), 32);
+        assert!(id.bytes().all(|b| b.is_ascii_hexdigit()));
+        assert_eq!(resp.into_body().to_string().await.unwrap(), id);
+
+        assert_ne!(random_request_id(), random_request_id());
+    }
+
+    #[tokio::test]
+    async fn test_request_id_custom() {
+        let mut router = Router::<(), Response>::with_state(());
+        router.get("/", |id: RequestId| async move { id.to_string() });
+
+        let header = HeaderName::from_static("x-correlation-id");
+        let service = RequestIdLayer::new().with_header(header.clone()).with_generator(|| "fixed").layer(router);
+
+        let resp = service.call(Request::get("/").body(Body::empty()).unwrap()).await.unwrap();
+
+        assert_eq!(resp.headers()[&header], "fixed");
+        assert!(!resp.headers().contains_key(X_REQUEST_ID));
+    }
+}
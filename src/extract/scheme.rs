@@ -1,7 +1,7 @@
 use core::str::FromStr;
 use http::{
     uri::{InvalidUri, Scheme},
-    HeaderMap, HeaderName,
+    HeaderMap, HeaderName, Uri,
 };
 
 use crate::{IntoResponse, Response};
@@ -26,49 +26,61 @@ impl IntoResponse for SchemeError {
     }
 }
 
-impl<S> FromRequestParts<S> for Scheme {
-    type Rejection = SchemeError;
+/// Determines the scheme of a request, in order of precedence from a [`Scheme`] request extension,
+/// the `Forwarded` header, the `X-Forwarded-Proto` header, or the request URI.
+pub(crate) fn request_scheme(
+    headers: &HeaderMap,
+    uri: &Uri,
+    extensions: &http::Extensions,
+) -> Result<Scheme, SchemeError> {
+    // borrowed from https://github.com/tokio-rs/axum/pull/2507
+    fn parse_forwarded(headers: &HeaderMap) -> Option<&str> {
+        // if there are multiple `Forwarded` `HeaderMap::get` will return the first one
+        let forwarded_values = headers.get(http::header::FORWARDED)?.to_str().ok()?;
 
-    fn from_request_parts(
-        parts: &mut http::request::Parts,
-        _state: &S,
-    ) -> impl std::future::Future<Output = Result<Self, Self::Rejection>> + Send {
-        // borrowed from https://github.com/tokio-rs/axum/pull/2507
-        fn parse_forwarded(headers: &HeaderMap) -> Option<&str> {
-            // if there are multiple `Forwarded` `HeaderMap::get` will return the first one
-            let forwarded_values = headers.get(http::header::FORWARDED)?.to_str().ok()?;
+        // get the first set of values
+        let first_value = forwarded_values.split(',').next()?;
 
-            // get the first set of values
-            let first_value = forwarded_values.split(',').next()?;
+        // find the value of the `proto` field
+        first_value.split(';').find_map(|pair| {
+            let (key, value) = pair.split_once('=')?;
+            key.trim().eq_ignore_ascii_case("proto").then(|| value.trim().trim_matches('"'))
+        })
+    }
 
-            // find the value of the `proto` field
-            first_value.split(';').find_map(|pair| {
-                let (key, value) = pair.split_once('=')?;
-                key.trim().eq_ignore_ascii_case("proto").then(|| value.trim().trim_matches('"'))
-            })
-        }
+    // Set by the server or a layer that knows better
+    if let Some(scheme) = extensions.get::<Scheme>() {
+        return Ok(scheme.clone());
+    }
+
+    if let Some(scheme) = parse_forwarded(headers) {
+        return Ok(Scheme::from_str(scheme)?);
+    }
 
-        async move {
-            if let Some(scheme) = parse_forwarded(&parts.headers) {
-                return Ok(Scheme::from_str(scheme)?);
-            }
+    // X-Forwarded-Proto
+    if let Some(scheme) = headers
+        .get(const { HeaderName::from_static("x-forwarded-proto") })
+        .map(|scheme| Scheme::try_from(scheme.as_bytes()))
+        .transpose()?
+    {
+        return Ok(scheme);
+    }
 
-            // X-Forwarded-Proto
-            if let Some(scheme) = parts
-                .headers
-                .get(const { HeaderName::from_static("x-forwarded-proto") })
-                .map(|scheme| Scheme::try_from(scheme.as_bytes()))
-                .transpose()?
-            {
-                return Ok(scheme);
-            }
+    // From parts of an HTTP/2 request
+    if let Some(scheme) = uri.scheme() {
+        return Ok(scheme.clone());
+    }
 
-            // From parts of an HTTP/2 request
-            if let Some(scheme) = parts.uri.scheme() {
-                return Ok(scheme.clone());
-            }
+    Err(SchemeError::MissingScheme)
+}
 
-            Err(SchemeError::MissingScheme)
-        }
+impl<S> FromRequestParts<S> for Scheme {
+    type Rejection = SchemeError;
+
+    fn from_request_parts(
+        parts: &mut http::request::Parts,
+        _state: &S,
+    ) -> impl std::future::Future<Output = Result<Self, Self::Rejection>> + Send {
+        core::future::ready(request_scheme(&parts.headers, &parts.uri, &parts.extensions))
     }
 }
@@ -0,0 +1,182 @@
+//! Generic connection upgrades, such as for `CONNECT` tunnels.
+
+use std::future::Future;
+
+use http::{header, HeaderValue, Method, StatusCode};
+use hyper::upgrade::{OnUpgrade, Upgraded};
+use hyper_util::rt::TokioIo;
+
+use crate::{IntoResponse, RequestParts, Response};
+
+use super::FromRequestParts;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum UpgradeError {
+    #[error("Connection cannot be upgraded")]
+    NotUpgradable,
+    #[error("Missing Upgrade header")]
+    MissingUpgrade,
+}
+
+impl IntoResponse for UpgradeError {
+    fn into_response(self) -> Response {
+        IntoResponse::into_response(match self {
+            UpgradeError::NotUpgradable => ("Connection cannot be upgraded", StatusCode::BAD_REQUEST),
+            UpgradeError::MissingUpgrade => ("Missing Upgrade header", StatusCode::BAD_REQUEST),
+        })
+    }
+}
+
+/// Takes over the underlying connection once the response has been sent, such as to tunnel
+/// a `CONNECT` request to an upstream server, or to speak a custom protocol.
+///
+/// For `CONNECT` requests, the upgrade is completed by responding with `200 OK`. For any other
+/// method, the request must include an `Upgrade` header, and the upgrade is completed by
+/// responding with `101 Switching Protocols` for the requested protocol.
+///
+/// `CONNECT` requests in authority-form, like `CONNECT example.com:443`, are routed as `/`,
+/// so `router.connect("/", handler)` receives all of them, with the target given by
+/// [`Uri::authority`](http::Uri::authority).
+///
+/// WebSockets should use [`Ws`](crate::ws::Ws) instead, which handles the handshake.
+///
+/// ```rust,ignore
+/// router.connect("/", |upgrade: Upgrade, uri: Uri| async move {
+///     let mut upstream = TcpStream::connect(uri.authority().unwrap().as_str()).await?;
+///
+///     Ok::<_, Error>(upgrade.on_upgrade(|io| async move {
+///         if let Ok(mut io) = io {
+///             _ = tokio::io::copy_bidirectional(&mut io, &mut upstream).await;
+///         }
+///     }))
+/// });
+/// ```
+pub struct Upgrade {
+    on_upgrade: OnUpgrade,
+    /// `None` for `CONNECT`
+    protocol: Option<HeaderValue>,
+}
+
+impl<S> FromRequestParts<S> for Upgrade {
+    type Rejection = UpgradeError;
+
+    fn from_request_parts(
+        parts: &mut RequestParts,
+        _state: &S,
+    ) -> impl Future<Output = Result<Self, Self::Rejection>> + Send {
+        core::future::ready('upgrade: {
+            let protocol = match parts.method {
+                Method::CONNECT => None,
+                _ => match parts.headers.get(header::UPGRADE) {
+                    Some(protocol) => Some(protocol.clone()),
+                    None => break 'upgrade Err(UpgradeError::MissingUpgrade),
+                },
+            };
+
+            match parts.extensions.remove::<OnUpgrade>() {
+                Some(on_upgrade) => Ok(Upgrade { on_upgrade, protocol }),
+                None => Err(UpgradeError::NotUpgradable),
+            }
+        })
+    }
+}
+
+impl Upgrade {
+    /// Responds to complete the upgrade, then calls `func` with the upgraded connection
+    /// in a new task.
+    ///
+    /// The response can be extended with additional headers, but changing the status code
+    /// will prevent the upgrade, in which case `func` is given an error.
+    #[must_use]
+    pub fn on_upgrade<F, Fut>(self, func: F) -> impl IntoResponse
+    where
+        F: FnOnce(Result<TokioIo<Upgraded>, hyper::Error>) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send,
+    {
+        let on_upgrade = self.on_upgrade;
+
+        tokio::spawn(async move {
+            let io = on_upgrade.await.map(TokioIo::new);
+
+            if let Err(ref e) = io {
+                log::error!("upgrade error: {e}");
+            }
+
+            func(io).await;
+        });
+
+        match self.protocol {
+            None => StatusCode::OK.into_response(),
+            Some(protocol) => IntoResponse::into_response((
+                StatusCode::SWITCHING_PROTOCOLS,
+                [
+                    (header::CONNECTION, const { HeaderValue::from_static("upgrade") }),
+                    (header::UPGRADE, protocol),
+                ],
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+    use crate::{
+        layers::{cloneable::Cloneable, convert_body::ConvertBody},
+        serve::Server,
+        Layer, Router,
+    };
+
+    #[tokio::test]
+    async fn test_connect_upgrade() {
+        let mut router = Router::<(), Response>::with_state(());
+        router.connect("/", |upgrade: Upgrade, uri: http::Uri| async move {
+            let target = uri.authority().map(|a| a.to_string()).unwrap_or_default();
+
+            upgrade.on_upgrade(move |io| async move {
+                let mut io = io.unwrap();
+
+                // echo back the target, then whatever the client sends
+                io.write_all(target.as_bytes()).await.unwrap();
+
+                let mut buf = [0; 4];
+                io.read_exact(&mut buf).await.unwrap();
+                io.write_all(&buf).await.unwrap();
+            })
+        });
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = Server::from_tcp(listener);
+        let handle = server.handle();
+
+        let serving = tokio::spawn(server.serve(Cloneable::default().layer(ConvertBody::default().layer(router))));
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"CONNECT example.com:443 HTTP/1.1\r\nHost: example.com:443\r\n\r\n")
+            .await
+            .unwrap();
+
+        // read the response head
+        let mut head = Vec::new();
+        while !head.ends_with(b"\r\n\r\n") {
+            head.push(stream.read_u8().await.unwrap());
+        }
+
+        assert!(head.starts_with(b"HTTP/1.1 200 OK"));
+
+        stream.write_all(b"ping").await.unwrap();
+
+        let mut rest = [0; 19];
+        stream.read_exact(&mut rest).await.unwrap();
+        assert_eq!(&rest, b"example.com:443ping");
+
+        drop(stream);
+
+        handle.shutdown();
+        serving.await.unwrap().unwrap();
+    }
+}
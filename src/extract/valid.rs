@@ -0,0 +1,268 @@
+use core::future::Future;
+use core::ops::Deref;
+use std::borrow::Cow;
+use std::fmt::{self, Write as _};
+
+use http::{header::CONTENT_TYPE, HeaderValue, StatusCode};
+
+use crate::{error::ErrorResponse, IntoResponse, Request, RequestParts, Response};
+
+use super::{FromRequest, FromRequestParts};
+
+/// Types that can check their own invariants after being extracted, see [`Valid`].
+pub trait Validate {
+    fn validate(&self) -> Result<(), ValidationErrors>;
+}
+
+/// A single invalid field, as part of [`ValidationErrors`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldError {
+    pub field: Cow<'static, str>,
+    pub message: Cow<'static, str>,
+}
+
+/// A list of invalid fields, responding with `422 Unprocessable Entity` and a JSON body such as
+/// `{"errors":[{"field":"name","message":"must not be empty"}]}`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[must_use]
+pub struct ValidationErrors(pub Vec<FieldError>);
+
+impl ValidationErrors {
+    pub const fn new() -> Self {
+        ValidationErrors(Vec::new())
+    }
+
+    /// Adds an error for the given field.
+    pub fn add(&mut self, field: impl Into<Cow<'static, str>>, message: impl Into<Cow<'static, str>>) {
+        self.0.push(FieldError {
+            field: field.into(),
+            message: message.into(),
+        });
+    }
+
+    /// Adds an error for the given field if `valid` is false, for chaining checks.
+    pub fn check(
+        mut self,
+        valid: bool,
+        field: impl Into<Cow<'static, str>>,
+        message: impl Into<Cow<'static, str>>,
+    ) -> Self {
+        if !valid {
+            self.add(field, message);
+        }
+
+        self
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns `Ok(())` if there are no errors, or `Err(self)` otherwise.
+    pub fn into_result(self) -> Result<(), Self> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            Err(self)
+        }
+    }
+}
+
+impl fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, error) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+
+            write!(f, "{}: {}", error.field, error.message)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for ValidationErrors {}
+
+impl IntoResponse for ValidationErrors {
+    fn into_response(self) -> Response {
+        let mut body = String::from(r#"{"errors":["#);
+
+        for (i, error) in self.0.iter().enumerate() {
+            if i > 0 {
+                body.push(',');
+            }
+
+            _ = write!(
+                body,
+                r#"{{"field":"{}","message":"{}"}}"#,
+                v_jsonescape::escape(&error.field),
+                v_jsonescape::escape(&error.message)
+            );
+        }
+
+        body.push_str("]}");
+
+        let mut resp = (body, StatusCode::UNPROCESSABLE_ENTITY).into_response();
+        resp.headers_mut().insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        resp
+    }
+}
+
+impl From<ValidationErrors> for crate::Error {
+    #[inline]
+    fn from(errors: ValidationErrors) -> Self {
+        ErrorResponse::new(errors).into()
+    }
+}
+
+/// Extractor wrapper that runs [`Validate::validate`] after extracting `T`, rejecting
+/// the request with the [`ValidationErrors`] if it fails.
+///
+/// ```rust,ignore
+/// impl Validate for NewUser {
+///     fn validate(&self) -> Result<(), ValidationErrors> {
+///         ValidationErrors::new()
+///             .check(!self.name.is_empty(), "name", "must not be empty")
+///             .check(self.age >= 13, "age", "must be at least 13")
+///             .into_result()
+///     }
+/// }
+///
+/// async fn create_user(Valid(Json(user)): Valid<Json<NewUser>>) { /* ... */ }
+/// ```
+///
+/// [`Validate`] is implemented for the body and query extractors when their contents implement it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct Valid<T>(pub T);
+
+impl<T> Deref for Valid<T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<S, T> FromRequestParts<S> for Valid<T>
+where
+    S: Sync,
+    T: FromRequestParts<S> + Validate,
+{
+    type Rejection = crate::Error;
+
+    fn from_request_parts(
+        parts: &mut RequestParts,
+        state: &S,
+    ) -> impl Future<Output = Result<Self, Self::Rejection>> + Send {
+        async move {
+            let value = T::from_request_parts(parts, state).await.map_err(Into::into)?;
+
+            match value.validate() {
+                Ok(()) => Ok(Valid(value)),
+                Err(errors) => Err(errors.into()),
+            }
+        }
+    }
+}
+
+impl<S, T> FromRequest<S> for Valid<T>
+where
+    S: Sync,
+    T: FromRequest<S> + Validate,
+{
+    type Rejection = crate::Error;
+
+    fn from_request(req: Request, state: &S) -> impl Future<Output = Result<Self, Self::Rejection>> + Send {
+        async move {
+            let value = T::from_request(req, state).await.map_err(Into::into)?;
+
+            match value.validate() {
+                Ok(()) => Ok(Valid(value)),
+                Err(errors) => Err(errors.into()),
+            }
+        }
+    }
+}
+
+macro_rules! impl_validate_wrapper {
+    ($($(#[$meta:meta])* $ty:ty),* $(,)?) => {$(
+        $(#[$meta])*
+        impl<T: Validate> Validate for $ty {
+            #[inline]
+            fn validate(&self) -> Result<(), ValidationErrors> {
+                self.0.validate()
+            }
+        }
+    )*};
+}
+
+impl_validate_wrapper! {
+    super::Form<T>,
+    super::query::Query<T>,
+    #[cfg(feature = "json")] super::Json<T>,
+    #[cfg(feature = "cbor")] super::Cbor<T>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{body::Body, service::Service, Router};
+
+    #[derive(serde::Deserialize)]
+    struct NewUser {
+        name: String,
+        age: u32,
+    }
+
+    impl Validate for NewUser {
+        fn validate(&self) -> Result<(), ValidationErrors> {
+            ValidationErrors::new()
+                .check(!self.name.is_empty(), "name", "must not be empty")
+                .check(self.age >= 13, "age", "must be at least 13")
+                .into_result()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_errors_escaped() {
+        let errors = ValidationErrors::new().check(false, "bio", "must not contain \"quotes\"\nor newlines");
+
+        let resp = errors.into_result().unwrap_err().into_response();
+        assert_eq!(
+            resp.into_body().to_string().await.unwrap(),
+            r#"{"errors":[{"field":"bio","message":"must not contain \"quotes\"\nor newlines"}]}"#
+        );
+    }
+
+    #[cfg(feature = "json")]
+    #[tokio::test]
+    async fn test_valid_json() {
+        use crate::extract::Json;
+
+        let mut router = Router::<(), Response>::with_state(());
+        router.post(
+            "/users",
+            |Valid(Json(user)): Valid<Json<NewUser>>| async move { user.name },
+        );
+
+        let post = |body: &'static str| {
+            http::Request::post("/users").body(Body::from(bytes::Bytes::from_static(body.as_bytes()))).unwrap()
+        };
+
+        let resp = router.call(post(r#"{"name":"alice","age":30}"#)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.into_body().to_string().await.unwrap(), "alice");
+
+        let resp = router.call(post(r#"{"name":"","age":5}"#)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(resp.headers()[CONTENT_TYPE], "application/json");
+        assert_eq!(
+            resp.into_body().to_string().await.unwrap(),
+            r#"{"errors":[{"field":"name","message":"must not be empty"},{"field":"age","message":"must be at least 13"}]}"#
+        );
+    }
+}
@@ -20,7 +20,7 @@ use headers::{
     LastModified, Range,
 };
 
-use crate::{body::Body, IntoResponse, RequestParts, Response};
+use crate::{body::Body, layers::NoCompression, IntoResponse, RequestParts, Response};
 
 // TODO: https://github.com/magiclen/entity-tag/blob/master/src/lib.rs
 // https://github.com/pillarjs/send/blob/master/index.js
@@ -50,6 +50,11 @@ pub trait FileMetadata {
     fn modified(&self) -> io::Result<SystemTime>;
     fn blksize(&self) -> u64;
 
+    /// The `(device, inode)` pair identifying the file on disk, if available.
+    fn inode(&self) -> Option<(u64, u64)> {
+        None
+    }
+
     fn is_empty(&self) -> bool {
         self.len() == 0
     }
@@ -84,6 +89,22 @@ impl FileMetadata for Metadata {
     fn blksize(&self) -> u64 {
         0
     }
+
+    #[cfg(unix)]
+    #[inline]
+    fn inode(&self) -> Option<(u64, u64)> {
+        use std::os::unix::fs::MetadataExt;
+
+        Some((MetadataExt::dev(self), MetadataExt::ino(self)))
+    }
+}
+
+/// Compute the default weak ETag for a file from its last modified time and length.
+pub fn default_etag(meta: &impl FileMetadata) -> EntityTag {
+    EntityTag::from_file(
+        meta.modified().ok().and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok()),
+        meta.len(),
+    )
 }
 
 pub trait FileCache<S: Send + Sync> {
@@ -112,6 +133,31 @@ pub trait FileCache<S: Send + Sync> {
     fn is_method_allowed(&self, method: &Method) -> bool {
         method == Method::GET || method == Method::HEAD
     }
+
+    /// Compute the ETag for an opened file. By default, this uses the file's
+    /// last modified time and length, see [`default_etag`].
+    ///
+    /// If the file is read from, it must be rewound to the start before returning.
+    fn etag(
+        &self,
+        file: &mut Self::File,
+        meta: &Self::Meta,
+    ) -> impl Future<Output = io::Result<EntityTag>> + Send {
+        _ = file;
+
+        core::future::ready(Ok(default_etag(meta)))
+    }
+
+    /// Check if range requests are supported for an opened file, such as files that are
+    /// transformed or compressed on the fly. By default, ranges are always supported.
+    ///
+    /// When unsupported, `Accept-Ranges: none` is advertised and any `Range` header
+    /// is ignored, always responding with the full content.
+    fn supports_ranges(&self, file: &Self::File) -> bool {
+        _ = file;
+
+        true
+    }
 }
 
 pub trait FileCacheExtra<S: Send + Sync>: FileCache<S> {
@@ -162,6 +208,25 @@ impl<S: Send + Sync, F: FileCache<S>> FileCache<S> for &F {
     fn file_metadata(&self, file: &Self::File, state: &S) -> impl Future<Output = io::Result<Self::Meta>> + Send {
         (**self).file_metadata(file, state)
     }
+
+    #[inline(always)]
+    fn is_method_allowed(&self, method: &Method) -> bool {
+        (**self).is_method_allowed(method)
+    }
+
+    #[inline(always)]
+    fn etag(
+        &self,
+        file: &mut Self::File,
+        meta: &Self::Meta,
+    ) -> impl Future<Output = io::Result<EntityTag>> + Send {
+        (**self).etag(file, meta)
+    }
+
+    #[inline(always)]
+    fn supports_ranges(&self, file: &Self::File) -> bool {
+        (**self).supports_ranges(file)
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -192,6 +257,144 @@ impl<S: Send + Sync> FileCache<S> for NoCache {
     }
 }
 
+/// How [`WithETag`] computes ETags for files.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ETagMode {
+    /// Last modified time and length, see [`default_etag`].
+    #[default]
+    Modified,
+
+    /// Last modified time and length combined with the file's device and inode numbers,
+    /// where available. Falls back to [`ETagMode::Modified`] otherwise.
+    Inode,
+
+    /// Hash the file contents with SHA-256 for files up to `max_len` bytes,
+    /// larger files fall back to [`ETagMode::Modified`].
+    ///
+    /// This keeps ETags stable across deployments that rewrite modification times,
+    /// at the cost of reading the file on every request.
+    ContentHash { max_len: u64 },
+}
+
+/// [`FileCache`] wrapper that computes ETags according to an [`ETagMode`].
+///
+/// ```rust,ignore
+/// let cache = WithETag::new(NoCache, ETagMode::ContentHash { max_len: 64 * 1024 });
+/// ```
+#[derive(Debug, Default, Clone, Copy)]
+pub struct WithETag<F = NoCache> {
+    pub inner: F,
+    pub mode: ETagMode,
+}
+
+impl<F> WithETag<F> {
+    pub const fn new(inner: F, mode: ETagMode) -> Self {
+        WithETag { inner, mode }
+    }
+}
+
+fn hashed_etag(hash: u64, len: u64) -> EntityTag {
+    EntityTag::weak(&format!("{hash:016x}-{len:x}"))
+}
+
+/// ETag from a SHA-256 digest of the content, truncated to 192 bits to fit within an [`EntityTag`],
+/// which is still collision resistant, unlike a general purpose hash.
+///
+/// The tag is weak, as the response may still be compressed by another layer.
+fn content_etag(digest: aws_lc_rs::digest::Digest) -> EntityTag {
+    use std::fmt::Write;
+
+    let mut tag = String::with_capacity(48);
+
+    for byte in &digest.as_ref()[..24] {
+        _ = write!(tag, "{byte:02x}");
+    }
+
+    EntityTag::weak(&tag)
+}
+
+impl<S: Send + Sync, F: FileCache<S> + Sync> FileCache<S> for WithETag<F> {
+    type File = F::File;
+    type Meta = F::Meta;
+
+    #[inline]
+    fn clear(&self, state: &S) -> impl Future<Output = ()> + Send {
+        self.inner.clear(state)
+    }
+
+    #[inline]
+    fn open(
+        &self,
+        path: &Path,
+        accepts: Option<AcceptEncoding>,
+        state: &S,
+    ) -> impl Future<Output = io::Result<Self::File>> + Send {
+        self.inner.open(path, accepts, state)
+    }
+
+    #[inline]
+    fn metadata(&self, path: &Path, state: &S) -> impl Future<Output = io::Result<Self::Meta>> + Send {
+        self.inner.metadata(path, state)
+    }
+
+    #[inline]
+    fn file_metadata(&self, file: &Self::File, state: &S) -> impl Future<Output = io::Result<Self::Meta>> + Send {
+        self.inner.file_metadata(file, state)
+    }
+
+    #[inline]
+    fn is_method_allowed(&self, method: &Method) -> bool {
+        self.inner.is_method_allowed(method)
+    }
+
+    #[inline]
+    fn supports_ranges(&self, file: &Self::File) -> bool {
+        self.inner.supports_ranges(file)
+    }
+
+    fn etag(
+        &self,
+        file: &mut Self::File,
+        meta: &Self::Meta,
+    ) -> impl Future<Output = io::Result<EntityTag>> + Send {
+        use std::hash::{Hash, Hasher};
+        use tokio::io::AsyncReadExt;
+
+        let len = meta.len();
+        let modified = meta.modified().ok().and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok());
+        let inode = meta.inode();
+        let mode = self.mode;
+
+        async move {
+            match mode {
+                ETagMode::Inode if inode.is_some() => {
+                    let mut hasher = rustc_hash::FxHasher::default();
+                    (inode, modified).hash(&mut hasher);
+
+                    Ok(hashed_etag(hasher.finish(), len))
+                }
+                ETagMode::ContentHash { max_len } if len <= max_len => {
+                    use aws_lc_rs::digest::{digest, SHA256};
+
+                    let digest = match file.full() {
+                        Some(full) => digest(&SHA256, &full),
+                        None => {
+                            let mut buf = Vec::with_capacity(len as usize);
+                            file.read_to_end(&mut buf).await?;
+                            file.seek(SeekFrom::Start(0)).await?;
+
+                            digest(&SHA256, &buf)
+                        }
+                    };
+
+                    Ok(content_etag(digest))
+                }
+                _ => Ok(EntityTag::from_file(modified, len)),
+            }
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Conditionals {
     if_modified_since: Option<IfModifiedSince>,
@@ -395,18 +598,23 @@ async fn file_reply<S: Send + Sync, F: FileCache<S> + ?Sized>(
         },
     };
 
+    let supports_ranges = cache.supports_ranges(&file);
+
     // parse after opening the file handle to save time on open error
-    let conditionals = Conditionals::new(req, range);
+    let conditionals = Conditionals::new(req, range.filter(|_| supports_ranges));
 
     let modified = metadata.modified().ok();
     let last_modified = modified.map(LastModified::from);
 
     let mut len = metadata.len();
 
-    let etag = EntityTag::from_file(
-        modified.and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok()),
-        len,
-    );
+    let etag = match cache.etag(&mut file, &metadata).await {
+        Ok(etag) => etag,
+        Err(e) => {
+            log::error!("Error computing file ETag: {e}");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
 
     match conditionals.check(last_modified, &etag) {
         Cond::NoBody(resp) => resp.with_header(etag).into_response(),
@@ -468,14 +676,22 @@ async fn file_reply<S: Send + Sync, F: FileCache<S> + ?Sized>(
                 }
 
                 parts.headers.typed_insert(ContentLength(len));
-                parts.headers.typed_insert(AcceptRanges::bytes());
+                parts.headers.typed_insert(match supports_ranges {
+                    true => AcceptRanges::bytes(),
+                    false => AcceptRanges::none(),
+                });
+
+                let ext = path.extension().and_then(std::ffi::OsStr::to_str);
 
-                let mime = path
-                    .extension()
-                    .and_then(std::ffi::OsStr::to_str)
+                let mime = ext
                     .and_then(|ext| mime_db::lookup_ext(ext)?.types.first().copied())
                     .unwrap_or("application/octet-stream");
 
+                // don't waste time trying to compress media that is already compressed
+                if is_incompressible(ext, mime) {
+                    parts.extensions.insert(NoCompression);
+                }
+
                 parts.headers.append(
                     const { HeaderName::from_static("content-type") },
                     HeaderValue::from_static(mime),
@@ -487,6 +703,30 @@ async fn file_reply<S: Send + Sync, F: FileCache<S> + ?Sized>(
     }
 }
 
+/// Checks if a file is already compressed, going by its media type or file extension,
+/// as the media type may be unknown.
+fn is_incompressible(ext: Option<&str>, mime: &str) -> bool {
+    #[rustfmt::skip]
+    const EXTENSIONS: &[&str] = &[
+        // images
+        "png", "apng", "jpg", "jpeg", "gif", "webp", "avif", "heic", "heif", "jxl",
+        // video
+        "mp4", "m4v", "webm", "mkv", "mov", "avi", "ogv",
+        // audio
+        "mp3", "m4a", "aac", "ogg", "oga", "opus", "flac",
+        // archives and fonts
+        "zip", "gz", "tgz", "bz2", "xz", "zst", "br", "7z", "rar", "woff", "woff2",
+    ];
+
+    if mime != "image/svg+xml"
+        && (mime.starts_with("image/") || mime.starts_with("video/") || mime.starts_with("audio/"))
+    {
+        return true;
+    }
+
+    ext.is_some_and(|ext| EXTENSIONS.iter().any(|e| e.eq_ignore_ascii_case(ext)))
+}
+
 pub struct BadRange;
 pub fn bytes_range(range: Option<Range>, max_len: u64) -> Result<(u64, u64), BadRange> {
     use std::ops::Bound;
@@ -515,3 +755,326 @@ pub fn bytes_range(range: Option<Range>, max_len: u64) -> Result<(u64, u64), Bad
         None => Ok((0, max_len)),
     }
 }
+
+#[derive(Debug, thiserror::Error)]
+pub enum SaveError {
+    #[error(transparent)]
+    Sanitize(#[from] SanitizeError),
+
+    #[error(transparent)]
+    Body(#[from] crate::body::BodyError),
+
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+}
+
+impl IntoResponse for SaveError {
+    fn into_response(self) -> Response {
+        match self {
+            SaveError::Sanitize(e) => e.to_string().with_status(StatusCode::BAD_REQUEST).into_response(),
+            SaveError::Body(e) => e.into_response(),
+            SaveError::Io(e) => {
+                log::error!("save: {e}");
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+}
+
+impl From<SaveError> for crate::Error {
+    #[inline]
+    fn from(e: SaveError) -> Self {
+        crate::error::ErrorResponse::new(e).into()
+    }
+}
+
+impl From<SanitizeError> for crate::Error {
+    #[inline]
+    fn from(e: SanitizeError) -> Self {
+        SaveError::Sanitize(e).into()
+    }
+}
+
+/// Removes the temporary file of [`save`] if dropped before being disarmed,
+/// which covers both errors and the future itself being dropped mid-upload.
+struct TempFileGuard(Option<PathBuf>);
+
+impl Drop for TempFileGuard {
+    fn drop(&mut self) {
+        if let Some(path) = self.0.take() {
+            if let Err(e) = std::fs::remove_file(&path) {
+                log::warn!("save: failed to remove temporary file {}: {e}", path.display());
+            }
+        }
+    }
+}
+
+/// Streams the body to a file at `request_path` within `base`, returning the metadata of the new file.
+///
+/// The destination is sanitized with [`sanitize_path`], and bodies larger than `limit` bytes are rejected.
+/// The body is first written to a temporary file in the same directory, which is then atomically
+/// renamed to the destination once complete, so partial uploads never replace an existing file.
+/// The temporary file is removed if an error occurs or the returned future is dropped.
+///
+/// The parent directory of the destination must already exist.
+pub async fn save(
+    body: Body,
+    request_path: impl AsRef<str>,
+    base: impl Into<PathBuf>,
+    limit: u64,
+) -> Result<Metadata, SaveError> {
+    use http_body_util::BodyExt as _;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use tokio::io::AsyncWriteExt as _;
+
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let dest = sanitize_path(base, request_path.as_ref())?;
+
+    let Some(file_name) = dest.file_name() else {
+        return Err(SanitizeError::InvalidPath.into());
+    };
+
+    // reject early if the body is known to be too large
+    if body.original_size_hint().lower() > limit {
+        return Err(crate::body::BodyError::LengthLimitError.into());
+    }
+
+    let mut body = body.limit(limit)?;
+
+    let mut temp_name = std::ffi::OsString::from(".");
+    temp_name.push(file_name);
+    temp_name.push(format!(
+        ".{}.{}.tmp",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let temp = dest.with_file_name(temp_name);
+
+    let mut file = tokio::fs::OpenOptions::new().write(true).create_new(true).open(&temp).await?;
+
+    let mut guard = TempFileGuard(Some(temp.clone()));
+
+    while let Some(frame) = body.frame().await {
+        if let Ok(data) = frame?.into_data() {
+            file.write_all(&data).await?;
+        }
+    }
+
+    file.flush().await?;
+    file.sync_all().await?;
+    drop(file);
+
+    tokio::fs::rename(&temp, &dest).await?;
+
+    guard.0 = None; // the temporary file no longer exists
+
+    Ok(tokio::fs::metadata(&dest).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::{save, ETagMode, FileCache, NoCache, SaveError, WithETag};
+    use crate::body::{Body, BodyError};
+
+    fn temp_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("ftl-save-{name}-{}", std::process::id()));
+        _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn entries(dir: &std::path::Path) -> Vec<String> {
+        let mut entries: Vec<_> =
+            std::fs::read_dir(dir).unwrap().map(|e| e.unwrap().file_name().into_string().unwrap()).collect();
+        entries.sort();
+        entries
+    }
+
+    #[tokio::test]
+    async fn test_save() {
+        let dir = temp_dir("ok");
+
+        let meta = save(Body::from(Bytes::from_static(b"hello")), "upload.txt", &dir, 16).await.unwrap();
+
+        assert_eq!(meta.len(), 5);
+        assert_eq!(std::fs::read(dir.join("upload.txt")).unwrap(), b"hello");
+        assert_eq!(entries(&dir), ["upload.txt"]);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_save_over_limit() {
+        let dir = temp_dir("limit");
+
+        // streamed, so the size is not known ahead of time
+        let (body, tx) = Body::channel(4);
+        tx.send(Ok(http_body::Frame::data(Bytes::from_static(b"hello")))).await.unwrap();
+        tx.send(Ok(http_body::Frame::data(Bytes::from_static(b"world")))).await.unwrap();
+        drop(tx);
+
+        let err = save(body, "upload.txt", &dir, 8).await.unwrap_err();
+
+        assert!(matches!(err, SaveError::Body(BodyError::LengthLimitError)));
+        assert!(entries(&dir).is_empty());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_save_aborted() {
+        let dir = temp_dir("abort");
+
+        let (body, tx) = Body::channel(4);
+        tx.send(Ok(http_body::Frame::data(Bytes::from_static(b"partial")))).await.unwrap();
+        assert!(tx.abort().await);
+
+        let err = save(body, "upload.txt", &dir, 1024).await.unwrap_err();
+
+        assert!(matches!(err, SaveError::Body(BodyError::StreamAborted)));
+        assert!(entries(&dir).is_empty());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(feature = "_meta_compression")]
+    #[tokio::test]
+    async fn test_media_not_compressed() {
+        use crate::{layers::compression::CompressionLayer, service::Service, Layer, RequestParts, Router};
+
+        let dir = temp_dir("media");
+
+        // highly compressible content, so only the extension prevents compression
+        std::fs::write(dir.join("image.png"), [0u8; 4096]).unwrap();
+        std::fs::write(dir.join("data.txt"), [0u8; 4096]).unwrap();
+
+        let mut router = Router::<(), crate::Response>::with_state(());
+
+        let base = dir.clone();
+        router.get("/{file}", move |parts: RequestParts| {
+            let path = base.join(parts.uri.path().trim_start_matches('/'));
+            async move { super::file(&parts, &(), path, &NoCache).await }
+        });
+
+        let service = CompressionLayer::default().layer(router);
+
+        let get = |path: &str| {
+            http::Request::get(path).header(http::header::ACCEPT_ENCODING, "gzip").body(Body::empty()).unwrap()
+        };
+
+        let resp = service.call(get("/image.png")).await.unwrap();
+        assert!(!resp.headers().contains_key(http::header::CONTENT_ENCODING));
+        assert_eq!(resp.headers()[http::header::CONTENT_LENGTH], "4096");
+        assert_eq!(resp.into_body().to_string().await.unwrap().len(), 4096);
+
+        let resp = service.call(get("/data.txt")).await.unwrap();
+        assert_eq!(resp.headers()[http::header::CONTENT_ENCODING], "gzip");
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_no_range_support() {
+        use super::{AcceptEncoding, Metadata, Path, TkFile};
+        use crate::RequestParts;
+
+        struct NoRanges;
+
+        impl FileCache<()> for NoRanges {
+            type File = TkFile;
+            type Meta = Metadata;
+
+            async fn clear(&self, _state: &()) {}
+
+            async fn open(
+                &self,
+                path: &Path,
+                accepts: Option<AcceptEncoding>,
+                state: &(),
+            ) -> std::io::Result<TkFile> {
+                NoCache.open(path, accepts, state).await
+            }
+
+            async fn metadata(&self, path: &Path, state: &()) -> std::io::Result<Metadata> {
+                FileCache::<()>::metadata(&NoCache, path, state).await
+            }
+
+            async fn file_metadata(&self, file: &TkFile, state: &()) -> std::io::Result<Metadata> {
+                NoCache.file_metadata(file, state).await
+            }
+
+            fn supports_ranges(&self, _file: &TkFile) -> bool {
+                false
+            }
+        }
+
+        let dir = temp_dir("ranges");
+        let path = dir.join("data.txt");
+        std::fs::write(&path, b"hello world").unwrap();
+
+        let parts = |range| -> RequestParts {
+            http::Request::get("/data.txt").header(http::header::RANGE, range).body(()).unwrap().into_parts().0
+        };
+
+        let resp = super::file(&parts("bytes=0-4"), &(), &path, &NoCache).await;
+        assert_eq!(resp.status(), http::StatusCode::PARTIAL_CONTENT);
+        assert_eq!(resp.headers()[http::header::ACCEPT_RANGES], "bytes");
+        assert_eq!(resp.into_body().to_string().await.unwrap(), "hello");
+
+        let resp = super::file(&parts("bytes=0-4"), &(), &path, &NoRanges).await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        assert_eq!(resp.headers()[http::header::ACCEPT_RANGES], "none");
+        assert!(!resp.headers().contains_key(http::header::CONTENT_RANGE));
+        assert_eq!(resp.into_body().to_string().await.unwrap(), "hello world");
+
+        // unsatisfiable ranges are ignored as well
+        let resp = super::file(&parts("bytes=100-200"), &(), &path, &NoRanges).await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_content_hash_etag() {
+        let dir = temp_dir("etag");
+
+        let mtime = std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000);
+
+        for (name, content) in [("a.txt", b"aaaa"), ("b.txt", b"bbbb")] {
+            let file = std::fs::File::create(dir.join(name)).unwrap();
+            std::io::Write::write_all(&mut &file, content).unwrap();
+            file.set_modified(mtime).unwrap();
+        }
+
+        async fn etag<F: FileCache<()>>(cache: &F, path: &std::path::Path) -> String {
+            let mut file = cache.open(path, None, &()).await.unwrap();
+            let meta = cache.file_metadata(&file, &()).await.unwrap();
+            cache.etag(&mut file, &meta).await.unwrap().to_string()
+        }
+
+        let (a, b) = (dir.join("a.txt"), dir.join("b.txt"));
+
+        // identical mtime and length collide by default
+        assert_eq!(etag(&NoCache, &a).await, etag(&NoCache, &b).await);
+
+        let hashed = WithETag::new(NoCache, ETagMode::ContentHash { max_len: 1024 });
+        assert_ne!(etag(&hashed, &a).await, etag(&hashed, &b).await);
+        assert_eq!(etag(&hashed, &a).await, etag(&hashed, &a).await);
+
+        // the leading 192 bits of the SHA-256 digest of the content
+        let content = std::fs::read(&a).unwrap();
+        let expected = aws_lc_rs::digest::digest(&aws_lc_rs::digest::SHA256, &content);
+        let expected = expected.as_ref()[..24].iter().map(|b| format!("{b:02x}")).collect::<String>();
+        assert_eq!(etag(&hashed, &a).await, format!("W/\"{expected}\""));
+
+        // too large to hash, falls back to the default
+        let limited = WithETag::new(NoCache, ETagMode::ContentHash { max_len: 2 });
+        assert_eq!(etag(&limited, &a).await, etag(&NoCache, &a).await);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
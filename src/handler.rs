@@ -1,5 +1,6 @@
 #![allow(dead_code)]
 
+use core::convert::Infallible;
 use core::future::{Future, IntoFuture};
 use std::sync::Arc;
 
@@ -7,6 +8,7 @@ use futures::future::BoxFuture;
 
 use crate::{
     extract::{FromRequest, FromRequestParts},
+    service::Service,
     IntoResponse, Request, Response,
 };
 
@@ -174,11 +176,119 @@ where
     pub fn call(&self, req: Request, state: S) -> BoxFuture<'static, R> {
         self.0.call(req, state)
     }
+
+    /// Binds the handler to the given state, ignoring whatever state it's called with.
+    pub fn with_fixed_state<T>(self, state: S) -> BoxedErasedHandler<T, R> {
+        BoxedErasedHandler(Arc::new(FixedStateHandler { handler: self, state }))
+    }
+
+    /// Calls the handler with the state passed through the request by a handler
+    /// from [`from_stateful_service`](Self::from_stateful_service), if any.
+    pub fn with_request_state(self) -> Self {
+        BoxedErasedHandler(Arc::new(RequestStateHandler(self)))
+    }
+
+    /// Erases a service as a handler, passing the state it's called with through the request,
+    /// to be used by handlers within the service wrapped with [`with_request_state`](Self::with_request_state).
+    ///
+    /// This allows the state to be replaced after the service has been built, such as by layers.
+    pub fn from_stateful_service<Svc>(service: Svc) -> Self
+    where
+        Svc: Service<Request, Response = R, Error = Infallible> + Send + Sync + 'static,
+    {
+        BoxedErasedHandler(Arc::new(StatefulServiceHandler(ServiceHandler(Arc::new(service)))))
+    }
+}
+
+impl<S, R> BoxedErasedHandler<S, R> {
+    /// Erases a service as a handler, ignoring whatever state it's called with.
+    pub fn from_service<Svc>(service: Svc) -> Self
+    where
+        Svc: Service<Request, Response = R, Error = Infallible> + Send + Sync + 'static,
+    {
+        BoxedErasedHandler(Arc::new(ServiceHandler(Arc::new(service))))
+    }
+}
+
+/// Erased handler calling a service, see [`BoxedErasedHandler::from_service`].
+struct ServiceHandler<Svc>(Arc<Svc>);
+
+impl<S, R, Svc> ErasedHandler<S, R> for ServiceHandler<Svc>
+where
+    Svc: Service<Request, Response = R, Error = Infallible> + Send + Sync + 'static,
+{
+    #[inline]
+    fn call(&self, req: Request, _state: S) -> BoxFuture<'static, R> {
+        let service = self.0.clone();
+
+        Box::pin(async move {
+            match service.call(req).await {
+                Ok(resp) => resp,
+                Err(e) => match e {},
+            }
+        })
+    }
+}
+
+/// State passed through the request extensions, see [`BoxedErasedHandler::from_stateful_service`].
+#[derive(Clone)]
+struct RequestState<S>(S);
+
+/// Erased handler calling a service with the state in the request, see [`BoxedErasedHandler::from_stateful_service`].
+struct StatefulServiceHandler<Svc>(ServiceHandler<Svc>);
+
+impl<S, R, Svc> ErasedHandler<S, R> for StatefulServiceHandler<Svc>
+where
+    S: Clone + Send + Sync + 'static,
+    Svc: Service<Request, Response = R, Error = Infallible> + Send + Sync + 'static,
+{
+    #[inline]
+    fn call(&self, mut req: Request, state: S) -> BoxFuture<'static, R> {
+        req.extensions_mut().insert(RequestState(state.clone()));
+
+        self.0.call(req, state)
+    }
+}
+
+/// Erased handler using the state in the request, see [`BoxedErasedHandler::with_request_state`].
+struct RequestStateHandler<S, R>(BoxedErasedHandler<S, R>);
+
+impl<S, R> ErasedHandler<S, R> for RequestStateHandler<S, R>
+where
+    S: Clone + Send + Sync + 'static,
+    R: 'static,
+{
+    #[inline]
+    fn call(&self, mut req: Request, state: S) -> BoxFuture<'static, R> {
+        let state = match req.extensions_mut().remove::<RequestState<S>>() {
+            Some(RequestState(state)) => state,
+            None => state,
+        };
+
+        self.0.call(req, state)
+    }
+}
+
+/// Erased handler bound to a fixed state, see [`BoxedErasedHandler::with_fixed_state`].
+struct FixedStateHandler<S, R> {
+    handler: BoxedErasedHandler<S, R>,
+    state: S,
+}
+
+impl<S, T, R> ErasedHandler<T, R> for FixedStateHandler<S, R>
+where
+    S: Clone + Send + Sync + 'static,
+    R: 'static,
+{
+    #[inline]
+    fn call(&self, req: Request, _state: T) -> BoxFuture<'static, R> {
+        self.handler.call(req, self.state.clone())
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::extract::State;
+    use crate::extract::{SharedState, State};
 
     use super::*;
 
@@ -189,4 +299,16 @@ mod tests {
 
         let _x: BoxedErasedHandler<u32, Response> = BoxedErasedHandler::erase(HandlerIntoResponse(my_handler));
     }
+
+    fn _test_shared_state_handler() {
+        struct AppState {
+            name: String,
+        }
+
+        async fn my_handler(SharedState(state): SharedState<AppState>) -> String {
+            state.name.clone()
+        }
+
+        crate::Router::<_, Response>::with_shared_state(AppState { name: "test".into() }).get("/", my_handler);
+    }
 }
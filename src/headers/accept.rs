@@ -0,0 +1,162 @@
+use headers::Header;
+use http::HeaderValue;
+use mime::Mime;
+use smallvec::SmallVec;
+
+use super::quality::QValue;
+
+/// The `Accept` header, a list of media ranges with their q-values.
+///
+/// Media type parameters other than `q` are not considered when matching.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct Accept(pub SmallVec<[(Mime, QValue); 4]>);
+
+/// How specific a media range is, `*/*` being the least specific.
+fn specificity(range: &Mime) -> u8 {
+    match (range.type_() == mime::STAR, range.subtype() == mime::STAR) {
+        (true, _) => 0,
+        (false, true) => 1,
+        (false, false) => 2,
+    }
+}
+
+fn matches(range: &Mime, mime: &Mime) -> bool {
+    range.type_() == mime::STAR
+        || (range.type_() == mime.type_() && (range.subtype() == mime::STAR || range.subtype() == mime.subtype()))
+}
+
+impl Accept {
+    /// Returns the q-value of the most specific media range matching `mime`,
+    /// or `None` if no range matches it.
+    #[must_use]
+    pub fn quality(&self, mime: &Mime) -> Option<QValue> {
+        let mut matched: Option<(u8, QValue)> = None;
+
+        for (range, q) in &self.0 {
+            let specificity = specificity(range);
+
+            if matches(range, mime) && matched.is_none_or(|(s, _)| specificity > s) {
+                matched = Some((specificity, *q));
+            }
+        }
+
+        matched.map(|(_, q)| q)
+    }
+
+    /// Pick the best media type out of `available` for this header.
+    ///
+    /// The media type with the highest non-zero q-value wins, with ties broken by
+    /// the order of `available`, so it should be given in order of preference.
+    ///
+    /// If the header is empty, the first available media type is returned.
+    #[must_use]
+    pub fn negotiate<'a>(&self, available: &'a [Mime]) -> Option<&'a Mime> {
+        if self.0.is_empty() {
+            return available.first();
+        }
+
+        let mut best: Option<(&Mime, QValue)> = None;
+
+        for mime in available {
+            if let Some(q) = self.quality(mime) {
+                if !q.is_zero() && best.is_none_or(|(_, best_q)| q > best_q) {
+                    best = Some((mime, q));
+                }
+            }
+        }
+
+        best.map(|(mime, _)| mime)
+    }
+}
+
+impl Header for Accept {
+    fn name() -> &'static http::HeaderName {
+        &http::header::ACCEPT
+    }
+
+    fn decode<'i, I>(values: &mut I) -> Result<Self, headers::Error>
+    where
+        Self: Sized,
+        I: Iterator<Item = &'i HeaderValue>,
+    {
+        let mut ranges = SmallVec::new();
+
+        for value in values {
+            let value = value.to_str().map_err(|_| headers::Error::invalid())?;
+
+            for item in value.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+                let mut v = item.split(';').map(str::trim);
+
+                let Some(Ok(range)) = v.next().map(str::parse::<Mime>) else {
+                    return Err(headers::Error::invalid());
+                };
+
+                let mut q = QValue::one();
+
+                for param in v {
+                    if param.starts_with(['q', 'Q']) && param[1..].starts_with('=') {
+                        q = QValue::parse(param).ok_or(headers::Error::invalid())?;
+                    }
+                }
+
+                ranges.push((range, q));
+            }
+        }
+
+        Ok(Accept(ranges))
+    }
+
+    fn encode<E: Extend<HeaderValue>>(&self, values: &mut E) {
+        use std::fmt::Write;
+
+        let mut s = String::new();
+
+        for (range, q) in &self.0 {
+            if !s.is_empty() {
+                s.push_str(", ");
+            }
+
+            match *q == QValue::one() {
+                true => write!(s, "{range}").unwrap(),
+                false => write!(s, "{range};q={q}").unwrap(),
+            }
+        }
+
+        if let Ok(value) = HeaderValue::try_from(s) {
+            values.extend(Some(value));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Accept {
+        Accept::decode(&mut [HeaderValue::from_str(s).unwrap()].iter()).unwrap()
+    }
+
+    #[test]
+    fn test_negotiate() {
+        let available = [mime::TEXT_HTML, mime::APPLICATION_JSON];
+
+        assert_eq!(
+            parse("application/json").negotiate(&available),
+            Some(&mime::APPLICATION_JSON)
+        );
+        assert_eq!(
+            parse("text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8").negotiate(&available),
+            Some(&mime::TEXT_HTML)
+        );
+        assert_eq!(
+            parse("text/*;q=0.5, application/json").negotiate(&available),
+            Some(&mime::APPLICATION_JSON)
+        );
+        assert_eq!(
+            parse("*/*, text/html;q=0").negotiate(&available),
+            Some(&mime::APPLICATION_JSON)
+        );
+        assert_eq!(parse("image/png").negotiate(&available), None);
+        assert_eq!(Accept::default().negotiate(&available), Some(&mime::TEXT_HTML));
+    }
+}
@@ -1,5 +1,8 @@
 use headers::Header;
-use http::HeaderValue;
+use http::{HeaderMap, HeaderValue};
+use smallvec::SmallVec;
+
+pub use super::quality::QValue;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[must_use]
@@ -96,6 +99,17 @@ impl FilterEncoding {
         self.zstd = enable;
         self
     }
+
+    /// Returns `true` if the given encoding is enabled, with `Identity` never being filtered out.
+    const fn contains(self, encoding: ContentEncoding) -> bool {
+        match encoding {
+            ContentEncoding::Deflate => self.deflate,
+            ContentEncoding::Gzip => self.gzip,
+            ContentEncoding::Brotli => self.br,
+            ContentEncoding::Zstd => self.zstd,
+            ContentEncoding::Identity => true,
+        }
+    }
 }
 
 impl core::ops::BitOr for FilterEncoding {
@@ -162,31 +176,198 @@ impl Header for ContentEncoding {
         let mut encoding = ContentEncoding::Identity;
 
         for value in values.filter_map(|hval| hval.to_str().ok()).flat_map(|s| s.split(',')) {
-            encoding = encoding.max(match value.trim() {
-                enc if (enc.eq_ignore_ascii_case("gzip") || enc.eq_ignore_ascii_case("x-gzip")) => {
-                    ContentEncoding::Gzip
-                }
-                enc if enc.eq_ignore_ascii_case("br") => ContentEncoding::Brotli,
-                enc if enc.eq_ignore_ascii_case("deflate") => ContentEncoding::Deflate,
-                enc if enc.eq_ignore_ascii_case("zstd") => ContentEncoding::Zstd,
-                _ => continue, // ignore unknown encodings
-            });
+            match ContentEncoding::from_token(value.trim()) {
+                Some(enc) => encoding = encoding.max(enc),
+                None => continue, // ignore unknown encodings
+            }
         }
 
         Ok(encoding)
     }
 
     fn encode<E: Extend<HeaderValue>>(&self, values: &mut E) {
-        values.extend(Some(match self {
-            ContentEncoding::Gzip => HeaderValue::from_static("gzip"),
-            ContentEncoding::Brotli => HeaderValue::from_static("br"),
-            ContentEncoding::Deflate => HeaderValue::from_static("deflate"),
-            ContentEncoding::Zstd => HeaderValue::from_static("zstd"),
-            ContentEncoding::Identity => return,
-        }));
+        if *self != ContentEncoding::Identity {
+            values.extend(Some(HeaderValue::from_static(self.as_str())));
+        }
+    }
+}
+
+impl ContentEncoding {
+    /// Returns the token used for this encoding in `Content-Encoding` and `Accept-Encoding` headers.
+    #[must_use]
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            ContentEncoding::Identity => "identity",
+            ContentEncoding::Deflate => "deflate",
+            ContentEncoding::Gzip => "gzip",
+            ContentEncoding::Brotli => "br",
+            ContentEncoding::Zstd => "zstd",
+        }
+    }
+
+    /// Parses a single encoding token, case-insensitively. Returns `None` for unknown encodings.
+    #[must_use]
+    pub fn from_token(token: &str) -> Option<Self> {
+        Some(match token {
+            enc if (enc.eq_ignore_ascii_case("gzip") || enc.eq_ignore_ascii_case("x-gzip")) => {
+                ContentEncoding::Gzip
+            }
+            enc if enc.eq_ignore_ascii_case("br") => ContentEncoding::Brotli,
+            enc if enc.eq_ignore_ascii_case("deflate") => ContentEncoding::Deflate,
+            enc if enc.eq_ignore_ascii_case("zstd") => ContentEncoding::Zstd,
+            enc if enc.eq_ignore_ascii_case("identity") => ContentEncoding::Identity,
+            _ => return None,
+        })
+    }
+}
+
+/// An ordered list of content encodings, such as `Content-Encoding: gzip, br`,
+/// listed in the order they were applied to the content.
+///
+/// Unlike [`ContentEncoding`], which only decodes the most preferred encoding,
+/// this preserves every stacked encoding and fails to decode if any of them
+/// are unknown, as the content could not be correctly decoded otherwise.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[must_use]
+pub struct ContentEncodings(SmallVec<[ContentEncoding; 2]>);
+
+impl ContentEncodings {
+    /// Creates an empty list of encodings, equivalent to `identity`.
+    pub const fn new() -> Self {
+        Self(SmallVec::new_const())
+    }
+
+    /// Appends an encoding applied after all current encodings.
+    /// `Identity` is ignored.
+    pub fn push(&mut self, encoding: ContentEncoding) {
+        if encoding != ContentEncoding::Identity {
+            self.0.push(encoding);
+        }
+    }
+
+    /// Returns `true` if no encodings have been applied.
+    #[must_use]
+    pub fn is_identity(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the number of stacked encodings.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns `true` if no encodings have been applied, same as [`is_identity`](Self::is_identity).
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Iterates over the encodings in the order they were applied.
+    #[must_use]
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = ContentEncoding> + '_ {
+        self.0.iter().copied()
+    }
+
+    /// Iterates over the encodings in the order they must be removed to decode the content,
+    /// which is the reverse of the order they were applied.
+    pub fn iter_decode(&self) -> impl Iterator<Item = ContentEncoding> + '_ {
+        self.0.iter().rev().copied()
+    }
+
+    /// Returns the outermost (last applied) encoding, or `Identity` if there are none.
+    pub fn last(&self) -> ContentEncoding {
+        self.0.last().copied().unwrap_or_default()
+    }
+}
+
+impl From<ContentEncoding> for ContentEncodings {
+    fn from(encoding: ContentEncoding) -> Self {
+        let mut encodings = Self::new();
+        encodings.push(encoding);
+        encodings
+    }
+}
+
+impl FromIterator<ContentEncoding> for ContentEncodings {
+    fn from_iter<T: IntoIterator<Item = ContentEncoding>>(iter: T) -> Self {
+        let mut encodings = Self::new();
+        for encoding in iter {
+            encodings.push(encoding);
+        }
+        encodings
+    }
+}
+
+impl Header for ContentEncodings {
+    fn name() -> &'static http::HeaderName {
+        &http::header::CONTENT_ENCODING
+    }
+
+    fn decode<'i, I>(values: &mut I) -> Result<Self, headers::Error>
+    where
+        Self: Sized,
+        I: Iterator<Item = &'i HeaderValue>,
+    {
+        let mut encodings = ContentEncodings::new();
+
+        for value in values {
+            let value = value.to_str().map_err(|_| headers::Error::invalid())?;
+
+            for token in value.split(',').map(str::trim).filter(|token| !token.is_empty()) {
+                encodings.push(ContentEncoding::from_token(token).ok_or(headers::Error::invalid())?);
+            }
+        }
+
+        Ok(encodings)
+    }
+
+    fn encode<E: Extend<HeaderValue>>(&self, values: &mut E) {
+        let mut s = String::new();
+
+        for encoding in self.iter() {
+            if !s.is_empty() {
+                s.push_str(", ");
+            }
+            s.push_str(encoding.as_str());
+        }
+
+        if !s.is_empty() {
+            if let Ok(value) = HeaderValue::try_from(s) {
+                values.extend(Some(value));
+            }
+        }
     }
 }
 
+/// Why an encoding was chosen by [`AcceptEncoding::negotiate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NegotiationReason {
+    /// The encoding was listed by name in `Accept-Encoding`, with or without a q-value.
+    Explicit,
+
+    /// The encoding was only acceptable through the `*` wildcard.
+    Wildcard,
+
+    /// No enabled encoding was acceptable, such as with an empty or missing `Accept-Encoding`,
+    /// so the content is left as `identity`.
+    Default,
+}
+
+/// The result of [`AcceptEncoding::negotiate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[must_use]
+pub struct Negotiated {
+    /// The chosen encoding, `Identity` if none was acceptable.
+    pub encoding: ContentEncoding,
+
+    /// The q-value the client gave the chosen encoding.
+    pub quality: QValue,
+
+    /// Why the encoding was chosen.
+    pub reason: NegotiationReason,
+}
+
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 #[must_use]
 pub struct AcceptEncoding {
@@ -197,6 +378,92 @@ pub struct AcceptEncoding {
 }
 
 impl AcceptEncoding {
+    /// Negotiates the encoding for a response from the request's `Accept-Encoding` headers,
+    /// as done by the compression layer, returning the encoding along with why it was chosen.
+    ///
+    /// Encodings disabled by `filter` are never chosen. A missing or invalid header
+    /// is treated as empty, which results in `Identity`.
+    ///
+    /// ```rust
+    /// use ftl::headers::accept_encoding::{AcceptEncoding, ContentEncoding, FilterEncoding, NegotiationReason};
+    /// use http::{header::ACCEPT_ENCODING, HeaderMap, HeaderValue};
+    ///
+    /// let mut headers = HeaderMap::new();
+    /// headers.insert(ACCEPT_ENCODING, HeaderValue::from_static("gzip;q=0.5, *;q=0.8"));
+    ///
+    /// let negotiated = AcceptEncoding::negotiate(&headers, FilterEncoding::all());
+    /// assert_eq!(negotiated.encoding, ContentEncoding::Zstd);
+    /// assert_eq!(negotiated.reason, NegotiationReason::Wildcard);
+    /// ```
+    pub fn negotiate(headers: &HeaderMap, filter: FilterEncoding) -> Negotiated {
+        let values = &mut headers.get_all(http::header::ACCEPT_ENCODING).iter();
+        let (accept, listed) = Self::decode_listed(values).unwrap_or_default();
+
+        let encoding = accept.preferred_encoding(filter);
+
+        let reason = match encoding {
+            ContentEncoding::Identity => NegotiationReason::Default,
+            _ if listed.contains(encoding) => NegotiationReason::Explicit,
+            _ => NegotiationReason::Wildcard,
+        };
+
+        Negotiated {
+            encoding,
+            quality: accept.quality(encoding),
+            reason,
+        }
+    }
+
+    /// Decodes the header values, along with which encodings were listed by name
+    /// rather than only accepted through the `*` wildcard.
+    fn decode_listed<'i, I>(values: &mut I) -> Result<(Self, FilterEncoding), headers::Error>
+    where
+        I: Iterator<Item = &'i HeaderValue>,
+    {
+        let mut encodings = AcceptEncoding::default();
+        let mut listed = FilterEncoding::none();
+
+        for value in values.filter_map(|hval| hval.to_str().ok()).flat_map(|s| s.split(',')) {
+            let mut v = value.splitn(2, ';');
+
+            let Some(encoding) = v.next() else {
+                continue; // ignore bad encodings?
+            };
+
+            let mut wildcard = QValue::zero();
+            let mut is_wildcard = false;
+
+            let (encoding, is_listed) = match encoding.trim() {
+                enc if enc.eq_ignore_ascii_case("br") => (&mut encodings.br, &mut listed.br),
+                enc if enc.eq_ignore_ascii_case("deflate") => (&mut encodings.deflate, &mut listed.deflate),
+                enc if enc.eq_ignore_ascii_case("zstd") => (&mut encodings.zstd, &mut listed.zstd),
+                enc if (enc.eq_ignore_ascii_case("gzip") || enc.eq_ignore_ascii_case("x-gzip")) => {
+                    (&mut encodings.gzip, &mut listed.gzip)
+                }
+
+                "*" => (&mut wildcard, &mut is_wildcard),
+
+                _ => continue, // ignore unknown encodings
+            };
+
+            *encoding = match v.next() {
+                Some(qval) => QValue::parse(qval.trim()).ok_or(headers::Error::invalid())?,
+                None => QValue::one(),
+            };
+
+            *is_listed = true;
+
+            if wildcard.value > 0 {
+                encodings.gzip.wildcard(wildcard);
+                encodings.br.wildcard(wildcard);
+                encodings.deflate.wildcard(wildcard);
+                encodings.zstd.wildcard(wildcard);
+            }
+        }
+
+        Ok((encodings, listed))
+    }
+
     pub fn preferred_encoding(&self, filter: FilterEncoding) -> ContentEncoding {
         // order encodings by preference
         let list = [
@@ -228,6 +495,45 @@ impl AcceptEncoding {
         }
     }
 
+    /// Returns the q-value of the given encoding, with `Identity` always being acceptable.
+    pub fn quality(&self, encoding: ContentEncoding) -> QValue {
+        match encoding {
+            ContentEncoding::Deflate => self.deflate,
+            ContentEncoding::Gzip => self.gzip,
+            ContentEncoding::Brotli => self.br,
+            ContentEncoding::Zstd => self.zstd,
+            ContentEncoding::Identity => QValue::one(),
+        }
+    }
+
+    /// Returns the q-value of an encoding `token` not otherwise known to [`AcceptEncoding`],
+    /// such as a custom encoding, from the raw `Accept-Encoding` header values.
+    ///
+    /// The token is matched case-insensitively, falling back to the `*` wildcard if not listed.
+    /// Invalid q-values are treated as zero.
+    pub fn custom_quality<'i>(values: impl IntoIterator<Item = &'i HeaderValue>, token: &str) -> QValue {
+        let mut wildcard = QValue::zero();
+
+        for value in values.into_iter().filter_map(|hval| hval.to_str().ok()).flat_map(|s| s.split(',')) {
+            let mut v = value.splitn(2, ';');
+
+            let encoding = v.next().map(str::trim);
+
+            let q = match v.next() {
+                Some(qval) => QValue::parse(qval.trim()).unwrap_or_default(),
+                None => QValue::one(),
+            };
+
+            match encoding {
+                Some(enc) if enc.eq_ignore_ascii_case(token) => return q,
+                Some("*") => wildcard = q,
+                _ => {}
+            }
+        }
+
+        wildcard
+    }
+
     #[must_use]
     pub fn allows(&self, encoding: ContentEncoding) -> bool {
         match encoding {
@@ -250,45 +556,7 @@ impl Header for AcceptEncoding {
         Self: Sized,
         I: Iterator<Item = &'i HeaderValue>,
     {
-        #[allow(unused)]
-        let mut encodings = AcceptEncoding::default();
-
-        for value in values.filter_map(|hval| hval.to_str().ok()).flat_map(|s| s.split(',')) {
-            let mut v = value.splitn(2, ';');
-
-            let Some(encoding) = v.next() else {
-                continue; // ignore bad encodings?
-            };
-
-            let mut wildcard = QValue::zero();
-
-            let encoding = match encoding.trim() {
-                enc if enc.eq_ignore_ascii_case("br") => &mut encodings.br,
-                enc if enc.eq_ignore_ascii_case("deflate") => &mut encodings.deflate,
-                enc if enc.eq_ignore_ascii_case("zstd") => &mut encodings.zstd,
-                enc if (enc.eq_ignore_ascii_case("gzip") || enc.eq_ignore_ascii_case("x-gzip")) => {
-                    &mut encodings.gzip
-                }
-
-                "*" => &mut wildcard,
-
-                _ => continue, // ignore unknown encodings
-            };
-
-            *encoding = match v.next() {
-                Some(qval) => QValue::parse(qval.trim()).ok_or(headers::Error::invalid())?,
-                None => QValue::one(),
-            };
-
-            if wildcard.value > 0 {
-                encodings.gzip.wildcard(wildcard);
-                encodings.br.wildcard(wildcard);
-                encodings.deflate.wildcard(wildcard);
-                encodings.zstd.wildcard(wildcard);
-            }
-        }
-
-        Ok(encodings)
+        Self::decode_listed(values).map(|(encodings, _)| encodings)
     }
 
     #[allow(unused)]
@@ -321,112 +589,13 @@ impl Header for AcceptEncoding {
     }
 }
 
-#[derive(Default, Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
-#[must_use]
-#[repr(transparent)]
-pub struct QValue {
-    value: u16,
-}
-
-use std::fmt;
-
-impl fmt::Display for QValue {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        if self.value >= 1000 {
-            f.write_str("1")
-        } else {
-            write!(f, "0.{:03}", self.value)
-        }
-    }
-}
-
-impl QValue {
-    #[must_use]
-    pub const fn new(value: u16) -> Option<Self> {
-        if value <= 1000 {
-            Some(Self { value })
-        } else {
-            None
-        }
-    }
-
-    #[inline]
-    pub const fn one() -> Self {
-        Self { value: 1000 }
-    }
-
-    #[inline]
-    pub const fn zero() -> Self {
-        Self { value: 0 }
-    }
-
-    pub fn wildcard(&mut self, new: Self) {
-        if self.value == 0 {
-            self.value = new.value;
-        }
-    }
-
-    // Parse a q-value as specified in RFC 7231 section 5.3.1.
-    #[must_use]
-    pub fn parse(s: &str) -> Option<Self> {
-        let mut c = s.chars();
-        // Parse "q=" (case-insensitively).
-        match c.next() {
-            Some('q' | 'Q') => (),
-            _ => return None,
-        };
-        match c.next() {
-            Some('=') => (),
-            _ => return None,
-        };
-
-        // Parse leading digit. Since valid q-values are between 0.000 and 1.000, only "0" and "1"
-        // are allowed.
-        let mut value = match c.next() {
-            Some('0') => 0,
-            Some('1') => 1000,
-            _ => return None,
-        };
-
-        // Parse optional decimal point.
-        match c.next() {
-            Some('.') => (),
-            None => return Some(Self { value }),
-            _ => return None,
-        };
-
-        // Parse optional fractional digits. The value of each digit is multiplied by `factor`.
-        // Since the q-value is represented as an integer between 0 and 1000, `factor` is `100` for
-        // the first digit, `10` for the next, and `1` for the digit after that.
-        let mut factor = 100;
-        loop {
-            match c.next() {
-                Some(n @ '0'..='9') => {
-                    // If `factor` is less than `1`, three digits have already been parsed. A
-                    // q-value having more than 3 fractional digits is invalid.
-                    if factor < 1 {
-                        return None;
-                    }
-                    // Add the digit's value multiplied by `factor` to `value`.
-                    value += factor * (n as u16 - '0' as u16);
-                }
-                None => {
-                    // No more characters to parse. Check that the value representing the q-value is
-                    // in the valid range.
-                    return if value <= 1000 { Some(Self { value }) } else { None };
-                }
-                _ => return None,
-            };
-            factor /= 10;
-        }
-    }
-}
-
 #[cfg(test)]
 mod test {
     use http::HeaderValue;
 
-    use super::{AcceptEncoding, ContentEncoding, FilterEncoding, Header, QValue};
+    use super::{
+        AcceptEncoding, ContentEncoding, ContentEncodings, FilterEncoding, Header, NegotiationReason, QValue,
+    };
 
     #[test]
     fn test_accept_encoding() {
@@ -466,4 +635,100 @@ mod test {
         );
         assert_eq!(v("*").preferred_encoding(filter), ContentEncoding::Zstd);
     }
+
+    #[test]
+    fn test_custom_quality() {
+        let values = [
+            HeaderValue::from_static("gzip, LZ4;q=0.5"),
+            HeaderValue::from_static("*;q=0.1"),
+        ];
+
+        assert_eq!(
+            AcceptEncoding::custom_quality(&values, "lz4"),
+            QValue::new(500).unwrap()
+        );
+        assert_eq!(
+            AcceptEncoding::custom_quality(&values, "snappy"),
+            QValue::new(100).unwrap()
+        );
+        assert_eq!(AcceptEncoding::custom_quality(&values[..1], "snappy"), QValue::zero());
+    }
+
+    #[test]
+    fn test_stacked_content_encoding() {
+        let encodings = ContentEncodings::decode(&mut [HeaderValue::from_static("gzip, br")].iter()).unwrap();
+
+        assert_eq!(
+            encodings.iter().collect::<Vec<_>>(),
+            [ContentEncoding::Gzip, ContentEncoding::Brotli]
+        );
+        assert_eq!(
+            encodings.iter_decode().collect::<Vec<_>>(),
+            [ContentEncoding::Brotli, ContentEncoding::Gzip]
+        );
+
+        let mut values = vec![];
+        encodings.encode(&mut values);
+        assert_eq!(values, [HeaderValue::from_static("gzip, br")]);
+
+        assert_eq!(ContentEncodings::decode(&mut values.iter()).unwrap(), encodings);
+
+        // split across multiple header values
+        let split = [HeaderValue::from_static("gzip"), HeaderValue::from_static("br")];
+        assert_eq!(ContentEncodings::decode(&mut split.iter()).unwrap(), encodings);
+
+        // identity is a no-op, unknown encodings are an error
+        let identity = ContentEncodings::decode(&mut [HeaderValue::from_static("identity")].iter()).unwrap();
+        assert!(identity.is_identity());
+        assert!(ContentEncodings::decode(&mut [HeaderValue::from_static("gzip, foo")].iter()).is_err());
+    }
+
+    #[test]
+    fn test_negotiate_reason() {
+        fn negotiate(value: Option<&'static str>, filter: FilterEncoding) -> (ContentEncoding, NegotiationReason) {
+            let mut headers = http::HeaderMap::new();
+            if let Some(value) = value {
+                headers.insert(http::header::ACCEPT_ENCODING, HeaderValue::from_static(value));
+            }
+
+            let negotiated = AcceptEncoding::negotiate(&headers, filter);
+            (negotiated.encoding, negotiated.reason)
+        }
+
+        let all = FilterEncoding::all();
+
+        // explicit
+        assert_eq!(
+            negotiate(Some("gzip, br;q=0.5"), all),
+            (ContentEncoding::Gzip, NegotiationReason::Explicit)
+        );
+        assert_eq!(
+            negotiate(Some("x-gzip, *;q=0.5"), all),
+            (ContentEncoding::Gzip, NegotiationReason::Explicit)
+        );
+
+        // wildcard
+        assert_eq!(
+            negotiate(Some("*"), all),
+            (ContentEncoding::Zstd, NegotiationReason::Wildcard)
+        );
+        assert_eq!(
+            negotiate(Some("gzip;q=0.5, *"), FilterEncoding::br() | FilterEncoding::gzip()),
+            (ContentEncoding::Brotli, NegotiationReason::Wildcard)
+        );
+
+        // default
+        assert_eq!(
+            negotiate(None, all),
+            (ContentEncoding::Identity, NegotiationReason::Default)
+        );
+        assert_eq!(
+            negotiate(Some(""), all),
+            (ContentEncoding::Identity, NegotiationReason::Default)
+        );
+        assert_eq!(
+            negotiate(Some("br"), FilterEncoding::gzip()),
+            (ContentEncoding::Identity, NegotiationReason::Default)
+        );
+    }
 }
@@ -0,0 +1,216 @@
+use std::borrow::Cow;
+use std::fmt;
+
+use headers::Header;
+use http::HeaderValue;
+use smallvec::SmallVec;
+
+use super::quality::QValue;
+
+/// A language tag such as `en`, `en-US` or `zh-Hant`, compared case-insensitively.
+#[derive(Debug, Clone)]
+#[must_use]
+pub struct LanguageTag(Cow<'static, str>);
+
+impl LanguageTag {
+    /// Create a new language tag from a static string, such as `LanguageTag::from_static("en-US")`.
+    pub const fn from_static(tag: &'static str) -> Self {
+        LanguageTag(Cow::Borrowed(tag))
+    }
+
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Returns `true` if this is the `*` wildcard.
+    #[must_use]
+    pub fn is_wildcard(&self) -> bool {
+        &*self.0 == "*"
+    }
+
+    /// Returns `true` if this tag, used as a language range, matches the given tag
+    /// according to the basic filtering of [RFC 4647 section 3.3.1](https://www.rfc-editor.org/rfc/rfc4647#section-3.3.1),
+    /// i.e. `en` matches `en` and `en-US`, but not `eng`.
+    #[must_use]
+    pub fn matches(&self, tag: &LanguageTag) -> bool {
+        if self.is_wildcard() {
+            return true;
+        }
+
+        let (range, tag) = (self.0.as_bytes(), tag.0.as_bytes());
+
+        tag.len() >= range.len()
+            && tag[..range.len()].eq_ignore_ascii_case(range)
+            && (tag.len() == range.len() || tag[range.len()] == b'-')
+    }
+}
+
+impl From<&'static str> for LanguageTag {
+    #[inline]
+    fn from(tag: &'static str) -> Self {
+        LanguageTag::from_static(tag)
+    }
+}
+
+impl From<String> for LanguageTag {
+    #[inline]
+    fn from(tag: String) -> Self {
+        LanguageTag(Cow::Owned(tag))
+    }
+}
+
+impl PartialEq for LanguageTag {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.eq_ignore_ascii_case(&other.0)
+    }
+}
+
+impl Eq for LanguageTag {}
+
+impl fmt::Display for LanguageTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// The `Accept-Language` header, a list of language ranges with their q-values.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct AcceptLanguage(pub SmallVec<[(LanguageTag, QValue); 4]>);
+
+impl AcceptLanguage {
+    /// Pick the best language out of `available` for this header.
+    ///
+    /// Each available tag is given the q-value of the most specific range matching it,
+    /// and the tag with the highest non-zero q-value wins. Ties are broken by the order
+    /// of `available`, so it should be given in order of preference.
+    ///
+    /// If the header is empty, the first available language is returned.
+    #[must_use]
+    pub fn negotiate<'a>(&self, available: &'a [LanguageTag]) -> Option<&'a LanguageTag> {
+        if self.0.is_empty() {
+            return available.first();
+        }
+
+        let mut best: Option<(&LanguageTag, QValue)> = None;
+
+        for tag in available {
+            let mut matched: Option<(usize, QValue)> = None;
+
+            for (range, q) in &self.0 {
+                // wildcards are the least specific of all
+                let specificity = if range.is_wildcard() { 0 } else { range.0.len() };
+
+                if range.matches(tag) && matched.is_none_or(|(s, _)| specificity > s) {
+                    matched = Some((specificity, *q));
+                }
+            }
+
+            if let Some((_, q)) = matched {
+                if !q.is_zero() && best.is_none_or(|(_, best_q)| q > best_q) {
+                    best = Some((tag, q));
+                }
+            }
+        }
+
+        best.map(|(tag, _)| tag)
+    }
+}
+
+impl Header for AcceptLanguage {
+    fn name() -> &'static http::HeaderName {
+        &http::header::ACCEPT_LANGUAGE
+    }
+
+    fn decode<'i, I>(values: &mut I) -> Result<Self, headers::Error>
+    where
+        Self: Sized,
+        I: Iterator<Item = &'i HeaderValue>,
+    {
+        let mut languages = SmallVec::new();
+
+        for value in values {
+            let value = value.to_str().map_err(|_| headers::Error::invalid())?;
+
+            for item in value.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+                let mut v = item.splitn(2, ';');
+
+                let tag = match v.next().map(str::trim) {
+                    Some(tag) if !tag.is_empty() => tag,
+                    _ => return Err(headers::Error::invalid()),
+                };
+
+                let q = match v.next() {
+                    Some(qval) => QValue::parse(qval.trim()).ok_or(headers::Error::invalid())?,
+                    None => QValue::one(),
+                };
+
+                languages.push((LanguageTag::from(tag.to_owned()), q));
+            }
+        }
+
+        Ok(AcceptLanguage(languages))
+    }
+
+    fn encode<E: Extend<HeaderValue>>(&self, values: &mut E) {
+        use std::fmt::Write;
+
+        let mut s = String::new();
+
+        for (tag, q) in &self.0 {
+            if !s.is_empty() {
+                s.push_str(", ");
+            }
+
+            match *q == QValue::one() {
+                true => write!(s, "{tag}").unwrap(),
+                false => write!(s, "{tag};q={q}").unwrap(),
+            }
+        }
+
+        if let Ok(value) = HeaderValue::try_from(s) {
+            values.extend(Some(value));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> AcceptLanguage {
+        AcceptLanguage::decode(&mut [HeaderValue::from_str(s).unwrap()].iter()).unwrap()
+    }
+
+    const EN: LanguageTag = LanguageTag::from_static("en");
+    const FR: LanguageTag = LanguageTag::from_static("fr");
+    const EN_US: LanguageTag = LanguageTag::from_static("en-US");
+
+    #[test]
+    fn test_negotiate() {
+        assert_eq!(parse("en;q=0.8, fr;q=0.9").negotiate(&[EN, FR]), Some(&FR));
+        assert_eq!(parse("fr;q=0, en").negotiate(&[FR, EN]), Some(&EN));
+        assert_eq!(parse("de").negotiate(&[EN, FR]), None);
+        assert_eq!(parse("de, *;q=0.1").negotiate(&[EN, FR]), Some(&EN));
+
+        // ranges match subtags, and the most specific range wins
+        assert_eq!(parse("EN").negotiate(&[FR, EN_US]), Some(&EN_US));
+        assert_eq!(
+            parse("en;q=0.9, en-us;q=0.1, fr;q=0.5").negotiate(&[EN_US, FR]),
+            Some(&FR)
+        );
+
+        assert_eq!(AcceptLanguage::default().negotiate(&[EN, FR]), Some(&EN));
+    }
+
+    #[test]
+    fn test_encode_roundtrip() {
+        let header = parse("en-US,fr;q=0.5");
+
+        let mut values = Vec::new();
+        header.encode(&mut values);
+
+        assert_eq!(values[0], "en-US, fr;q=0.500");
+        assert_eq!(parse(values[0].to_str().unwrap()), header);
+    }
+}
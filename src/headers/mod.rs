@@ -4,13 +4,23 @@ use headers::{ContentType, Header as HeaderType, HeaderMapExt};
 
 use crate::{extract::FromRequestParts, response::IntoResponseParts, Error, RequestParts, ResponseParts};
 
+pub mod accept;
 pub mod accept_encoding;
+pub mod accept_language;
 pub mod entity_tag;
+pub mod quality;
+pub mod retry_after;
 pub mod server_timing;
+pub mod www_authenticate;
+
+pub use quality::QValue;
 
 pub static APPLICATION_CBOR: LazyLock<ContentType> =
     LazyLock::new(|| ContentType::from("application/cbor".parse::<mime::Mime>().unwrap()));
 
+pub static APPLICATION_NDJSON: LazyLock<ContentType> =
+    LazyLock::new(|| ContentType::from("application/x-ndjson".parse::<mime::Mime>().unwrap()));
+
 /// A typed header, which can be extracted from a request and inserted into a response.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[repr(transparent)]
@@ -0,0 +1,118 @@
+//! Quality values (`q=`) as used in `Accept-*` headers, see [RFC 9110 section 12.4.2](https://www.rfc-editor.org/rfc/rfc9110#section-12.4.2).
+
+use std::fmt;
+
+/// A quality value between `0` and `1` with up to three decimal places,
+/// stored as an integer in thousandths.
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[must_use]
+#[repr(transparent)]
+pub struct QValue {
+    pub(crate) value: u16,
+}
+
+impl fmt::Display for QValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.value >= 1000 {
+            f.write_str("1")
+        } else {
+            write!(f, "0.{:03}", self.value)
+        }
+    }
+}
+
+impl QValue {
+    #[must_use]
+    pub const fn new(value: u16) -> Option<Self> {
+        if value <= 1000 {
+            Some(Self { value })
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    pub const fn one() -> Self {
+        Self { value: 1000 }
+    }
+
+    #[inline]
+    pub const fn zero() -> Self {
+        Self { value: 0 }
+    }
+
+    /// The q-value in thousandths, from `0` to `1000`.
+    #[inline]
+    #[must_use]
+    pub const fn value(self) -> u16 {
+        self.value
+    }
+
+    /// Returns `true` if the q-value is zero, meaning "not acceptable".
+    #[inline]
+    #[must_use]
+    pub const fn is_zero(self) -> bool {
+        self.value == 0
+    }
+
+    pub fn wildcard(&mut self, new: Self) {
+        if self.value == 0 {
+            self.value = new.value;
+        }
+    }
+
+    // Parse a q-value as specified in RFC 7231 section 5.3.1.
+    #[must_use]
+    pub fn parse(s: &str) -> Option<Self> {
+        let mut c = s.chars();
+        // Parse "q=" (case-insensitively).
+        match c.next() {
+            Some('q' | 'Q') => (),
+            _ => return None,
+        };
+        match c.next() {
+            Some('=') => (),
+            _ => return None,
+        };
+
+        // Parse leading digit. Since valid q-values are between 0.000 and 1.000, only "0" and "1"
+        // are allowed.
+        let mut value = match c.next() {
+            Some('0') => 0,
+            Some('1') => 1000,
+            _ => return None,
+        };
+
+        // Parse optional decimal point.
+        match c.next() {
+            Some('.') => (),
+            None => return Some(Self { value }),
+            _ => return None,
+        };
+
+        // Parse optional fractional digits. The value of each digit is multiplied by `factor`.
+        // Since the q-value is represented as an integer between 0 and 1000, `factor` is `100` for
+        // the first digit, `10` for the next, and `1` for the digit after that.
+        let mut factor = 100;
+        loop {
+            match c.next() {
+                Some(n @ '0'..='9') => {
+                    // If `factor` is less than `1`, three digits have already been parsed. A
+                    // q-value having more than 3 fractional digits is invalid.
+                    if factor < 1 {
+                        return None;
+                    }
+                    // Add the digit's value multiplied by `factor` to `value`.
+                    value += factor * (n as u16 - '0' as u16);
+                }
+                None => {
+                    // No more characters to parse. Check that the value representing the q-value is
+                    // in the valid range.
+                    return if value <= 1000 { Some(Self { value }) } else { None };
+                }
+                _ => return None,
+            };
+            factor /= 10;
+        }
+    }
+}
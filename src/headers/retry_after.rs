@@ -0,0 +1,131 @@
+use std::time::{Duration, SystemTime};
+
+use headers::Header;
+use http::{HeaderValue, StatusCode};
+
+use crate::{IntoResponse, Response};
+
+/// The `Retry-After` header, either as a delay in seconds or as an absolute HTTP-date.
+///
+/// Delays are encoded in whole seconds, and dates to the second, so any
+/// sub-second precision is truncated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RetryAfter {
+    /// Retry after the given delay.
+    Delay(Duration),
+
+    /// Retry after the given point in time.
+    Date(SystemTime),
+}
+
+impl RetryAfter {
+    /// Returns how long to wait from `now` before retrying.
+    ///
+    /// Dates in the past give a zero duration.
+    #[must_use]
+    pub fn delay_from(&self, now: SystemTime) -> Duration {
+        match *self {
+            RetryAfter::Delay(delay) => delay,
+            RetryAfter::Date(date) => date.duration_since(now).unwrap_or_default(),
+        }
+    }
+}
+
+impl From<Duration> for RetryAfter {
+    #[inline]
+    fn from(delay: Duration) -> Self {
+        RetryAfter::Delay(delay)
+    }
+}
+
+impl From<SystemTime> for RetryAfter {
+    #[inline]
+    fn from(date: SystemTime) -> Self {
+        RetryAfter::Date(date)
+    }
+}
+
+impl Header for RetryAfter {
+    fn name() -> &'static http::HeaderName {
+        &http::header::RETRY_AFTER
+    }
+
+    fn decode<'i, I>(values: &mut I) -> Result<Self, headers::Error>
+    where
+        Self: Sized,
+        I: Iterator<Item = &'i HeaderValue>,
+    {
+        let value = values.next().ok_or_else(headers::Error::invalid)?;
+
+        let s = value.to_str().map_err(|_| headers::Error::invalid())?.trim();
+
+        if !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()) {
+            return match s.parse() {
+                Ok(secs) => Ok(RetryAfter::Delay(Duration::from_secs(secs))),
+                Err(_) => Err(headers::Error::invalid()),
+            };
+        }
+
+        headers::Date::decode(&mut std::iter::once(value)).map(|date| RetryAfter::Date(date.into()))
+    }
+
+    fn encode<E: Extend<HeaderValue>>(&self, values: &mut E) {
+        match *self {
+            RetryAfter::Delay(delay) => values.extend(Some(HeaderValue::from(delay.as_secs()))),
+            RetryAfter::Date(date) => headers::Date::from(date).encode(values),
+        }
+    }
+}
+
+/// A `503 Service Unavailable` response with a [`RetryAfter`] header,
+/// such as for maintenance windows or scheduled downtime.
+#[must_use]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(transparent)]
+pub struct ServiceUnavailable(pub RetryAfter);
+
+impl IntoResponse for ServiceUnavailable {
+    fn into_response(self) -> Response {
+        "Service Unavailable"
+            .with_status(StatusCode::SERVICE_UNAVAILABLE)
+            .with_header(self.0)
+            .into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(retry_after: RetryAfter) -> (HeaderValue, RetryAfter) {
+        let mut values = Vec::new();
+        retry_after.encode(&mut values);
+
+        let decoded = RetryAfter::decode(&mut values.iter()).unwrap();
+
+        (values.pop().unwrap(), decoded)
+    }
+
+    #[test]
+    fn test_retry_after_round_trip() {
+        let delay = RetryAfter::Delay(Duration::from_secs(120));
+        assert_eq!(round_trip(delay), (HeaderValue::from_static("120"), delay));
+
+        // HTTP-dates have a resolution of one second
+        let date = RetryAfter::Date(SystemTime::UNIX_EPOCH + Duration::from_secs(784_111_777));
+        assert_eq!(
+            round_trip(date),
+            (HeaderValue::from_static("Sun, 06 Nov 1994 08:49:37 GMT"), date)
+        );
+
+        assert!(RetryAfter::decode(&mut [HeaderValue::from_static("soon")].iter()).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_service_unavailable() {
+        let resp = ServiceUnavailable(RetryAfter::Delay(Duration::from_secs(30))).into_response();
+
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(resp.headers()[http::header::RETRY_AFTER], "30");
+    }
+}
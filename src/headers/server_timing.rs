@@ -105,6 +105,81 @@ impl ServerTimings {
 
         self
     }
+
+    /// Starts a timer for the given metric, which records the elapsed
+    /// duration into this collection when the returned guard is dropped.
+    ///
+    /// ```
+    /// use ftl::headers::{server_timing::ServerTimings, Header};
+    /// # async fn query_database() -> Vec<u32> { vec![1, 2, 3] }
+    /// # fn render(rows: &[u32]) -> String { format!("{rows:?}") }
+    ///
+    /// async fn handler() -> impl ftl::IntoResponse {
+    ///     let mut timings = ServerTimings::new();
+    ///
+    ///     let rows = {
+    ///         let _timer = timings.start("db");
+    ///         query_database().await
+    ///     };
+    ///
+    ///     let html = {
+    ///         let _timer = timings.start("render").with_description("Template Render");
+    ///         render(&rows)
+    ///     };
+    ///
+    ///     // Server-Timing: db;dur=0.012, render;desc="Template Render";dur=0.004
+    ///     (html, Header(timings))
+    /// }
+    /// ```
+    pub fn start(&mut self, name: impl Into<Cow<'static, str>>) -> TimingGuard<'_> {
+        TimingGuard {
+            timings: self,
+            timing: Some(ServerTiming::new(name)),
+            start: Instant::now(),
+        }
+    }
+}
+
+/// Scoped timer returned by [`ServerTimings::start`], which records the elapsed duration
+/// into the [`ServerTimings`] when dropped or [stopped](TimingGuard::stop).
+#[must_use = "the timer records its duration when dropped, so dropping it immediately records nothing useful"]
+pub struct TimingGuard<'a> {
+    timings: &'a mut ServerTimings,
+    timing: Option<ServerTiming>,
+    start: Instant,
+}
+
+impl TimingGuard<'_> {
+    /// Sets the description of the recorded metric.
+    pub fn with_description(mut self, description: impl Into<Cow<'static, str>>) -> Self {
+        if let Some(timing) = self.timing.take() {
+            self.timing = Some(timing.with_description(description));
+        }
+
+        self
+    }
+
+    /// Stops the timer, recording the elapsed duration and returning it.
+    #[allow(clippy::must_use_candidate)] // the duration is already recorded, so it's fine to ignore
+    pub fn stop(mut self) -> Duration {
+        self.record()
+    }
+
+    fn record(&mut self) -> Duration {
+        let elapsed = self.start.elapsed();
+
+        if let Some(timing) = self.timing.take() {
+            self.timings.push(timing.with_duration(elapsed));
+        }
+
+        elapsed
+    }
+}
+
+impl Drop for TimingGuard<'_> {
+    fn drop(&mut self) {
+        self.record();
+    }
 }
 
 use headers::{Header, HeaderName, HeaderValue};
@@ -267,4 +342,29 @@ mod test {
 
         assert_eq!(iter.next(), None);
     }
+
+    #[test]
+    fn test_timing_guard() {
+        let mut timings = ServerTimings::new();
+
+        {
+            let _timer = timings.start("db");
+            std::thread::sleep(Duration::from_millis(2));
+        }
+
+        let elapsed = timings.start("render").with_description("Render").stop();
+
+        let mut iter = timings.iter();
+
+        let db = iter.next().unwrap();
+        assert_eq!(db.name, "db");
+        assert!(db.duration.unwrap() >= Duration::from_millis(2));
+
+        let render = iter.next().unwrap();
+        assert_eq!(render.name, "render");
+        assert_eq!(render.description.as_deref(), Some("Render"));
+        assert_eq!(render.duration, Some(elapsed));
+
+        assert_eq!(iter.next(), None);
+    }
 }
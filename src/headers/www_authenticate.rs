@@ -0,0 +1,118 @@
+use headers::Header;
+use http::{HeaderValue, StatusCode};
+
+use crate::{error::ErrorResponse, Error, IntoResponse, Response};
+
+/// The `WWW-Authenticate` header, a challenge telling the client how to authenticate.
+///
+/// `401 Unauthorized` responses are required to include at least one challenge,
+/// see [`Unauthorized`] for a response that does.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[repr(transparent)]
+pub struct WwwAuthenticate(pub HeaderValue);
+
+impl WwwAuthenticate {
+    /// A `Bearer` challenge without parameters.
+    #[must_use]
+    pub const fn bearer() -> Self {
+        WwwAuthenticate(HeaderValue::from_static("Bearer"))
+    }
+
+    /// A `Bearer` challenge for the given realm.
+    #[must_use]
+    pub fn bearer_realm(realm: &str) -> Self {
+        Self::with_realm("Bearer", realm)
+    }
+
+    /// A `Basic` challenge for the given realm, with `charset="UTF-8"` as per RFC 7617.
+    #[must_use]
+    pub fn basic(realm: &str) -> Self {
+        let mut challenge = Self::with_realm("Basic", realm);
+
+        if let Ok(value) = HeaderValue::from_bytes(&[challenge.0.as_bytes(), b", charset=\"UTF-8\""].concat()) {
+            challenge.0 = value;
+        }
+
+        challenge
+    }
+
+    fn with_realm(scheme: &str, realm: &str) -> Self {
+        let mut value = format!("{scheme} realm=\"");
+
+        for c in realm.chars() {
+            if matches!(c, '"' | '\\') {
+                value.push('\\');
+            }
+            value.push(c);
+        }
+
+        value.push('"');
+
+        // fall back to the bare scheme if the realm contains invalid characters, such as newlines
+        WwwAuthenticate(HeaderValue::try_from(value).unwrap_or_else(|_| HeaderValue::from_str(scheme).unwrap()))
+    }
+}
+
+impl Header for WwwAuthenticate {
+    fn name() -> &'static http::HeaderName {
+        &http::header::WWW_AUTHENTICATE
+    }
+
+    fn decode<'i, I>(values: &mut I) -> Result<Self, headers::Error>
+    where
+        Self: Sized,
+        I: Iterator<Item = &'i HeaderValue>,
+    {
+        values.next().cloned().map(WwwAuthenticate).ok_or_else(headers::Error::invalid)
+    }
+
+    fn encode<E: Extend<HeaderValue>>(&self, values: &mut E) {
+        values.extend(Some(self.0.clone()));
+    }
+}
+
+/// A `401 Unauthorized` response with a [`WwwAuthenticate`] challenge.
+///
+/// This converts into an [`Error`], so it can be used as the rejection of authentication
+/// extractors, unlike the bare [`Error::Unauthorized`] which has no challenge.
+#[must_use]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[repr(transparent)]
+pub struct Unauthorized(pub WwwAuthenticate);
+
+impl IntoResponse for Unauthorized {
+    fn into_response(self) -> Response {
+        "Unauthorized".with_status(StatusCode::UNAUTHORIZED).with_header(self.0).into_response()
+    }
+}
+
+impl From<Unauthorized> for Error {
+    #[inline]
+    fn from(unauthorized: Unauthorized) -> Self {
+        ErrorResponse::new(unauthorized).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_challenges() {
+        assert_eq!(WwwAuthenticate::bearer().0, "Bearer");
+        assert_eq!(WwwAuthenticate::bearer_realm("api").0, "Bearer realm=\"api\"");
+        assert_eq!(
+            WwwAuthenticate::basic("say \"hi\"").0,
+            "Basic realm=\"say \\\"hi\\\"\", charset=\"UTF-8\""
+        );
+    }
+
+    #[test]
+    fn test_unauthorized_error() {
+        let error: Error = Unauthorized(WwwAuthenticate::bearer_realm("api")).into();
+        let resp = error.into_response();
+
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(resp.headers()[http::header::WWW_AUTHENTICATE], "Bearer realm=\"api\"");
+    }
+}
@@ -0,0 +1,520 @@
+//! In-memory caching of `GET` responses.
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use bytes::Bytes;
+use headers::{CacheControl, HeaderMapExt as _};
+use http::{
+    header,
+    uri::{Authority, Scheme},
+    HeaderMap, HeaderName, HeaderValue, Method, StatusCode,
+};
+use http_body::Body as _;
+use http_body_util::BodyExt as _;
+
+use crate::{
+    body::Body,
+    extract::{extract_authority, scheme::request_scheme},
+    headers::entity_tag::{EntityTag, IfNoneMatch},
+    service::ServiceFuture,
+    IntoResponse, Layer, Request, Response, Service,
+};
+
+type CacheMap = scc::HashMap<CacheKey, Arc<CachedResponse>, foldhash::fast::RandomState>;
+
+/// Largest TTL a response can be cached for, as [RFC 9111 §1.2.2] caps delta-seconds at 2^31.
+///
+/// [RFC 9111 §1.2.2]: https://www.rfc-editor.org/rfc/rfc9111#section-1.2.2
+const MAX_TTL: Duration = Duration::from_secs(1 << 31);
+
+/// Headers of a `304 Not Modified` that replace those of the stored response when revalidating,
+/// as per [RFC 9111 §4.3.4](https://www.rfc-editor.org/rfc/rfc9111#section-4.3.4).
+const REVALIDATED_HEADERS: [HeaderName; 5] = [
+    header::ETAG,
+    header::CACHE_CONTROL,
+    header::DATE,
+    header::EXPIRES,
+    header::LAST_MODIFIED,
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    /// Scheme of the request, if known.
+    scheme: Option<Scheme>,
+
+    /// Authority of the request, from the `Host` header or request URI.
+    authority: Option<Authority>,
+
+    /// Path and query of the request.
+    path: Box<str>,
+
+    /// Request values of the configured vary headers, in order.
+    vary: Box<[Option<HeaderValue>]>,
+}
+
+struct CachedResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+    etag: Option<EntityTag>,
+    stored: Instant,
+    expires: Instant,
+}
+
+impl CachedResponse {
+    fn is_fresh(&self, now: Instant) -> bool {
+        now < self.expires
+    }
+
+    /// Replays the cached response, or a `304 Not Modified` if the client already has it.
+    fn replay(&self, if_none_match: Option<&IfNoneMatch>, now: Instant) -> Response {
+        let not_modified = match (if_none_match, &self.etag) {
+            (Some(if_none_match), Some(etag)) => if_none_match.iter().any(|e| e.weak_eq(etag)),
+            _ => false,
+        };
+
+        let mut resp = match not_modified {
+            true => Response::new(Body::empty()),
+            false => Response::new(Body::from(self.body.clone())),
+        };
+
+        *resp.status_mut() = match not_modified {
+            true => StatusCode::NOT_MODIFIED,
+            false => self.status,
+        };
+
+        *resp.headers_mut() = self.headers.clone();
+
+        if not_modified {
+            resp.headers_mut().remove(header::CONTENT_LENGTH);
+        }
+
+        let age = now.saturating_duration_since(self.stored).as_secs();
+        resp.headers_mut().insert(header::AGE, HeaderValue::from(age));
+
+        resp
+    }
+}
+
+/// A layer that caches successful `GET` responses in memory, keyed by the request scheme, authority,
+/// path and query along with the values of any configured [`vary`](ResponseCacheLayer::vary) headers.
+///
+/// Responses are only cached if they:
+/// - have a `200 OK` status,
+/// - are not marked `Cache-Control: no-store`, `no-cache` or `private`,
+/// - do not set cookies,
+/// - only `Vary` on headers the layer was configured with,
+/// - have a body of known size no larger than the [`max_body_size`](ResponseCacheLayer::max_body_size).
+///
+/// Cached responses expire after the response's `s-maxage` or `max-age`, if given,
+/// otherwise the layer's default TTL. Expired responses with an `ETag` are revalidated by
+/// forwarding the request with `If-None-Match`, and reused if the inner service replies
+/// with `304 Not Modified`, taking the `ETag`, `Cache-Control`, `Date`, `Expires` and `Last-Modified`
+/// headers of the `304` in place of the stored ones. Clients sending a matching `If-None-Match` get a `304 Not Modified`
+/// from the cache directly.
+///
+/// Requests with `Cache-Control: no-store` or `no-cache`, or with an `Authorization` header,
+/// bypass the cache.
+///
+/// Clones of the layer share the same cache, so it can be kept around to
+/// [`invalidate`](ResponseCacheLayer::invalidate) entries after writes.
+#[derive(Clone)]
+#[must_use]
+pub struct ResponseCacheLayer {
+    entries: Arc<CacheMap>,
+    ttl: Duration,
+    max_body_size: u64,
+    max_entries: usize,
+    vary: Arc<[HeaderName]>,
+}
+
+/// The service created by the [`ResponseCacheLayer`].
+#[derive(Clone)]
+pub struct ResponseCache<S> {
+    inner: S,
+    layer: ResponseCacheLayer,
+}
+
+impl ResponseCacheLayer {
+    /// Creates a new cache layer with the given default TTL, allowing up to 1024 entries
+    /// of at most 1 MiB each.
+    pub fn new(ttl: Duration) -> Self {
+        ResponseCacheLayer {
+            entries: Arc::default(),
+            ttl,
+            max_body_size: 1024 * 1024,
+            max_entries: 1024,
+            vary: Arc::new([]),
+        }
+    }
+
+    /// Sets the largest response body that will be cached, in bytes.
+    pub fn max_body_size(mut self, max_body_size: u64) -> Self {
+        self.max_body_size = max_body_size;
+        self
+    }
+
+    /// Sets the maximum number of cached responses.
+    ///
+    /// When full, expired entries are evicted to make room, and if none have expired
+    /// new responses are not cached until some do.
+    pub fn max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+
+    /// Adds a request header whose value is part of the cache key, such as `Accept-Encoding`.
+    pub fn vary(mut self, name: HeaderName) -> Self {
+        let mut vary = self.vary.to_vec();
+        vary.push(name);
+        self.vary = vary.into();
+        self
+    }
+
+    /// Returns the number of cached responses, including expired ones not yet evicted.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if no responses are cached.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Removes all cached responses for the given path, regardless of host, query or vary headers.
+    pub async fn invalidate(&self, path: &str) {
+        self.entries
+            .retain_async(|key, _| key.path.split_once('?').map_or(&*key.path, |(p, _)| p) != path)
+            .await;
+    }
+
+    /// Removes all cached responses.
+    pub async fn clear(&self) {
+        self.entries.clear_async().await;
+    }
+
+    fn key(&self, req: &Request) -> CacheKey {
+        let uri = req.uri();
+
+        CacheKey {
+            scheme: request_scheme(req.headers(), uri, req.extensions()).ok(),
+            authority: extract_authority(uri, req.headers()).ok(),
+            path: uri.path_and_query().map_or(uri.path(), |pq| pq.as_str()).into(),
+            vary: self.vary.iter().map(|name| req.headers().get(name).cloned()).collect(),
+        }
+    }
+
+    /// Returns the TTL for a response, or `None` if it can't be cached.
+    fn cacheable(&self, resp: &Response) -> Option<Duration> {
+        if resp.status() != StatusCode::OK || resp.headers().contains_key(header::SET_COOKIE) {
+            return None;
+        }
+
+        if resp.body().size_hint().upper().is_none_or(|len| len > self.max_body_size) {
+            return None;
+        }
+
+        for value in resp.headers().get_all(header::VARY) {
+            let value = value.to_str().ok()?;
+
+            for name in value.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+                if !self.vary.iter().any(|vary| vary.as_str().eq_ignore_ascii_case(name)) {
+                    return None; // includes `Vary: *`
+                }
+            }
+        }
+
+        self.ttl(resp.headers())
+    }
+
+    /// Returns the TTL given by the response headers, or `None` if they forbid caching.
+    fn ttl(&self, headers: &HeaderMap) -> Option<Duration> {
+        let ttl = match headers.typed_get::<CacheControl>() {
+            Some(cc) if cc.no_store() || cc.no_cache() || cc.private() => return None,
+            Some(cc) => cc.s_max_age().or(cc.max_age()).unwrap_or(self.ttl),
+            None => self.ttl,
+        };
+
+        (!ttl.is_zero()).then_some(ttl.min(MAX_TTL))
+    }
+
+    async fn store(&self, key: CacheKey, entry: CachedResponse) {
+        if self.entries.len() >= self.max_entries {
+            let now = Instant::now();
+            self.entries.retain_async(|_, entry| entry.is_fresh(now)).await;
+
+            if self.entries.len() >= self.max_entries && !self.entries.contains_async(&key).await {
+                return;
+            }
+        }
+
+        self.entries.upsert_async(key, Arc::new(entry)).await;
+    }
+}
+
+impl<S> Layer<S> for ResponseCacheLayer {
+    type Service = ResponseCache<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ResponseCache {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+fn bypass(req: &Request) -> bool {
+    if req.method() != Method::GET || req.headers().contains_key(header::AUTHORIZATION) {
+        return true;
+    }
+
+    req.headers().typed_get::<CacheControl>().is_some_and(|cc| cc.no_store() || cc.no_cache())
+}
+
+impl<S> Service<Request> for ResponseCache<S>
+where
+    S: Service<Request, Response = Response>,
+{
+    type Response = Response;
+    type Error = S::Error;
+
+    fn call(&self, mut req: Request) -> impl ServiceFuture<Self::Response, Self::Error> {
+        async move {
+            if bypass(&req) {
+                return self.inner.call(req).await;
+            }
+
+            let layer = &self.layer;
+            let key = layer.key(&req);
+            let if_none_match = req.headers().typed_get::<IfNoneMatch>();
+            let now = Instant::now();
+
+            let cached = layer.entries.read_async(&key, |_, entry| entry.clone()).await;
+
+            // entry to revalidate if the inner service replies with 304 Not Modified
+            let mut revalidating = None;
+
+            if let Some(cached) = cached {
+                if cached.is_fresh(now) {
+                    return Ok(cached.replay(if_none_match.as_ref(), now));
+                }
+
+                // only revalidate if the client isn't making its own conditional request
+                if let (Some(etag), None) = (cached.etag, &if_none_match) {
+                    req.headers_mut().typed_insert(IfNoneMatch(vec![etag]));
+                    revalidating = Some(cached);
+                }
+            }
+
+            let resp = self.inner.call(req).await?;
+
+            if let Some(cached) = revalidating {
+                if resp.status() == StatusCode::NOT_MODIFIED {
+                    let mut headers = cached.headers.clone();
+
+                    for name in REVALIDATED_HEADERS {
+                        if resp.headers().contains_key(&name) {
+                            headers.remove(&name);
+
+                            for value in resp.headers().get_all(&name) {
+                                headers.append(&name, value.clone());
+                            }
+                        }
+                    }
+
+                    let now = Instant::now();
+
+                    let mut entry = CachedResponse {
+                        status: cached.status,
+                        etag: headers.typed_get::<EntityTag>(),
+                        headers,
+                        body: cached.body.clone(),
+                        stored: now,
+                        expires: now,
+                    };
+
+                    let resp = entry.replay(None, now);
+
+                    match layer.ttl(&entry.headers).and_then(|ttl| now.checked_add(ttl)) {
+                        Some(expires) => {
+                            entry.expires = expires;
+                            layer.store(key, entry).await;
+                        }
+                        // no longer cacheable, so drop the stale entry
+                        None => _ = layer.entries.remove_async(&key).await,
+                    }
+
+                    return Ok(resp);
+                }
+            }
+
+            let Some(ttl) = layer.cacheable(&resp) else {
+                return Ok(resp);
+            };
+
+            let (parts, body) = resp.into_parts();
+
+            let body = match body.collect().await {
+                Ok(body) => body.to_bytes(),
+                Err(e) => return Ok(e.into_response()),
+            };
+
+            let now = Instant::now();
+
+            let Some(expires) = now.checked_add(ttl) else {
+                return Ok(Response::from_parts(parts, Body::from(body)));
+            };
+
+            let entry = CachedResponse {
+                status: parts.status,
+                etag: parts.headers.typed_get::<EntityTag>(),
+                headers: parts.headers.clone(),
+                body: body.clone(),
+                stored: now,
+                expires,
+            };
+
+            layer.store(key, entry).await;
+
+            Ok(Response::from_parts(parts, Body::from(body)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::{extract::State, Router};
+
+    #[tokio::test]
+    async fn test_response_cache() {
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        let mut router = Router::<_, Response>::with_state(calls.clone());
+        router.get("/items", |State(calls): State<Arc<AtomicUsize>>| async move {
+            format!("items {}", calls.fetch_add(1, Ordering::SeqCst))
+        });
+        router.get("/secret", |State(calls): State<Arc<AtomicUsize>>| async move {
+            let n = calls.fetch_add(1, Ordering::SeqCst);
+            format!("secret {n}").with_header(CacheControl::new().with_private())
+        });
+
+        let cache = ResponseCacheLayer::new(Duration::from_secs(60));
+        let service = cache.layer(router);
+
+        async fn get(service: &impl Service<Request, Response = Response>, path: &str) -> String {
+            let req = http::Request::get(path).body(Body::empty()).unwrap();
+
+            match service.call(req).await {
+                Ok(resp) => resp.into_body().to_string().await.unwrap(),
+                Err(_) => unreachable!(),
+            }
+        }
+
+        assert_eq!(get(&service, "/items").await, "items 0");
+        assert_eq!(get(&service, "/items").await, "items 0");
+        assert_eq!(get(&service, "/items?page=2").await, "items 1");
+
+        // private responses are never cached
+        assert_eq!(get(&service, "/secret").await, "secret 2");
+        assert_eq!(get(&service, "/secret").await, "secret 3");
+
+        cache.invalidate("/items").await;
+        assert!(cache.is_empty());
+
+        assert_eq!(get(&service, "/items").await, "items 4");
+    }
+
+    #[tokio::test]
+    async fn test_response_cache_revalidation() {
+        const ETAG: EntityTag = EntityTag::strong("v1");
+
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        let mut router = Router::<_, Response>::with_state(calls.clone());
+        router.get(
+            "/doc",
+            |State(calls): State<Arc<AtomicUsize>>, req: Request| async move {
+                calls.fetch_add(1, Ordering::SeqCst);
+
+                match req.headers().typed_get::<IfNoneMatch>() {
+                    Some(inm) if inm.contains(&ETAG) => StatusCode::NOT_MODIFIED
+                        .with_header(CacheControl::new().with_public().with_max_age(Duration::from_secs(3600)))
+                        .into_response(),
+                    _ => "document".with_header(ETAG).into_response(),
+                }
+            },
+        );
+
+        let service = ResponseCacheLayer::new(Duration::from_millis(50)).layer(router);
+
+        let get = |inm: Option<&'static str>| {
+            let mut req = http::Request::get("/doc");
+
+            if let Some(inm) = inm {
+                req = req.header(header::IF_NONE_MATCH, inm);
+            }
+
+            req.body(Body::empty()).unwrap()
+        };
+
+        let resp = service.call(get(None)).await.unwrap();
+        assert_eq!(resp.into_body().to_string().await.unwrap(), "document");
+
+        // fresh, with a matching etag from the client
+        let resp = service.call(get(Some("\"v1\""))).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+
+        // stale, revalidated with the inner service
+        let resp = service.call(get(None)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers()[header::CACHE_CONTROL], "public, max-age=3600");
+        assert_eq!(resp.into_body().to_string().await.unwrap(), "document");
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+
+        // still fresh, as the 304 extended the lifetime of the stored response
+        let resp = service.call(get(None)).await.unwrap();
+        assert_eq!(resp.headers()[header::ETAG], "\"v1\"");
+        assert_eq!(resp.headers()[header::CACHE_CONTROL], "public, max-age=3600");
+        assert_eq!(resp.into_body().to_string().await.unwrap(), "document");
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_response_cache_authority() {
+        let mut router = Router::<(), Response>::with_state(());
+        router.get("/", |authority: Authority| async move { authority.to_string() });
+
+        let service = ResponseCacheLayer::new(Duration::from_secs(60)).layer(router);
+
+        let get = |host: &'static str, proto: &'static str| {
+            let req = http::Request::get("/")
+                .header(header::HOST, host)
+                .header("x-forwarded-proto", proto)
+                .body(Body::empty())
+                .unwrap();
+
+            let resp = service.call(req);
+            async move { resp.await.unwrap().into_body().to_string().await.unwrap() }
+        };
+
+        assert_eq!(get("a.example", "https").await, "a.example");
+        assert_eq!(get("b.example", "https").await, "b.example");
+        assert_eq!(get("a.example", "https").await, "a.example");
+        assert_eq!(get("a.example", "http").await, "a.example");
+
+        assert_eq!(service.layer.len(), 3);
+    }
+}
@@ -1,10 +1,14 @@
 use std::io;
+use std::pin::Pin;
 use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
 
 use headers::HeaderMapExt as _;
-use http::header;
+use http::{header, HeaderValue};
 use http_body::Frame;
 use http_body_util::BodyStream;
+use tokio::io::{AsyncBufRead, AsyncRead};
 use tokio_stream::StreamExt;
 use tokio_util::io::{ReaderStream, StreamReader};
 
@@ -16,22 +20,60 @@ use crate::{Layer, Service};
 
 pub mod predicate;
 
+pub use super::NoCompression;
+
 use predicate::{DefaultPredicate, Predicate};
 
+/// A custom content encoding for the [`CompressionLayer`], registered with [`CompressionLayer::encoder`].
+///
+/// ```rust,ignore
+/// struct Lz4;
+///
+/// impl Encoder for Lz4 {
+///     fn token(&self) -> &'static str {
+///         "lz4"
+///     }
+///
+///     fn encode(&self, body: Pin<Box<dyn AsyncBufRead + Send>>, _level: Level) -> Pin<Box<dyn AsyncRead + Send>> {
+///         Box::pin(Lz4Encoder::new(body))
+///     }
+/// }
+///
+/// static LZ4: Lz4 = Lz4;
+///
+/// let layer = CompressionLayer::new().encoder(&LZ4);
+/// ```
+pub trait Encoder: Send + Sync + 'static {
+    /// The token for this encoding in `Accept-Encoding` and `Content-Encoding` headers, such as `lz4`.
+    ///
+    /// This is matched case-insensitively against the `Accept-Encoding` header.
+    fn token(&self) -> &'static str;
+
+    /// Wraps the uncompressed body, returning a reader of the encoded body.
+    fn encode(&self, body: Pin<Box<dyn AsyncBufRead + Send>>, level: Level) -> Pin<Box<dyn AsyncRead + Send>>;
+}
+
 #[derive(Clone, Copy)]
 #[must_use]
 pub struct CompressionLayer<P: Predicate = DefaultPredicate> {
     filter: FilterEncoding,
     predicate: P,
     level: Level,
+    poll_budget: Option<Duration>,
+    encoder: Option<&'static dyn Encoder>,
 }
 
+/// Default for [`CompressionLayer::poll_budget`]
+const DEFAULT_POLL_BUDGET: Duration = Duration::from_millis(2);
+
 impl Default for CompressionLayer<DefaultPredicate> {
     fn default() -> Self {
         Self {
             filter: FilterEncoding::default(),
             predicate: DefaultPredicate,
             level: Level::Default,
+            poll_budget: Some(DEFAULT_POLL_BUDGET),
+            encoder: None,
         }
     }
 }
@@ -89,6 +131,34 @@ impl CompressionLayer {
         self
     }
 
+    /// Sets the maximum time a compressed body may spend encoding before yielding to the runtime.
+    ///
+    /// Compression is performed synchronously as the body is polled, and as long as the
+    /// connection can accept more data, the body is polled continuously. For large bodies at
+    /// high compression levels, especially with Brotli, this could otherwise monopolize a
+    /// runtime worker thread and stall other tasks. Once the budget is exhausted, the body
+    /// yields and is rescheduled, letting other tasks run in between.
+    ///
+    /// Each individual encoding step is bounded by the size of the output chunk, so this budget
+    /// may be exceeded slightly. Smaller budgets improve fairness at the cost of more frequent
+    /// rescheduling, which slightly reduces throughput for the compressed response itself.
+    /// `None` disables yielding entirely.
+    ///
+    /// Defaults to 2 milliseconds.
+    pub fn poll_budget(mut self, budget: impl Into<Option<Duration>>) -> Self {
+        self.poll_budget = budget.into();
+        self
+    }
+
+    /// Registers a custom [`Encoder`], used when the client accepts its token with a q-value
+    /// at least as high as that of the preferred built-in encoding.
+    ///
+    /// Only one custom encoder can be registered, replacing any previous one.
+    pub fn encoder(mut self, encoder: &'static dyn Encoder) -> Self {
+        self.encoder = Some(encoder);
+        self
+    }
+
     /// Disables the gzip encoding.
     ///
     /// This method is available even if the `gzip` crate feature is disabled.
@@ -130,6 +200,8 @@ impl CompressionLayer {
             filter: self.filter,
             predicate,
             level: self.level,
+            poll_budget: self.poll_budget,
+            encoder: self.encoder,
         }
     }
 }
@@ -148,6 +220,31 @@ where
     }
 }
 
+/// Checks if a response should be compressed, which is never the case for responses that
+/// are already encoded, partial, without a body, or marked with [`NoCompression`], regardless of the predicate.
+fn should_compress<P: Predicate>(parts: &crate::ResponseParts, predicate: &P) -> bool {
+    use http::StatusCode;
+
+    if parts.extensions.get::<NoCompression>().is_some() {
+        return false;
+    }
+
+    // bodiless responses have nothing to compress, and compressing a partial response
+    // would corrupt it, even if the `Content-Range` header was stripped
+    if parts.status.is_informational()
+        || matches!(
+            parts.status,
+            StatusCode::NO_CONTENT | StatusCode::PARTIAL_CONTENT | StatusCode::NOT_MODIFIED
+        )
+    {
+        return false;
+    }
+
+    !parts.headers.contains_key(header::CONTENT_ENCODING)
+        && !parts.headers.contains_key(header::CONTENT_RANGE)
+        && predicate.should_compress(parts)
+}
+
 impl<S, P, ReqBody, RespBody> Service<http::Request<ReqBody>> for Compression<S, P>
 where
     S: Service<http::Request<ReqBody>, Response = http::Response<RespBody>>,
@@ -160,29 +257,44 @@ where
 
     fn call(
         &self,
-        req: http::Request<ReqBody>,
+        mut req: http::Request<ReqBody>,
     ) -> impl crate::service::ServiceFuture<Self::Response, Self::Error> {
-        let encoding = req
-            .headers()
-            .typed_get::<AcceptEncoding>()
-            .unwrap_or_default()
-            .preferred_encoding(self.layer.filter);
+        // allow handlers to negotiate the encoding themselves using the same filter
+        req.extensions_mut().insert(self.layer.filter);
+
+        let negotiated = AcceptEncoding::negotiate(req.headers(), self.layer.filter);
+        let encoding = negotiated.encoding;
+
+        log::trace!("negotiated {} encoding ({:?})", encoding.as_str(), negotiated.reason);
+
+        let custom = self.layer.encoder.filter(|custom| {
+            let q = AcceptEncoding::custom_quality(req.headers().get_all(header::ACCEPT_ENCODING), custom.token());
+
+            !q.is_zero() && (encoding == ContentEncoding::Identity || q >= negotiated.quality)
+        });
 
         let inner = self.inner.call(req);
 
         async move {
             let (mut parts, body) = inner.await?.into_parts();
+            let body = Body::from_any_body(body);
+
+            // let the predicate see the length of bodies with a known size, such as to skip small responses
+            if !parts.headers.contains_key(header::CONTENT_LENGTH) {
+                // empty bodies may be for `HEAD` requests, where the length would be wrong
+                if let Some(len) = body.content_length().filter(|&len| len > 0) {
+                    parts.headers.typed_insert(headers::ContentLength(len));
+                }
+            }
 
-            let should_compress = !parts.headers.contains_key(header::CONTENT_ENCODING)
-                && !parts.headers.contains_key(header::CONTENT_RANGE)
-                && self.layer.predicate.should_compress(&parts);
+            let should_compress = should_compress(&parts, &self.layer.predicate);
 
             if should_compress {
                 parts.headers.append(header::VARY, header::ACCEPT_ENCODING.into());
             }
 
-            if !should_compress || encoding == ContentEncoding::Identity {
-                return Ok(http::Response::from_parts(parts, Body::from_any_body(body)));
+            if !should_compress || (encoding == ContentEncoding::Identity && custom.is_none()) {
+                return Ok(http::Response::from_parts(parts, body));
             }
 
             use std::sync::Mutex;
@@ -203,9 +315,8 @@ where
 
             let map = move |r: Result<_, io::Error>| match r {
                 Ok(data) => Ok(Frame::data(data)),
-                Err(e) => match e.downcast::<<RespBody as http_body::Body>::Error>() {
-                    // TODO: Handle internal body errors better?
-                    Ok(e) => Err(BodyError::Generic(e.into())),
+                Err(e) => match e.downcast::<BodyError>() {
+                    Ok(e) => Err(e),
                     Err(e) => Err(BodyError::Io(e)),
                 },
             };
@@ -222,19 +333,35 @@ where
 
             use async_compression::tokio::bufread::{BrotliEncoder, DeflateEncoder, GzipEncoder, ZstdEncoder};
 
-            let compressed = match encoding {
-                ContentEncoding::Identity => unreachable!(),
-                ContentEncoding::Deflate => Body::stream(
-                    ReaderStream::new(DeflateEncoder::with_quality(stream, self.layer.level))
-                        .map(map)
-                        .chain(trailers),
+            let budget = self.layer.poll_budget;
+
+            let compressed = match (custom, encoding) {
+                (Some(custom), _) => Body::stream(
+                    Budgeted::new(
+                        ReaderStream::new(custom.encode(Box::pin(stream), self.layer.level)),
+                        budget,
+                    )
+                    .map(map)
+                    .chain(trailers),
                 ),
-                ContentEncoding::Gzip => Body::stream(
-                    ReaderStream::new(GzipEncoder::with_quality(stream, self.layer.level))
-                        .map(map)
-                        .chain(trailers),
+                (None, ContentEncoding::Identity) => unreachable!(),
+                (None, ContentEncoding::Deflate) => Body::stream(
+                    Budgeted::new(
+                        ReaderStream::new(DeflateEncoder::with_quality(stream, self.layer.level)),
+                        budget,
+                    )
+                    .map(map)
+                    .chain(trailers),
                 ),
-                ContentEncoding::Brotli => Body::stream({
+                (None, ContentEncoding::Gzip) => Body::stream(
+                    Budgeted::new(
+                        ReaderStream::new(GzipEncoder::with_quality(stream, self.layer.level)),
+                        budget,
+                    )
+                    .map(map)
+                    .chain(trailers),
+                ),
+                (None, ContentEncoding::Brotli) => Body::stream({
                     // The brotli crate used under the hood here has a default compression level of 11,
                     // which is the max for brotli. This causes extremely slow compression times, so we
                     // manually set a default of 4 here.
@@ -245,9 +372,11 @@ where
                         level => level,
                     };
 
-                    ReaderStream::new(BrotliEncoder::with_quality(stream, level)).map(map).chain(trailers)
+                    Budgeted::new(ReaderStream::new(BrotliEncoder::with_quality(stream, level)), budget)
+                        .map(map)
+                        .chain(trailers)
                 }),
-                ContentEncoding::Zstd => Body::stream({
+                (None, ContentEncoding::Zstd) => Body::stream({
                     // See https://issues.chromium.org/issues/41493659:
                     //  "For memory usage reasons, Chromium limits the window size to 8MB"
                     // See https://datatracker.ietf.org/doc/html/rfc8878#name-window-descriptor
@@ -272,18 +401,203 @@ where
                         &[]
                     };
 
-                    ReaderStream::new(ZstdEncoder::with_quality_and_params(stream, self.layer.level, params))
-                        .map(map)
-                        .chain(trailers)
+                    Budgeted::new(
+                        ReaderStream::new(ZstdEncoder::with_quality_and_params(stream, self.layer.level, params)),
+                        budget,
+                    )
+                    .map(map)
+                    .chain(trailers)
                 }),
             };
 
             parts.headers.remove(header::ACCEPT_RANGES);
             parts.headers.remove(header::CONTENT_LENGTH);
 
-            parts.headers.typed_insert(encoding);
+            match custom {
+                Some(custom) => {
+                    _ = parts.headers.insert(header::CONTENT_ENCODING, HeaderValue::from_static(custom.token()))
+                }
+                None => parts.headers.typed_insert(encoding),
+            }
 
             Ok(http::Response::from_parts(parts, compressed))
         }
     }
 }
+
+/// Stream wrapper that yields to the runtime once the time spent
+/// polling the inner stream exceeds the budget, since the last time it yielded.
+#[pin_project::pin_project]
+struct Budgeted<S> {
+    #[pin]
+    inner: S,
+    budget: Option<Duration>,
+    spent: Duration,
+    yielded: bool,
+}
+
+impl<S> Budgeted<S> {
+    fn new(inner: S, budget: Option<Duration>) -> Self {
+        Budgeted {
+            inner,
+            budget,
+            spent: Duration::ZERO,
+            yielded: false,
+        }
+    }
+}
+
+impl<S: futures::Stream> futures::Stream for Budgeted<S> {
+    type Item = S::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+
+        let Some(budget) = *this.budget else {
+            return this.inner.poll_next(cx);
+        };
+
+        // always make progress after yielding, even if the budget is zero
+        if *this.spent >= budget && !*this.yielded {
+            *this.spent = Duration::ZERO;
+            *this.yielded = true;
+
+            // reschedule this task behind any others waiting to run
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+
+        *this.yielded = false;
+
+        let start = Instant::now();
+        let res = this.inner.poll_next(cx);
+
+        *this.spent = match res {
+            // the task will yield anyway
+            Poll::Pending => Duration::ZERO,
+            Poll::Ready(_) => *this.spent + start.elapsed(),
+        };
+
+        res
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::task::{Context, Poll};
+    use std::time::Duration;
+
+    use futures::{task::noop_waker_ref, Stream};
+
+    use http::StatusCode;
+
+    use super::{should_compress, Budgeted};
+
+    #[test]
+    fn test_budgeted_yields() {
+        let mut cx = Context::from_waker(noop_waker_ref());
+
+        // a zero budget is always exhausted, so it should yield before every item
+        let mut stream = std::pin::pin!(Budgeted::new(futures::stream::iter([1, 2]), Some(Duration::ZERO)));
+
+        assert_eq!(stream.as_mut().poll_next(&mut cx), Poll::Pending);
+        assert_eq!(stream.as_mut().poll_next(&mut cx), Poll::Ready(Some(1)));
+        assert_eq!(stream.as_mut().poll_next(&mut cx), Poll::Pending);
+        assert_eq!(stream.as_mut().poll_next(&mut cx), Poll::Ready(Some(2)));
+
+        // without a budget, it never yields
+        let mut stream = std::pin::pin!(Budgeted::new(futures::stream::iter([1, 2]), None));
+
+        assert_eq!(stream.as_mut().poll_next(&mut cx), Poll::Ready(Some(1)));
+        assert_eq!(stream.as_mut().poll_next(&mut cx), Poll::Ready(Some(2)));
+    }
+
+    #[tokio::test]
+    async fn test_custom_encoder() {
+        use std::pin::Pin;
+
+        use tokio::io::{AsyncBufRead, AsyncRead};
+
+        use super::{CompressionLayer, Encoder, Level};
+        use crate::{body::Body, Layer, Response, Router, Service};
+
+        /// Passes the body through unchanged
+        struct Passthrough;
+
+        impl Encoder for Passthrough {
+            fn token(&self) -> &'static str {
+                "x-passthrough"
+            }
+
+            fn encode(&self, body: Pin<Box<dyn AsyncBufRead + Send>>, _: Level) -> Pin<Box<dyn AsyncRead + Send>> {
+                body
+            }
+        }
+
+        static PASSTHROUGH: Passthrough = Passthrough;
+
+        let text = "hello world ".repeat(200);
+
+        let mut router = Router::<_, Response>::with_state(text.clone());
+        router.get(
+            "/",
+            |crate::extract::State(text): crate::extract::State<String>| async move { text },
+        );
+
+        let service = CompressionLayer::new().encoder(&PASSTHROUGH).layer(router);
+
+        for (accept, expected) in [
+            ("x-passthrough, gzip;q=0.5", Some("x-passthrough")),
+            ("gzip, X-Passthrough", Some("x-passthrough")),
+            ("gzip, x-passthrough;q=0.5", Some("gzip")),
+            ("identity", None),
+        ] {
+            let req =
+                http::Request::get("/").header(http::header::ACCEPT_ENCODING, accept).body(Body::empty()).unwrap();
+            let resp = service.call(req).await.unwrap();
+
+            let encoding =
+                resp.headers().get(http::header::CONTENT_ENCODING).map(|v| v.to_str().unwrap().to_owned());
+            assert_eq!(encoding.as_deref(), expected, "{accept}");
+
+            if expected == Some("x-passthrough") {
+                assert_eq!(resp.into_body().to_string().await.unwrap(), text);
+            }
+        }
+    }
+
+    #[test]
+    fn test_never_compress_bodiless_or_partial() {
+        let parts = |status: StatusCode| {
+            let mut parts = http::Response::new(()).into_parts().0;
+            parts.status = status;
+            parts
+        };
+
+        assert!(should_compress(&parts(StatusCode::OK), &true));
+
+        for status in [
+            StatusCode::CONTINUE,
+            StatusCode::NO_CONTENT,
+            StatusCode::PARTIAL_CONTENT,
+            StatusCode::NOT_MODIFIED,
+        ] {
+            assert!(
+                !should_compress(&parts(status), &true),
+                "{status} should not be compressed"
+            );
+        }
+
+        // partial content, even without a status code hint
+        let mut partial = parts(StatusCode::OK);
+        partial.headers.insert(
+            http::header::CONTENT_RANGE,
+            http::HeaderValue::from_static("bytes 0-9/100"),
+        );
+        assert!(!should_compress(&partial, &true));
+    }
+}
@@ -0,0 +1,176 @@
+use std::sync::Arc;
+
+use http::StatusCode;
+use tokio::sync::Semaphore;
+
+use crate::{service::ServiceFuture, IntoResponse, Layer, Response, Service};
+
+/// A [`Layer`] that limits the number of requests being processed by the inner service at once.
+///
+/// When the limit is reached, further requests wait for an earlier one to complete, or with
+/// [`load_shed`](Self::load_shed) enabled, are immediately answered with `503 Service Unavailable`.
+///
+/// All services created from the same layer share the limit, including clones of them. The permit is held
+/// until the inner service's future completes or is dropped, but not while the response body is streamed.
+///
+/// This complements the per-IP connection limits of the `LimitedTcpAcceptor`, but applies
+/// to requests across all connections, including multiplexed HTTP/2 streams.
+#[derive(Debug, Clone)]
+#[must_use]
+pub struct ConcurrencyLimitLayer {
+    semaphore: Arc<Semaphore>,
+    load_shed: bool,
+}
+
+/// The service created by the [`ConcurrencyLimitLayer`].
+#[derive(Debug, Clone)]
+pub struct ConcurrencyLimit<S> {
+    inner: S,
+    layer: ConcurrencyLimitLayer,
+}
+
+impl ConcurrencyLimitLayer {
+    /// Creates a new layer allowing at most `max` requests to be processed at once.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max` is zero, as no request could ever be processed.
+    pub fn new(max: usize) -> Self {
+        assert!(max > 0, "concurrency limit must be greater than zero");
+
+        ConcurrencyLimitLayer {
+            semaphore: Arc::new(Semaphore::new(max)),
+            load_shed: false,
+        }
+    }
+
+    /// Sets whether to reject requests over the limit with `503 Service Unavailable`
+    /// rather than waiting for capacity.
+    ///
+    /// Defaults to `false`.
+    pub fn load_shed(mut self, load_shed: bool) -> Self {
+        self.load_shed = load_shed;
+        self
+    }
+}
+
+impl<S> Layer<S> for ConcurrencyLimitLayer {
+    type Service = ConcurrencyLimit<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ConcurrencyLimit {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+impl<S, B> Service<http::Request<B>> for ConcurrencyLimit<S>
+where
+    S: Service<http::Request<B>, Response = Response>,
+    B: Send,
+{
+    type Response = Response;
+    type Error = S::Error;
+
+    fn call(&self, req: http::Request<B>) -> impl ServiceFuture<Self::Response, Self::Error> {
+        async move {
+            let permit = match self.layer.load_shed {
+                true => self.layer.semaphore.clone().try_acquire_owned().ok(),
+                // the semaphore is never closed
+                false => self.layer.semaphore.clone().acquire_owned().await.ok(),
+            };
+
+            let Some(_permit) = permit else {
+                return Ok(StatusCode::SERVICE_UNAVAILABLE.into_response());
+            };
+
+            self.inner.call(req).await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::pin::pin;
+
+    use tokio::sync::{mpsc, Notify};
+
+    use super::*;
+    use crate::{body::Body, Router};
+
+    /// `/wait` signals `entered` once it's being processed, then waits for `release`.
+    fn router(entered: mpsc::UnboundedSender<()>, release: Arc<Notify>) -> Router<(), Response> {
+        let mut router = Router::<(), Response>::with_state(());
+        router.get("/wait", move || {
+            let (entered, release) = (entered.clone(), release.clone());
+            async move {
+                _ = entered.send(());
+                release.notified().await;
+                "done"
+            }
+        });
+        router.get("/fast", || async { "fast" });
+        router
+    }
+
+    fn get(path: &str) -> http::Request<Body> {
+        http::Request::get(path).body(Body::empty()).unwrap()
+    }
+
+    #[test]
+    #[should_panic = "concurrency limit must be greater than zero"]
+    fn test_zero_limit() {
+        _ = ConcurrencyLimitLayer::new(0);
+    }
+
+    #[tokio::test]
+    async fn test_load_shed() {
+        let (tx, mut entered) = mpsc::unbounded_channel();
+        let release = Arc::new(Notify::new());
+        let service = ConcurrencyLimitLayer::new(1).load_shed(true).layer(router(tx, release.clone()));
+
+        let mut first = pin!(service.call(get("/wait")));
+        assert!(futures::poll!(&mut first).is_pending());
+        entered.try_recv().expect("first request holds the permit");
+
+        let resp = service.call(get("/fast")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        release.notify_one();
+        assert_eq!(first.await.unwrap().status(), StatusCode::OK);
+
+        // the permit is released on completion
+        let resp = service.call(get("/fast")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_backpressure() {
+        let (tx, mut entered) = mpsc::unbounded_channel();
+        let release = Arc::new(Notify::new());
+        let service = ConcurrencyLimitLayer::new(1).layer(router(tx, release.clone()));
+
+        let mut first = pin!(service.call(get("/wait")));
+        assert!(futures::poll!(&mut first).is_pending());
+        entered.try_recv().expect("first request holds the permit");
+
+        // waits for the first request to complete
+        let mut second = pin!(service.call(get("/fast")));
+        assert!(futures::poll!(&mut second).is_pending());
+
+        release.notify_one();
+        assert_eq!(first.await.unwrap().status(), StatusCode::OK);
+        assert_eq!(second.await.unwrap().status(), StatusCode::OK);
+
+        // dropping a pending request releases its permit too
+        {
+            let mut pending = pin!(service.call(get("/wait")));
+            assert!(futures::poll!(&mut pending).is_pending());
+            entered.try_recv().expect("pending request holds the permit");
+        }
+
+        let resp = service.call(get("/fast")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,482 @@
+//! [Cross-Origin Resource Sharing](https://fetch.spec.whatwg.org/#http-cors-protocol) (CORS) layer.
+//!
+//! ```rust,ignore
+//! let cors = CorsLayer::new()
+//!     .allow_origin(AllowOrigin::exact([HeaderValue::from_static("https://example.com")]))
+//!     .allow_methods([Method::GET, Method::POST, Method::DELETE])
+//!     .allow_headers([header::CONTENT_TYPE, header::AUTHORIZATION])
+//!     .allow_credentials(true)
+//!     .max_age(Duration::from_secs(3600));
+//! ```
+
+use std::{fmt, sync::Arc, time::Duration};
+
+use futures::FutureExt as _;
+use http::{header, HeaderMap, HeaderName, HeaderValue, Method, StatusCode};
+
+use crate::{body::Body, service::ServiceFuture, Layer, Response, Service};
+
+/// Which origins are allowed to make cross-origin requests.
+#[derive(Clone, Default)]
+pub enum AllowOrigin {
+    /// No origins are allowed, which is the default.
+    #[default]
+    None,
+
+    /// Any origin is allowed, sent as `*`.
+    ///
+    /// Browsers reject `*` for credentialed requests, so this cannot be combined
+    /// with [`CorsLayer::allow_credentials`], see [`AllowOrigin::MirrorRequest`].
+    Any,
+
+    /// Any origin is allowed by echoing back the request `Origin`, which unlike [`AllowOrigin::Any`]
+    /// is also accepted for credentialed requests.
+    ///
+    /// Combined with [`CorsLayer::allow_credentials`], this lets any website make authenticated
+    /// requests on behalf of your users and read the responses, so it's rarely what you want.
+    /// See [`CorsLayer::mirror_request_origin`].
+    MirrorRequest,
+
+    /// Only the listed origins are allowed, compared exactly, such as `https://example.com`.
+    Exact(Arc<[HeaderValue]>),
+
+    /// Origins are allowed if the predicate returns `true` for the `Origin` header value.
+    Predicate(Arc<dyn Fn(&HeaderValue) -> bool + Send + Sync + 'static>),
+}
+
+impl AllowOrigin {
+    /// Allows only the listed origins.
+    pub fn exact(origins: impl IntoIterator<Item = HeaderValue>) -> Self {
+        AllowOrigin::Exact(origins.into_iter().collect())
+    }
+
+    /// Allows origins for which the predicate returns `true`.
+    pub fn predicate<F>(f: F) -> Self
+    where
+        F: Fn(&HeaderValue) -> bool + Send + Sync + 'static,
+    {
+        AllowOrigin::Predicate(Arc::new(f))
+    }
+
+    fn allows(&self, origin: &HeaderValue) -> bool {
+        match self {
+            AllowOrigin::None => false,
+            AllowOrigin::Any | AllowOrigin::MirrorRequest => true,
+            AllowOrigin::Exact(origins) => origins.contains(origin),
+            AllowOrigin::Predicate(f) => f(origin),
+        }
+    }
+}
+
+impl fmt::Debug for AllowOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AllowOrigin::None => f.write_str("None"),
+            AllowOrigin::Any => f.write_str("Any"),
+            AllowOrigin::MirrorRequest => f.write_str("MirrorRequest"),
+            AllowOrigin::Exact(origins) => f.debug_tuple("Exact").field(origins).finish(),
+            AllowOrigin::Predicate(_) => f.write_str("Predicate(..)"),
+        }
+    }
+}
+
+/// A layer that implements CORS, answering preflight requests directly and adding
+/// the `Access-Control-*` headers to responses for allowed origins.
+///
+/// Preflight requests, being `OPTIONS` requests with both `Origin` and `Access-Control-Request-Method`
+/// headers, are answered with `204 No Content` without calling the inner service. Requests from
+/// origins that are not allowed are still processed, but without any CORS headers, so the browser
+/// will not expose the response to the page.
+///
+/// By default, no origins are allowed, the CORS-safelisted methods `GET`, `HEAD` and `POST` are allowed,
+/// and no additional request headers are allowed or response headers exposed.
+///
+/// # Panics
+///
+/// Creating the service panics if credentials are allowed with [`AllowOrigin::Any`],
+/// as browsers reject `Access-Control-Allow-Origin: *` for credentialed requests.
+#[derive(Debug, Clone)]
+#[must_use]
+pub struct CorsLayer {
+    allow_origin: AllowOrigin,
+    allow_methods: HeaderValue,
+    allow_headers: Option<HeaderValue>,
+    mirror_headers: bool,
+    expose_headers: Option<HeaderValue>,
+    allow_credentials: bool,
+    max_age: Option<HeaderValue>,
+}
+
+/// The service created by the [`CorsLayer`].
+#[derive(Debug, Clone)]
+pub struct Cors<S> {
+    inner: S,
+    layer: CorsLayer,
+}
+
+impl Default for CorsLayer {
+    fn default() -> Self {
+        CorsLayer {
+            allow_origin: AllowOrigin::None,
+            allow_methods: HeaderValue::from_static("GET, HEAD, POST"),
+            allow_headers: None,
+            mirror_headers: false,
+            expose_headers: None,
+            allow_credentials: false,
+            max_age: None,
+        }
+    }
+}
+
+/// Joins header names or methods into a comma-separated header value.
+fn join<T: AsRef<str>>(items: impl IntoIterator<Item = T>) -> Option<HeaderValue> {
+    let mut value = String::new();
+
+    for item in items {
+        if !value.is_empty() {
+            value.push_str(", ");
+        }
+
+        value.push_str(item.as_ref());
+    }
+
+    match value.is_empty() {
+        true => None,
+        false => Some(HeaderValue::try_from(value).expect("tokens are valid header values")),
+    }
+}
+
+impl CorsLayer {
+    /// Creates a new layer that allows no origins, see [`allow_origin`](Self::allow_origin).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new layer that allows any origin, any request headers and the common methods,
+    /// without credentials. Useful for public APIs or development.
+    pub fn permissive() -> Self {
+        Self::new().allow_origin(AllowOrigin::Any).allow_any_headers().allow_methods([
+            Method::GET,
+            Method::HEAD,
+            Method::POST,
+            Method::PUT,
+            Method::PATCH,
+            Method::DELETE,
+        ])
+    }
+
+    /// Sets which origins are allowed.
+    pub fn allow_origin(mut self, allow_origin: AllowOrigin) -> Self {
+        self.allow_origin = allow_origin;
+        self
+    }
+
+    /// Allows any origin by echoing back the request `Origin`, see [`AllowOrigin::MirrorRequest`].
+    ///
+    /// # Security
+    ///
+    /// With [`allow_credentials`](Self::allow_credentials), any website can make requests
+    /// with the user's cookies and read the responses. Prefer [`AllowOrigin::exact`] or
+    /// [`AllowOrigin::predicate`] for credentialed requests.
+    pub fn mirror_request_origin(self) -> Self {
+        self.allow_origin(AllowOrigin::MirrorRequest)
+    }
+
+    /// Sets the methods allowed in preflight requests, replacing the default of `GET, HEAD, POST`.
+    pub fn allow_methods(mut self, methods: impl IntoIterator<Item = Method>) -> Self {
+        self.allow_methods = join(methods).unwrap_or(HeaderValue::from_static(""));
+        self
+    }
+
+    /// Sets the request headers allowed in preflight requests.
+    pub fn allow_headers(mut self, headers: impl IntoIterator<Item = HeaderName>) -> Self {
+        self.allow_headers = join(headers);
+        self.mirror_headers = false;
+        self
+    }
+
+    /// Allows any request headers in preflight requests, by echoing back
+    /// the `Access-Control-Request-Headers` of the request.
+    pub fn allow_any_headers(mut self) -> Self {
+        self.allow_headers = None;
+        self.mirror_headers = true;
+        self
+    }
+
+    /// Sets the response headers exposed to the page, beyond the CORS-safelisted response headers.
+    pub fn expose_headers(mut self, headers: impl IntoIterator<Item = HeaderName>) -> Self {
+        self.expose_headers = join(headers);
+        self
+    }
+
+    /// Sets whether to allow credentials, such as cookies or `Authorization` headers.
+    ///
+    /// Browsers reject `Access-Control-Allow-Origin: *` for credentialed requests,
+    /// so this cannot be combined with [`AllowOrigin::Any`], see [`mirror_request_origin`](Self::mirror_request_origin).
+    pub fn allow_credentials(mut self, allow_credentials: bool) -> Self {
+        self.allow_credentials = allow_credentials;
+        self
+    }
+
+    /// Sets how long browsers may cache the result of a preflight request.
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(HeaderValue::from(max_age.as_secs()));
+        self
+    }
+
+    /// Returns the `Access-Control-Allow-Origin` value for the request origin, if allowed.
+    fn allowed_origin(&self, origin: Option<&HeaderValue>) -> Option<HeaderValue> {
+        match (&self.allow_origin, origin) {
+            (AllowOrigin::Any, _) => Some(HeaderValue::from_static("*")),
+            (allow_origin, Some(origin)) if allow_origin.allows(origin) => Some(origin.clone()),
+            _ => None,
+        }
+    }
+
+    /// Adds `Vary: Origin` if the response depends on the request origin.
+    fn vary(&self, headers: &mut HeaderMap, preflight: bool) {
+        if !matches!(self.allow_origin, AllowOrigin::Any) {
+            headers.append(header::VARY, HeaderValue::from_static("origin"));
+        }
+
+        if preflight {
+            headers.append(header::VARY, HeaderValue::from_static("access-control-request-method"));
+
+            if self.mirror_headers {
+                headers.append(header::VARY, HeaderValue::from_static("access-control-request-headers"));
+            }
+        }
+    }
+
+    fn preflight(&self, req_headers: &HeaderMap) -> Response {
+        let mut resp = Response::new(Body::empty());
+        *resp.status_mut() = StatusCode::NO_CONTENT;
+
+        let headers = resp.headers_mut();
+
+        self.vary(headers, true);
+
+        let Some(origin) = self.allowed_origin(req_headers.get(header::ORIGIN)) else {
+            return resp;
+        };
+
+        headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+        headers.insert(header::ACCESS_CONTROL_ALLOW_METHODS, self.allow_methods.clone());
+
+        let allow_headers = match self.mirror_headers {
+            true => req_headers.get(header::ACCESS_CONTROL_REQUEST_HEADERS),
+            false => self.allow_headers.as_ref(),
+        };
+
+        if let Some(allow_headers) = allow_headers {
+            headers.insert(header::ACCESS_CONTROL_ALLOW_HEADERS, allow_headers.clone());
+        }
+
+        if self.allow_credentials {
+            headers.insert(
+                header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
+                HeaderValue::from_static("true"),
+            );
+        }
+
+        if let Some(ref max_age) = self.max_age {
+            headers.insert(header::ACCESS_CONTROL_MAX_AGE, max_age.clone());
+        }
+
+        resp
+    }
+
+    fn apply(&self, headers: &mut HeaderMap, origin: Option<HeaderValue>) {
+        self.vary(headers, false);
+
+        let Some(origin) = origin else { return };
+
+        headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+
+        if self.allow_credentials {
+            headers.insert(
+                header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
+                HeaderValue::from_static("true"),
+            );
+        }
+
+        if let Some(ref expose_headers) = self.expose_headers {
+            headers.insert(header::ACCESS_CONTROL_EXPOSE_HEADERS, expose_headers.clone());
+        }
+    }
+}
+
+impl<S> Layer<S> for CorsLayer {
+    type Service = Cors<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        assert!(
+            !(self.allow_credentials && matches!(self.allow_origin, AllowOrigin::Any)),
+            "CORS credentials cannot be allowed for any origin, use `CorsLayer::mirror_request_origin` instead"
+        );
+
+        Cors {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+impl<S, B> Service<http::Request<B>> for Cors<S>
+where
+    S: Service<http::Request<B>, Response = Response>,
+{
+    type Response = Response;
+    type Error = S::Error;
+
+    fn call(&self, req: http::Request<B>) -> impl ServiceFuture<Self::Response, Self::Error> {
+        use futures::future::{ready, Either};
+
+        let headers = req.headers();
+
+        if req.method() == Method::OPTIONS
+            && headers.contains_key(header::ORIGIN)
+            && headers.contains_key(header::ACCESS_CONTROL_REQUEST_METHOD)
+        {
+            return Either::Left(ready(Ok(self.layer.preflight(headers))));
+        }
+
+        let origin = self.layer.allowed_origin(headers.get(header::ORIGIN));
+
+        Either::Right(self.inner.call(req).map(move |res| {
+            res.map(|mut resp| {
+                self.layer.apply(resp.headers_mut(), origin);
+                resp
+            })
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{IntoResponse, Router};
+
+    fn router() -> Router<(), Response> {
+        let mut router = Router::<(), Response>::with_state(());
+        router.get("/", || async { "index" });
+        router
+    }
+
+    fn request(method: Method, headers: &[(HeaderName, &'static str)]) -> http::Request<Body> {
+        let mut req = http::Request::builder().method(method).uri("/");
+
+        for (name, value) in headers {
+            req = req.header(name, *value);
+        }
+
+        req.body(Body::empty()).unwrap()
+    }
+
+    async fn call(
+        service: &impl Service<http::Request<Body>, Response = Response, Error = crate::Error>,
+        req: http::Request<Body>,
+    ) -> Response {
+        service.call(req).await.unwrap_or_else(IntoResponse::into_response)
+    }
+
+    #[tokio::test]
+    async fn test_cors_preflight() {
+        let service = CorsLayer::new()
+            .allow_origin(AllowOrigin::exact([HeaderValue::from_static("https://example.com")]))
+            .allow_methods([Method::GET, Method::DELETE])
+            .allow_headers([header::CONTENT_TYPE])
+            .max_age(Duration::from_secs(600))
+            .layer(router());
+
+        let preflight = |origin| {
+            request(
+                Method::OPTIONS,
+                &[
+                    (header::ORIGIN, origin),
+                    (header::ACCESS_CONTROL_REQUEST_METHOD, "DELETE"),
+                ],
+            )
+        };
+
+        let resp = call(&service, preflight("https://example.com")).await;
+        let headers = resp.headers();
+
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_ORIGIN], "https://example.com");
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_METHODS], "GET, DELETE");
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_HEADERS], "content-type");
+        assert_eq!(headers[header::ACCESS_CONTROL_MAX_AGE], "600");
+        assert!(!headers.contains_key(header::ACCESS_CONTROL_ALLOW_CREDENTIALS));
+        assert!(headers.get_all(header::VARY).iter().any(|v| v == "origin"));
+
+        let resp = call(&service, preflight("https://evil.example")).await;
+
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        assert!(!resp.headers().contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+        assert!(!resp.headers().contains_key(header::ACCESS_CONTROL_ALLOW_METHODS));
+    }
+
+    #[tokio::test]
+    async fn test_cors_actual_request() {
+        let service = CorsLayer::permissive().expose_headers([header::ETAG]).layer(router());
+
+        let resp = call(&service, request(Method::GET, &[(header::ORIGIN, "https://a.example")])).await;
+
+        assert_eq!(resp.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+        assert_eq!(resp.headers()[header::ACCESS_CONTROL_EXPOSE_HEADERS], "etag");
+        assert_eq!(resp.into_body().to_string().await.unwrap(), "index");
+
+        // credentialed requests must echo the exact origin
+        let service = CorsLayer::permissive().mirror_request_origin().allow_credentials(true).layer(router());
+
+        let resp = call(&service, request(Method::GET, &[(header::ORIGIN, "https://a.example")])).await;
+
+        assert_eq!(resp.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], "https://a.example");
+        assert_eq!(resp.headers()[header::ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+        assert_eq!(resp.headers()[header::VARY], "origin");
+
+        // preflight requests mirror the request headers
+        let req = request(
+            Method::OPTIONS,
+            &[
+                (header::ORIGIN, "https://a.example"),
+                (header::ACCESS_CONTROL_REQUEST_METHOD, "PUT"),
+                (header::ACCESS_CONTROL_REQUEST_HEADERS, "x-custom"),
+            ],
+        );
+
+        let resp = call(&service, req).await;
+        assert_eq!(resp.headers()[header::ACCESS_CONTROL_ALLOW_HEADERS], "x-custom");
+
+        // predicates
+        let service = CorsLayer::new()
+            .allow_origin(AllowOrigin::predicate(|origin| {
+                origin.as_bytes().ends_with(b".example.com")
+            }))
+            .layer(router());
+
+        let resp = call(
+            &service,
+            request(Method::GET, &[(header::ORIGIN, "https://api.example.com")]),
+        )
+        .await;
+        assert_eq!(
+            resp.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+            "https://api.example.com"
+        );
+
+        let resp = call(
+            &service,
+            request(Method::GET, &[(header::ORIGIN, "https://example.org")]),
+        )
+        .await;
+        assert!(!resp.headers().contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+    }
+
+    #[test]
+    #[should_panic = "CORS credentials cannot be allowed for any origin"]
+    fn test_cors_any_origin_credentials() {
+        _ = CorsLayer::permissive().allow_credentials(true).layer(router());
+    }
+}
@@ -0,0 +1,333 @@
+//! Decompression of request bodies sent with a `Content-Encoding`.
+//!
+//! Stacked encodings, such as `Content-Encoding: gzip, br`, are removed in reverse order.
+//! If any of the encodings are unknown or disabled, the request is rejected with
+//! `415 Unsupported Media Type`, as the body could not be decoded otherwise.
+//!
+//! A malformed compressed body yields a [`BodyError::Decompression`] when read,
+//! which becomes a `400 Bad Request` response.
+//!
+//! Note that the [`LimitReqBody`](super::limit_req_body::LimitReqBody) layer should be applied
+//! *after* this layer (closer to the handlers) to limit the size of the decompressed body,
+//! otherwise small compressed bodies can expand without bound.
+
+use std::io;
+use std::pin::Pin;
+
+use headers::HeaderMapExt as _;
+use http::{header, StatusCode};
+use http_body::Frame;
+use http_body_util::BodyStream;
+use tokio::io::{AsyncBufRead, BufReader};
+use tokio_stream::StreamExt;
+use tokio_util::io::{ReaderStream, StreamReader};
+
+use crate::body::{Body, BodyError};
+use crate::headers::accept_encoding::{ContentEncoding, ContentEncodings, FilterEncoding};
+use crate::service::{Service, ServiceFuture};
+use crate::{IntoResponse, Layer, Request, Response};
+
+/// Layer that decompresses request bodies according to their `Content-Encoding`.
+///
+/// All encodings enabled by the `compression-*` crate features are accepted by default.
+#[derive(Debug, Default, Clone, Copy)]
+#[must_use]
+pub struct DecompressionLayer {
+    filter: FilterEncoding,
+}
+
+#[derive(Debug, Clone, Copy)]
+#[must_use]
+pub struct Decompression<S> {
+    inner: S,
+    filter: FilterEncoding,
+}
+
+impl DecompressionLayer {
+    /// Creates a new [`DecompressionLayer`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether to accept the gzip encoding.
+    #[cfg(feature = "compression-gzip")]
+    pub fn gzip(mut self, enable: bool) -> Self {
+        self.filter.set_gzip(enable);
+        self
+    }
+
+    /// Sets whether to accept the Deflate encoding.
+    #[cfg(feature = "compression-deflate")]
+    pub fn deflate(mut self, enable: bool) -> Self {
+        self.filter.set_deflate(enable);
+        self
+    }
+
+    /// Sets whether to accept the Brotli encoding.
+    #[cfg(feature = "compression-br")]
+    pub fn br(mut self, enable: bool) -> Self {
+        self.filter.set_br(enable);
+        self
+    }
+
+    /// Sets whether to accept the Zstd encoding.
+    #[cfg(feature = "compression-zstd")]
+    pub fn zstd(mut self, enable: bool) -> Self {
+        self.filter.set_zstd(enable);
+        self
+    }
+}
+
+impl<S> Layer<S> for DecompressionLayer {
+    type Service = Decompression<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Decompression {
+            inner,
+            filter: self.filter,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum DecompressionError<E> {
+    #[error(transparent)]
+    Inner(E),
+
+    #[error("Unsupported Content-Encoding")]
+    UnsupportedEncoding,
+}
+
+impl<E: IntoResponse> IntoResponse for DecompressionError<E> {
+    fn into_response(self) -> Response {
+        match self {
+            DecompressionError::Inner(e) => e.into_response(),
+            DecompressionError::UnsupportedEncoding => {
+                ("Unsupported Content-Encoding", StatusCode::UNSUPPORTED_MEDIA_TYPE).into_response()
+            }
+        }
+    }
+}
+
+type Reader = Pin<Box<dyn AsyncBufRead + Send>>;
+
+/// Wraps the reader with the decoder for the given encoding, if it is enabled and available.
+#[allow(unused_variables, unreachable_code)]
+fn decoder(reader: Reader, encoding: ContentEncoding, filter: FilterEncoding) -> Option<Reader> {
+    #[allow(unused_imports)]
+    use async_compression::tokio::bufread::{BrotliDecoder, DeflateDecoder, GzipDecoder, ZstdDecoder};
+
+    Some(match encoding {
+        ContentEncoding::Identity => reader,
+
+        #[cfg(feature = "compression-gzip")]
+        ContentEncoding::Gzip if filter.gzip => {
+            let mut decoder = GzipDecoder::new(reader);
+            decoder.multiple_members(true);
+            Box::pin(BufReader::new(decoder))
+        }
+
+        #[cfg(feature = "compression-deflate")]
+        ContentEncoding::Deflate if filter.deflate => Box::pin(BufReader::new(DeflateDecoder::new(reader))),
+
+        #[cfg(feature = "compression-br")]
+        ContentEncoding::Brotli if filter.br => Box::pin(BufReader::new(BrotliDecoder::new(reader))),
+
+        #[cfg(feature = "compression-zstd")]
+        ContentEncoding::Zstd if filter.zstd => Box::pin(BufReader::new(ZstdDecoder::new(reader))),
+
+        _ => return None,
+    })
+}
+
+impl<S> Service<Request> for Decompression<S>
+where
+    S: Service<Request>,
+{
+    type Response = S::Response;
+    type Error = DecompressionError<S::Error>;
+
+    fn call(&self, req: Request) -> impl ServiceFuture<Self::Response, Self::Error> {
+        async move {
+            let (mut parts, body) = req.into_parts();
+
+            if !parts.headers.contains_key(header::CONTENT_ENCODING) {
+                return self.inner.call(Request::from_parts(parts, body)).await.map_err(DecompressionError::Inner);
+            }
+
+            // unknown encodings fail to decode entirely
+            let Some(encodings) = parts.headers.typed_get::<ContentEncodings>() else {
+                return Err(DecompressionError::UnsupportedEncoding);
+            };
+
+            // request trailers are dropped, as they would likely describe the encoded body anyway
+            let mut reader: Reader =
+                Box::pin(StreamReader::new(BodyStream::new(body).filter_map(
+                    |frame| match frame {
+                        Err(e) => Some(Err(io::Error::other(e))),
+                        Ok(frame) => frame.into_data().ok().map(Ok),
+                    },
+                )));
+
+            for encoding in encodings.iter_decode() {
+                reader = decoder(reader, encoding, self.filter).ok_or(DecompressionError::UnsupportedEncoding)?;
+            }
+
+            let body = Body::stream(ReaderStream::new(reader).map(|r| match r {
+                Ok(data) => Ok(Frame::data(data)),
+                // errors from the original body are passed through, while anything else is from the decoder
+                Err(e) => match e.downcast::<BodyError>() {
+                    Ok(e) => Err(e),
+                    Err(e) => Err(BodyError::Decompression(e)),
+                },
+            }));
+
+            parts.headers.remove(header::CONTENT_ENCODING);
+            parts.headers.remove(header::CONTENT_LENGTH);
+
+            self.inner.call(Request::from_parts(parts, body)).await.map_err(DecompressionError::Inner)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::AsyncReadExt as _;
+
+    use super::*;
+    use crate::Router;
+
+    fn service() -> impl Service<Request, Response = Response, Error = DecompressionError<crate::Error>> {
+        let mut router = Router::<(), Response>::with_state(());
+        router.post("/echo", |body: String| async move { body });
+
+        DecompressionLayer::new().layer(router)
+    }
+
+    async fn call(
+        service: &impl Service<Request, Response = Response, Error = DecompressionError<crate::Error>>,
+        req: Request,
+    ) -> Response {
+        service.call(req).await.unwrap_or_else(IntoResponse::into_response)
+    }
+
+    #[cfg(feature = "compression-gzip")]
+    #[tokio::test]
+    async fn test_decompress_gzip() {
+        use async_compression::tokio::bufread::GzipEncoder;
+
+        let mut compressed = Vec::new();
+        GzipEncoder::new(&b"hello world"[..]).read_to_end(&mut compressed).await.unwrap();
+
+        let service = service();
+
+        let req = http::Request::post("/echo")
+            .header(header::CONTENT_ENCODING, "gzip")
+            .header(header::CONTENT_LENGTH, compressed.len())
+            .body(Body::from(compressed.clone()))
+            .unwrap();
+
+        let resp = call(&service, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.into_body().to_string().await.unwrap(), "hello world");
+
+        // truncated streams are malformed
+        compressed.truncate(compressed.len() / 2);
+
+        let req = http::Request::post("/echo")
+            .header(header::CONTENT_ENCODING, "gzip")
+            .body(Body::from(compressed))
+            .unwrap();
+
+        assert_eq!(call(&service, req).await.status(), StatusCode::BAD_REQUEST);
+    }
+
+    fn encoded(encoding: &'static str, body: Vec<u8>) -> Request {
+        http::Request::post("/echo")
+            .header(header::CONTENT_ENCODING, encoding)
+            .body(Body::from(body))
+            .unwrap()
+    }
+
+    #[cfg(feature = "compression-br")]
+    #[tokio::test]
+    async fn test_decompress_br() {
+        use async_compression::tokio::bufread::BrotliEncoder;
+
+        let mut compressed = Vec::new();
+        BrotliEncoder::new(&b"hello brotli"[..]).read_to_end(&mut compressed).await.unwrap();
+
+        let resp = call(&service(), encoded("br", compressed.clone())).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.into_body().to_string().await.unwrap(), "hello brotli");
+
+        // rejected when disabled
+
+        let mut router = Router::<(), Response>::with_state(());
+        router.post("/echo", |body: String| async move { body });
+        let service = DecompressionLayer::new().br(false).layer(router);
+
+        let resp = call(&service, encoded("br", compressed)).await;
+        assert_eq!(resp.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+
+    #[cfg(feature = "compression-zstd")]
+    #[tokio::test]
+    async fn test_decompress_zstd() {
+        use async_compression::tokio::bufread::ZstdEncoder;
+
+        let mut compressed = Vec::new();
+        ZstdEncoder::new(&b"hello zstd"[..]).read_to_end(&mut compressed).await.unwrap();
+
+        let resp = call(&service(), encoded("zstd", compressed.clone())).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.into_body().to_string().await.unwrap(), "hello zstd");
+
+        // not a zstd frame
+        compressed[0] ^= 0xFF;
+
+        assert_eq!(
+            call(&service(), encoded("zstd", compressed)).await.status(),
+            StatusCode::BAD_REQUEST
+        );
+    }
+
+    #[cfg(all(feature = "compression-gzip", feature = "compression-br"))]
+    #[tokio::test]
+    async fn test_decompress_stacked() {
+        use async_compression::tokio::bufread::{BrotliEncoder, GzipEncoder};
+
+        // `gzip, br` means gzip was applied first, then brotli
+        let mut compressed = Vec::new();
+        BrotliEncoder::new(BufReader::new(GzipEncoder::new(&b"hello stacked"[..])))
+            .read_to_end(&mut compressed)
+            .await
+            .unwrap();
+
+        let resp = call(&service(), encoded("gzip, br", compressed.clone())).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.into_body().to_string().await.unwrap(), "hello stacked");
+
+        // decoding in the wrong order fails
+        let resp = call(&service(), encoded("br, gzip", compressed)).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_decompress_passthrough() {
+        let service = service();
+
+        let req = http::Request::post("/echo").body(Body::from(bytes::Bytes::from_static(b"plain"))).unwrap();
+        assert_eq!(
+            call(&service, req).await.into_body().to_string().await.unwrap(),
+            "plain"
+        );
+
+        let req = http::Request::post("/echo")
+            .header(header::CONTENT_ENCODING, "lz4")
+            .body(Body::from(bytes::Bytes::from_static(b"plain")))
+            .unwrap();
+
+        assert_eq!(call(&service, req).await.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+}
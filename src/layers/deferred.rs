@@ -135,12 +135,24 @@ where
                         }
                     }
 
-                    let (new_parts, body) = deferred.0.into_response(encoding).into_parts();
+                    let (new_parts, body) = deferred.inner.into_response(encoding).into_parts();
 
                     if !new_parts.status.is_success() {
                         parts = new_parts;
                     } else {
-                        parts.headers.extend(new_parts.headers);
+                        // headers already given for the response take precedence over those from the encoder
+                        let mut keep = None;
+
+                        for (name, value) in new_parts.headers {
+                            if let Some(name) = name {
+                                keep = (!parts.headers.contains_key(&name)).then_some(name);
+                            }
+
+                            if let Some(ref name) = keep {
+                                parts.headers.append(name.clone(), value);
+                            }
+                        }
+
                         parts.extensions.extend(new_parts.extensions);
                     }
 
@@ -151,3 +163,46 @@ where
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use http::{header, HeaderValue, StatusCode};
+
+    use super::*;
+    use crate::{body::deferred::Deferred, body::Body, Router};
+
+    #[tokio::test]
+    async fn test_deferred_parts() {
+        let mut router = Router::<(), Response>::with_state(());
+        router.post("/users", || async {
+            Deferred::new([1, 2, 3]).with_response_parts((
+                StatusCode::CREATED,
+                [(header::LOCATION, HeaderValue::from_static("/users/42"))],
+            ))
+        });
+        router.get("/problem", || async {
+            let mut deferred = Deferred::new("oops");
+            deferred.parts_mut().status = StatusCode::BAD_REQUEST;
+            deferred.parts_mut().headers.insert(
+                header::CONTENT_TYPE,
+                HeaderValue::from_static("application/problem+json"),
+            );
+            deferred
+        });
+
+        let service = DeferredEncoding::default().layer(router);
+
+        let req = http::Request::post("/users").body(Body::empty()).unwrap();
+        let resp = service.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        assert_eq!(resp.headers()[header::LOCATION], "/users/42");
+        assert_eq!(resp.headers()[header::CONTENT_TYPE], "application/json");
+        assert_eq!(resp.into_body().to_string().await.unwrap(), "[1,2,3]");
+
+        let req = http::Request::get("/problem").body(Body::empty()).unwrap();
+        let resp = service.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(resp.headers()[header::CONTENT_TYPE], "application/problem+json");
+        assert_eq!(resp.into_body().to_string().await.unwrap(), r#""oops""#);
+    }
+}
@@ -6,6 +6,7 @@ use crate::{
     response::{IntoResponse, Response},
     service::ServiceFuture,
     Layer,
+    RequestParts,
     Service,
 };
 
@@ -26,6 +27,14 @@ impl<F, T> HandleErrorLayer<F, T> {
     }
 }
 
+impl<F> HandleErrorLayer<F, RequestParts> {
+    /// Create a new `HandleErrorLayer` where the error handler also receives
+    /// the request parts, see [`HandleError::with_request_parts`].
+    pub fn with_request_parts(f: F) -> Self {
+        Self::new(f)
+    }
+}
+
 impl<F, T> Clone for HandleErrorLayer<F, T>
 where
     F: Clone,
@@ -75,6 +84,18 @@ impl<S, F, T> HandleError<S, F, T> {
     }
 }
 
+impl<S, F> HandleError<S, F, RequestParts> {
+    /// Create a new `HandleError` where the error handler is given the request parts
+    /// alongside the error, such as to negotiate the error response format or to log
+    /// the request path.
+    ///
+    /// The parts are cloned before calling the inner service, and the returned future
+    /// cannot borrow them, so anything needed after the handler returns must be copied out.
+    pub fn with_request_parts(inner: S, f: F) -> Self {
+        Self::new(inner, f)
+    }
+}
+
 impl<S, F, T> Clone for HandleError<S, F, T>
 where
     S: Clone,
@@ -125,6 +146,32 @@ where
     }
 }
 
+impl<S, F, B, Fut, Res> Service<Request<B>> for HandleError<S, F, RequestParts>
+where
+    S: Service<Request<B>>,
+    S::Response: IntoResponse + Send,
+    S::Error: Send,
+    F: FnOnce(&RequestParts, S::Error) -> Fut + Clone + Send + Sync,
+    Fut: Future<Output = Res> + Send,
+    Res: IntoResponse,
+    B: Send + 'static,
+{
+    type Response = Response;
+    type Error = Infallible;
+
+    fn call(&self, req: Request<B>) -> impl ServiceFuture<Self::Response, Self::Error> {
+        async move {
+            let (parts, body) = req.into_parts();
+            let cloned = parts.clone();
+
+            match self.inner.call(Request::from_parts(parts, body)).await {
+                Ok(res) => Ok(res.into_response()),
+                Err(err) => Ok((self.f.clone())(&cloned, err).await.into_response()),
+            }
+        }
+    }
+}
+
 // TODO: Revisit with new error handling
 //
 // #[allow(unused_macros)]
@@ -185,3 +232,51 @@ where
 // impl_service!(T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12, T13, T14);
 // impl_service!(T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12, T13, T14, T15);
 // impl_service!(T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12, T13, T14, T15, T16);
+
+#[cfg(test)]
+mod tests {
+    use http::{header::ACCEPT, HeaderValue, StatusCode};
+
+    use super::*;
+    use crate::body::Body;
+
+    struct Failing;
+
+    impl Service<Request<Body>> for Failing {
+        type Response = Response;
+        type Error = &'static str;
+
+        fn call(&self, _req: Request<Body>) -> impl ServiceFuture<Self::Response, Self::Error> {
+            async { Err("something went wrong") }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_negotiated_error() {
+        let svc = HandleErrorLayer::with_request_parts(|parts: &RequestParts, err: &'static str| {
+            let json = parts.headers.get(ACCEPT).is_some_and(|v| v == "application/json");
+
+            async move {
+                let body = match json {
+                    true => format!(r#"{{"error":"{err}"}}"#),
+                    false => err.to_owned(),
+                };
+
+                body.with_status(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        })
+        .layer(Failing);
+
+        let req = Request::get("/").header(ACCEPT, HeaderValue::from_static("application/json"));
+        let resp = svc.call(req.body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(
+            resp.into_body().to_string().await.unwrap(),
+            r#"{"error":"something went wrong"}"#
+        );
+
+        let req = Request::get("/").header(ACCEPT, HeaderValue::from_static("text/plain"));
+        let resp = svc.call(req.body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(resp.into_body().to_string().await.unwrap(), "something went wrong");
+    }
+}
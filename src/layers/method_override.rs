@@ -0,0 +1,184 @@
+use headers::{ContentType, HeaderMapExt as _};
+use http::{HeaderName, Method};
+use http_body_util::BodyExt as _;
+
+use crate::{
+    body::{Body, BodyError},
+    extract::form::FormLimit,
+    service::ServiceFuture,
+    IntoResponse, Layer, Request, Response, Service,
+};
+
+const X_HTTP_METHOD_OVERRIDE: HeaderName = HeaderName::from_static("x-http-method-override");
+
+/// Allows `POST` requests to override their method with the `X-HTTP-Method-Override` header
+/// or a `_method` field in an `application/x-www-form-urlencoded` body, for clients such as
+/// HTML forms that can only send `GET` and `POST` requests.
+///
+/// This must be placed in front of the router, so the method is rewritten before routing.
+///
+/// Only `POST` requests may be overridden, and only to `PUT`, `PATCH` or `DELETE`, so
+/// `GET` requests (including links and redirects) can never be turned into unsafe methods.
+/// Other values are ignored.
+///
+/// To find the `_method` field, form bodies are buffered up to the [`FormLimit`] of the request,
+/// and passed on to the inner service as-is.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(transparent)]
+pub struct MethodOverride<S = ()>(pub S);
+
+impl<S> Layer<S> for MethodOverride {
+    type Service = MethodOverride<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        MethodOverride(inner)
+    }
+}
+
+/// Returns the method given by the `X-HTTP-Method-Override` header, if allowed for this request.
+pub(crate) fn header_override(method: &Method, headers: &http::HeaderMap) -> Option<Method> {
+    match *method {
+        Method::POST => parse_override(headers.get(X_HTTP_METHOD_OVERRIDE)?.as_bytes()),
+        _ => None,
+    }
+}
+
+fn parse_override(value: &[u8]) -> Option<Method> {
+    match Method::from_bytes(value) {
+        Ok(method @ (Method::PUT | Method::PATCH | Method::DELETE)) => Some(method),
+        _ => None,
+    }
+}
+
+fn is_form(req: &Request) -> bool {
+    req.headers().typed_get::<ContentType>().is_some_and(|content_type| {
+        let mime = mime::Mime::from(content_type);
+        mime.type_() == mime::APPLICATION && mime.subtype() == mime::WWW_FORM_URLENCODED
+    })
+}
+
+async fn find_override(req: &mut Request) -> Result<Option<Method>, BodyError> {
+    if req.headers().contains_key(X_HTTP_METHOD_OVERRIDE) {
+        return Ok(header_override(req.method(), req.headers()));
+    }
+
+    if !is_form(req) {
+        return Ok(None);
+    }
+
+    let FormLimit(limit) = req.extensions().get::<FormLimit>().copied().unwrap_or_default();
+
+    let body = req.body_mut().take();
+
+    // reject early if the body is known to be too large
+    if body.original_size_hint().lower() > limit {
+        return Err(BodyError::LengthLimitError);
+    }
+
+    let bytes = body.limit(limit)?.collect().await?.to_bytes();
+
+    // an invalid form is left for the handler to reject
+    let method = crate::form_impl::from_bytes::<Vec<(String, String)>>(&bytes)
+        .ok()
+        .and_then(|fields| fields.into_iter().find(|(key, _)| key == "_method"))
+        .and_then(|(_, value)| parse_override(value.as_bytes()));
+
+    *req.body_mut() = Body::from(bytes);
+
+    Ok(method)
+}
+
+impl<S> Service<Request> for MethodOverride<S>
+where
+    S: Service<Request, Response: IntoResponse>,
+{
+    type Response = Response;
+    type Error = S::Error;
+
+    fn call(&self, mut req: Request) -> impl ServiceFuture<Self::Response, Self::Error> {
+        async move {
+            if req.method() == Method::POST {
+                match find_override(&mut req).await {
+                    Ok(Some(method)) => *req.method_mut() = method,
+                    Ok(None) => {}
+                    Err(e) => return Ok(e.into_response()),
+                }
+            }
+
+            self.0.call(req).await.map(IntoResponse::into_response)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use http::header::CONTENT_TYPE;
+
+    use super::*;
+    use crate::{body::Form, Router};
+
+    #[derive(serde::Deserialize)]
+    struct Item {
+        name: String,
+    }
+
+    fn service() -> impl Service<Request, Response = Response> {
+        let mut router = Router::<(), Response>::with_state(());
+        router.get("/item", || async { "get" });
+        router.post("/item", || async { "post" });
+        router.put("/item", || async { "put" });
+        router.delete("/item", |Form(item): Form<Item>| async move {
+            format!("delete {}", item.name)
+        });
+
+        MethodOverride::default().layer(router)
+    }
+
+    async fn call(service: &impl Service<Request, Response = Response>, req: Request) -> String {
+        match service.call(req).await {
+            Ok(resp) => resp.into_body().to_string().await.unwrap(),
+            Err(_) => unreachable!(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_method_override_header() {
+        let service = service();
+
+        let req = http::Request::post("/item").header(X_HTTP_METHOD_OVERRIDE, "PUT").body(Body::empty()).unwrap();
+        assert_eq!(call(&service, req).await, "put");
+
+        // only safe overrides are allowed
+        let req = http::Request::post("/item").header(X_HTTP_METHOD_OVERRIDE, "GET").body(Body::empty()).unwrap();
+        assert_eq!(call(&service, req).await, "post");
+
+        let req =
+            http::Request::post("/item").header(X_HTTP_METHOD_OVERRIDE, "CONNECT").body(Body::empty()).unwrap();
+        assert_eq!(call(&service, req).await, "post");
+
+        // GET requests are never overridden
+        let req = http::Request::get("/item").header(X_HTTP_METHOD_OVERRIDE, "PUT").body(Body::empty()).unwrap();
+        assert_eq!(call(&service, req).await, "get");
+    }
+
+    #[tokio::test]
+    async fn test_method_override_form() {
+        let service = service();
+
+        let req = http::Request::post("/item")
+            .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(Body::from(Bytes::from_static(b"name=widget&_method=DELETE")))
+            .unwrap();
+
+        // the body is still available to the handler
+        assert_eq!(call(&service, req).await, "delete widget");
+
+        let req = http::Request::post("/item")
+            .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(Body::from(Bytes::from_static(b"name=widget")))
+            .unwrap();
+
+        assert_eq!(call(&service, req).await, "post");
+    }
+}
@@ -1,15 +1,23 @@
 pub use tower_layer::{layer_fn, Identity, LayerFn, Stack};
 
 pub use crate::extract::real_ip::RealIpLayer;
+pub use crate::extract::request_id::RequestIdLayer;
 
 pub mod catch_panic;
 pub mod cloneable;
+pub mod concurrency_limit;
 pub mod convert_body;
+pub mod cors;
 pub mod deferred;
 pub mod handle_error;
 pub mod limit_req_body;
+pub mod method_override;
 pub mod normalize;
 pub mod resp_timing;
+pub mod security_headers;
+pub mod set_header;
+pub mod timeout;
+pub mod trace;
 
 #[cfg(feature = "gcra")]
 pub mod rate_limit;
@@ -17,6 +25,19 @@ pub mod rate_limit;
 #[cfg(feature = "_meta_compression")]
 pub mod compression;
 
+#[cfg(feature = "_meta_compression")]
+pub mod decompression;
+
+#[cfg(feature = "cache")]
+pub mod cache;
+
+/// Response extension that prevents the compression layer from compressing the response,
+/// regardless of its predicate, such as for media files that are already compressed.
+///
+/// This is set by the [`fs`](crate::fs) responders for images, video, audio and archives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NoCompression;
+
 /// Decorates a [`Service`](crate::Service), transforming either the request or the response.
 /// This is re-exported from the [`tower_layer`] crate, but is used
 /// differently here.
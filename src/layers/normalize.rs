@@ -1,13 +1,19 @@
 use std::convert::Infallible;
 
 use futures::FutureExt as _;
-use http::{header, HeaderMap, HeaderName, HeaderValue, Method};
+use http::{header, HeaderMap, HeaderValue, Method};
 use http_body::Body as _;
 
 use crate::{body::Body, service::ServiceFuture, IntoResponse, Layer, Response, Service};
 
 /// Normalizes the response by ensuring that the `Content-Length` header is set
 /// and the body is empty for `HEAD` requests and `CONNECT` responses.
+///
+/// The `X-HTTP-Method-Override` header is also applied to the request, with the same
+/// restrictions as the [`MethodOverride`](super::method_override::MethodOverride) layer:
+/// only `POST` requests may be overridden, and only to `PUT`, `PATCH` or `DELETE`.
+/// Any other override, such as of a `GET` request or to `CONNECT`, is ignored and
+/// the request keeps its original method.
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
 #[repr(transparent)]
 pub struct Normalize<S = ()>(pub S);
@@ -29,11 +35,10 @@ where
 
     #[inline]
     fn call(&self, mut req: http::Request<B>) -> impl ServiceFuture<Self::Response, Self::Error> {
-        // This is sometimes used in old browsers without support for PATCH or OPTIONS methods.
-        if let Some(method) = req.headers().get(const { HeaderName::from_static("x-http-method-override") }) {
-            if let Ok(method_override) = Method::from_bytes(method.as_bytes()) {
-                *req.method_mut() = method_override;
-            }
+        // This is sometimes used in old browsers without support for PUT, PATCH or DELETE methods.
+        // See the `MethodOverride` layer for which overrides are allowed.
+        if let Some(method) = super::method_override::header_override(req.method(), req.headers()) {
+            *req.method_mut() = method;
         }
 
         let method = match *req.method() {
@@ -100,3 +105,40 @@ fn set_content_length(size_hint: http_body::SizeHint, headers: &mut HeaderMap) {
         headers.insert(header::CONTENT_LENGTH, header_value);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Request, Router};
+
+    #[tokio::test]
+    async fn test_method_override() {
+        let mut router = Router::<(), Response>::with_state(());
+        router.get("/item", || async { "get" });
+        router.post("/item", || async { "post" });
+        router.put("/item", || async { "put" });
+        router.any("/tunnel", |req: Request| async move { req.method().to_string() });
+
+        let service = Normalize(router);
+
+        let call = |method: Method, path: &'static str, value: &'static str| {
+            let req = http::Request::builder()
+                .method(method)
+                .uri(path)
+                .header("x-http-method-override", value)
+                .body(Body::empty())
+                .unwrap();
+
+            async { service.call(req).await.unwrap().into_body().to_string().await.unwrap() }
+        };
+
+        assert_eq!(call(Method::POST, "/item", "PUT").await, "put");
+
+        // GET requests are never overridden
+        assert_eq!(call(Method::GET, "/item", "PUT").await, "get");
+
+        // nor can anything be overridden to CONNECT or other methods
+        assert_eq!(call(Method::POST, "/tunnel", "CONNECT").await, "POST");
+        assert_eq!(call(Method::POST, "/tunnel", "OPTIONS").await, "POST");
+    }
+}
@@ -66,18 +66,35 @@ impl<K: Eq + Hash, H: BuildHasher> RateLimiter<K, H> {
     }
 
     /// Perform a request, returning an error if the request is too soon.
+    #[inline]
     pub async fn req(&self, key: K, quota: Quota, now: Instant) -> Result<(), RateLimitError> {
+        self.req_cost(key, quota, now, 1).await
+    }
+
+    /// Synchonous version of [`RateLimiter::req`].
+    #[inline]
+    pub fn req_sync(&self, key: K, quota: Quota, now: Instant) -> Result<(), RateLimitError> {
+        self.req_cost_sync(key, quota, now, 1)
+    }
+
+    /// Perform a weighted request that consumes `cost` cells of the quota, as if `cost`
+    /// requests were made at once, returning an error if the request is too soon.
+    ///
+    /// Like regular requests, a weighted request is allowed as long as the quota is not
+    /// already exhausted, even if `cost` exceeds the remaining burst, so expensive requests
+    /// are never starved, but delay subsequent requests accordingly.
+    pub async fn req_cost(&self, key: K, quota: Quota, now: Instant, cost: u64) -> Result<(), RateLimitError> {
         let now = self.relative(now);
 
-        let Some(res) = self.limits.read_async(&key, |_, gcra| gcra.req(quota, now)).await else {
+        let Some(res) = self.limits.read_async(&key, |_, gcra| gcra.req_cost(quota, now, cost)).await else {
             if self.should_gc() {
                 self.gc(now).await;
             }
 
             return match self.limits.entry_async(key).await {
-                Entry::Occupied(gcra) => gcra.get().req(quota, now),
+                Entry::Occupied(gcra) => gcra.get().req_cost(quota, now, cost),
                 Entry::Vacant(gcra) => {
-                    gcra.insert_entry(Gcra::first(quota, now));
+                    gcra.insert_entry(Gcra::first_cost(quota, now, cost));
                     Ok(())
                 }
             };
@@ -86,19 +103,19 @@ impl<K: Eq + Hash, H: BuildHasher> RateLimiter<K, H> {
         res
     }
 
-    /// Synchonous version of [`RateLimiter::req`].
-    pub fn req_sync(&self, key: K, quota: Quota, now: Instant) -> Result<(), RateLimitError> {
+    /// Synchonous version of [`RateLimiter::req_cost`].
+    pub fn req_cost_sync(&self, key: K, quota: Quota, now: Instant, cost: u64) -> Result<(), RateLimitError> {
         let now = self.relative(now);
 
-        let Some(res) = self.limits.read(&key, |_, gcra| gcra.req(quota, now)) else {
+        let Some(res) = self.limits.read(&key, |_, gcra| gcra.req_cost(quota, now, cost)) else {
             if self.should_gc() {
                 self.gc_sync(now);
             }
 
             return match self.limits.entry(key) {
-                Entry::Occupied(gcra) => gcra.get().req(quota, now),
+                Entry::Occupied(gcra) => gcra.get().req_cost(quota, now, cost),
                 Entry::Vacant(gcra) => {
-                    gcra.insert_entry(Gcra::first(quota, now));
+                    gcra.insert_entry(Gcra::first_cost(quota, now, cost));
                     Ok(())
                 }
             };
@@ -107,12 +124,13 @@ impl<K: Eq + Hash, H: BuildHasher> RateLimiter<K, H> {
         res
     }
 
-    /// Variant of [`RateLimiter::req`] that allows for a peek at the key after it's been inserted.
+    /// Variant of [`RateLimiter::req_cost`] that allows for a peek at the key after it's been inserted.
     pub(crate) async fn req_peek_key<F>(
         &self,
         key: K,
         quota: Quota,
         now: Instant,
+        cost: u64,
         peek: F,
     ) -> Result<(), RateLimitError>
     where
@@ -124,7 +142,7 @@ impl<K: Eq + Hash, H: BuildHasher> RateLimiter<K, H> {
         let read = self
             .limits
             .read_async(&key, |_, gcra| {
-                gcra.req(quota, now)?;
+                gcra.req_cost(quota, now, cost)?;
                 let peek = unsafe { peek.take().unwrap_unchecked() }; // SAFETY: peek is Some
                 peek(&key);
                 Ok(())
@@ -143,10 +161,10 @@ impl<K: Eq + Hash, H: BuildHasher> RateLimiter<K, H> {
 
             let entry = match self.limits.entry_async(key).await {
                 Entry::Occupied(gcra) => {
-                    gcra.get().req(quota, now)?;
+                    gcra.get().req_cost(quota, now, cost)?;
                     gcra
                 }
-                Entry::Vacant(gcra) => gcra.insert_entry(Gcra::first(quota, now)),
+                Entry::Vacant(gcra) => gcra.insert_entry(Gcra::first_cost(quota, now, cost)),
             };
 
             // NOTE: By using the returned entry from either branch, we potentially avoid duplicate codegen for peek
@@ -211,6 +229,73 @@ impl<K: Eq + Hash, H: BuildHasher> RateLimiter<K, H> {
     }
 }
 
+impl<K: Eq + Hash + Clone, H: BuildHasher> RateLimiter<K, H> {
+    /// Takes a snapshot of the limiter state, such as to persist it across restarts.
+    ///
+    /// Each key is paired with the number of nanoseconds until its limit has fully
+    /// replenished, as of when the snapshot was taken. Keys that have already
+    /// replenished are omitted, as they are equivalent to absent keys.
+    ///
+    /// See [`RateLimiter::restore`] for restoring the snapshot.
+    pub async fn snapshot(&self) -> Vec<(K, u64)> {
+        let now = self.relative(Instant::now());
+        let mut snapshot = Vec::new();
+
+        self.limits.scan_async(|k, v| Self::snapshot_entry(&mut snapshot, now, k, v)).await;
+
+        snapshot
+    }
+
+    /// Synchronous version of [`RateLimiter::snapshot`].
+    pub fn snapshot_sync(&self) -> Vec<(K, u64)> {
+        let now = self.relative(Instant::now());
+        let mut snapshot = Vec::new();
+
+        self.limits.scan(|k, v| Self::snapshot_entry(&mut snapshot, now, k, v));
+
+        snapshot
+    }
+
+    fn snapshot_entry(snapshot: &mut Vec<(K, u64)>, now: u64, key: &K, gcra: &Gcra) {
+        let remaining = gcra.0.load(Ordering::Relaxed).saturating_sub(now);
+
+        if remaining > 0 {
+            snapshot.push((key.clone(), remaining));
+        }
+    }
+
+    /// Restores a snapshot taken with [`RateLimiter::snapshot`], as if it were taken at `now`.
+    ///
+    /// Time elapsed between taking and restoring the snapshot is not subtracted, so restored
+    /// limits are conservative. If a key is already present, the stricter of the two is kept.
+    pub async fn restore(&self, snapshot: impl IntoIterator<Item = (K, u64)>, now: Instant) {
+        let now = self.relative(now);
+
+        for (key, remaining) in snapshot {
+            let value = now.saturating_add(remaining);
+
+            match self.limits.entry_async(key).await {
+                Entry::Occupied(gcra) => _ = gcra.get().0.fetch_max(value, Ordering::Relaxed),
+                Entry::Vacant(gcra) => _ = gcra.insert_entry(Gcra(AtomicU64::new(value))),
+            }
+        }
+    }
+
+    /// Synchronous version of [`RateLimiter::restore`].
+    pub fn restore_sync(&self, snapshot: impl IntoIterator<Item = (K, u64)>, now: Instant) {
+        let now = self.relative(now);
+
+        for (key, remaining) in snapshot {
+            let value = now.saturating_add(remaining);
+
+            match self.limits.entry(key) {
+                Entry::Occupied(gcra) => _ = gcra.get().0.fetch_max(value, Ordering::Relaxed),
+                Entry::Vacant(gcra) => _ = gcra.insert_entry(Gcra(AtomicU64::new(value))),
+            }
+        }
+    }
+}
+
 impl<K: Eq + Hash, H: BuildHasher> Default for RateLimiter<K, H>
 where
     H: Default,
@@ -244,7 +329,7 @@ use http::StatusCode;
 
 impl IntoResponse for RateLimitError {
     fn into_response(self) -> Response {
-        use http::{HeaderName, HeaderValue};
+        use http::{header::RETRY_AFTER, HeaderName, HeaderValue};
 
         // reuse as_duration value
         let reset = self.as_duration();
@@ -273,7 +358,8 @@ impl IntoResponse for RateLimitError {
 
         headers.insert(const { HeaderName::from_static("ratelimit-reset") }, value.clone());
         headers.insert(const { HeaderName::from_static("x-ratelimit-reset") }, value.clone());
-        headers.insert(const { HeaderName::from_static("retry-after") }, value.clone());
+        // same as `RetryAfter::Delay`, without encoding it again
+        headers.insert(RETRY_AFTER, value.clone());
         headers.insert(
             const { HeaderName::from_static("ratelimit-remaining") },
             const { HeaderValue::from_static("0") },
@@ -339,6 +425,47 @@ impl Quota {
     pub const fn simple(emission_interval: Duration) -> Quota {
         Self::new(emission_interval, NonZeroU64::MIN)
     }
+
+    /// Constructs a new quota allowing `n` requests per `period`, with a burst size of `n`.
+    ///
+    /// Requests are released evenly over the period, so `Quota::per_period(100, Duration::from_secs(1))`
+    /// is equivalent to `Quota::new(Duration::from_millis(10), 100)`. Up to `n` requests may be made
+    /// at once, after which the rate settles to `n` per `period`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is zero.
+    #[must_use]
+    pub const fn per_period(n: u64, period: Duration) -> Quota {
+        let Some(burst) = NonZeroU64::new(n) else {
+            panic!("quota must allow at least one request");
+        };
+
+        let t = period.as_nanos() / n as u128;
+
+        Self::new(
+            Duration::from_nanos(if t > u64::MAX as u128 { u64::MAX } else { t as u64 }),
+            burst,
+        )
+    }
+
+    /// Constructs a new quota allowing `n` requests per second, see [`Quota::per_period`].
+    #[must_use]
+    pub const fn per_second(n: u64) -> Quota {
+        Self::per_period(n, Duration::from_secs(1))
+    }
+
+    /// Constructs a new quota allowing `n` requests per minute, see [`Quota::per_period`].
+    #[must_use]
+    pub const fn per_minute(n: u64) -> Quota {
+        Self::per_period(n, Duration::from_secs(60))
+    }
+
+    /// Constructs a new quota allowing `n` requests per hour, see [`Quota::per_period`].
+    #[must_use]
+    pub const fn per_hour(n: u64) -> Quota {
+        Self::per_period(n, Duration::from_secs(60 * 60))
+    }
 }
 
 /// Generic Cell Rate Algorithm (GCRA) implementation.
@@ -354,13 +481,26 @@ impl Gcra {
     /// This is equivalent to `Gcra(now + t).req()`, but more efficient.
     #[inline]
     #[must_use]
-    pub const fn first(Quota { t, .. }: Quota, now: u64) -> Gcra {
-        // Equivalent to `Gcra(now + t).req()` to calculate the first request
-        Gcra(AtomicU64::new(now.saturating_add(t).saturating_add(t)))
+    pub const fn first(quota: Quota, now: u64) -> Gcra {
+        Self::first_cost(quota, now, 1)
+    }
+
+    /// Constructs a new GCRA for the first weighted request at the given time.
+    ///
+    /// This is equivalent to `Gcra(now + t).req_cost(cost)`, but more efficient.
+    #[inline]
+    #[must_use]
+    pub const fn first_cost(Quota { t, .. }: Quota, now: u64, cost: u64) -> Gcra {
+        // Equivalent to `Gcra(now + t).req_cost(cost)` to calculate the first request
+        Gcra(AtomicU64::new(
+            now.saturating_add(t).saturating_add(t.saturating_mul(cost)),
+        ))
     }
 
     /// Core GCRA logic. Returns the next time a request can be made, either as an error or a success.
-    fn decide(prev: u64, now: u64, Quota { tau, t }: Quota) -> Result<u64, RateLimitError> {
+    ///
+    /// Each request advances the state by `t * cost`.
+    fn decide(prev: u64, now: u64, Quota { tau, t }: Quota, cost: u64) -> Result<u64, RateLimitError> {
         // burst's act as an offset to allow more through at the start
         let next = prev.saturating_sub(tau);
 
@@ -368,16 +508,22 @@ impl Gcra {
             // SAFETY: next > now, so next - now is non-zero by definition
             Err(RateLimitError(unsafe { NonZeroU64::new_unchecked(next - now) }))
         } else {
-            Ok(now.max(prev).saturating_add(t))
+            Ok(now.max(prev).saturating_add(t.saturating_mul(cost)))
         }
     }
 
     /// Perform a request, returning an error if the request is too soon.
+    #[inline]
     pub fn req(&self, quota: Quota, now: u64) -> Result<(), RateLimitError> {
+        self.req_cost(quota, now, 1)
+    }
+
+    /// Perform a weighted request consuming `cost` cells, returning an error if the request is too soon.
+    pub fn req_cost(&self, quota: Quota, now: u64, cost: u64) -> Result<(), RateLimitError> {
         let mut prev = self.0.load(Ordering::Acquire);
 
         loop {
-            let next = Self::decide(prev, now, quota)?;
+            let next = Self::decide(prev, now, quota, cost)?;
 
             match self.0.compare_exchange_weak(prev, next, Ordering::Release, Ordering::Relaxed) {
                 Ok(_) => return Ok(()),
@@ -386,3 +532,105 @@ impl Gcra {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_per_second() {
+        let quota = Quota::per_second(100);
+        assert_eq!(quota.t, Duration::from_millis(10).as_nanos() as u64);
+
+        let limiter = RateLimiter::<(), foldhash::fast::RandomState>::default();
+        let start = Instant::now();
+
+        // a full burst is allowed at once
+        for _ in 0..100 {
+            limiter.req_sync((), quota, start).unwrap();
+        }
+
+        let err = limiter.req_sync((), quota, start).unwrap_err();
+        assert!(err.as_duration() <= Duration::from_millis(10));
+
+        // after that, requests are released at 100/s
+        let allowed = (1..=1000u64)
+            .filter(|ms| limiter.req_sync((), quota, start + Duration::from_millis(*ms)).is_ok())
+            .count();
+
+        assert!((99..=101).contains(&allowed), "allowed {allowed} requests");
+    }
+
+    #[test]
+    fn test_error_response() {
+        use headers::HeaderMapExt as _;
+
+        use crate::headers::retry_after::RetryAfter;
+
+        let err = RateLimitError(NonZeroU64::new(2_500_000_000).unwrap());
+        let resp = err.into_response();
+
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(resp.headers()["ratelimit-reset"], "2");
+        assert_eq!(
+            resp.headers().typed_get::<RetryAfter>(),
+            Some(RetryAfter::Delay(Duration::from_secs(2)))
+        );
+    }
+
+    #[test]
+    fn test_req_cost() {
+        let quota = Quota::per_second(10);
+        let now = Instant::now();
+
+        let limiter = RateLimiter::<u8, foldhash::fast::RandomState>::default();
+
+        // one cost-5 request and five cost-1 requests leave the same remaining quota
+        limiter.req_cost_sync(0, quota, now, 5).unwrap();
+
+        for _ in 0..5 {
+            limiter.req_sync(1, quota, now).unwrap();
+        }
+
+        let remaining = |key| (0..10).take_while(|_| limiter.req_sync(key, quota, now).is_ok()).count();
+
+        assert_eq!(remaining(0), 5);
+        assert_eq!(remaining(1), 5);
+
+        // and recover at the same rate
+        let later = now + Duration::from_millis(500);
+        assert_eq!(limiter.req_cost_sync(0, quota, later, 5), Ok(()));
+        assert_eq!(limiter.req_cost_sync(1, quota, later, 5), Ok(()));
+        assert!(limiter.req_sync(0, quota, later).is_err());
+        assert!(limiter.req_sync(1, quota, later).is_err());
+    }
+
+    #[test]
+    fn test_snapshot_restore() {
+        let quota = Quota::per_second(10);
+        let now = Instant::now();
+
+        let limiter = RateLimiter::<u8, foldhash::fast::RandomState>::default();
+
+        // exhaust key 0, and use part of key 1
+        for _ in 0..10 {
+            limiter.req_sync(0, quota, now).unwrap();
+        }
+        limiter.req_cost_sync(1, quota, now, 4).unwrap();
+
+        let mut snapshot = limiter.snapshot_sync();
+        snapshot.sort_unstable();
+        assert_eq!(snapshot.iter().map(|(k, _)| *k).collect::<Vec<_>>(), [0, 1]);
+
+        // a fresh limiter, as after a restart, with a different start time
+        let restarted = RateLimiter::<u8, foldhash::fast::RandomState>::default();
+        let later = Instant::now() + Duration::from_secs(5);
+        restarted.restore_sync(snapshot, later);
+
+        assert!(restarted.req_sync(0, quota, later).is_err());
+
+        let remaining = |key| (0..10).take_while(|_| restarted.req_sync(key, quota, later).is_ok()).count();
+        assert!((5..=6).contains(&remaining(1)));
+        assert_eq!(remaining(2), 10);
+    }
+}
@@ -33,6 +33,20 @@ impl<K> Key for K where K: Hash + Eq + Send + Sync + 'static {}
 pub mod gcra;
 pub use gcra::RateLimitError;
 
+/// Request extension giving the cost of a request to the [`RateLimitLayer`], where each unit
+/// of cost consumes as much of the quota as a regular request. Requests without it cost `1`.
+///
+/// This must be inserted before the rate limiter runs, such as by a layer in front of it,
+/// for expensive operations to consume more of the quota.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RequestCost(pub u64);
+
+impl Default for RequestCost {
+    fn default() -> Self {
+        RequestCost(1)
+    }
+}
+
 /// Interval for garbage collection of the rate limiter, which can be either
 /// a number of requests or a time duration.
 ///
@@ -172,8 +186,34 @@ impl<T> RouteWithKey<T> {
             method: self.method.as_ref().map(Cow::Borrowed),
         }
     }
+
+    fn into_snapshot_entry(self, remaining: u64) -> (Route<'static>, T, u64) {
+        let route = Route {
+            path: Cow::Owned(String::from(&*self.path)),
+            method: self.method.map(Cow::Owned),
+        };
+
+        (route, self.key, remaining)
+    }
+
+    fn from_snapshot_entry((route, key, remaining): (Route<'_>, T, u64)) -> (Self, u64) {
+        let path = match route.path {
+            path if path.is_empty() => MatchedPath::Fallback,
+            path => MatchedPath::Matched(FtlMatchedPath(Arc::from(&*path))),
+        };
+
+        let method = route.method.map(Cow::into_owned);
+
+        (RouteWithKey { path, method, key }, remaining)
+    }
 }
 
+/// A snapshot of the state of a [`RateLimitLayer`], as taken by [`RateLimitLayer::snapshot`].
+///
+/// Each entry is the route, the key, and the number of nanoseconds until the limit for
+/// that route and key has fully replenished. Fallback entries have an empty path.
+pub type Snapshot<K> = Vec<(Route<'static>, K, u64)>;
+
 /// Hashmap of quotas for rate limiting, mapping a path as passed to [`Router`](crate::router::Router) to a [`gcra::Quota`].
 type Quotas = HashMap<Route<'static>, gcra::Quota, foldhash::fast::RandomState>;
 
@@ -238,6 +278,49 @@ pub struct RateLimitLayerBuilder<K = ()> {
     global_fallback: FallbackBehavior,
     gc_interval: GCInterval,
     shutdown: BuilderDropNotify,
+    on_shutdown: Option<Box<dyn OnShutdown<K>>>,
+}
+
+/// Object-safe trait for the snapshot-on-shutdown callback,
+/// which requires `K: Clone` to take the snapshot.
+trait OnShutdown<K: Key>: Send + Sync + 'static {
+    fn on_shutdown(self: Box<Self>, limiter: &gcra::RateLimiter<RouteWithKey<K>>);
+}
+
+struct SnapshotOnShutdown<F>(F);
+
+impl<K: Key, F> OnShutdown<K> for SnapshotOnShutdown<F>
+where
+    K: Clone,
+    F: FnOnce(Snapshot<K>) + Send + Sync + 'static,
+{
+    fn on_shutdown(self: Box<Self>, limiter: &gcra::RateLimiter<RouteWithKey<K>>) {
+        let entries = limiter.snapshot_sync();
+        (self.0)(entries.into_iter().map(|(key, remaining)| key.into_snapshot_entry(remaining)).collect());
+    }
+}
+
+/// The rate limiter shared by all clones of a [`RateLimitLayer`], and its background GC task.
+struct Limiter<K: Key> {
+    inner: gcra::RateLimiter<RouteWithKey<K>>,
+    on_shutdown: Option<Box<dyn OnShutdown<K>>>,
+}
+
+impl<K: Key> Deref for Limiter<K> {
+    type Target = gcra::RateLimiter<RouteWithKey<K>>;
+
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl<K: Key> Drop for Limiter<K> {
+    fn drop(&mut self) {
+        if let Some(on_shutdown) = self.on_shutdown.take() {
+            on_shutdown.on_shutdown(&self.inner);
+        }
+    }
 }
 
 impl<K> Drop for RateLimitLayerBuilder<K> {
@@ -253,7 +336,7 @@ impl<K> Drop for RateLimitLayerBuilder<K> {
 /// Note: The limiter is shared across all clones of the layer and service.
 pub struct RateLimitLayer<K: Key = ()> {
     builder: Arc<RateLimitLayerBuilder<K>>,
-    limiter: Arc<gcra::RateLimiter<RouteWithKey<K>>>,
+    limiter: Arc<Limiter<K>>,
 }
 
 /// Object-safe trait for setting an extension on a request.
@@ -324,6 +407,47 @@ impl<K: Key> RateLimitLayer<K> {
             layer: self.clone(),
         }
     }
+
+    /// Takes a snapshot of the rate limiter state, such as to persist it across restarts.
+    ///
+    /// See [`gcra::RateLimiter::snapshot`] for more information.
+    pub async fn snapshot(&self) -> Snapshot<K>
+    where
+        K: Clone,
+    {
+        let entries = self.limiter.snapshot().await;
+        entries.into_iter().map(|(key, remaining)| key.into_snapshot_entry(remaining)).collect()
+    }
+
+    /// Synchronous version of [`RateLimitLayer::snapshot`].
+    #[must_use]
+    pub fn snapshot_sync(&self) -> Snapshot<K>
+    where
+        K: Clone,
+    {
+        let entries = self.limiter.snapshot_sync();
+        entries.into_iter().map(|(key, remaining)| key.into_snapshot_entry(remaining)).collect()
+    }
+
+    /// Restores a snapshot taken with [`RateLimitLayer::snapshot`], as if it were taken at `now`.
+    ///
+    /// See [`gcra::RateLimiter::restore`] for more information.
+    pub async fn restore(&self, snapshot: Snapshot<K>, now: Instant)
+    where
+        K: Clone,
+    {
+        let entries = snapshot.into_iter().map(RouteWithKey::from_snapshot_entry);
+        self.limiter.restore(entries, now).await;
+    }
+
+    /// Synchronous version of [`RateLimitLayer::restore`].
+    pub fn restore_sync(&self, snapshot: Snapshot<K>, now: Instant)
+    where
+        K: Clone,
+    {
+        let entries = snapshot.into_iter().map(RouteWithKey::from_snapshot_entry);
+        self.limiter.restore_sync(entries, now);
+    }
 }
 
 impl<K: Key> RateLimitLayerBuilder<K> {
@@ -336,6 +460,7 @@ impl<K: Key> RateLimitLayerBuilder<K> {
             global_fallback: FallbackBehavior::default(),
             gc_interval: GCInterval::default(),
             shutdown: BuilderDropNotify::default(),
+            on_shutdown: None,
         }
     }
 
@@ -426,6 +551,22 @@ impl<K: Key> RateLimitLayerBuilder<K> {
         };
         self
     }
+
+    /// Set a callback to be given a [snapshot](RateLimitLayer::snapshot) of the rate limiter state
+    /// when it shuts down, which is when the last clone of the layer and its services is dropped,
+    /// such as after the server has shut down.
+    ///
+    /// The snapshot can then be persisted and [restored](RateLimitLayer::restore) after a restart,
+    /// so clients cannot bypass rate limits by waiting for a restart.
+    #[must_use]
+    pub fn with_snapshot_on_shutdown<F>(mut self, cb: F) -> Self
+    where
+        K: Clone,
+        F: FnOnce(Snapshot<K>) + Send + Sync + 'static,
+    {
+        self.on_shutdown = Some(Box::new(SnapshotOnShutdown(cb)));
+        self
+    }
 }
 
 impl Default for RateLimitLayerBuilder<()> {
@@ -471,6 +612,7 @@ impl<K: Key> RateLimitLayer<K> {
         &self,
         mut key: RouteWithKey<K>,
         now: std::time::Instant,
+        cost: u64,
         peek: F,
     ) -> Result<(), RateLimitError>
     where
@@ -497,7 +639,7 @@ impl<K: Key> RateLimitLayer<K> {
             }
         };
 
-        self.limiter.req_peek_key(key, quota, now, peek).await
+        self.limiter.req_peek_key(key, quota, now, cost, peek).await
     }
 }
 
@@ -573,6 +715,8 @@ where
             None => MatchedPath::Fallback,
         };
 
+        let RequestCost(cost) = req.extensions().get::<RequestCost>().copied().unwrap_or_default();
+
         let (mut parts, body) = req.into_parts();
 
         async move {
@@ -584,7 +728,7 @@ where
                 method: Some(parts.method.clone()),
             };
 
-            let res = self.layer.req_peek_key(key, now, |key| {
+            let res = self.layer.req_peek_key(key, now, cost, |key| {
                 if let Some(ref set_ext) = self.layer.builder.set_ext {
                     // set_extension will clone the key internally
                     set_ext.set_extension(&mut parts.extensions, key, self.layer.clone());
@@ -633,11 +777,11 @@ where
     /// Use [`RateLimitLayerBuilder::handle_error`] or [`RateLimitLayerBuilder::default_handle_error`] to create a stack
     /// with the rate limiter layer and the error-handler layer combined.
     #[must_use]
-    pub fn build(self) -> RateLimitLayer<K> {
-        let limiter = Arc::new(gcra::RateLimiter::new(
-            self.gc_interval.to_requests(),
-            Default::default(),
-        ));
+    pub fn build(mut self) -> RateLimitLayer<K> {
+        let limiter = Arc::new(Limiter {
+            inner: gcra::RateLimiter::new(self.gc_interval.to_requests(), Default::default()),
+            on_shutdown: self.on_shutdown.take(),
+        });
 
         if let GCInterval::Time(d) = self.gc_interval {
             let limiter = limiter.clone();
@@ -865,3 +1009,55 @@ pub mod extensions {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::{body::Body, Router};
+
+    #[tokio::test]
+    async fn test_snapshot_on_shutdown() {
+        let mut router = Router::<(), Response>::with_state(());
+        router.get("/", || async { "ok" });
+        let router = Arc::new(router);
+
+        let saved = Arc::new(Mutex::new(None));
+
+        let layer = RateLimitLayer::<()>::builder()
+            .with_default_quota(gcra::Quota::per_minute(2))
+            .with_snapshot_on_shutdown({
+                let saved = saved.clone();
+                move |snapshot| *saved.lock().unwrap() = Some(snapshot)
+            })
+            .build();
+
+        let service = layer.layer(router.clone());
+
+        let get = || http::Request::get("/").body(Body::empty()).unwrap();
+
+        assert!(service.call(get()).await.is_ok());
+        assert!(service.call(get()).await.is_ok());
+        assert!(matches!(service.call(get()).await, Err(Error::RateLimit(_))));
+
+        // the snapshot is only taken once the last clone is dropped
+        drop(layer);
+        assert!(saved.lock().unwrap().is_none());
+        drop(service);
+
+        let snapshot = saved.lock().unwrap().take().expect("no snapshot taken on shutdown");
+        assert_eq!(snapshot.len(), 1);
+
+        // restored after a restart, the limit still applies
+        let layer = RateLimitLayer::<()>::builder().with_default_quota(gcra::Quota::per_minute(2)).build();
+        layer.restore_sync(snapshot, Instant::now());
+
+        let service = layer.layer(router.clone());
+        assert!(matches!(service.call(get()).await, Err(Error::RateLimit(_))));
+
+        // whereas a fresh limiter would allow it
+        let layer = RateLimitLayer::<()>::builder().with_default_quota(gcra::Quota::per_minute(2)).build();
+        assert!(layer.layer(router).call(get()).await.is_ok());
+    }
+}
@@ -1,25 +1,115 @@
-use std::time::Instant;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+use std::time::{Duration, Instant};
 
 use crate::{
+    extract::MatchedPath,
     headers::server_timing::{ServerTiming, ServerTimings},
     service::{Service, ServiceFuture},
     Layer,
 };
 
 use futures::TryFutureExt as _;
-use headers::HeaderMapExt as _;
+use headers::Header as _;
 
 /// A [`Layer`] that adds a `Server-Timing` header to the response with the
-/// duration of the request.
-#[derive(Default, Debug, Clone, Copy)]
-#[repr(transparent)]
-pub struct RespTimingLayer<S = ()>(pub S);
+/// duration of the request, and logs the request timing at `debug` level.
+///
+/// If a [slow threshold](RespTimingLayer::slow_threshold) is set, requests taking
+/// longer than it are logged at `warn` level instead, to surface latency outliers.
+///
+/// At high request rates, [sampling](RespTimingLayer::sample_one_in) can be used to only log
+/// a fraction of requests, while slow requests and server errors are always logged. Each record
+/// includes a `sample_rate` field, so that aggregators can extrapolate the true counts.
+///
+/// The route is taken from the [`MatchedPath`] extension of either the request or response,
+/// so it is only available when this is used as a route layer or when the inner
+/// service forwards the [`MatchedPath`] to the response extensions.
+#[derive(Debug, Clone)]
+pub struct RespTimingLayer<S = ()> {
+    inner: S,
+    slow_threshold: Option<Duration>,
+    sample_rate: u32,
+    /// Shared by all services created from the same layer, `None` when not sampling.
+    sampled: Option<Arc<AtomicU64>>,
+}
+
+impl RespTimingLayer {
+    #[inline]
+    #[must_use]
+    pub const fn new() -> Self {
+        RespTimingLayer {
+            inner: (),
+            slow_threshold: None,
+            sample_rate: 1,
+            sampled: None,
+        }
+    }
+
+    /// Log requests taking longer than the given threshold at `warn` level.
+    #[inline]
+    #[must_use]
+    pub const fn slow_threshold(mut self, threshold: Duration) -> Self {
+        self.slow_threshold = Some(threshold);
+        self
+    }
+
+    /// Only log one in every `n` requests, except for slow requests and server errors,
+    /// which are always logged. The `Server-Timing` header is still added to every response.
+    ///
+    /// Requests are counted across every service created from this layer, such as for each route
+    /// it's applied to, regardless of which thread they run on. A value of `0` or `1` logs every request,
+    /// which is the default.
+    #[inline]
+    #[must_use]
+    pub fn sample_one_in(mut self, n: u32) -> Self {
+        self.sample_rate = n.max(1);
+        self.sampled = (self.sample_rate > 1).then(Default::default);
+        self
+    }
+}
+
+impl<S> RespTimingLayer<S> {
+    /// Wraps the given service with the default configuration,
+    /// equivalent to `RespTimingLayer::new().layer(inner)`.
+    #[inline]
+    #[must_use]
+    pub const fn from_inner(inner: S) -> Self {
+        RespTimingLayer {
+            inner,
+            slow_threshold: None,
+            sample_rate: 1,
+            sampled: None,
+        }
+    }
+
+    /// Returns the inner service.
+    #[inline]
+    #[must_use]
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: Default> Default for RespTimingLayer<S> {
+    #[inline]
+    fn default() -> Self {
+        Self::from_inner(S::default())
+    }
+}
 
 impl<S> Layer<S> for RespTimingLayer {
     type Service = RespTimingLayer<S>;
 
     fn layer(&self, inner: S) -> Self::Service {
-        RespTimingLayer(inner)
+        RespTimingLayer {
+            inner,
+            slow_threshold: self.slow_threshold,
+            sample_rate: self.sample_rate,
+            sampled: self.sampled.clone(),
+        }
     }
 }
 
@@ -41,12 +131,174 @@ where
 
         req.extensions_mut().insert(StartTime(start));
 
-        self.0.call(req).map_ok(move |mut resp| {
+        let method = req.method().clone();
+        let path = req.extensions().get::<MatchedPath>().cloned();
+        let slow_threshold = self.slow_threshold;
+        let sample_rate = self.sample_rate;
+        let sampled = self.sampled.clone();
+
+        self.inner.call(req).map_ok(move |mut resp| {
+            let elapsed = start.elapsed();
+
+            // decide the level at response time, based on the measured elapsed time
+            let slow = slow_threshold.is_some_and(|threshold| elapsed > threshold);
+
+            let status = resp.status();
+
+            // slow requests and server errors bypass sampling
+            let sample_rate = if slow || status.is_server_error() { 1 } else { sample_rate };
+
+            // one in every `sample_rate` requests through this layer is logged
+            let sampled = || match sampled {
+                Some(ref count) if sample_rate > 1 => {
+                    count.fetch_add(1, Ordering::Relaxed) % u64::from(sample_rate) == 0
+                }
+                _ => true,
+            };
+
+            if slow || (log::enabled!(log::Level::DEBUG) && sampled()) {
+                let path = path.or_else(|| resp.extensions().get::<MatchedPath>().cloned());
+                let path = path.as_deref().unwrap_or("<unmatched>");
+
+                if slow {
+                    log::warn!(sample_rate, "slow request: {method} {path} -> {status} in {elapsed:?}");
+                } else {
+                    log::debug!(sample_rate, "{method} {path} -> {status} in {elapsed:?}");
+                }
+            }
+
             let timing = ServerTiming::new("resp").elapsed_from(start);
 
-            resp.headers_mut().typed_insert(ServerTimings::new().with(timing));
+            // append rather than insert, to keep any timings added by the handler
+            let mut values = Vec::with_capacity(1);
+            ServerTimings::new().with(timing).encode(&mut values);
+
+            for value in values {
+                resp.headers_mut().append(ServerTimings::name(), value);
+            }
 
             resp
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::{body::Body, Response, Router};
+
+    /// Captures formatted log lines for the duration of the test.
+    fn capture_logs() -> (Arc<Mutex<Vec<u8>>>, log::subscriber::DefaultGuard) {
+        let logs = Arc::new(Mutex::new(Vec::new()));
+
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(log::Level::DEBUG)
+            .with_ansi(false)
+            .with_writer({
+                let logs = logs.clone();
+                move || Writer(logs.clone())
+            })
+            .finish();
+
+        struct Writer(Arc<Mutex<Vec<u8>>>);
+
+        impl std::io::Write for Writer {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(buf);
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        (logs, log::subscriber::set_default(subscriber))
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_slow_threshold() {
+        let (logs, _guard) = capture_logs();
+
+        for threshold in [Duration::from_millis(1), Duration::from_secs(60)] {
+            let mut router = Router::<(), Response>::with_state(());
+            router.get("/users/{id}", || async {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            });
+
+            let service = router.route_layer(RespTimingLayer::new().slow_threshold(threshold));
+
+            let resp = service.call(http::Request::get("/users/1").body(Body::empty()).unwrap()).await.unwrap();
+            assert!(resp.headers().contains_key(ServerTimings::name()));
+        }
+
+        let logs = String::from_utf8(logs.lock().unwrap().clone()).unwrap();
+        let lines: Vec<&str> = logs.lines().collect();
+
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].contains("WARN"));
+        assert!(lines[0].contains("slow request: GET /users/{id} -> 200 OK in"));
+        assert!(lines[1].contains("DEBUG"));
+        assert!(lines[1].contains("GET /users/{id} -> 200 OK in"));
+
+        let wrapped = RespTimingLayer::from_inner("inner");
+        assert_eq!(wrapped.into_inner(), "inner");
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_sampling() {
+        use log::instrument::WithSubscriber as _;
+
+        let (logs, _guard) = capture_logs();
+
+        let dispatch = log::dispatcher::get_default(Clone::clone);
+
+        let layer = RespTimingLayer::new().sample_one_in(4);
+
+        // services from the same layer, such as separate routes, share the count
+        let services = ["/a", "/b"].map(|path| {
+            let mut router = Router::<(), Response>::with_state(());
+            router.get(path, || async {});
+            Arc::new(router.route_layer(layer.clone()))
+        });
+
+        let mut tasks = Vec::new();
+
+        for i in 0..16 {
+            let service = services[i % 2].clone();
+            let path = ["/a", "/b"][i % 2];
+
+            // spread across threads, which doesn't affect the count
+            let call = async move {
+                service.call(http::Request::get(path).body(Body::empty()).unwrap()).await.unwrap();
+            };
+
+            tasks.push(tokio::spawn(call.with_subscriber(dispatch.clone())));
+        }
+
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        let logs = String::from_utf8(logs.lock().unwrap().clone()).unwrap();
+        assert_eq!(logs.lines().count(), 4, "{logs}");
+        assert!(logs.lines().all(|line| line.contains("sample_rate=4")));
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_default_sample_rate() {
+        let (logs, _guard) = capture_logs();
+
+        let mut router = Router::<(), Response>::with_state(());
+        router.get("/", || async {});
+
+        let service = router.route_layer(RespTimingLayer::default());
+        service.call(http::Request::get("/").body(Body::empty()).unwrap()).await.unwrap();
+
+        let logs = String::from_utf8(logs.lock().unwrap().clone()).unwrap();
+        assert_eq!(logs.lines().count(), 1, "{logs}");
+        assert!(logs.contains("sample_rate=1"), "{logs}");
+    }
+}
@@ -0,0 +1,207 @@
+use futures::FutureExt as _;
+use http::{header, uri::Scheme, HeaderMap, HeaderName, HeaderValue};
+
+use crate::{extract::scheme::request_scheme, service::ServiceFuture, Layer, Response, Service};
+
+/// A layer that adds common security headers to responses, unless already set by the inner service.
+///
+/// By default, the following headers are added:
+///
+/// | Header                      | Default value                          |
+/// |-----------------------------|----------------------------------------|
+/// | `Content-Security-Policy`   | `default-src 'self'`                   |
+/// | `X-Content-Type-Options`    | `nosniff`                              |
+/// | `X-Frame-Options`           | `DENY`                                 |
+/// | `Referrer-Policy`           | `strict-origin-when-cross-origin`      |
+/// | `Strict-Transport-Security` | `max-age=31536000; includeSubDomains`  |
+///
+/// Each can be overridden or disabled with the `with_*` methods, by passing `None`.
+///
+/// `Strict-Transport-Security` is only added to responses for HTTPS requests, as determined
+/// the same way as the [`Scheme`] extractor does, so a `Scheme::HTTPS` request extension
+/// or forwarded headers from a trusted proxy are needed for HTTP/1 requests.
+#[derive(Debug, Clone)]
+#[must_use]
+pub struct SecurityHeadersLayer {
+    content_security_policy: Option<HeaderValue>,
+    nosniff: bool,
+    frame_options: Option<HeaderValue>,
+    referrer_policy: Option<HeaderValue>,
+    strict_transport_security: Option<HeaderValue>,
+}
+
+/// The service created by the [`SecurityHeadersLayer`].
+#[derive(Debug, Clone)]
+pub struct SecurityHeaders<S> {
+    inner: S,
+    layer: SecurityHeadersLayer,
+}
+
+impl Default for SecurityHeadersLayer {
+    fn default() -> Self {
+        SecurityHeadersLayer {
+            content_security_policy: Some(HeaderValue::from_static("default-src 'self'")),
+            nosniff: true,
+            frame_options: Some(HeaderValue::from_static("DENY")),
+            referrer_policy: Some(HeaderValue::from_static("strict-origin-when-cross-origin")),
+            strict_transport_security: Some(HeaderValue::from_static("max-age=31536000; includeSubDomains")),
+        }
+    }
+}
+
+impl SecurityHeadersLayer {
+    /// Creates a new layer with the default headers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the `Content-Security-Policy` header, or disables it with `None`.
+    pub fn with_content_security_policy(mut self, value: Option<HeaderValue>) -> Self {
+        self.content_security_policy = value;
+        self
+    }
+
+    /// Sets whether to add `X-Content-Type-Options: nosniff`.
+    pub fn with_nosniff(mut self, nosniff: bool) -> Self {
+        self.nosniff = nosniff;
+        self
+    }
+
+    /// Sets the `X-Frame-Options` header, or disables it with `None`.
+    pub fn with_frame_options(mut self, value: Option<HeaderValue>) -> Self {
+        self.frame_options = value;
+        self
+    }
+
+    /// Sets the `Referrer-Policy` header, or disables it with `None`.
+    pub fn with_referrer_policy(mut self, value: Option<HeaderValue>) -> Self {
+        self.referrer_policy = value;
+        self
+    }
+
+    /// Sets the `Strict-Transport-Security` header for HTTPS requests, or disables it with `None`.
+    pub fn with_strict_transport_security(mut self, value: Option<HeaderValue>) -> Self {
+        self.strict_transport_security = value;
+        self
+    }
+
+    fn apply(&self, headers: &mut HeaderMap, https: bool) {
+        fn set(headers: &mut HeaderMap, name: HeaderName, value: &Option<HeaderValue>) {
+            if let Some(value) = value {
+                headers.entry(name).or_insert_with(|| value.clone());
+            }
+        }
+
+        set(headers, header::CONTENT_SECURITY_POLICY, &self.content_security_policy);
+        set(headers, header::X_FRAME_OPTIONS, &self.frame_options);
+        set(headers, header::REFERRER_POLICY, &self.referrer_policy);
+
+        if self.nosniff {
+            headers
+                .entry(header::X_CONTENT_TYPE_OPTIONS)
+                .or_insert(const { HeaderValue::from_static("nosniff") });
+        }
+
+        if https {
+            set(
+                headers,
+                header::STRICT_TRANSPORT_SECURITY,
+                &self.strict_transport_security,
+            );
+        }
+    }
+}
+
+impl<S> Layer<S> for SecurityHeadersLayer {
+    type Service = SecurityHeaders<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        SecurityHeaders {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+impl<S, B> Service<http::Request<B>> for SecurityHeaders<S>
+where
+    S: Service<http::Request<B>, Response = Response>,
+{
+    type Response = Response;
+    type Error = S::Error;
+
+    #[inline]
+    fn call(&self, req: http::Request<B>) -> impl ServiceFuture<Self::Response, Self::Error> {
+        let https = self.layer.strict_transport_security.is_some()
+            && request_scheme(req.headers(), req.uri(), req.extensions()).is_ok_and(|s| s == Scheme::HTTPS);
+
+        self.inner.call(req).map(move |res| {
+            res.map(|mut resp| {
+                self.layer.apply(resp.headers_mut(), https);
+                resp
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{body::Body, IntoResponse, Router};
+
+    fn router() -> Router<(), Response> {
+        let mut router = Router::<(), Response>::with_state(());
+        router.get("/", || async { "index" });
+        router.get("/embed", || async {
+            "embed".with([(
+                header::CONTENT_SECURITY_POLICY,
+                HeaderValue::from_static("frame-ancestors *"),
+            )])
+        });
+        router
+    }
+
+    #[tokio::test]
+    async fn test_default_security_headers() {
+        let service = SecurityHeadersLayer::default().layer(router());
+
+        let resp = service.call(http::Request::get("/").body(Body::empty()).unwrap()).await.unwrap();
+        let headers = resp.headers();
+
+        assert_eq!(headers[header::CONTENT_SECURITY_POLICY], "default-src 'self'");
+        assert_eq!(headers[header::X_CONTENT_TYPE_OPTIONS], "nosniff");
+        assert_eq!(headers[header::X_FRAME_OPTIONS], "DENY");
+        assert_eq!(headers[header::REFERRER_POLICY], "strict-origin-when-cross-origin");
+        assert!(!headers.contains_key(header::STRICT_TRANSPORT_SECURITY));
+
+        let req = http::Request::get("/").header("x-forwarded-proto", "https").body(Body::empty()).unwrap();
+        let resp = service.call(req).await.unwrap();
+
+        assert_eq!(
+            resp.headers()[header::STRICT_TRANSPORT_SECURITY],
+            "max-age=31536000; includeSubDomains"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_security_header_overrides() {
+        let service = SecurityHeadersLayer::new()
+            .with_frame_options(None)
+            .with_nosniff(false)
+            .with_referrer_policy(Some(HeaderValue::from_static("no-referrer")))
+            .layer(router());
+
+        let mut req = http::Request::get("/embed").body(Body::empty()).unwrap();
+        req.extensions_mut().insert(Scheme::HTTPS);
+
+        let resp = service.call(req).await.unwrap();
+        let headers = resp.headers();
+
+        // set by the handler, so not overridden
+        assert_eq!(headers[header::CONTENT_SECURITY_POLICY], "frame-ancestors *");
+        assert_eq!(headers[header::REFERRER_POLICY], "no-referrer");
+        assert!(!headers.contains_key(header::X_FRAME_OPTIONS));
+        assert!(!headers.contains_key(header::X_CONTENT_TYPE_OPTIONS));
+        assert!(headers.contains_key(header::STRICT_TRANSPORT_SECURITY));
+    }
+}
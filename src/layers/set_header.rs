@@ -0,0 +1,372 @@
+use futures::FutureExt as _;
+use http::{HeaderName, HeaderValue};
+
+use crate::{service::ServiceFuture, Layer, RequestParts, Response, Service};
+
+/// Produces a header value for a response, as used by [`SetResponseHeaderLayer`].
+///
+/// This is implemented for [`HeaderValue`] and `Option<HeaderValue>` for static values,
+/// and for closures `Fn(&Response) -> Option<HeaderValue>` for values computed from the
+/// response. Returning `None` leaves the response untouched.
+pub trait MakeHeaderValue: Send + Sync + 'static {
+    fn make_header_value(&self, resp: &Response) -> Option<HeaderValue>;
+}
+
+impl MakeHeaderValue for HeaderValue {
+    #[inline]
+    fn make_header_value(&self, _resp: &Response) -> Option<HeaderValue> {
+        Some(self.clone())
+    }
+}
+
+impl MakeHeaderValue for Option<HeaderValue> {
+    #[inline]
+    fn make_header_value(&self, _resp: &Response) -> Option<HeaderValue> {
+        self.clone()
+    }
+}
+
+impl<F> MakeHeaderValue for F
+where
+    F: Fn(&Response) -> Option<HeaderValue> + Send + Sync + 'static,
+{
+    #[inline]
+    fn make_header_value(&self, resp: &Response) -> Option<HeaderValue> {
+        self(resp)
+    }
+}
+
+/// Produces a header value for a request, as used by [`SetRequestHeaderLayer`].
+///
+/// This is implemented for [`HeaderValue`] and `Option<HeaderValue>` for static values,
+/// and for closures `Fn(&RequestParts) -> Option<HeaderValue>` for values computed from the
+/// request. Returning `None` leaves the request untouched.
+pub trait MakeRequestHeaderValue: Send + Sync + 'static {
+    fn make_header_value(&self, parts: &RequestParts) -> Option<HeaderValue>;
+}
+
+impl MakeRequestHeaderValue for HeaderValue {
+    #[inline]
+    fn make_header_value(&self, _parts: &RequestParts) -> Option<HeaderValue> {
+        Some(self.clone())
+    }
+}
+
+impl MakeRequestHeaderValue for Option<HeaderValue> {
+    #[inline]
+    fn make_header_value(&self, _parts: &RequestParts) -> Option<HeaderValue> {
+        self.clone()
+    }
+}
+
+impl<F> MakeRequestHeaderValue for F
+where
+    F: Fn(&RequestParts) -> Option<HeaderValue> + Send + Sync + 'static,
+{
+    #[inline]
+    fn make_header_value(&self, parts: &RequestParts) -> Option<HeaderValue> {
+        self(parts)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Override,
+    Append,
+    IfNotPresent,
+}
+
+/// A layer that sets a response header, with either a static value or one computed from the response.
+///
+/// ```rust,ignore
+/// use http::{header, HeaderValue};
+///
+/// let layer = (
+///     SetResponseHeaderLayer::if_not_present(header::CACHE_CONTROL, HeaderValue::from_static("no-store")),
+///     SetResponseHeaderLayer::overriding(header::CONTENT_LENGTH, |resp: &Response| {
+///         resp.body().size_hint().exact().map(HeaderValue::from)
+///     }),
+/// );
+/// ```
+#[derive(Debug, Clone)]
+#[must_use]
+pub struct SetResponseHeaderLayer<M> {
+    name: HeaderName,
+    make: M,
+    mode: Mode,
+}
+
+/// The service created by the [`SetResponseHeaderLayer`].
+#[derive(Debug, Clone)]
+pub struct SetResponseHeader<S, M> {
+    inner: S,
+    layer: SetResponseHeaderLayer<M>,
+}
+
+impl<M: MakeHeaderValue> SetResponseHeaderLayer<M> {
+    /// Sets the header, replacing any values already present.
+    pub fn overriding(name: HeaderName, make: M) -> Self {
+        SetResponseHeaderLayer {
+            name,
+            make,
+            mode: Mode::Override,
+        }
+    }
+
+    /// Appends the header, keeping any values already present.
+    pub fn appending(name: HeaderName, make: M) -> Self {
+        SetResponseHeaderLayer {
+            name,
+            make,
+            mode: Mode::Append,
+        }
+    }
+
+    /// Sets the header only if not already present, in which case the value is not computed at all.
+    pub fn if_not_present(name: HeaderName, make: M) -> Self {
+        SetResponseHeaderLayer {
+            name,
+            make,
+            mode: Mode::IfNotPresent,
+        }
+    }
+
+    fn apply(&self, resp: &mut Response) {
+        if self.mode == Mode::IfNotPresent && resp.headers().contains_key(&self.name) {
+            return;
+        }
+
+        let Some(value) = self.make.make_header_value(resp) else {
+            return;
+        };
+
+        let headers = resp.headers_mut();
+
+        match self.mode {
+            Mode::Append => _ = headers.append(self.name.clone(), value),
+            Mode::Override | Mode::IfNotPresent => _ = headers.insert(self.name.clone(), value),
+        }
+    }
+}
+
+impl<S, M: Clone> Layer<S> for SetResponseHeaderLayer<M> {
+    type Service = SetResponseHeader<S, M>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        SetResponseHeader {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+impl<S, M, B> Service<http::Request<B>> for SetResponseHeader<S, M>
+where
+    S: Service<http::Request<B>, Response = Response>,
+    M: MakeHeaderValue,
+{
+    type Response = Response;
+    type Error = S::Error;
+
+    #[inline]
+    fn call(&self, req: http::Request<B>) -> impl ServiceFuture<Self::Response, Self::Error> {
+        self.inner.call(req).map(move |res| {
+            res.map(|mut resp| {
+                self.layer.apply(&mut resp);
+                resp
+            })
+        })
+    }
+}
+
+/// A layer that sets a request header before it reaches the inner service, with either
+/// a static value or one computed from the request, such as to inject defaults for handlers.
+///
+/// ```rust,ignore
+/// let layer = SetRequestHeaderLayer::if_not_present(header::ACCEPT, HeaderValue::from_static("application/json"));
+/// ```
+#[derive(Debug, Clone)]
+#[must_use]
+pub struct SetRequestHeaderLayer<M> {
+    name: HeaderName,
+    make: M,
+    mode: Mode,
+}
+
+/// The service created by the [`SetRequestHeaderLayer`].
+#[derive(Debug, Clone)]
+pub struct SetRequestHeader<S, M> {
+    inner: S,
+    layer: SetRequestHeaderLayer<M>,
+}
+
+impl<M: MakeRequestHeaderValue> SetRequestHeaderLayer<M> {
+    /// Sets the header, replacing any values already present.
+    pub fn overriding(name: HeaderName, make: M) -> Self {
+        SetRequestHeaderLayer {
+            name,
+            make,
+            mode: Mode::Override,
+        }
+    }
+
+    /// Appends the header, keeping any values already present.
+    pub fn appending(name: HeaderName, make: M) -> Self {
+        SetRequestHeaderLayer {
+            name,
+            make,
+            mode: Mode::Append,
+        }
+    }
+
+    /// Sets the header only if not already present, in which case the value is not computed at all.
+    pub fn if_not_present(name: HeaderName, make: M) -> Self {
+        SetRequestHeaderLayer {
+            name,
+            make,
+            mode: Mode::IfNotPresent,
+        }
+    }
+
+    fn apply(&self, parts: &mut RequestParts) {
+        if self.mode == Mode::IfNotPresent && parts.headers.contains_key(&self.name) {
+            return;
+        }
+
+        let Some(value) = self.make.make_header_value(parts) else {
+            return;
+        };
+
+        match self.mode {
+            Mode::Append => _ = parts.headers.append(self.name.clone(), value),
+            Mode::Override | Mode::IfNotPresent => _ = parts.headers.insert(self.name.clone(), value),
+        }
+    }
+}
+
+impl<S, M: Clone> Layer<S> for SetRequestHeaderLayer<M> {
+    type Service = SetRequestHeader<S, M>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        SetRequestHeader {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+impl<S, M, B> Service<http::Request<B>> for SetRequestHeader<S, M>
+where
+    S: Service<http::Request<B>>,
+    M: MakeRequestHeaderValue,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+
+    #[inline]
+    fn call(&self, req: http::Request<B>) -> impl ServiceFuture<Self::Response, Self::Error> {
+        let (mut parts, body) = req.into_parts();
+
+        self.layer.apply(&mut parts);
+
+        self.inner.call(http::Request::from_parts(parts, body))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::header;
+
+    use super::*;
+    use crate::{body::Body, IntoResponse, Router};
+
+    fn router() -> Router<(), Response> {
+        let mut router = Router::<(), Response>::with_state(());
+        router.get("/", || async { "index" });
+        router.get("/gone", || async { http::StatusCode::GONE });
+        router.get("/cached", || async {
+            "cached".with([(header::CACHE_CONTROL, HeaderValue::from_static("max-age=60"))])
+        });
+        router
+    }
+
+    async fn call<S>(service: &S, path: &str) -> Response
+    where
+        S: Service<crate::Request, Response = Response, Error = crate::Error>,
+    {
+        service.call(http::Request::get(path).body(Body::empty()).unwrap()).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_if_not_present() {
+        let service =
+            SetResponseHeaderLayer::if_not_present(header::CACHE_CONTROL, HeaderValue::from_static("no-store"))
+                .layer(router());
+
+        assert_eq!(call(&service, "/").await.headers()[header::CACHE_CONTROL], "no-store");
+        assert_eq!(
+            call(&service, "/cached").await.headers()[header::CACHE_CONTROL],
+            "max-age=60"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_overriding() {
+        let service = SetResponseHeaderLayer::overriding(header::CACHE_CONTROL, |resp: &Response| {
+            resp.status().is_success().then(|| HeaderValue::from_static("private"))
+        })
+        .layer(router());
+
+        let resp = call(&service, "/cached").await;
+        let values = resp.headers().get_all(header::CACHE_CONTROL).iter().collect::<Vec<_>>();
+        assert_eq!(values, ["private"]);
+
+        // computed from the response, so a `None` leaves it untouched
+        let resp = call(&service, "/gone").await;
+        assert!(!resp.headers().contains_key(header::CACHE_CONTROL));
+    }
+
+    #[tokio::test]
+    async fn test_appending() {
+        use hyper::body::Body as _;
+
+        let service = (
+            SetResponseHeaderLayer::appending(header::CACHE_CONTROL, HeaderValue::from_static("no-transform")),
+            SetResponseHeaderLayer::overriding(header::CONTENT_LENGTH, |resp: &Response| {
+                resp.body().size_hint().exact().map(HeaderValue::from)
+            }),
+        )
+            .layer(router());
+
+        let resp = call(&service, "/cached").await;
+        let values = resp.headers().get_all(header::CACHE_CONTROL).iter().collect::<Vec<_>>();
+        assert_eq!(values, ["max-age=60", "no-transform"]);
+        assert_eq!(resp.headers()[header::CONTENT_LENGTH], "6");
+    }
+
+    #[tokio::test]
+    async fn test_request_headers() {
+        let mut router = Router::<(), Response>::with_state(());
+        router.get("/", |headers: http::HeaderMap| async move {
+            let values = headers.get_all(header::ACCEPT).iter().map(|v| v.to_str().unwrap()).collect::<Vec<_>>();
+            format!("{} {}", values.join(","), headers[header::FROM].to_str().unwrap())
+        });
+
+        let service = (
+            SetRequestHeaderLayer::if_not_present(header::ACCEPT, HeaderValue::from_static("text/plain")),
+            SetRequestHeaderLayer::appending(header::ACCEPT, HeaderValue::from_static("*/*")),
+            SetRequestHeaderLayer::overriding(header::FROM, |parts: &RequestParts| {
+                HeaderValue::from_str(parts.uri.path()).ok()
+            }),
+        )
+            .layer(router);
+
+        let req = http::Request::get("/").header(header::FROM, "someone").body(Body::empty()).unwrap();
+        let resp = service.call(req).await.unwrap();
+        assert_eq!(resp.into_body().to_string().await.unwrap(), "text/plain,*/* /");
+
+        let req = http::Request::get("/").header(header::ACCEPT, "text/html").body(Body::empty()).unwrap();
+        let resp = service.call(req).await.unwrap();
+        assert_eq!(resp.into_body().to_string().await.unwrap(), "text/html,*/* /");
+    }
+}
@@ -0,0 +1,192 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use crate::{extract::MatchedPath, service::ServiceFuture, Error, IntoResponse, Layer, Response, Service};
+
+/// A layer that fails requests with [`TimeoutError::TimedOut`] (`408 Request Timeout`)
+/// if the inner service does not respond within the configured duration.
+///
+/// Once elapsed, the inner future is dropped immediately, releasing anything it held.
+/// Errors of the inner service are kept as [`TimeoutError::Inner`].
+///
+/// Per-route durations are looked up by [`MatchedPath`], which is only known once the router
+/// has matched, so the layer must be applied with [`Router::route_layer`](crate::Router::route_layer)
+/// for them to take effect. Since route services cannot fail, combine it with a
+/// [`HandleErrorLayer`](super::handle_error::HandleErrorLayer) there:
+///
+/// ```rust,ignore
+/// let timeout = TimeoutLayer::new(Duration::from_secs(10)).with_route("/upload", Duration::from_secs(120));
+///
+/// let router = router.route_layer((HandleErrorLayer::new(|e: TimeoutError<Infallible>| async move { e }), timeout));
+/// ```
+#[derive(Debug, Clone)]
+#[must_use]
+pub struct TimeoutLayer {
+    duration: Duration,
+    routes: Arc<HashMap<Box<str>, Duration>>,
+}
+
+/// The service created by the [`TimeoutLayer`].
+#[derive(Debug, Clone)]
+pub struct Timeout<S> {
+    inner: S,
+    layer: TimeoutLayer,
+}
+
+impl TimeoutLayer {
+    /// Creates a new layer with the given timeout for all routes.
+    pub fn new(duration: Duration) -> Self {
+        TimeoutLayer {
+            duration,
+            routes: Arc::default(),
+        }
+    }
+
+    /// Overrides the timeout for the route with the given path, as registered with the router,
+    /// such as `/users/{id}`.
+    pub fn with_route(mut self, path: impl Into<Box<str>>, duration: Duration) -> Self {
+        Arc::make_mut(&mut self.routes).insert(path.into(), duration);
+        self
+    }
+
+    /// Overrides the timeouts for multiple routes, see [`with_route`](Self::with_route).
+    pub fn with_routes(mut self, routes: impl IntoIterator<Item = (impl Into<Box<str>>, Duration)>) -> Self {
+        Arc::make_mut(&mut self.routes).extend(routes.into_iter().map(|(path, duration)| (path.into(), duration)));
+        self
+    }
+
+    fn duration(&self, path: Option<&MatchedPath>) -> Duration {
+        match path {
+            Some(path) => self.routes.get(&**path).copied().unwrap_or(self.duration),
+            None => self.duration,
+        }
+    }
+}
+
+impl<S> Layer<S> for TimeoutLayer {
+    type Service = Timeout<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Timeout {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TimeoutError<E> {
+    #[error(transparent)]
+    Inner(E),
+
+    #[error("Request timed out")]
+    TimedOut,
+}
+
+impl<E: IntoResponse> IntoResponse for TimeoutError<E> {
+    fn into_response(self) -> Response {
+        match self {
+            TimeoutError::Inner(e) => e.into_response(),
+            TimeoutError::TimedOut => Error::TimedOut.into_response(),
+        }
+    }
+}
+
+impl<E: Into<Error>> From<TimeoutError<E>> for Error {
+    fn from(err: TimeoutError<E>) -> Self {
+        match err {
+            TimeoutError::Inner(e) => e.into(),
+            TimeoutError::TimedOut => Error::TimedOut,
+        }
+    }
+}
+
+impl<S, B> Service<http::Request<B>> for Timeout<S>
+where
+    S: Service<http::Request<B>>,
+{
+    type Response = S::Response;
+    type Error = TimeoutError<S::Error>;
+
+    fn call(&self, req: http::Request<B>) -> impl ServiceFuture<Self::Response, Self::Error> {
+        let duration = self.layer.duration(req.extensions().get::<MatchedPath>());
+        let inner = self.inner.call(req);
+
+        async move {
+            // the inner future is owned by the `tokio::time::Timeout`, which is dropped as soon as it completes
+            match tokio::time::timeout(duration, inner).await {
+                Ok(res) => res.map_err(TimeoutError::Inner),
+                Err(_) => Err(TimeoutError::TimedOut),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use http::StatusCode;
+
+    use super::*;
+    use crate::{body::Body, layers::handle_error::HandleErrorLayer, IntoResponse, Response, Router};
+
+    #[tokio::test]
+    async fn test_timeout() {
+        let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+        let tx = Arc::new(std::sync::Mutex::new(Some(tx)));
+
+        let mut router = Router::<(), Response>::with_state(());
+        router.get("/fast", || async { "fast" });
+        router.get("/slow", move || {
+            let tx = tx.lock().unwrap().take();
+
+            async move {
+                let _tx = tx; // dropped along with the future
+                tokio::time::sleep(Duration::from_secs(60)).await;
+                "slow"
+            }
+        });
+        router.get("/slow-ok", || async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            "slow-ok"
+        });
+
+        let timeout = TimeoutLayer::new(Duration::from_millis(10)).with_route("/slow-ok", Duration::from_secs(10));
+
+        let router = router.route_layer((
+            HandleErrorLayer::new(|e: TimeoutError<Infallible>| async move { e }),
+            timeout,
+        ));
+
+        async fn call(
+            router: &impl Service<crate::Request, Response = Response, Error = Error>,
+            path: &str,
+        ) -> Response {
+            let req = http::Request::get(path).body(Body::empty()).unwrap();
+            router.call(req).await.unwrap_or_else(IntoResponse::into_response)
+        }
+
+        assert_eq!(
+            call(&router, "/fast").await.into_body().to_string().await.unwrap(),
+            "fast"
+        );
+        assert_eq!(call(&router, "/slow").await.status(), StatusCode::REQUEST_TIMEOUT);
+
+        // the timed out handler has already been dropped
+        assert!(rx.await.is_err());
+
+        assert_eq!(
+            call(&router, "/slow-ok").await.into_body().to_string().await.unwrap(),
+            "slow-ok"
+        );
+
+        // without a matched path, the default applies, and inner errors are kept
+        let service = TimeoutLayer::new(Duration::from_secs(1)).layer(Router::<(), Response>::with_state(()));
+
+        let req = http::Request::get("/missing").body(Body::empty()).unwrap();
+        assert!(matches!(
+            service.call(req).await,
+            Err(TimeoutError::Inner(Error::NotFound))
+        ));
+    }
+}
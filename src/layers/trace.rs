@@ -0,0 +1,379 @@
+use std::time::Instant;
+
+use futures::FutureExt as _;
+use log::{field::Empty, Instrument as _, Level, Span};
+
+use crate::{
+    error::ErrorResponse,
+    extract::{
+        real_ip::{get_ip_from_headers, RealIp},
+        MatchedPath,
+    },
+    service::{Service, ServiceFuture},
+    Error, IntoResponse, Layer,
+};
+
+/// A [`Layer`] that opens a `tracing` span per request, and logs each completed request
+/// with its status code and latency.
+///
+/// The `request` span is entered whenever the inner service's future is polled, so any
+/// logs from handlers or inner layers are correlated with the request. It records the
+/// following fields, each of which can be disabled with the builder methods:
+///
+/// | Field      | Value                                                    |
+/// |------------|----------------------------------------------------------|
+/// | `method`   | The request method                                       |
+/// | `path`     | The [`MatchedPath`] of the route, see below              |
+/// | `version`  | The HTTP version                                         |
+/// | `ip`       | The [`RealIp`](crate::extract::real_ip::RealIp) of the client |
+/// | `status`   | The response status code, once completed                 |
+/// | `latency`  | The time taken to produce the response, once completed   |
+///
+/// Completed requests are logged at `debug` level, client errors at `warn` and server errors
+/// at `error`, which can be changed or disabled with [`success_level`](Self::success_level),
+/// [`client_error_level`](Self::client_error_level) and [`server_error_level`](Self::server_error_level).
+///
+/// Errors of the inner service are logged by the status of their response, so a [`Router`](crate::Router)'s
+/// `404` and `405` errors are logged as client errors. For this, they are converted into their
+/// response up-front and returned as [`Error::Response`], which produces the same response.
+///
+/// Like with [`RespTimingLayer`](super::resp_timing::RespTimingLayer), the matched path is taken from
+/// the [`MatchedPath`] extension of either the request or response, so it is only available when
+/// used as a route layer or when the inner service forwards it to the response extensions.
+#[derive(Debug, Clone, Copy)]
+#[must_use]
+pub struct TraceLayer {
+    span_level: Level,
+    success_level: Option<Level>,
+    client_error_level: Option<Level>,
+    server_error_level: Option<Level>,
+    method: bool,
+    path: bool,
+    version: bool,
+    ip: bool,
+}
+
+/// The service created by the [`TraceLayer`].
+#[derive(Debug, Clone, Copy)]
+pub struct Trace<S> {
+    inner: S,
+    layer: TraceLayer,
+}
+
+impl Default for TraceLayer {
+    fn default() -> Self {
+        TraceLayer {
+            span_level: Level::INFO,
+            success_level: Some(Level::DEBUG),
+            client_error_level: Some(Level::WARN),
+            server_error_level: Some(Level::ERROR),
+            method: true,
+            path: true,
+            version: true,
+            ip: true,
+        }
+    }
+}
+
+impl TraceLayer {
+    /// Creates a new layer with the default levels, recording all fields.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the level of the request span, `info` by default.
+    pub fn span_level(mut self, level: Level) -> Self {
+        self.span_level = level;
+        self
+    }
+
+    /// Sets the level at which successful (non-error) requests are logged, or `None` to not log them.
+    pub fn success_level(mut self, level: impl Into<Option<Level>>) -> Self {
+        self.success_level = level.into();
+        self
+    }
+
+    /// Sets the level at which `4xx` responses are logged, or `None` to not log them.
+    pub fn client_error_level(mut self, level: impl Into<Option<Level>>) -> Self {
+        self.client_error_level = level.into();
+        self
+    }
+
+    /// Sets the level at which `5xx` responses are logged, or `None` to not log them.
+    pub fn server_error_level(mut self, level: impl Into<Option<Level>>) -> Self {
+        self.server_error_level = level.into();
+        self
+    }
+
+    /// Sets whether to record the request method.
+    pub fn with_method(mut self, enable: bool) -> Self {
+        self.method = enable;
+        self
+    }
+
+    /// Sets whether to record the matched path.
+    pub fn with_path(mut self, enable: bool) -> Self {
+        self.path = enable;
+        self
+    }
+
+    /// Sets whether to record the HTTP version.
+    pub fn with_version(mut self, enable: bool) -> Self {
+        self.version = enable;
+        self
+    }
+
+    /// Sets whether to record the client IP address.
+    pub fn with_ip(mut self, enable: bool) -> Self {
+        self.ip = enable;
+        self
+    }
+
+    fn span<B>(&self, req: &http::Request<B>) -> Span {
+        // span levels must be known statically
+        macro_rules! span {
+            ($($level:ident),*) => {
+                match self.span_level {
+                    $(Level::$level => log::span!(
+                        Level::$level,
+                        "request",
+                        method = Empty,
+                        path = Empty,
+                        version = Empty,
+                        ip = Empty,
+                        status = Empty,
+                        latency = Empty,
+                    ),)*
+                }
+            };
+        }
+
+        let span = span!(TRACE, DEBUG, INFO, WARN, ERROR);
+
+        if span.is_disabled() {
+            return span;
+        }
+
+        if self.method {
+            span.record("method", req.method().as_str());
+        }
+
+        if self.path {
+            if let Some(path) = req.extensions().get::<MatchedPath>() {
+                span.record("path", &**path);
+            }
+        }
+
+        if self.version {
+            span.record("version", log::field::debug(req.version()));
+        }
+
+        if self.ip {
+            let ip = match req.extensions().get::<RealIp>() {
+                Some(ip) => Some(*ip),
+                None => get_ip_from_headers(req.headers(), req.extensions()),
+            };
+
+            if let Some(ip) = ip {
+                span.record("ip", log::field::display(ip));
+            }
+        }
+
+        span
+    }
+}
+
+/// Logs an event at a level only known at runtime.
+macro_rules! event_at {
+    ($level:expr, $($arg:tt)+) => {
+        match $level {
+            Level::TRACE => log::trace!($($arg)+),
+            Level::DEBUG => log::debug!($($arg)+),
+            Level::INFO => log::info!($($arg)+),
+            Level::WARN => log::warn!($($arg)+),
+            Level::ERROR => log::error!($($arg)+),
+        }
+    };
+}
+
+impl<S> Layer<S> for TraceLayer {
+    type Service = Trace<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Trace { inner, layer: *self }
+    }
+}
+
+impl<ReqBody, ResBody, S> Service<http::Request<ReqBody>> for Trace<S>
+where
+    S: Service<http::Request<ReqBody>, Response = http::Response<ResBody>, Error: IntoResponse>,
+{
+    type Response = S::Response;
+    type Error = Error;
+
+    #[inline]
+    fn call(&self, req: http::Request<ReqBody>) -> impl ServiceFuture<Self::Response, Self::Error> {
+        let layer = self.layer;
+        let span = layer.span(&req);
+        let start = Instant::now();
+
+        // create the inner future within the span too, in case the service does any work up-front
+        let inner = span.in_scope(|| self.inner.call(req));
+
+        let record = span.clone();
+
+        inner
+            .map(move |res| {
+                let latency = start.elapsed();
+
+                record.record("latency", log::field::debug(latency));
+
+                let (res, status, message) = match res {
+                    Ok(resp) => {
+                        if layer.path {
+                            if let Some(path) = resp.extensions().get::<MatchedPath>() {
+                                record.record("path", &**path);
+                            }
+                        }
+
+                        let status = resp.status();
+
+                        (Ok(resp), status, "finished processing request")
+                    }
+                    Err(e) => {
+                        let e = ErrorResponse::new(e);
+                        let status = e.status();
+
+                        (Err(Error::Response(e)), status, "request failed with a service error")
+                    }
+                };
+
+                record.record("status", status.as_u16());
+
+                let level = match status {
+                    _ if status.is_server_error() => layer.server_error_level,
+                    _ if status.is_client_error() => layer.client_error_level,
+                    _ => layer.success_level,
+                };
+
+                if let Some(level) = level {
+                    event_at!(level, status = status.as_u16(), ?latency, "{message}");
+                }
+
+                res
+            })
+            .instrument(span)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::{body::Body, Response, Router};
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_trace_span() {
+        let _guard = log::subscriber::set_default(tracing_subscriber::registry());
+
+        let mut router = Router::<(), Response>::with_state(());
+        router.get("/", || async {
+            // the request span is current within the handler
+            tokio::task::yield_now().await;
+            Span::current().metadata().map(|m| m.name()).unwrap_or_default().into_response()
+        });
+
+        let service = TraceLayer::new().success_level(None).layer(router);
+
+        let resp = service.call(http::Request::get("/").body(Body::empty()).unwrap()).await.unwrap();
+
+        assert_eq!(resp.into_body().to_string().await.unwrap(), "request");
+    }
+
+    /// Collects the level and `status` field of each event, and the `status` recorded on spans.
+    type Events = Vec<(Level, Option<u64>)>;
+
+    #[derive(Clone, Default)]
+    struct Capture {
+        events: Arc<Mutex<Events>>,
+        spans: Arc<Mutex<Vec<u64>>>,
+    }
+
+    struct StatusVisitor(Option<u64>);
+
+    impl log::field::Visit for StatusVisitor {
+        fn record_u64(&mut self, field: &log::field::Field, value: u64) {
+            if field.name() == "status" {
+                self.0 = Some(value);
+            }
+        }
+
+        fn record_debug(&mut self, _: &log::field::Field, _: &dyn std::fmt::Debug) {}
+    }
+
+    impl<S: log::Subscriber> tracing_subscriber::Layer<S> for Capture {
+        fn on_event(&self, event: &log::Event<'_>, _: tracing_subscriber::layer::Context<'_, S>) {
+            let mut visitor = StatusVisitor(None);
+            event.record(&mut visitor);
+            self.events.lock().unwrap().push((*event.metadata().level(), visitor.0));
+        }
+
+        fn on_record(
+            &self,
+            _: &log::span::Id,
+            values: &log::span::Record<'_>,
+            _: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            let mut visitor = StatusVisitor(None);
+            values.record(&mut visitor);
+            self.spans.lock().unwrap().extend(visitor.0);
+        }
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_trace_levels() {
+        use tracing_subscriber::layer::SubscriberExt as _;
+
+        let capture = Capture::default();
+        let _guard = log::subscriber::set_default(tracing_subscriber::registry().with(capture.clone()));
+
+        let mut router = Router::<(), Response>::with_state(());
+        router.get("/", || async { "index" });
+        router.get("/fail", || async { Err::<(), _>(Error::Custom("oops".into())) });
+
+        let service = TraceLayer::new().layer(router.method_not_allowed(true));
+
+        let call = |method: http::Method, path: &'static str| {
+            let req = http::Request::builder().method(method).uri(path).body(Body::empty()).unwrap();
+            service.call(req)
+        };
+
+        assert!(call(http::Method::GET, "/").await.is_ok());
+
+        // router errors are client errors, and still produce the same response
+        let err = call(http::Method::GET, "/missing").await.unwrap_err();
+        assert_eq!(err.into_response().status(), http::StatusCode::NOT_FOUND);
+
+        let err = call(http::Method::POST, "/").await.unwrap_err();
+        assert_eq!(err.into_response().status(), http::StatusCode::METHOD_NOT_ALLOWED);
+
+        let resp = call(http::Method::GET, "/fail").await.unwrap();
+        assert_eq!(resp.status(), http::StatusCode::INTERNAL_SERVER_ERROR);
+
+        let events = capture.events.lock().unwrap().clone();
+        let finished = events.iter().filter(|(_, status)| status.is_some()).copied().collect::<Vec<_>>();
+
+        assert_eq!(
+            finished,
+            [
+                (Level::DEBUG, Some(200)),
+                (Level::WARN, Some(404)),
+                (Level::WARN, Some(405)),
+                (Level::ERROR, Some(500)),
+            ]
+        );
+
+        assert_eq!(*capture.spans.lock().unwrap(), [200, 404, 405, 500]);
+    }
+}
@@ -8,6 +8,8 @@ use std::{borrow::Cow, convert::Infallible};
 pub use crate::{body::Body, Response, ResponseParts};
 use crate::{extract::Extension, headers::Header};
 
+pub mod sse;
+
 pub trait IntoResponseParts {
     fn into_response_parts(self, parts: &mut ResponseParts);
 
@@ -40,6 +42,62 @@ pub trait IntoResponseParts {
     }
 }
 
+/// Conversion into a [`Response`].
+///
+/// # Tuples
+///
+/// Tuples compose a response from a single [`IntoResponse`] value, which provides the body,
+/// followed by any number of [`IntoResponseParts`], such as a status code, headers or extensions.
+/// The body must always come first, and parts are applied in order after it.
+///
+/// ```rust
+/// use ftl::{body::Body, headers::Header, IntoResponse};
+/// use headers::ContentType;
+/// use http::{header, HeaderValue, StatusCode};
+///
+/// let resp = (
+///     Body::from("{}".to_owned()),
+///     StatusCode::CREATED,
+///     Header(ContentType::json()),
+///     [(header::CACHE_CONTROL, HeaderValue::from_static("no-store"))],
+/// )
+///     .into_response();
+///
+/// assert_eq!(resp.status(), StatusCode::CREATED);
+/// assert_eq!(resp.headers()[header::CONTENT_TYPE], "application/json");
+/// assert_eq!(resp.headers()[header::CACHE_CONTROL], "no-store");
+/// ```
+///
+/// The same can be written with the builder-like [`with`](IntoResponse::with),
+/// [`with_status`](IntoResponse::with_status) and [`with_header`](IntoResponse::with_header)
+/// methods, which produce those tuples:
+///
+/// ```rust
+/// use ftl::{body::Body, IntoResponse};
+/// use headers::ContentType;
+/// use http::StatusCode;
+///
+/// let resp = Body::from("{}".to_owned()).with_status(StatusCode::CREATED).with_header(ContentType::json()).into_response();
+///
+/// assert_eq!(resp.status(), StatusCode::CREATED);
+/// ```
+///
+/// Parts are applied after the body is converted, so they take precedence over what the body sets,
+/// except that a [`StatusCode`] part only applies if the status is still `200 OK`.
+///
+/// As an exception to the body-first rule, `(StatusCode, Body)` and `(ResponseParts, Body)`
+/// are also accepted, for when the body is built separately from the rest of the response:
+///
+/// ```rust
+/// use ftl::{body::Body, IntoResponse, Response};
+/// use http::StatusCode;
+///
+/// let (mut parts, _) = Response::default().into_parts();
+/// parts.status = StatusCode::ACCEPTED;
+///
+/// assert_eq!((parts, Body::from("queued".to_owned())).into_response().status(), StatusCode::ACCEPTED);
+/// assert_eq!((StatusCode::GONE, Body::empty()).into_response().status(), StatusCode::GONE);
+/// ```
 pub trait IntoResponse {
     #[must_use]
     fn into_response(self) -> Response;
@@ -237,6 +295,22 @@ where
     }
 }
 
+impl IntoResponse for (StatusCode, Body) {
+    #[inline]
+    fn into_response(self) -> Response {
+        let mut resp = Response::new(self.1);
+        *resp.status_mut() = self.0;
+        resp
+    }
+}
+
+impl IntoResponse for (ResponseParts, Body) {
+    #[inline]
+    fn into_response(self) -> Response {
+        Response::from_parts(self.0, self.1)
+    }
+}
+
 impl IntoResponse for Bytes {
     #[inline]
     fn into_response(self) -> Response {
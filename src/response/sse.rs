@@ -0,0 +1,287 @@
+//! [Server-Sent Events](https://html.spec.whatwg.org/multipage/server-sent-events.html) responses.
+//!
+//! ```rust,ignore
+//! async fn events() -> impl IntoResponse {
+//!     let stream = futures::stream::iter(1..=3).map(|n| Ok::<_, Infallible>(Event::default().data(n.to_string())));
+//!
+//!     Sse::new(stream).keep_alive(Duration::from_secs(15))
+//! }
+//! ```
+
+use std::{
+    error::Error as StdError,
+    fmt::Write as _,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use bytes::Bytes;
+use futures::Stream;
+use http::{header, HeaderValue};
+use http_body::Frame;
+use tokio::time::{Instant, Sleep};
+
+use crate::{
+    body::{Body, BodyError},
+    layers::NoCompression,
+    IntoResponse, Response,
+};
+
+/// A single Server-Sent Event, built from its fields.
+///
+/// # Panics
+///
+/// The `event` and `id` fields, and comments, cannot contain line breaks, and will panic if they do.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[must_use]
+pub struct Event {
+    comment: Option<String>,
+    event: Option<String>,
+    id: Option<String>,
+    retry: Option<Duration>,
+    data: Option<String>,
+}
+
+fn assert_single_line(field: &str, value: &str) {
+    assert!(
+        !value.contains(['\r', '\n']),
+        "SSE `{field}` cannot contain line breaks"
+    );
+}
+
+impl Event {
+    /// Sets the data of the event, which is sent as one `data:` line per line of `data`.
+    pub fn data(mut self, data: impl Into<String>) -> Self {
+        self.data = Some(data.into());
+        self
+    }
+
+    /// Sets the data of the event to `data` serialized as JSON.
+    #[cfg(feature = "json")]
+    pub fn json_data<T: serde::Serialize>(self, data: &T) -> Result<Self, json_impl::Error> {
+        Ok(self.data(json_impl::to_string(data)?))
+    }
+
+    /// Sets the event type, dispatched to listeners of that type rather than `message`.
+    pub fn event(mut self, event: impl Into<String>) -> Self {
+        let event = event.into();
+        assert_single_line("event", &event);
+        self.event = Some(event);
+        self
+    }
+
+    /// Sets the event ID, which clients send back in `Last-Event-ID` when reconnecting.
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        let id = id.into();
+        assert_single_line("id", &id);
+        assert!(!id.contains('\0'), "SSE `id` cannot contain null characters");
+        self.id = Some(id);
+        self
+    }
+
+    /// Sets how long clients should wait before reconnecting if the connection is lost.
+    pub fn retry(mut self, retry: Duration) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    /// Sets a comment, which is ignored by clients.
+    pub fn comment(mut self, comment: impl Into<String>) -> Self {
+        let comment = comment.into();
+        assert_single_line("comment", &comment);
+        self.comment = Some(comment);
+        self
+    }
+
+    /// Encodes the event in the `text/event-stream` format, ending with a blank line.
+    fn encode(&self) -> Bytes {
+        let mut buf = String::new();
+
+        if let Some(ref comment) = self.comment {
+            _ = writeln!(buf, ":{comment}");
+        }
+
+        if let Some(ref event) = self.event {
+            _ = writeln!(buf, "event: {event}");
+        }
+
+        if let Some(ref id) = self.id {
+            _ = writeln!(buf, "id: {id}");
+        }
+
+        if let Some(retry) = self.retry {
+            _ = writeln!(buf, "retry: {}", retry.as_millis());
+        }
+
+        if let Some(ref data) = self.data {
+            // any of CRLF, LF or CR end a line
+            for line in data.split('\n') {
+                for line in line.strip_suffix('\r').unwrap_or(line).split('\r') {
+                    _ = writeln!(buf, "data: {line}");
+                }
+            }
+        }
+
+        buf.push('\n');
+
+        Bytes::from(buf)
+    }
+}
+
+/// Server-Sent Events response, sending each [`Event`] of the stream as it is produced.
+///
+/// The response is sent with `Content-Type: text/event-stream` and `Cache-Control: no-cache`,
+/// and is never compressed, as compression would buffer events. If the stream yields an error,
+/// the response body is aborted.
+#[must_use]
+pub struct Sse<S> {
+    stream: S,
+    keep_alive: Option<Duration>,
+}
+
+impl<S> Sse<S> {
+    /// Creates a new Server-Sent Events response from a stream of events.
+    pub fn new(stream: S) -> Self {
+        Sse {
+            stream,
+            keep_alive: None,
+        }
+    }
+
+    /// Sends a comment line whenever no event has been sent for the given interval,
+    /// to prevent proxies and clients from closing idle connections.
+    pub fn keep_alive(mut self, interval: Duration) -> Self {
+        self.keep_alive = Some(interval);
+        self
+    }
+}
+
+impl<S, E> IntoResponse for Sse<S>
+where
+    S: Stream<Item = Result<Event, E>> + Send + 'static,
+    E: Into<Box<dyn StdError + Send + Sync + 'static>>,
+{
+    fn into_response(self) -> Response {
+        let mut resp = Response::new(Body::stream(SseStream {
+            stream: self.stream,
+            keep_alive: self.keep_alive.map(|interval| KeepAlive {
+                interval,
+                sleep: Box::pin(tokio::time::sleep(interval)),
+            }),
+        }));
+
+        let headers = resp.headers_mut();
+
+        headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("text/event-stream"));
+        headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+
+        resp.extensions_mut().insert(NoCompression);
+
+        resp
+    }
+}
+
+struct KeepAlive {
+    interval: Duration,
+    sleep: Pin<Box<Sleep>>,
+}
+
+#[pin_project::pin_project]
+struct SseStream<S> {
+    #[pin]
+    stream: S,
+    keep_alive: Option<KeepAlive>,
+}
+
+impl<S, E> Stream for SseStream<S>
+where
+    S: Stream<Item = Result<Event, E>>,
+    E: Into<Box<dyn StdError + Send + Sync + 'static>>,
+{
+    type Item = Result<Frame<Bytes>, BodyError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+
+        match this.stream.poll_next(cx) {
+            Poll::Ready(Some(event)) => {
+                if let Some(keep_alive) = this.keep_alive {
+                    keep_alive.sleep.as_mut().reset(Instant::now() + keep_alive.interval);
+                }
+
+                Poll::Ready(Some(match event {
+                    Ok(event) => Ok(Frame::data(event.encode())),
+                    Err(e) => Err(BodyError::Generic(e.into())),
+                }))
+            }
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => {
+                let Some(keep_alive) = this.keep_alive else {
+                    return Poll::Pending;
+                };
+
+                futures::ready!(keep_alive.sleep.as_mut().poll(cx));
+
+                keep_alive.sleep.as_mut().reset(Instant::now() + keep_alive.interval);
+
+                Poll::Ready(Some(Ok(Frame::data(Bytes::from_static(b":\n\n")))))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use futures::StreamExt;
+    use http_body_util::BodyExt;
+
+    use super::*;
+
+    #[test]
+    fn test_event_encoding() {
+        let event = Event::default()
+            .event("update")
+            .id("42")
+            .retry(Duration::from_secs(3))
+            .data("line 1\nline 2\r\nline 3\rline 4");
+
+        assert_eq!(
+            event.encode(),
+            "event: update\nid: 42\nretry: 3000\ndata: line 1\ndata: line 2\ndata: line 3\ndata: line 4\n\n"
+        );
+
+        assert_eq!(Event::default().comment("hi").data("").encode(), ":hi\ndata: \n\n");
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_event_rejects_multiline_id() {
+        _ = Event::default().id("a\nb");
+    }
+
+    #[tokio::test]
+    async fn test_sse_response() {
+        let events = futures::stream::iter([Ok::<_, Infallible>(Event::default().data("first"))]);
+
+        // keep-alive comments are sent while the stream is idle
+        let resp = Sse::new(events.chain(futures::stream::pending()))
+            .keep_alive(Duration::from_millis(10))
+            .into_response();
+
+        assert_eq!(resp.headers()[header::CONTENT_TYPE], "text/event-stream");
+        assert_eq!(resp.headers()[header::CACHE_CONTROL], "no-cache");
+        assert!(resp.extensions().get::<NoCompression>().is_some());
+
+        let mut body = resp.into_body();
+
+        let frame = body.frame().await.unwrap().unwrap();
+        assert_eq!(frame.into_data().unwrap(), "data: first\n\n");
+
+        let frame = body.frame().await.unwrap().unwrap();
+        assert_eq!(frame.into_data().unwrap(), ":\n\n");
+    }
+}
@@ -1,11 +1,17 @@
 use std::convert::Infallible;
 
-use crate::{RequestParts, Service};
+use crate::{body::Body, service::ServiceFuture, IntoResponse, Layer, Request, RequestParts, Response, Service};
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum RedirectKind {
+    /// `301 Moved Permanently`
     Permanent,
+    /// `302 Found`
     Temporary,
+    /// `308 Permanent Redirect`, which unlike `301` requires clients to preserve the method and body.
+    PermanentRedirect,
+    /// `307 Temporary Redirect`, which unlike `302` requires clients to preserve the method and body.
+    TemporaryRedirect,
 }
 
 /// Helper service that redirects all using the provided function.
@@ -32,6 +38,50 @@ where
     pub const fn temporary(f: F) -> Self {
         Self(RedirectKind::Temporary, f)
     }
+
+    pub const fn permanent_redirect(f: F) -> Self {
+        Self(RedirectKind::PermanentRedirect, f)
+    }
+
+    pub const fn temporary_redirect(f: F) -> Self {
+        Self(RedirectKind::TemporaryRedirect, f)
+    }
+
+    /// Only rewrite requests matching the predicate, passing all others through to an inner service.
+    ///
+    /// The returned [`ConditionalRewrite`] is a [`Layer`] rather than a terminal service.
+    ///
+    /// ```rust,ignore
+    /// let legacy = RewriteService::permanent_redirect(|parts| parts.uri.path().replacen("/legacy", "", 1))
+    ///     .when(|parts| parts.uri.path().starts_with("/legacy/"));
+    ///
+    /// let service = legacy.layer(router);
+    /// ```
+    pub const fn when<P>(self, predicate: P) -> ConditionalRewrite<P, F>
+    where
+        P: Fn(&RequestParts) -> bool + Clone + Send + Sync + 'static,
+    {
+        ConditionalRewrite {
+            rewrite: self,
+            predicate,
+            inner: (),
+        }
+    }
+
+    fn redirect(&self, parts: &RequestParts) -> http::Response<http_body_util::Empty<bytes::Bytes>> {
+        let status = match self.0 {
+            RedirectKind::Permanent => http::StatusCode::MOVED_PERMANENTLY,
+            RedirectKind::Temporary => http::StatusCode::FOUND,
+            RedirectKind::PermanentRedirect => http::StatusCode::PERMANENT_REDIRECT,
+            RedirectKind::TemporaryRedirect => http::StatusCode::TEMPORARY_REDIRECT,
+        };
+
+        http::Response::builder()
+            .header(http::header::LOCATION, (self.1)(parts))
+            .status(status)
+            .body(Default::default())
+            .unwrap()
+    }
 }
 
 impl<F, B> Service<http::Request<B>> for RewriteService<F>
@@ -47,19 +97,99 @@ where
 
         drop(body); // explicitly drop the body
 
-        if let Ok(authority) = crate::extract::extract_authority(&parts) {
+        if let Ok(authority) = crate::extract::extract_authority(&parts.uri, &parts.headers) {
             parts.extensions.insert(authority);
         }
 
-        let status = match self.0 {
-            RedirectKind::Permanent => http::StatusCode::MOVED_PERMANENTLY,
-            RedirectKind::Temporary => http::StatusCode::FOUND,
-        };
+        std::future::ready(Ok(self.redirect(&parts)))
+    }
+}
 
-        std::future::ready(Ok(http::Response::builder()
-            .header(http::header::LOCATION, (self.1)(&parts))
-            .status(status)
-            .body(Default::default())
-            .unwrap()))
+/// A [`RewriteService`] that only rewrites requests matching a predicate, see [`RewriteService::when`].
+///
+/// Requests that do not match are passed through to the inner service. Matching requests
+/// are given the [Authority](http::uri::Authority) extension before the predicate is called.
+#[derive(Clone)]
+pub struct ConditionalRewrite<P, F, S = ()> {
+    rewrite: RewriteService<F>,
+    predicate: P,
+    inner: S,
+}
+
+impl<P, F, S> Layer<S> for ConditionalRewrite<P, F>
+where
+    P: Clone,
+    F: Clone,
+{
+    type Service = ConditionalRewrite<P, F, S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ConditionalRewrite {
+            rewrite: self.rewrite.clone(),
+            predicate: self.predicate.clone(),
+            inner,
+        }
+    }
+}
+
+impl<P, F, S> Service<Request> for ConditionalRewrite<P, F, S>
+where
+    P: Fn(&RequestParts) -> bool + Clone + Send + Sync + 'static,
+    F: Fn(&RequestParts) -> String + Clone + Send + Sync + 'static,
+    S: Service<Request, Response: IntoResponse>,
+{
+    type Response = Response;
+    type Error = S::Error;
+
+    fn call(&self, req: Request) -> impl ServiceFuture<Self::Response, Self::Error> {
+        async move {
+            let (mut parts, body) = req.into_parts();
+
+            if let Ok(authority) = crate::extract::extract_authority(&parts.uri, &parts.headers) {
+                parts.extensions.insert(authority);
+            }
+
+            if (self.predicate)(&parts) {
+                let (parts, _) = self.rewrite.redirect(&parts).into_parts();
+
+                return Ok(Response::from_parts(parts, Body::empty()));
+            }
+
+            self.inner.call(Request::from_parts(parts, body)).await.map(IntoResponse::into_response)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::{header::LOCATION, StatusCode};
+
+    use super::*;
+    use crate::Router;
+
+    #[tokio::test]
+    async fn test_conditional_rewrite() {
+        let mut router = Router::<(), Response>::with_state(());
+        router.get("/about", || async { "about" });
+
+        let service = RewriteService::permanent_redirect(|parts| parts.uri.path().replacen("/legacy", "", 1))
+            .when(|parts| parts.uri.path().starts_with("/legacy/"))
+            .layer(router);
+
+        let get = |path: &str| http::Request::get(path).body(Body::empty()).unwrap();
+
+        let resp = service.call(get("/legacy/about")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::PERMANENT_REDIRECT);
+        assert_eq!(resp.headers()[LOCATION], "/about");
+
+        let resp = service.call(get("/about")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.into_body().to_string().await.unwrap(), "about");
+
+        // not under `/legacy/`, so passed through to the router
+        assert!(matches!(
+            service.call(get("/legacy")).await,
+            Err(crate::Error::NotFound)
+        ));
     }
 }
@@ -11,6 +11,7 @@ use tower_layer::Layer;
 use crate::{
     extract::MatchedPath,
     handler::{BoxedErasedHandler, Handler, HandlerIntoResponse},
+    headers::{accept::Accept, Header},
     service::{Service, ServiceFuture},
     IntoResponse, Request, Response,
 };
@@ -37,16 +38,30 @@ where
 #[derive(Clone)]
 pub struct Route<SERVICE> {
     path: Arc<str>,
+    meta: http::Extensions,
+    /// Whether the service has been wrapped by [`Router::route_layer`].
+    layered: bool,
     service: SERVICE,
 }
 
 impl<S> Route<S> {
+    fn new(path: &str, service: S) -> Self {
+        Route {
+            path: Arc::from(path),
+            meta: http::Extensions::new(),
+            layered: false,
+            service,
+        }
+    }
+
     fn wrap<L>(self, layer: &L) -> Route<L::Service>
     where
         L: Layer<S>,
     {
         Route {
             path: self.path,
+            meta: self.meta,
+            layered: true,
             service: layer.layer(self.service),
         }
     }
@@ -81,10 +96,19 @@ pub struct Router<STATE, RETURN = Response, SERVICE = HandlerService<STATE, RETU
     r_options: matchit::Router<NodeId>,
     r_trace: matchit::Router<NodeId>,
     r_any: matchit::Router<NodeId>,
+    /// Fallbacks of nested routers, scoped to their prefix.
+    r_fallback: matchit::Router<NodeId>,
     routes: HashMap<NodeId, Route<SERVICE>, rustc_hash::FxRandomState>,
     state: STATE,
     counter: u64,
+    last_route: Option<NodeId>,
     trim_trailing_slash: bool,
+    strict_routes: bool,
+    method_not_allowed: bool,
+    /// Strips the body of responses to `HEAD` requests routed to `GET` routes, if enabled.
+    implicit_head: Option<fn(RETURN) -> RETURN>,
+    /// Responds to `OPTIONS` requests with the allowed methods, if enabled.
+    implicit_options: Option<fn(Vec<Method>) -> RETURN>,
     _return: PhantomData<fn() -> RETURN>,
 }
 
@@ -105,10 +129,16 @@ where
             r_options: matchit::Router::new(),
             r_trace: matchit::Router::new(),
             r_any: matchit::Router::new(),
+            r_fallback: matchit::Router::new(),
             routes: HashMap::default(),
             state,
             counter: 1,
+            last_route: None,
             trim_trailing_slash: true,
+            strict_routes: false,
+            method_not_allowed: false,
+            implicit_head: None,
+            implicit_options: None,
             _return: PhantomData,
         }
     }
@@ -125,6 +155,31 @@ where
         self
     }
 
+    /// Set whether or not to reject ambiguous route registrations.
+    ///
+    /// Routes registered for specific methods always take precedence over [`any`](Router::any)
+    /// routes, so registering both `GET /x` and `any /x` means `GET` requests never reach the
+    /// `any` route. When strict, registering the same path for both will panic instead.
+    ///
+    /// By default, this is set to `false`.
+    pub fn strict_routes(mut self, strict: bool) -> Self {
+        self.strict_routes = strict;
+        self
+    }
+
+    /// Set whether to respond with `405 Method Not Allowed` when the path is registered,
+    /// but not for the request method, with an `Allow` header listing the methods it is registered for.
+    ///
+    /// When disabled, such requests are handled by the fallback as if the path were not registered,
+    /// which usually results in `404 Not Found`. Paths registered with [`any`](Router::any) accept
+    /// every method, so they are never considered.
+    ///
+    /// By default, this is set to `false`.
+    pub fn method_not_allowed(mut self, enabled: bool) -> Self {
+        self.method_not_allowed = enabled;
+        self
+    }
+
     pub fn route_layer<L>(self, layer: L) -> Router<STATE, RETURN, L::Service>
     where
         L: Layer<SERVICE>,
@@ -142,40 +197,532 @@ where
             r_options: self.r_options,
             r_trace: self.r_trace,
             r_any: self.r_any,
+            r_fallback: self.r_fallback,
             state: self.state,
             counter: self.counter,
+            last_route: self.last_route,
             trim_trailing_slash: self.trim_trailing_slash,
+            strict_routes: self.strict_routes,
+            method_not_allowed: self.method_not_allowed,
+            implicit_head: self.implicit_head,
+            implicit_options: self.implicit_options,
             _return: PhantomData,
         }
     }
 
+    /// Attaches a metadata value to the most recently added route, which is inserted
+    /// into the request extensions whenever that route matches, much like [`MatchedPath`].
+    ///
+    /// This allows layers to make per-route decisions, such as picking a rate-limit tier
+    /// or requiring authentication, without matching on paths themselves.
+    ///
+    /// ```rust,ignore
+    /// #[derive(Clone)]
+    /// struct Public;
+    ///
+    /// router.get("/health", health).with_meta(Public);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if no route has been added yet.
+    pub fn with_meta<M>(&mut self, meta: M) -> &mut Self
+    where
+        M: Clone + Send + Sync + 'static,
+    {
+        let route = self.last_route.and_then(|id| self.routes.get_mut(&id));
+
+        route.expect("no route to attach metadata to").meta.insert(meta);
+
+        self
+    }
+
+    /// Checks if exactly `path` has been registered in the given router,
+    /// rather than another route that happens to match it.
+    fn is_registered(&self, router: &matchit::Router<NodeId>, path: &str) -> bool {
+        router.at(path).is_ok_and(|m| self.routes.get(m.value).is_some_and(|route| *route.path == *path))
+    }
+
+    fn check_overlap(&self, path: &str, methods: Option<&[Method]>) {
+        if !self.strict_routes {
+            return;
+        }
+
+        let overlapping = match methods {
+            // a new `any` route overlaps any method-specific route
+            None => self.method_routers().into_iter().find(|(_, router)| self.is_registered(router, path)),
+
+            // a new method-specific route overlaps an `any` route
+            Some(methods) => match self.is_registered(&self.r_any, path) {
+                true => self.method_routers().into_iter().find(|(method, _)| methods.contains(method)),
+                false => None,
+            },
+        };
+
+        if let Some((method, _)) = overlapping {
+            panic!("route `{path}` is registered for both `{method}` and `any`, the `{method}` route takes precedence");
+        }
+    }
+
+    pub(crate) fn _any(&mut self, path: &str, service: SERVICE) {
+        self.check_overlap(path, None);
+
+        let id = self.counter;
+        self.counter += 1;
+        self.routes.insert(id, Route::new(path, service));
+        self.last_route = Some(id);
+
+        self.r_any.insert(path, id).unwrap();
+    }
+
     pub(crate) fn _on(&mut self, path: &str, methods: &[Method], service: SERVICE) {
+        self.check_overlap(path, Some(methods));
+
         let id = self.counter;
         self.counter += 1;
-        self.routes.insert(
-            id,
-            Route {
-                path: Arc::from(path),
-                service,
-            },
-        );
+        self.routes.insert(id, Route::new(path, service));
+        self.last_route = Some(id);
 
         for method in methods {
-            let router = match *method {
-                Method::GET => &mut self.r_get,
-                Method::POST => &mut self.r_post,
-                Method::PUT => &mut self.r_put,
-                Method::DELETE => &mut self.r_delete,
-                Method::PATCH => &mut self.r_patch,
-                Method::HEAD => &mut self.r_head,
-                Method::CONNECT => &mut self.r_connect,
-                Method::OPTIONS => &mut self.r_options,
-                Method::TRACE => &mut self.r_trace,
-                _ => &mut self.r_any,
-            };
+            self.method_router_mut(method).insert(path, id).unwrap();
+        }
+    }
+
+    /// Returns the router for the given method, or the `any` router for other methods.
+    fn method_router_mut(&mut self, method: &Method) -> &mut matchit::Router<NodeId> {
+        match *method {
+            Method::GET => &mut self.r_get,
+            Method::POST => &mut self.r_post,
+            Method::PUT => &mut self.r_put,
+            Method::DELETE => &mut self.r_delete,
+            Method::PATCH => &mut self.r_patch,
+            Method::HEAD => &mut self.r_head,
+            Method::CONNECT => &mut self.r_connect,
+            Method::OPTIONS => &mut self.r_options,
+            Method::TRACE => &mut self.r_trace,
+            _ => &mut self.r_any,
+        }
+    }
+
+    /// Mounts all routes of another router under the given path prefix.
+    ///
+    /// Each nested route is registered as if its path had been prefixed, so `/users/{id}`
+    /// nested under `/api` is matched and reported by [`MatchedPath`] as `/api/users/{id}`.
+    /// Nested routes keep the state and route layers they were given by the other router,
+    /// but path handling such as [trailing slash trimming](Router::trim_trailing_slash)
+    /// is that of this router.
+    ///
+    /// If the other router has a [fallback](Router::fallback), it only handles requests
+    /// under the prefix, and this router's fallback handles everything else.
+    ///
+    /// ```rust,ignore
+    /// let mut api = Router::with_state(state.clone());
+    /// api.get("/users/{id}", get_user);
+    ///
+    /// let mut router = Router::with_state(state);
+    /// router.get("/", index);
+    /// router.nest("/api", api);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the prefix does not start with `/`, or if any nested route conflicts
+    /// with an existing route.
+    pub fn nest(&mut self, prefix: &str, mut other: Self) -> &mut Self {
+        assert!(prefix.starts_with('/'), "nest prefix must start with /");
+
+        let prefix = prefix.trim_end_matches('/');
+
+        let join = |path: &str| match path {
+            _ if prefix.is_empty() => path.to_owned(),
+            "/" => prefix.to_owned(),
+            _ => format!("{prefix}{path}"),
+        };
+
+        // node ids start at 1, with 0 being the fallback
+        let offset = self.counter - 1;
+
+        let mut ids = other.routes.keys().copied().filter(|&id| id != 0).collect::<Vec<_>>();
+        ids.sort_unstable();
+
+        for id in ids {
+            let route = other.routes.remove(&id).unwrap();
+
+            // find where the route was registered, by id as multiple routes may share a path
+            let registered =
+                |router: &matchit::Router<NodeId>| router.at(&route.path).is_ok_and(|m| *m.value == id);
+
+            let methods = other
+                .method_routers()
+                .into_iter()
+                .filter(|(_, router)| registered(router))
+                .map(|(method, _)| method)
+                .collect::<Vec<_>>();
+
+            let any = registered(&other.r_any);
+            let fallback = registered(&other.r_fallback);
+
+            let path = join(&route.path);
+            let new_id = id + offset;
+
+            if !methods.is_empty() {
+                self.check_overlap(&path, Some(&methods));
+            }
+
+            for method in &methods {
+                self.method_router_mut(method)
+                    .insert(&*path, new_id)
+                    .expect("nested route conflicts with an existing route");
+            }
+
+            if any {
+                self.check_overlap(&path, None);
+                self.r_any.insert(&*path, new_id).expect("nested route conflicts with an existing route");
+            }
+
+            if fallback {
+                self.insert_fallback(&path, new_id);
+            }
+
+            self.routes.insert(
+                new_id,
+                Route {
+                    path: Arc::from(path),
+                    meta: route.meta,
+                    layered: route.layered,
+                    service: route.service,
+                },
+            );
+        }
+
+        self.counter += other.counter - 1;
+
+        // scope the nested fallback to the prefix, rather than replacing this router's fallback
+        if let Some(fallback) = other.routes.remove(&0) {
+            let id = self.counter;
+            self.counter += 1;
+
+            let path = join("/");
+            self.insert_fallback(&path, id);
+
+            self.routes.insert(
+                id,
+                Route {
+                    path: Arc::from(path),
+                    meta: fallback.meta,
+                    layered: fallback.layered,
+                    service: fallback.service,
+                },
+            );
+        }
+
+        self.last_route = None;
+
+        self
+    }
+
+    /// Registers a nested fallback for `path` and everything under it.
+    fn insert_fallback(&mut self, path: &str, id: NodeId) {
+        let catch_all = format!("{}/{{*fallback}}", path.trim_end_matches('/'));
+
+        for path in [path, &catch_all] {
+            self.r_fallback.insert(path, id).expect("nested fallback conflicts with another nested fallback");
+        }
+    }
+}
+
+impl<STATE, RETURN, SERVICE> Router<STATE, RETURN, SERVICE>
+where
+    STATE: Default + Clone + Send + Sync + 'static,
+    RETURN: 'static,
+{
+    /// Creates a new router with the default state.
+    ///
+    /// If the real state isn't available yet when defining routes, the default
+    /// can serve as a placeholder until the state is given with [`Router::provide_state`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_state(STATE::default())
+    }
+}
+
+impl<STATE, RETURN, SERVICE> Default for Router<STATE, RETURN, SERVICE>
+where
+    STATE: Default + Clone + Send + Sync + 'static,
+    RETURN: 'static,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<STATE, RETURN> Router<STATE, RETURN, HandlerService<STATE, RETURN>>
+where
+    STATE: Clone,
+{
+    /// Replaces the state of the router and all routes registered so far, such as
+    /// when routes were defined before the state could be constructed.
+    ///
+    /// This must be done before any [`route_layer`](Router::route_layer) is applied. Routes
+    /// layered with [`RouteGroup::layer`] are unaffected by the order, and receive the provided state
+    /// whether it's given before or after the group's layers.
+    pub fn provide_state(mut self, state: STATE) -> Self {
+        for route in self.routes.values_mut() {
+            route.service.state = state.clone();
+        }
+
+        self.state = state;
+        self
+    }
+}
+
+impl<STATE, RETURN> Router<STATE, RETURN, HandlerService<STATE, RETURN>>
+where
+    STATE: Clone + Send + Sync + 'static,
+    RETURN: 'static,
+{
+    /// Maps the router state into a new state type, which routes added afterwards receive.
+    ///
+    /// Routes registered before mapping continue to receive the original state, as
+    /// their handlers were built for the original state type.
+    pub fn map_state<T, F>(self, f: F) -> Router<T, RETURN, HandlerService<T, RETURN>>
+    where
+        T: Clone + Send + Sync + 'static,
+        F: FnOnce(STATE) -> T,
+    {
+        let state = f(self.state);
+
+        Router {
+            routes: self
+                .routes
+                .into_iter()
+                .map(|(id, route)| {
+                    let service = HandlerService {
+                        state: state.clone(),
+                        handler: route.service.handler.with_fixed_state(route.service.state),
+                    };
+
+                    (
+                        id,
+                        Route {
+                            path: route.path,
+                            meta: route.meta,
+                            layered: false,
+                            service,
+                        },
+                    )
+                })
+                .collect(),
+
+            r_get: self.r_get,
+            r_post: self.r_post,
+            r_put: self.r_put,
+            r_delete: self.r_delete,
+            r_patch: self.r_patch,
+            r_head: self.r_head,
+            r_connect: self.r_connect,
+            r_options: self.r_options,
+            r_trace: self.r_trace,
+            r_any: self.r_any,
+            r_fallback: self.r_fallback,
+            state,
+            counter: self.counter,
+            last_route: self.last_route,
+            trim_trailing_slash: self.trim_trailing_slash,
+            strict_routes: self.strict_routes,
+            method_not_allowed: self.method_not_allowed,
+            implicit_head: self.implicit_head,
+            implicit_options: self.implicit_options,
+            _return: PhantomData,
+        }
+    }
+}
+
+impl<STATE, RETURN> Router<STATE, RETURN, HandlerService<STATE, RETURN>>
+where
+    STATE: Clone + Send + Sync + 'static,
+    RETURN: 'static,
+{
+    /// Registers routes within the closure as a group, to which layers
+    /// can then be applied without affecting any other routes.
+    ///
+    /// ```rust,ignore
+    /// router
+    ///     .group(|g| {
+    ///         g.get("/admin", admin_page);
+    ///         g.post("/admin/users", create_user);
+    ///     })
+    ///     .layer(auth_layer);
+    /// ```
+    ///
+    /// Layered routes are still called with the router's state, so [`provide_state`](Router::provide_state)
+    /// may be used before or after applying layers to the group.
+    #[must_use = "use `RouteGroup::layer` to apply layers to the group"]
+    pub fn group<F>(&mut self, f: F) -> RouteGroup<'_, STATE, RETURN>
+    where
+        F: FnOnce(&mut Self),
+    {
+        let start = self.counter;
+
+        f(self);
+
+        RouteGroup {
+            ids: start..self.counter,
+            router: self,
+        }
+    }
+}
+
+impl<STATE, RETURN> Router<STATE, RETURN, HandlerService<STATE, RETURN>>
+where
+    STATE: Clone + Send + Sync + 'static,
+    RETURN: 'static,
+{
+    /// Registers an arbitrary [`Service`] at the given path for the given methods, rather than a handler,
+    /// such as a proxy, a static file service or a `tower` service adapter.
+    ///
+    /// The service is called with the request as-is, and is otherwise treated like any other route,
+    /// so route layers, [`MatchedPath`] and URL parameters all apply to it. An empty list of methods
+    /// registers it for any method, like [`Router::any`].
+    ///
+    /// ```rust,ignore
+    /// router.route_service("/static/{*path}", [Method::GET, Method::HEAD], static_files);
+    /// ```
+    pub fn route_service<S>(
+        &mut self,
+        path: impl AsRef<str>,
+        methods: impl AsRef<[Method]>,
+        service: S,
+    ) -> &mut Self
+    where
+        S: Service<Request, Response = RETURN, Error = Infallible> + Send + Sync + 'static,
+    {
+        let path = path.as_ref();
+
+        assert!(path.starts_with("/"), "path must start with /");
+
+        let service = HandlerService {
+            state: self.state.clone(),
+            handler: BoxedErasedHandler::from_service(service),
+        };
+
+        match methods.as_ref() {
+            [] => self._any(path, service),
+            methods => self._on(path, methods, service),
+        }
+
+        self
+    }
+}
+
+/// A group of routes created with [`Router::group`].
+pub struct RouteGroup<'a, STATE, RETURN> {
+    router: &'a mut Router<STATE, RETURN>,
+    ids: std::ops::Range<NodeId>,
+}
+
+impl<STATE, RETURN> RouteGroup<'_, STATE, RETURN>
+where
+    STATE: Clone + Send + Sync + 'static,
+    RETURN: 'static,
+{
+    /// Wraps every route in the group with the given layer.
+    ///
+    /// Like with [`Router::route_layer`], the layer only runs once a route has matched.
+    /// The state is passed through the layer when the route is called, rather than
+    /// captured here, so a later [`Router::provide_state`] still applies to the handlers.
+    pub fn layer<L>(self, layer: L) -> Self
+    where
+        L: Layer<HandlerService<STATE, RETURN>>,
+        L::Service: Service<Request, Response = RETURN, Error = Infallible> + Send + Sync + 'static,
+    {
+        for id in self.ids.clone() {
+            if let Some(route) = self.router.routes.get_mut(&id) {
+                let state = route.service.state.clone();
+
+                let inner = HandlerService {
+                    handler: route.service.handler.clone().with_request_state(),
+                    state: state.clone(),
+                };
 
-            router.insert(path, id).unwrap();
+                route.service = HandlerService {
+                    handler: BoxedErasedHandler::from_stateful_service(layer.layer(inner)),
+                    state,
+                };
+            }
         }
+
+        self
+    }
+}
+
+impl<STATE> Router<STATE, Response>
+where
+    STATE: Clone + Send + Sync + 'static,
+{
+    /// Sets the fallback to a `404 Not Found` response that is either a JSON `{"error":"not found"}`
+    /// or a minimal HTML page, depending on which the request's `Accept` header prefers.
+    ///
+    /// HTML is used when there is no `Accept` header. This is a sensible default for servers
+    /// providing both an API and web pages, and can be replaced with [`Router::fallback`].
+    pub fn default_not_found(&mut self) -> &mut Self {
+        self.fallback(default_not_found)
+    }
+}
+
+/// Replaces the body of a response to a `HEAD` request, preserving its length.
+fn strip_body(resp: Response) -> Response {
+    use http::header::{HeaderValue, CONTENT_LENGTH};
+    use http_body::Body as _;
+
+    let (mut parts, body) = resp.into_parts();
+
+    if let Some(len) = body.size_hint().exact() {
+        parts.headers.entry(CONTENT_LENGTH).or_insert_with(|| HeaderValue::from(len));
+    }
+
+    // streaming bodies are dropped without being polled, ending them early
+    drop(body);
+
+    Response::from_parts(parts, crate::body::Body::empty())
+}
+
+/// Responds to an `OPTIONS` request with the allowed methods.
+fn options_response(allowed: Vec<Method>) -> Response {
+    let allow = allowed.into_iter().collect::<headers::Allow>();
+
+    http::StatusCode::NO_CONTENT.with_header(allow).into_response()
+}
+
+async fn default_not_found(accept: Option<Header<Accept>>) -> Response {
+    use headers::ContentType;
+    use http::StatusCode;
+
+    const HTML: &str = "<!DOCTYPE html><html><head><title>404 Not Found</title></head>\
+                        <body><h1>404 Not Found</h1></body></html>";
+
+    let accept = accept.map(|Header(accept)| accept).unwrap_or_default();
+
+    if accept.negotiate(&[mime::TEXT_HTML, mime::APPLICATION_JSON]) == Some(&mime::APPLICATION_JSON) {
+        (r#"{"error":"not found"}"#, StatusCode::NOT_FOUND).with_header(ContentType::json()).into_response()
+    } else {
+        (HTML, StatusCode::NOT_FOUND).with_header(ContentType::html()).into_response()
+    }
+}
+
+impl<T, RETURN, SERVICE> Router<Arc<T>, RETURN, SERVICE>
+where
+    T: Send + Sync + 'static,
+    RETURN: 'static,
+{
+    /// Creates a new router with the given state wrapped in an [`Arc`], so that
+    /// large or non-`Clone` state can be shared between handlers.
+    ///
+    /// Use the [`SharedState`](crate::extract::SharedState) extractor to access it
+    /// as `&T` directly, or [`State<Arc<T>>`](crate::extract::State). Either way, each
+    /// request only performs a cheap `Arc` clone rather than cloning `T`.
+    pub fn with_shared_state(state: T) -> Self {
+        Self::with_state(Arc::new(state))
     }
 }
 
@@ -220,6 +767,35 @@ impl<STATE, SERVICE> Router<STATE, Response, SERVICE>
 where
     STATE: Clone + Send + Sync + 'static,
 {
+    /// Set whether to route `HEAD` requests to the `GET` route of the same path,
+    /// when no `HEAD` route is registered for it.
+    ///
+    /// The `GET` route is called as usual, with the request method left as `HEAD`, so all headers
+    /// of the response are computed as they would be for `GET`. The response body is then dropped
+    /// without being polled, setting `Content-Length` from its size hint when exact and not already set.
+    ///
+    /// By default, this is set to `false`.
+    pub fn implicit_head(mut self, enabled: bool) -> Self {
+        self.implicit_head = enabled.then_some(strip_body as fn(Response) -> Response);
+        self
+    }
+
+    /// Set whether to respond to `OPTIONS` requests with `204 No Content` and an `Allow` header
+    /// listing the methods the path is registered for, including `OPTIONS` itself.
+    ///
+    /// Registered `OPTIONS` and [`any`](Router::any) routes always take precedence, and paths
+    /// not registered for any method are handled by the fallback as usual.
+    ///
+    /// By default, this is set to `false`.
+    pub fn implicit_options(mut self, enabled: bool) -> Self {
+        self.implicit_options = enabled.then_some(options_response as fn(Vec<Method>) -> Response);
+        self
+    }
+
+    /// Registers a route for any method, used when no route for the specific method matches.
+    ///
+    /// Method-specific routes always take precedence, even when the `any` route is the
+    /// more specific path. See [`Router::strict_routes`] to reject such overlaps.
     pub fn any<H, T>(&mut self, path: impl AsRef<str>, handler: H) -> &mut Self
     where
         H: Handler<T, STATE, Output: IntoResponse>,
@@ -313,17 +889,7 @@ where
 
         assert!(path.starts_with("/"), "path must start with /");
 
-        let id = self.counter;
-        self.counter += 1;
-        self.routes.insert(
-            id,
-            Route {
-                path: Arc::from(path),
-                service: SERVICE::from_handler(handler, self.state.clone()),
-            },
-        );
-
-        self.r_any.insert(path, id).unwrap();
+        self._any(path, SERVICE::from_handler(handler, self.state.clone()));
 
         self
     }
@@ -333,13 +899,8 @@ where
         H: Handler<T, STATE, Output = RETURN>,
         SERVICE: FromHandler<STATE, T, H>,
     {
-        self.routes.insert(
-            0,
-            Route {
-                path: Arc::default(),
-                service: SERVICE::from_handler(handler, self.state.clone()),
-            },
-        );
+        self.routes.insert(0, Route::new("", SERVICE::from_handler(handler, self.state.clone())));
+        self.last_route = Some(0);
 
         self
     }
@@ -408,9 +969,7 @@ impl<STATE, RETURN, SERVICE> Router<STATE, RETURN, SERVICE> {
             }
         };
 
-        if self.trim_trailing_slash && path != "/" {
-            path = path.trim_end_matches('/');
-        }
+        path = self.normalize_path(path);
 
         let maybe_match = match router.at(path) {
             Ok(match_) => Some(match_),
@@ -423,27 +982,141 @@ impl<STATE, RETURN, SERVICE> Router<STATE, RETURN, SERVICE> {
             Some(match_) => Ok(matchit::Match {
                 value: match self.routes.get(match_.value) {
                     Some(handler) => handler,
-                    None => return Err(self.routes.get(&0)),
+                    None => return Err(self.fallback_route(path)),
                 },
                 params: match_.params,
             }),
-            None => Err(self.routes.get(&0)), // fallback route
+            None => Err(self.fallback_route(path)),
         }
     }
-}
 
-impl<STATE, RETURN, SERVICE> Router<STATE, RETURN, SERVICE>
-where
-    STATE: Clone + Send + Sync + 'static,
-    SERVICE: Service<Request, Response = RETURN, Error = Infallible> + 'static,
-    RETURN: Send + 'static,
-{
+    /// Returns every registered route, in order of registration, for generating documentation or debugging.
+    ///
+    /// Routes registered for multiple methods are listed once per method, while [`any`](Router::any)
+    /// routes are listed once as [`RouteMethod::Any`]. Fallbacks are listed as [`RouteMethod::Fallback`],
+    /// with the path prefix they handle, `/` being the router's own fallback.
+    pub fn routes(&self) -> impl Iterator<Item = RouteInfo<'_>> {
+        let mut ids = self.routes.keys().copied().collect::<Vec<_>>();
+        ids.sort_unstable();
+
+        ids.into_iter().flat_map(move |id| {
+            let route = &self.routes[&id];
+
+            // find where the route was registered, by id as multiple routes may share a path
+            let registered =
+                |router: &matchit::Router<NodeId>| router.at(&route.path).is_ok_and(|m| *m.value == id);
+
+            let mut methods = Vec::new();
+
+            if id == 0 || registered(&self.r_fallback) {
+                methods.push(RouteMethod::Fallback);
+            } else {
+                for (method, router) in self.method_routers() {
+                    if registered(router) {
+                        methods.push(RouteMethod::Method(method));
+                    }
+                }
+
+                if registered(&self.r_any) {
+                    methods.push(RouteMethod::Any);
+                }
+            }
+
+            let path = if route.path.is_empty() { "/" } else { &*route.path };
+
+            methods.into_iter().map(move |method| RouteInfo {
+                method,
+                path,
+                layered: route.layered,
+            })
+        })
+    }
+
+    fn method_routers(&self) -> [(Method, &matchit::Router<NodeId>); 9] {
+        [
+            (Method::GET, &self.r_get),
+            (Method::POST, &self.r_post),
+            (Method::PUT, &self.r_put),
+            (Method::DELETE, &self.r_delete),
+            (Method::PATCH, &self.r_patch),
+            (Method::HEAD, &self.r_head),
+            (Method::CONNECT, &self.r_connect),
+            (Method::OPTIONS, &self.r_options),
+            (Method::TRACE, &self.r_trace),
+        ]
+    }
+
+    fn normalize_path<'p>(&self, mut path: &'p str) -> &'p str {
+        // authority-form targets (`CONNECT example.com:443`) have no path, so match them as `/`
+        if path.is_empty() {
+            path = "/";
+        }
+
+        if self.trim_trailing_slash && path != "/" {
+            path = path.trim_end_matches('/');
+        }
+
+        path
+    }
+
+    /// Returns the methods the path is registered for, if any, not including `any` routes.
+    pub(crate) fn allowed_methods(&self, path: &str) -> Vec<Method> {
+        let path = self.normalize_path(path);
+
+        let mut allowed = Vec::new();
+
+        for (method, router) in self.method_routers() {
+            if router.at(path).is_ok() {
+                allowed.push(method);
+            }
+        }
+
+        if self.implicit_head.is_some() && allowed.contains(&Method::GET) && !allowed.contains(&Method::HEAD) {
+            allowed.push(Method::HEAD);
+        }
+
+        if self.implicit_options.is_some() && !allowed.is_empty() && !allowed.contains(&Method::OPTIONS) {
+            allowed.push(Method::OPTIONS);
+        }
+
+        allowed
+    }
+
+    /// Finds the fallback for the path, preferring that of a nested router.
+    fn fallback_route(&self, path: &str) -> Option<&Route<SERVICE>> {
+        match self.r_fallback.at(path) {
+            Ok(match_) => self.routes.get(match_.value),
+            Err(_) => self.routes.get(&0),
+        }
+    }
+}
+
+impl<STATE, RETURN, SERVICE> Router<STATE, RETURN, SERVICE>
+where
+    STATE: Clone + Send + Sync + 'static,
+    SERVICE: Service<Request, Response = RETURN, Error = Infallible> + 'static,
+    RETURN: Send + 'static,
+{
+    /// Returns the router as a [`Service`] accepting any request body type, by converting
+    /// the body with [`ConvertBody`](crate::layers::convert_body::ConvertBody) on each route.
+    ///
+    /// This is the usual entry point for serving a router. See [`Router::into_service`]
+    /// for handling body conversion yourself.
     pub fn finish<B>(self) -> impl Service<http::Request<B>, Response = RETURN, Error = crate::Error>
     where
         B: http_body::Body<Data = bytes::Bytes, Error: std::error::Error + Send + Sync + 'static> + Send + 'static,
     {
         self.route_layer(crate::layers::convert_body::ConvertBody::default())
     }
+
+    /// Returns the router as a [`Service`] as-is, without any body conversion.
+    ///
+    /// Unlike [`Router::finish`], the returned service only accepts requests
+    /// already using [`Body`](crate::body::Body), so this is for when body
+    /// conversion is handled by your own layers or services in front of the router.
+    pub fn into_service(self) -> impl Service<Request, Response = RETURN, Error = crate::Error> {
+        self
+    }
 }
 
 impl<STATE, RETURN, SERVICE> Router<STATE, RETURN, SERVICE>
@@ -451,14 +1124,56 @@ where
     STATE: Clone + Send + Sync + 'static,
     RETURN: Send + 'static,
 {
-    pub async fn call_opt<B>(&self, req: http::Request<B>) -> Result<Option<RETURN>, SERVICE::Error>
+    /// Routes and calls the request, returning `None` if no route or fallback matched.
+    ///
+    /// With [`method_not_allowed`](Router::method_not_allowed) enabled, requests for a path
+    /// registered only for other methods return the [`MethodNotAllowed`] error instead.
+    pub async fn call_opt<B>(&self, req: http::Request<B>) -> Result<Option<RETURN>, crate::Error>
+    where
+        SERVICE: Service<http::Request<B>, Response = RETURN, Error: Into<crate::Error>> + 'static,
+        B: Send,
+    {
+        match self.dispatch(req).await {
+            Ok(Dispatched::Response(res)) => Ok(Some(res)),
+            Ok(Dispatched::NotFound) => Ok(None),
+            Ok(Dispatched::MethodNotAllowed(e)) => Err(e.into()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn dispatch<B>(&self, req: http::Request<B>) -> Result<Dispatched<RETURN>, SERVICE::Error>
     where
         SERVICE: Service<http::Request<B>, Response = RETURN> + 'static,
         B: Send,
     {
         let (mut parts, body) = req.into_parts();
 
-        let route = match self.match_route(&parts.method, parts.uri.path()) {
+        if let Some(respond) = self.implicit_options {
+            if parts.method == Method::OPTIONS {
+                let path = self.normalize_path(parts.uri.path());
+
+                if self.r_options.at(path).is_err() && self.r_any.at(path).is_err() {
+                    let allowed = self.allowed_methods(path);
+
+                    if !allowed.is_empty() {
+                        return Ok(Dispatched::Response(respond(allowed)));
+                    }
+                }
+            }
+        }
+
+        let implicit_head = match self.implicit_head {
+            Some(strip) if parts.method == Method::HEAD => {
+                let path = self.normalize_path(parts.uri.path());
+
+                (self.r_head.at(path).is_err() && self.r_get.at(path).is_ok()).then_some(strip)
+            }
+            _ => None,
+        };
+
+        let method = if implicit_head.is_some() { &Method::GET } else { &parts.method };
+
+        let route = match self.match_route(method, parts.uri.path()) {
             Ok(match_) => {
                 crate::params::insert_url_params(&mut parts.extensions, match_.params);
 
@@ -466,17 +1181,101 @@ where
 
                 match_.value
             }
-            Err(Some(fallback)) => fallback,
-            Err(None) => return Ok(None),
+            Err(fallback) => {
+                if self.method_not_allowed {
+                    let allowed = self.allowed_methods(parts.uri.path());
+
+                    if !allowed.is_empty() {
+                        return Ok(Dispatched::MethodNotAllowed(MethodNotAllowed { allowed }));
+                    }
+                }
+
+                match fallback {
+                    Some(fallback) => fallback,
+                    None => return Ok(Dispatched::NotFound),
+                }
+            }
         };
 
+        parts.extensions.extend(route.meta.clone());
+
         match route.service.call(http::Request::from_parts(parts, body)).await {
-            Ok(res) => Ok(Some(res)),
+            Ok(res) => Ok(Dispatched::Response(match implicit_head {
+                Some(strip) => strip(res),
+                None => res,
+            })),
             Err(err) => Err(err),
         }
     }
 }
 
+/// A registered route, as returned by [`Router::routes`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RouteInfo<'a> {
+    /// The method the route is registered for.
+    pub method: RouteMethod,
+
+    /// The path the route is registered at, as given when registered.
+    pub path: &'a str,
+
+    /// Whether the route has been wrapped by [`Router::route_layer`].
+    pub layered: bool,
+}
+
+/// The method a route is registered for, see [`RouteInfo`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RouteMethod {
+    /// Registered for a specific method.
+    Method(Method),
+
+    /// Registered for any method, with [`Router::any`].
+    Any,
+
+    /// A fallback, with [`Router::fallback`], handling requests no other route matches.
+    Fallback,
+}
+
+enum Dispatched<RETURN> {
+    Response(RETURN),
+    NotFound,
+    MethodNotAllowed(MethodNotAllowed),
+}
+
+/// A `405 Method Not Allowed` response, with an `Allow` header listing the allowed methods.
+///
+/// Returned as an [`Error`](crate::Error) by the [`Router`] when a path is registered,
+/// but not for the request method, see [`Router::method_not_allowed`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[must_use]
+pub struct MethodNotAllowed {
+    allowed: Vec<Method>,
+}
+
+impl MethodNotAllowed {
+    /// The methods the path is registered for.
+    #[must_use]
+    pub fn allowed(&self) -> &[Method] {
+        &self.allowed
+    }
+}
+
+impl IntoResponse for MethodNotAllowed {
+    fn into_response(self) -> Response {
+        use http::StatusCode;
+
+        let allow = self.allowed.into_iter().collect::<headers::Allow>();
+
+        "Method Not Allowed".with_status(StatusCode::METHOD_NOT_ALLOWED).with_header(allow).into_response()
+    }
+}
+
+impl From<MethodNotAllowed> for crate::Error {
+    #[inline]
+    fn from(e: MethodNotAllowed) -> Self {
+        crate::error::ErrorResponse::new(e).into()
+    }
+}
+
 impl<STATE, RETURN, SERVICE, B> Service<http::Request<B>> for Router<STATE, RETURN, SERVICE>
 where
     STATE: Clone + Send + Sync + 'static,
@@ -493,6 +1292,7 @@ where
             match self.call_opt(req).await {
                 Ok(Some(resp)) => Ok(resp),
                 Ok(None) => Err(crate::Error::NotFound),
+                Err(err) => Err(err),
             }
         }
     }
@@ -511,3 +1311,596 @@ where
         self.handler.call(req, self.state.clone()).map(Ok)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use crate::{
+        body::Body,
+        extract::State,
+        layers::cloneable::Cloneable,
+        serve::Server,
+        service::{Service, ServiceExt},
+        Layer, Response, Router,
+    };
+
+    #[tokio::test]
+    async fn test_provide_state() {
+        let mut router = Router::<&'static str, Response>::new();
+        router.get("/", |State(state): State<&'static str>| async move {
+            Bytes::from_static(state.as_bytes())
+        });
+        router.fallback(|State(state): State<&'static str>| async move { Bytes::from_static(state.as_bytes()) });
+
+        let router = router.provide_state("provided");
+
+        assert_eq!(*router.state(), "provided");
+
+        for path in ["/", "/missing"] {
+            let req = http::Request::get(path).body(Body::empty()).unwrap();
+            let body = router.call(req).await.unwrap().into_body().to_string().await.unwrap();
+
+            assert_eq!(body, "provided");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_provide_state_after_group_layer() {
+        let mut router = Router::<&'static str, Response>::new();
+        router
+            .group(|g| {
+                g.get("/a", |State(state): State<&'static str>| async move {
+                    Bytes::from_static(state.as_bytes())
+                });
+            })
+            .layer(Cloneable::default())
+            .layer(tower_layer::layer_fn(|inner| inner));
+
+        let router = router.provide_state("provided");
+
+        let req = http::Request::get("/a").body(Body::empty()).unwrap();
+        let body = router.call(req).await.unwrap().into_body().to_string().await.unwrap();
+
+        assert_eq!(body, "provided");
+    }
+
+    #[tokio::test]
+    async fn test_shared_state() {
+        use std::sync::Arc;
+
+        use crate::extract::SharedState;
+
+        // not `Clone`
+        struct AppState {
+            name: String,
+        }
+
+        async fn body(router: &Router<Arc<AppState>, Response>, path: &'static str) -> String {
+            let req = http::Request::get(path).body(Body::empty()).unwrap();
+            router.call(req).await.unwrap().into_body().to_string().await.unwrap()
+        }
+
+        let mut router = Router::<_, Response>::with_shared_state(AppState { name: "app".into() });
+        router.get(
+            "/name",
+            |state: SharedState<AppState>| async move { state.name.clone() },
+        );
+        router.get("/shared", |state: SharedState<AppState>| async move {
+            format!("{:p}", &*state)
+        });
+        router.get("/state", |State(state): State<Arc<AppState>>| async move {
+            format!("{:p}", &*state)
+        });
+
+        assert_eq!(body(&router, "/name").await, "app");
+
+        // both extractors share the same allocation as the router
+        let addr = format!("{:p}", &**router.state());
+        assert_eq!(body(&router, "/shared").await, addr);
+        assert_eq!(body(&router, "/state").await, addr);
+    }
+
+    #[tokio::test]
+    async fn test_map_state() {
+        #[derive(Clone)]
+        struct AppState(&'static str);
+
+        #[derive(Clone)]
+        struct RequestContext {
+            app: AppState,
+        }
+
+        async fn body(router: &Router<RequestContext, Response>, path: &'static str) -> String {
+            let req = http::Request::get(path).body(Body::empty()).unwrap();
+            router.call(req).await.unwrap().into_body().to_string().await.unwrap()
+        }
+
+        let mut router = Router::<AppState, Response>::with_state(AppState("app"));
+        router.get("/before", |State(app): State<AppState>| async move { app.0 });
+
+        let mut router = router.map_state(|app| RequestContext { app });
+        router.get("/after", |State(ctx): State<RequestContext>| async move { ctx.app.0 });
+
+        assert_eq!(body(&router, "/before").await, "app");
+        assert_eq!(body(&router, "/after").await, "app");
+    }
+
+    #[tokio::test]
+    async fn test_into_service_custom_conversion() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let mut router = Router::<(), Response>::with_state(());
+        router.get("/", || async { Bytes::from_static(b"Hello") });
+
+        // convert the body ourselves rather than using `ConvertBody`
+        let service =
+            router.into_service().map_request(|req: http::Request<hyper::body::Incoming>| req.map(Body::from));
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = Server::from_tcp(listener);
+        let handle = server.handle();
+
+        let serving = tokio::spawn(server.serve(Cloneable::default().layer(service)));
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream.write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n").await.unwrap();
+
+        let mut resp = String::new();
+        stream.read_to_string(&mut resp).await.unwrap();
+
+        assert!(resp.starts_with("HTTP/1.1 200 OK"));
+        assert!(resp.ends_with("Hello"));
+
+        handle.shutdown();
+        serving.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_trace_and_connect() {
+        use http::Method;
+
+        let mut router = Router::<(), Response>::with_state(());
+        router.trace("/echo", || async { "trace" });
+        router.connect("/", || async { "connect" });
+
+        async fn call(router: &Router<(), Response>, method: Method, uri: &str) -> Option<String> {
+            let req = http::Request::builder().method(method).uri(uri).body(Body::empty()).unwrap();
+
+            match router.call(req).await {
+                Ok(resp) => Some(resp.into_body().to_string().await.unwrap()),
+                Err(_) => None,
+            }
+        }
+
+        assert_eq!(call(&router, Method::TRACE, "/echo").await.as_deref(), Some("trace"));
+        assert_eq!(
+            call(&router, Method::CONNECT, "example.com:443").await.as_deref(),
+            Some("connect")
+        );
+
+        // method-specific routes are not shared
+        assert_eq!(call(&router, Method::GET, "/echo").await, None);
+        assert_eq!(call(&router, Method::TRACE, "/").await, None);
+    }
+
+    #[tokio::test]
+    async fn test_overlapping_any() {
+        use http::Method;
+
+        let mut router = Router::<(), Response>::with_state(());
+        router.get("/x", || async { "get" });
+        router.any("/x", || async { "any" });
+        router.get("/{*rest}", || async { "wildcard" });
+        router.any("/y", || async { "any" });
+
+        async fn call(router: &Router<(), Response>, method: Method, uri: &str) -> String {
+            let req = http::Request::builder().method(method).uri(uri).body(Body::empty()).unwrap();
+            router.call(req).await.unwrap().into_body().to_string().await.unwrap()
+        }
+
+        assert_eq!(call(&router, Method::GET, "/x").await, "get");
+        assert_eq!(call(&router, Method::POST, "/x").await, "any");
+
+        // method-specific routes win even when less specific
+        assert_eq!(call(&router, Method::GET, "/y").await, "wildcard");
+        assert_eq!(call(&router, Method::DELETE, "/y").await, "any");
+
+        // different paths that merely overlap are allowed when strict
+        let mut router = Router::<(), Response>::with_state(()).strict_routes(true);
+        router.get("/{*rest}", || async { "wildcard" });
+        router.any("/y", || async { "any" });
+        router.post("/x", || async { "post" });
+        router.any("/x/{id}", || async { "any" });
+    }
+
+    #[test]
+    #[should_panic(expected = "route `/x` is registered for both `GET` and `any`")]
+    fn test_strict_routes() {
+        let mut router = Router::<(), Response>::with_state(()).strict_routes(true);
+        router.any("/x", || async { "any" });
+        router.on([http::Method::POST, http::Method::GET], "/x", || async { "get" });
+    }
+
+    #[test]
+    #[should_panic(expected = "route `/x/{id}` is registered for both `PUT` and `any`")]
+    fn test_strict_routes_any_after() {
+        let mut router = Router::<(), Response>::with_state(()).strict_routes(true);
+        router.put("/x/{id}", || async { "put" });
+        router.any("/x/{id}", || async { "any" });
+    }
+
+    #[tokio::test]
+    async fn test_matched_path() {
+        use crate::{
+            extract::{Extension, FromRequestParts, MatchedPath},
+            IntoResponse,
+        };
+
+        let mut router = Router::<(), Response>::with_state(());
+        router.get(
+            "/users/{id}",
+            |direct: MatchedPath, Extension(ext): Extension<MatchedPath>| async move {
+                format!("{} {}", &*direct, &*ext)
+            },
+        );
+
+        let req = http::Request::get("/users/42").body(Body::empty()).unwrap();
+        let body = router.call(req).await.unwrap().into_body().to_string().await.unwrap();
+
+        assert_eq!(body, "/users/{id} /users/{id}");
+
+        // both forms are rejected the same way outside of a router
+        let (mut parts, _) = http::Request::get("/").body(()).unwrap().into_parts();
+
+        let direct = MatchedPath::from_request_parts(&mut parts, &()).await.unwrap_err().into_response();
+        let ext = Extension::<MatchedPath>::from_request_parts(&mut parts, &()).await.unwrap_err().into_response();
+
+        assert_eq!(direct.status(), http::StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(direct.status(), ext.status());
+    }
+
+    #[tokio::test]
+    async fn test_route_meta() {
+        use crate::{service::ServiceFuture, Request};
+
+        #[derive(Clone)]
+        struct Tier(&'static str);
+
+        // layer that exposes the route's tier as a response header
+        struct TierService<S>(S);
+
+        impl<S> Service<Request> for TierService<S>
+        where
+            S: Service<Request, Response = Response>,
+        {
+            type Response = Response;
+            type Error = S::Error;
+
+            fn call(&self, req: Request) -> impl ServiceFuture<Self::Response, Self::Error> {
+                let tier = req.extensions().get::<Tier>().map_or("default", |tier| tier.0);
+
+                async move {
+                    let mut resp = self.0.call(req).await?;
+                    resp.headers_mut().insert("x-tier", http::HeaderValue::from_static(tier));
+                    Ok(resp)
+                }
+            }
+        }
+
+        let mut router = Router::<(), Response>::with_state(());
+        router.get("/free", || async { "free" });
+        router.get("/premium", || async { "premium" }).with_meta(Tier("premium"));
+        router.fallback(|| async { "fallback" }).with_meta(Tier("fallback"));
+
+        let router = router.route_layer(tower_layer::layer_fn(TierService));
+
+        for (path, tier) in [("/free", "default"), ("/premium", "premium"), ("/missing", "fallback")] {
+            let req = http::Request::get(path).body(Body::empty()).unwrap();
+            let resp = router.call(req).await.unwrap();
+
+            assert_eq!(resp.headers()["x-tier"], tier);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_route_group() {
+        use crate::{service::ServiceFuture, Request};
+
+        // layer rejecting requests without an authorization header
+        struct Auth<S>(S);
+
+        impl<S> Service<Request> for Auth<S>
+        where
+            S: Service<Request, Response = Response>,
+        {
+            type Response = Response;
+            type Error = S::Error;
+
+            fn call(&self, req: Request) -> impl ServiceFuture<Self::Response, Self::Error> {
+                async move {
+                    if !req.headers().contains_key(http::header::AUTHORIZATION) {
+                        return Ok(crate::IntoResponse::into_response(http::StatusCode::UNAUTHORIZED));
+                    }
+
+                    self.0.call(req).await
+                }
+            }
+        }
+
+        let mut router = Router::<(), Response>::with_state(());
+        router.get("/", || async { "home" });
+        router
+            .group(|g| {
+                g.get("/admin", || async { "admin" });
+                g.get("/admin/users", || async { "users" });
+            })
+            .layer(tower_layer::layer_fn(Auth));
+        router.get("/about", || async { "about" });
+
+        for (path, status) in [("/", 200), ("/admin", 401), ("/admin/users", 401), ("/about", 200)] {
+            let req = http::Request::get(path).body(Body::empty()).unwrap();
+            assert_eq!(router.call(req).await.unwrap().status(), status, "{path}");
+        }
+
+        let req = http::Request::get("/admin").header("authorization", "token").body(Body::empty()).unwrap();
+        assert_eq!(
+            router.call(req).await.unwrap().into_body().to_string().await.unwrap(),
+            "admin"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_default_not_found() {
+        let mut router = Router::<(), Response>::with_state(());
+        router.get("/", || async { "index" }).default_not_found();
+
+        let req = http::Request::get("/missing")
+            .header(http::header::ACCEPT, "application/json")
+            .body(Body::empty())
+            .unwrap();
+        let resp = router.call(req).await.unwrap();
+        assert_eq!(resp.status(), http::StatusCode::NOT_FOUND);
+        assert_eq!(resp.headers()[http::header::CONTENT_TYPE], "application/json");
+        assert_eq!(resp.into_body().to_string().await.unwrap(), r#"{"error":"not found"}"#);
+
+        let req = http::Request::get("/missing")
+            .header(http::header::ACCEPT, "text/html,application/xhtml+xml,*/*;q=0.8")
+            .body(Body::empty())
+            .unwrap();
+        let resp = router.call(req).await.unwrap();
+        assert_eq!(resp.status(), http::StatusCode::NOT_FOUND);
+        assert_eq!(resp.headers()[http::header::CONTENT_TYPE], "text/html");
+        assert!(resp.into_body().to_string().await.unwrap().contains("<h1>404 Not Found</h1>"));
+    }
+
+    #[tokio::test]
+    async fn test_nest() {
+        use crate::extract::MatchedPath;
+
+        async fn call(router: &Router<(), Response>, method: http::Method, uri: &str) -> String {
+            let req = http::Request::builder().method(method).uri(uri).body(Body::empty()).unwrap();
+            router.call(req).await.unwrap().into_body().to_string().await.unwrap()
+        }
+
+        let mut users = Router::<(), Response>::with_state(());
+        users.get("/", || async { "list" });
+        users.get("/{id}", |path: MatchedPath| async move { path.0.to_string() });
+        users.any("/{id}/avatar", || async { "avatar" });
+        users.fallback(|| async { "users fallback" });
+
+        let mut router = Router::<(), Response>::with_state(());
+        router.get("/", || async { "index" });
+        router.fallback(|| async { "fallback" });
+        router.nest("/api/users/", users);
+        router.get("/about", || async { "about" });
+
+        assert_eq!(call(&router, http::Method::GET, "/").await, "index");
+        assert_eq!(call(&router, http::Method::GET, "/about").await, "about");
+        assert_eq!(call(&router, http::Method::GET, "/api/users").await, "list");
+        assert_eq!(
+            call(&router, http::Method::GET, "/api/users/42").await,
+            "/api/users/{id}"
+        );
+        assert_eq!(call(&router, http::Method::PUT, "/api/users/42/avatar").await, "avatar");
+
+        // the nested fallback is scoped to the prefix
+        assert_eq!(
+            call(&router, http::Method::GET, "/api/users/42/missing").await,
+            "users fallback"
+        );
+        assert_eq!(call(&router, http::Method::POST, "/api/users").await, "users fallback");
+        assert_eq!(call(&router, http::Method::GET, "/missing").await, "fallback");
+    }
+
+    #[test]
+    #[should_panic = "nest prefix must start with /"]
+    fn test_nest_prefix() {
+        let mut router = Router::<(), Response>::with_state(());
+        router.nest("api", Router::with_state(()));
+    }
+
+    #[tokio::test]
+    async fn test_route_service() {
+        use crate::{extract::MatchedPath, service::ServiceFuture, IntoResponse, Request};
+
+        /// Echoes the request body back, along with the matched path.
+        struct Echo;
+
+        impl Service<Request> for Echo {
+            type Response = Response;
+            type Error = std::convert::Infallible;
+
+            fn call(&self, req: Request) -> impl ServiceFuture<Self::Response, Self::Error> {
+                async move {
+                    let path = req.extensions().get::<MatchedPath>().map(|p| p.0.to_string()).unwrap_or_default();
+                    let body = req.into_body().to_string().await.unwrap();
+
+                    Ok(format!("{path}: {body}").into_response())
+                }
+            }
+        }
+
+        let mut router = Router::<(), Response>::with_state(());
+        router.route_service("/echo/{id}", [http::Method::POST], Echo);
+        router.route_service("/any", [], Echo);
+        router.get("/echo/{id}", || async { "get" });
+
+        async fn call(router: &Router<(), Response>, method: http::Method, uri: &str) -> String {
+            let req = http::Request::builder().method(method).uri(uri).body(Body::from("hi".to_owned())).unwrap();
+            router.call(req).await.unwrap().into_body().to_string().await.unwrap()
+        }
+
+        assert_eq!(call(&router, http::Method::POST, "/echo/1").await, "/echo/{id}: hi");
+        assert_eq!(call(&router, http::Method::GET, "/echo/1").await, "get");
+        assert_eq!(call(&router, http::Method::DELETE, "/any").await, "/any: hi");
+    }
+
+    #[tokio::test]
+    async fn test_method_not_allowed() {
+        use crate::IntoResponse;
+
+        let mut router = Router::<(), Response>::with_state(());
+        router.get("/users/{id}", || async { "get" });
+        router.delete("/users/{id}", || async { "delete" });
+        router.any("/any", || async { "any" });
+
+        async fn call(router: &Router<(), Response>, method: http::Method, uri: &str) -> Response {
+            let req = http::Request::builder().method(method).uri(uri).body(Body::empty()).unwrap();
+            router.call(req).await.unwrap_or_else(IntoResponse::into_response)
+        }
+
+        fn post(uri: &str) -> http::Request<Body> {
+            http::Request::post(uri).body(Body::empty()).unwrap()
+        }
+
+        // disabled by default, so wrong methods are not found
+        let resp = call(&router, http::Method::POST, "/users/1").await;
+        assert_eq!(resp.status(), http::StatusCode::NOT_FOUND);
+        assert!(matches!(router.call_opt(post("/users/1")).await, Ok(None)));
+
+        let router = router.method_not_allowed(true);
+
+        let resp = call(&router, http::Method::POST, "/users/1/").await;
+        assert_eq!(resp.status(), http::StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(resp.headers()[http::header::ALLOW], "GET, DELETE");
+
+        assert_eq!(
+            call(&router, http::Method::POST, "/any").await.status(),
+            http::StatusCode::OK
+        );
+        assert_eq!(
+            call(&router, http::Method::POST, "/missing").await.status(),
+            http::StatusCode::NOT_FOUND
+        );
+
+        // passed through by `call_opt` too
+        let err = router.call_opt(post("/users/1")).await.expect_err("expected 405");
+        assert_eq!(err.into_response().status(), http::StatusCode::METHOD_NOT_ALLOWED);
+    }
+
+    #[tokio::test]
+    async fn test_implicit_head() {
+        use crate::IntoResponse;
+
+        let mut router = Router::<(), Response>::with_state(());
+        router.get("/hello", |method: http::Method| async move {
+            format!("hello {method}").with_header(headers::ContentType::text())
+        });
+        router.get("/explicit", || async { "get" });
+        router.head("/explicit", || async { http::StatusCode::NO_CONTENT });
+
+        async fn call(router: &Router<(), Response>, method: http::Method, uri: &str) -> Response {
+            let req = http::Request::builder().method(method).uri(uri).body(Body::empty()).unwrap();
+            router.call(req).await.unwrap_or_else(IntoResponse::into_response)
+        }
+
+        // disabled by default
+        let resp = call(&router, http::Method::HEAD, "/hello").await;
+        assert_eq!(resp.status(), http::StatusCode::NOT_FOUND);
+
+        let router = router.implicit_head(true).method_not_allowed(true);
+
+        let resp = call(&router, http::Method::HEAD, "/hello").await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        assert_eq!(resp.headers()[http::header::CONTENT_LENGTH], "10");
+        assert_eq!(resp.headers()[http::header::CONTENT_TYPE], "text/plain");
+        assert_eq!(resp.into_body().to_string().await.unwrap(), "");
+
+        let resp = call(&router, http::Method::HEAD, "/explicit").await;
+        assert_eq!(resp.status(), http::StatusCode::NO_CONTENT);
+
+        let resp = call(&router, http::Method::POST, "/hello").await;
+        assert_eq!(resp.headers()[http::header::ALLOW], "GET, HEAD");
+    }
+
+    #[tokio::test]
+    async fn test_implicit_options() {
+        use crate::IntoResponse;
+
+        let mut router = Router::<(), Response>::with_state(());
+        router.get("/users/{id}", || async { "get" });
+        router.delete("/users/{id}", || async { "delete" });
+        router.get("/custom", || async { "get" });
+        router.options("/custom", || async { "options" });
+        router.fallback(|| async { http::StatusCode::NOT_FOUND });
+
+        async fn call(router: &Router<(), Response>, uri: &str) -> Response {
+            let req = http::Request::builder().method(http::Method::OPTIONS).uri(uri).body(Body::empty()).unwrap();
+            router.call(req).await.unwrap_or_else(IntoResponse::into_response)
+        }
+
+        // disabled by default, so handled by the fallback
+        let resp = call(&router, "/users/1").await;
+        assert_eq!(resp.status(), http::StatusCode::NOT_FOUND);
+
+        let router = router.implicit_options(true);
+
+        let resp = call(&router, "/users/1").await;
+        assert_eq!(resp.status(), http::StatusCode::NO_CONTENT);
+        assert_eq!(resp.headers()[http::header::ALLOW], "GET, DELETE, OPTIONS");
+
+        let resp = call(&router, "/custom").await;
+        assert_eq!(resp.into_body().to_string().await.unwrap(), "options");
+
+        let resp = call(&router, "/missing").await;
+        assert_eq!(resp.status(), http::StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_routes() {
+        use super::{RouteInfo, RouteMethod};
+
+        let mut api = Router::<(), Response>::with_state(());
+        api.get("/users/{id}", || async { "user" });
+        api.fallback(|| async { "api fallback" });
+
+        let mut router = Router::<(), Response>::with_state(());
+        router.on([http::Method::GET, http::Method::POST], "/", || async { "root" });
+        router.any("/any", || async { "any" });
+        router.fallback(|| async { "fallback" });
+        router.nest("/api", api);
+
+        let route = |method, path| RouteInfo {
+            method,
+            path,
+            layered: false,
+        };
+
+        assert_eq!(
+            router.routes().collect::<Vec<_>>(),
+            [
+                route(RouteMethod::Fallback, "/"),
+                route(RouteMethod::Method(http::Method::GET), "/"),
+                route(RouteMethod::Method(http::Method::POST), "/"),
+                route(RouteMethod::Any, "/any"),
+                route(RouteMethod::Method(http::Method::GET), "/api/users/{id}"),
+                route(RouteMethod::Fallback, "/api"),
+            ]
+        );
+
+        let router = router.route_layer(Cloneable::default());
+        assert!(router.routes().all(|route| route.layered));
+    }
+}
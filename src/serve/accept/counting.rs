@@ -0,0 +1,252 @@
+//! Acceptor that counts the bytes read and written on each connection.
+
+use std::{
+    fmt,
+    future::Future,
+    io,
+    net::SocketAddr,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    net::TcpStream,
+};
+
+use super::{Accept, DefaultAcceptor};
+
+/// Totals for a closed connection, as given to the [`CountingAcceptor`] callback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ConnectionStats {
+    /// The address of the peer.
+    pub peer: SocketAddr,
+
+    /// The number of bytes read from the peer.
+    pub bytes_read: u64,
+
+    /// The number of bytes written to the peer.
+    pub bytes_written: u64,
+
+    /// How long the connection was open, measured from when it was accepted.
+    pub duration: Duration,
+}
+
+/// An acceptor that counts the bytes read from and written to each connection,
+/// and reports the totals to a callback once the connection is closed, such as
+/// for billing, quotas or abuse detection.
+///
+/// Bytes are counted on the stream produced by the inner acceptor, so when wrapping a TLS
+/// acceptor, the decrypted bytes are counted. To count bytes on the wire, place the
+/// TLS acceptor outside of this one instead.
+#[derive(Clone)]
+pub struct CountingAcceptor<A = DefaultAcceptor> {
+    acceptor: A,
+    on_close: Arc<dyn Fn(&ConnectionStats) + Send + Sync>,
+}
+
+impl CountingAcceptor {
+    /// Creates a new counting acceptor that calls `on_close` with the totals of each closed connection.
+    pub fn new<F>(on_close: F) -> Self
+    where
+        F: Fn(&ConnectionStats) + Send + Sync + 'static,
+    {
+        CountingAcceptor {
+            acceptor: DefaultAcceptor,
+            on_close: Arc::new(on_close),
+        }
+    }
+}
+
+impl<A> CountingAcceptor<A> {
+    /// Overwrite the inner acceptor.
+    pub fn acceptor<Acceptor>(self, acceptor: Acceptor) -> CountingAcceptor<Acceptor> {
+        CountingAcceptor {
+            acceptor,
+            on_close: self.on_close,
+        }
+    }
+}
+
+impl<A: fmt::Debug> fmt::Debug for CountingAcceptor<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CountingAcceptor").field("acceptor", &self.acceptor).finish_non_exhaustive()
+    }
+}
+
+/// Stream produced by [`CountingAcceptor`], counting the bytes read and written.
+pub struct CountingStream<I> {
+    inner: I,
+    peer: SocketAddr,
+    bytes_read: u64,
+    bytes_written: u64,
+    accepted: Instant,
+    on_close: Arc<dyn Fn(&ConnectionStats) + Send + Sync>,
+}
+
+impl<I> CountingStream<I> {
+    /// The number of bytes read from the peer so far.
+    #[inline]
+    pub const fn bytes_read(&self) -> u64 {
+        self.bytes_read
+    }
+
+    /// The number of bytes written to the peer so far.
+    #[inline]
+    pub const fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+
+    /// Returns the current totals for this connection.
+    pub fn stats(&self) -> ConnectionStats {
+        ConnectionStats {
+            peer: self.peer,
+            bytes_read: self.bytes_read,
+            bytes_written: self.bytes_written,
+            duration: self.accepted.elapsed(),
+        }
+    }
+}
+
+impl<S, A> Accept<TcpStream, S> for CountingAcceptor<A>
+where
+    S: Send,
+    A: Accept<TcpStream, S>,
+{
+    type Stream = CountingStream<A::Stream>;
+    type Service = A::Service;
+
+    fn accept(
+        &self,
+        stream: TcpStream,
+        service: S,
+    ) -> impl Future<Output = io::Result<(Self::Stream, Self::Service)>> + Send {
+        async move {
+            let accepted = Instant::now();
+            let peer = stream.peer_addr()?;
+
+            let (stream, service) = self.acceptor.accept(stream, service).await?;
+
+            let stream = CountingStream {
+                inner: stream,
+                peer,
+                bytes_read: 0,
+                bytes_written: 0,
+                accepted,
+                on_close: self.on_close.clone(),
+            };
+
+            Ok((stream, service))
+        }
+    }
+}
+
+impl<I: AsyncRead + Unpin> AsyncRead for CountingStream<I> {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let filled = buf.filled().len();
+
+        let res = Pin::new(&mut self.inner).poll_read(cx, buf);
+
+        if let Poll::Ready(Ok(())) = res {
+            self.bytes_read += (buf.filled().len() - filled) as u64;
+        }
+
+        res
+    }
+}
+
+impl<I: AsyncWrite + Unpin> AsyncWrite for CountingStream<I> {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let res = Pin::new(&mut self.inner).poll_write(cx, buf);
+
+        if let Poll::Ready(Ok(n)) = res {
+            self.bytes_written += n as u64;
+        }
+
+        res
+    }
+
+    #[inline]
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        let res = Pin::new(&mut self.inner).poll_write_vectored(cx, bufs);
+
+        if let Poll::Ready(Ok(n)) = res {
+            self.bytes_written += n as u64;
+        }
+
+        res
+    }
+
+    #[inline]
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+}
+
+impl<I> Drop for CountingStream<I> {
+    fn drop(&mut self) {
+        (self.on_close)(&self.stats());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+    use crate::{
+        layers::{cloneable::Cloneable, convert_body::ConvertBody},
+        serve::Server,
+        Layer, Response, Router,
+    };
+
+    #[tokio::test]
+    async fn test_counting_acceptor() {
+        const REQUEST: &[u8] = b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n";
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = Server::from_tcp(listener).acceptor(CountingAcceptor::new(move |stats| {
+            _ = tx.send(*stats);
+        }));
+        let handle = server.handle();
+
+        let mut router = Router::<(), Response>::with_state(());
+        router.get("/", || async { "Hello" });
+
+        let serving = tokio::spawn(server.serve(Cloneable::default().layer(ConvertBody::default().layer(router))));
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(REQUEST).await.unwrap();
+
+        let mut resp = Vec::new();
+        stream.read_to_end(&mut resp).await.unwrap();
+
+        let stats = rx.recv().await.unwrap();
+
+        assert_eq!(stats.peer, stream.local_addr().unwrap());
+        assert_eq!(stats.bytes_read, REQUEST.len() as u64);
+        assert_eq!(stats.bytes_written, resp.len() as u64);
+
+        handle.shutdown();
+        serving.await.unwrap().unwrap();
+    }
+}
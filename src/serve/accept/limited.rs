@@ -6,6 +6,7 @@ use std::{
         atomic::{AtomicUsize, Ordering},
         Arc,
     },
+    time::{Duration, Instant},
 };
 
 use futures::FutureExt;
@@ -18,11 +19,94 @@ use super::Accept;
 
 type ConnTable = scc::HashIndex<IpAddr, Arc<ConnTracking>, foldhash::fast::RandomState>;
 
+/// Masks IPv6 addresses to remove the last 64 bits, if enabled.
+fn mask(mut ip: IpAddr, privacy_mask: bool) -> IpAddr {
+    match ip {
+        IpAddr::V6(ref mut ip) if privacy_mask => {
+            *ip = Ipv6Addr::from_bits(ip.to_bits() & 0xFFFF_FFFF_FFFF_FFFF_0000_0000_0000_0000);
+        }
+        _ => {}
+    }
+
+    ip
+}
+
+/// A shared list of banned IP addresses, each with an optional expiration time.
+///
+/// Expired bans are removed lazily when checked, or all at once with [`BanList::clean`].
+#[derive(Default)]
+pub struct BanList {
+    bans: scc::HashIndex<IpAddr, Option<Instant>, foldhash::fast::RandomState>,
+    privacy_mask: bool,
+}
+
+impl BanList {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Masks IPv6 addresses to remove the last 64 bits when banning, unbanning or checking them,
+    /// so that banning one address bans its entire `/64` network, as with
+    /// [`LimitedTcpAcceptor::with_privacy_mask`].
+    ///
+    /// Because the list applies the mask itself, addresses can be passed to it as-is,
+    /// such as by an abuse-detection system sharing the list.
+    ///
+    /// Default is `false`.
+    #[must_use]
+    pub fn with_privacy_mask(mut self, privacy_mask: bool) -> Self {
+        self.privacy_mask = privacy_mask;
+        self
+    }
+
+    /// Bans the given IP address for `duration`, replacing any existing ban.
+    ///
+    /// A duration too large to be represented, such as [`Duration::MAX`], bans the address permanently.
+    pub fn ban(&self, ip: IpAddr, duration: Duration) {
+        let ip = mask(ip, self.privacy_mask);
+        let until = Instant::now().checked_add(duration);
+
+        match self.bans.entry(ip) {
+            scc::hash_index::Entry::Occupied(occ) => occ.update(until),
+            scc::hash_index::Entry::Vacant(vac) => _ = vac.insert_entry(until),
+        }
+    }
+
+    /// Removes any ban on the given IP address, returning `true` if it was banned.
+    pub fn unban(&self, ip: IpAddr) -> bool {
+        self.bans.remove(&mask(ip, self.privacy_mask))
+    }
+
+    /// Returns `true` if the given IP address is currently banned.
+    pub fn is_banned(&self, ip: IpAddr) -> bool {
+        let ip = mask(ip, self.privacy_mask);
+        let now = Instant::now();
+
+        match self.bans.peek_with(&ip, |_, until| until.is_none_or(|until| until > now)) {
+            Some(true) => true,
+            Some(false) => {
+                self.bans.remove_if(&ip, |until| until.is_some_and(|until| until <= now));
+                false
+            }
+            None => false,
+        }
+    }
+
+    /// Removes all expired bans.
+    pub fn clean(&self) {
+        let now = Instant::now();
+
+        self.bans.retain(|_, until| until.is_none_or(|until| until > now));
+    }
+}
+
 #[derive(Clone)]
 pub struct LimitedTcpAcceptor<A> {
     acceptor: A,
     limit: usize,
     conns: Arc<ConnTable>,
+    bans: Arc<BanList>,
     privacy_mask: bool,
 }
 
@@ -32,19 +116,52 @@ impl<A> LimitedTcpAcceptor<A> {
             acceptor,
             limit,
             conns: Arc::new(ConnTable::default()),
+            bans: Arc::default(),
             privacy_mask: false,
         }
     }
 
+    /// Uses the given [`BanList`], such as one shared with an abuse-detection system.
+    ///
+    /// Connections from banned addresses are rejected before being passed to the inner acceptor,
+    /// so no TLS handshake or other work is performed for them. The list's own
+    /// [privacy mask](BanList::with_privacy_mask) setting applies to its bans.
+    pub fn with_ban_list(mut self, bans: Arc<BanList>) -> Self {
+        self.bans = bans;
+        self
+    }
+
+    /// Returns the [`BanList`] used by this acceptor.
+    #[must_use]
+    pub fn ban_list(&self) -> &Arc<BanList> {
+        &self.bans
+    }
+
+    /// Bans the given IP address for `duration`, see [`BanList::ban`].
+    ///
+    /// Existing connections are not affected.
+    pub fn ban(&self, ip: IpAddr, duration: Duration) {
+        self.bans.ban(ip, duration);
+    }
+
     /// Masks IPv6 addresses to remove the last 64 bits.
     ///
     /// This is useful for making sure clients with randomized IPv6 interfaces
     /// aren't treated as different clients. This can be common in some networks
     /// that attempt to preserve privacy.
     ///
+    /// This also applies to the default [`BanList`], but not to one given with
+    /// [`LimitedTcpAcceptor::with_ban_list`], which uses its own setting.
+    ///
     /// Default is `false`.
     pub fn with_privacy_mask(mut self, privacy_mask: bool) -> Self {
         self.privacy_mask = privacy_mask;
+
+        // only the default list is unshared, and it can't have any bans yet
+        if let Some(bans) = Arc::get_mut(&mut self.bans) {
+            bans.privacy_mask = privacy_mask;
+        }
+
         self
     }
 }
@@ -74,17 +191,16 @@ where
         service: S,
     ) -> impl Future<Output = io::Result<(Self::Stream, Self::Service)>> + Send {
         async move {
-            let mut ip = stream.peer_addr()?.ip();
+            let ip = stream.peer_addr()?.ip();
 
-            let (stream, service) = self.acceptor.accept(stream, service).await?;
-
-            match ip {
-                IpAddr::V6(ref mut ip) if self.privacy_mask => {
-                    *ip = Ipv6Addr::from_bits(ip.to_bits() & 0xFFFF_FFFF_FFFF_FFFF_0000_0000_0000_0000);
-                }
-                _ => {}
+            if self.bans.is_banned(ip) {
+                return Err(io::Error::new(io::ErrorKind::PermissionDenied, "address is banned"));
             }
 
+            let ip = mask(ip, self.privacy_mask);
+
+            let (stream, service) = self.acceptor.accept(stream, service).await?;
+
             let mut failed = false;
 
             // I know this is convoluted, but it has a happy fast path for when one connection is already established,
@@ -218,3 +334,66 @@ impl<I> Drop for TrackedTcpStream<I> {
         });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::serve::accept::DefaultAcceptor;
+
+    #[tokio::test]
+    async fn test_ban() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let acceptor = LimitedTcpAcceptor::new(DefaultAcceptor, 16);
+        acceptor.ban(addr.ip(), Duration::from_millis(100));
+
+        let accept = || async {
+            let _client = TcpStream::connect(addr).await.unwrap();
+            let (stream, _) = listener.accept().await.unwrap();
+            acceptor.accept(stream, ()).await.map(|_| ())
+        };
+
+        let err = accept().await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+
+        tokio::time::sleep(Duration::from_millis(150)).await;
+
+        accept().await.unwrap();
+        assert!(!acceptor.ban_list().is_banned(addr.ip()));
+    }
+
+    #[test]
+    fn test_ban_privacy_mask() {
+        let ip = |s: &str| s.parse::<IpAddr>().unwrap();
+
+        // shared with something that bans full addresses
+        let bans = Arc::new(BanList::new().with_privacy_mask(true));
+        bans.ban(ip("2001:db8::1"), Duration::MAX);
+
+        assert!(bans.is_banned(ip("2001:db8::1")));
+        assert!(bans.is_banned(ip("2001:db8::ffff:1234")), "same /64");
+        assert!(!bans.is_banned(ip("2001:db8:0:1::1")), "different /64");
+        assert!(!bans.is_banned(ip("192.0.2.1")));
+
+        // IPv4 addresses are not masked
+        bans.ban(ip("192.0.2.1"), Duration::MAX);
+        assert!(!bans.is_banned(ip("192.0.2.2")));
+
+        let acceptor = LimitedTcpAcceptor::new(DefaultAcceptor, 16).with_ban_list(bans.clone());
+        assert!(acceptor.ban_list().is_banned(ip("2001:db8::2")));
+        assert!(bans.unban(ip("2001:db8::3")));
+        assert!(!acceptor.ban_list().is_banned(ip("2001:db8::1")));
+
+        // the default list follows the acceptor's setting
+        let acceptor = LimitedTcpAcceptor::new(DefaultAcceptor, 16).with_privacy_mask(true);
+        acceptor.ban(ip("2001:db8::1"), Duration::MAX);
+        assert!(acceptor.ban_list().is_banned(ip("2001:db8::2")));
+
+        let unmasked = BanList::new();
+        unmasked.ban(ip("2001:db8::1"), Duration::MAX);
+        assert!(!unmasked.is_banned(ip("2001:db8::2")));
+    }
+}
@@ -1,4 +1,4 @@
-use std::{future::Future, io};
+use std::{future::Future, io, net::SocketAddr};
 
 use tokio::{
     io::{AsyncRead, AsyncWrite},
@@ -8,6 +8,12 @@ use tokio::{
 #[cfg(feature = "limited-acceptor")]
 pub mod limited;
 
+pub mod counting;
+pub use counting::CountingAcceptor;
+
+pub mod proxy;
+pub use proxy::ProxyProtocolAcceptor;
+
 /// An asynchronous function to modify io stream and service.
 pub trait Accept<I: Send, S: Send>: Send + Sync + 'static {
     /// IO stream produced by accept.
@@ -24,6 +30,25 @@ pub trait Accept<I: Send, S: Send>: Send + Sync + 'static {
     ) -> impl Future<Output = io::Result<(Self::Stream, Self::Service)>> + Send;
 }
 
+tokio::task_local! {
+    static PEER_ADDR: SocketAddr;
+}
+
+/// Returns the address of the peer whose connection is being accepted, if known.
+///
+/// This is set by the [`Server`](super::Server) while running its acceptor, so acceptors wrapping
+/// arbitrary streams can report it without requiring anything of the stream type.
+/// It is `None` outside of the server, such as when calling [`Accept::accept`] directly.
+#[must_use]
+pub fn peer_addr() -> Option<SocketAddr> {
+    PEER_ADDR.try_with(|addr| *addr).ok()
+}
+
+/// Runs the accept future with the given peer address available from [`peer_addr`].
+pub(crate) fn with_peer_addr<F: Future>(addr: SocketAddr, accept: F) -> impl Future<Output = F::Output> {
+    PEER_ADDR.scope(addr, accept)
+}
+
 /// A no-op acceptor.
 #[derive(Clone, Copy, Debug, Default)]
 pub struct DefaultAcceptor;
@@ -0,0 +1,262 @@
+//! Acceptor for the [PROXY protocol](https://www.haproxy.org/download/2.9/doc/proxy-protocol.txt),
+//! used by TCP load balancers such as HAProxy or AWS NLB to forward the original client address.
+
+use std::{
+    future::Future,
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+};
+
+use tokio::{io::AsyncReadExt, net::TcpStream};
+
+use super::{Accept, DefaultAcceptor};
+use crate::service::{Service, ServiceFuture};
+
+const V2_SIGNATURE: [u8; 12] = *b"\r\n\r\n\0\r\nQUIT\n";
+
+/// Length of the shortest possible v1 header, `PROXY UNKNOWN\r\n`
+const V1_MIN_LEN: usize = 15;
+
+/// Length of the longest possible v1 header, as defined by the specification
+const V1_MAX_LEN: usize = 107;
+
+/// An acceptor that reads a PROXY protocol v1 or v2 header from the start of each connection,
+/// then replaces the connection's [`SocketAddr`] request extension with the client address
+/// it describes, so [`RealIp`](crate::extract::real_ip::RealIp) and similar pick it up.
+///
+/// Only the header itself is read from the stream, the rest is passed through untouched.
+/// Connections without a valid header are rejected, so this should only be used when
+/// all connections are known to come through a load balancer that sends it.
+///
+/// `LOCAL` commands (such as health checks) and unknown address families
+/// are accepted, keeping the load balancer's own address.
+///
+/// Consider combining this with a [`TimeoutAcceptor`](super::TimeoutAcceptor)
+/// to avoid waiting forever on incomplete headers.
+#[derive(Clone, Copy, Debug, Default)]
+#[repr(transparent)]
+pub struct ProxyProtocolAcceptor<A = DefaultAcceptor>(pub A);
+
+/// Service wrapper produced by [`ProxyProtocolAcceptor`] that inserts the proxied
+/// client address into each request.
+#[derive(Clone, Debug)]
+pub struct ProxiedService<S> {
+    inner: S,
+    source: Option<SocketAddr>,
+}
+
+impl<S> ProxiedService<S> {
+    /// The client address given in the PROXY protocol header, if any.
+    #[inline]
+    pub const fn source(&self) -> Option<SocketAddr> {
+        self.source
+    }
+}
+
+impl<S, B> Service<http::Request<B>> for ProxiedService<S>
+where
+    S: Service<http::Request<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+
+    #[inline]
+    fn call(&self, mut req: http::Request<B>) -> impl ServiceFuture<Self::Response, Self::Error> {
+        if let Some(source) = self.source {
+            req.extensions_mut().insert(source);
+        }
+
+        self.inner.call(req)
+    }
+}
+
+impl<S: Send, A> Accept<TcpStream, S> for ProxyProtocolAcceptor<A>
+where
+    A: Accept<TcpStream, ProxiedService<S>>,
+{
+    type Stream = A::Stream;
+    type Service = A::Service;
+
+    fn accept(
+        &self,
+        mut stream: TcpStream,
+        service: S,
+    ) -> impl Future<Output = io::Result<(Self::Stream, Self::Service)>> + Send {
+        async move {
+            let source = read_header(&mut stream).await?;
+
+            self.0.accept(stream, ProxiedService { inner: service, source }).await
+        }
+    }
+}
+
+fn invalid(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+async fn read_header(stream: &mut TcpStream) -> io::Result<Option<SocketAddr>> {
+    let mut buf = [0u8; V1_MAX_LEN];
+
+    // both versions are at least this long, so this can't read past the header
+    stream.read_exact(&mut buf[..V1_MIN_LEN]).await?;
+
+    if buf[..12] == V2_SIGNATURE {
+        stream.read_exact(&mut buf[V1_MIN_LEN..16]).await?;
+
+        let mut addrs = vec![0u8; u16::from_be_bytes([buf[14], buf[15]]) as usize];
+        stream.read_exact(&mut addrs).await?;
+
+        return parse_v2(&buf[..16], &addrs);
+    }
+
+    if !buf.starts_with(b"PROXY ") {
+        return Err(invalid("missing PROXY protocol header"));
+    }
+
+    let mut len = V1_MIN_LEN;
+
+    while buf[len - 1] != b'\n' {
+        if len == V1_MAX_LEN {
+            return Err(invalid("PROXY protocol header too long"));
+        }
+
+        let n = stream.peek(&mut buf[len..]).await?;
+
+        if n == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        // anything before the newline is part of the header, so consume up to it
+        let n = match buf[len..len + n].iter().position(|&b| b == b'\n') {
+            Some(pos) => pos + 1,
+            None => n,
+        };
+
+        stream.read_exact(&mut buf[len..len + n]).await?;
+        len += n;
+    }
+
+    parse_v1(&buf[..len])
+}
+
+fn parse_v1(line: &[u8]) -> io::Result<Option<SocketAddr>> {
+    let Some(line) = line.strip_suffix(b"\r\n") else {
+        return Err(invalid("PROXY protocol header must end with CRLF"));
+    };
+
+    let Ok(line) = std::str::from_utf8(line) else {
+        return Err(invalid("invalid PROXY protocol header"));
+    };
+
+    let mut parts = line.split(' ').skip(1); // skip "PROXY"
+
+    let v4 = match parts.next() {
+        Some("TCP4") => true,
+        Some("TCP6") => false,
+        Some("UNKNOWN") => return Ok(None),
+        _ => return Err(invalid("unsupported PROXY protocol family")),
+    };
+
+    let (Some(src), Some(_dst), Some(sport), Some(_dport), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(invalid("invalid PROXY protocol header"));
+    };
+
+    let (Ok(ip), Ok(port)) = (src.parse::<IpAddr>(), sport.parse::<u16>()) else {
+        return Err(invalid("invalid PROXY protocol address"));
+    };
+
+    if ip.is_ipv4() != v4 {
+        return Err(invalid("PROXY protocol address does not match family"));
+    }
+
+    Ok(Some(SocketAddr::new(ip, port)))
+}
+
+fn parse_v2(header: &[u8], addrs: &[u8]) -> io::Result<Option<SocketAddr>> {
+    if header[12] >> 4 != 2 {
+        return Err(invalid("unsupported PROXY protocol version"));
+    }
+
+    match header[12] & 0x0F {
+        0x0 => return Ok(None), // LOCAL
+        0x1 => {}               // PROXY
+        _ => return Err(invalid("unsupported PROXY protocol command")),
+    }
+
+    Ok(match header[13] >> 4 {
+        // AF_INET, src(4) + dst(4) + src_port(2) + dst_port(2)
+        0x1 if addrs.len() >= 12 => {
+            let ip: [u8; 4] = addrs[..4].try_into().unwrap();
+            let port = u16::from_be_bytes([addrs[8], addrs[9]]);
+
+            Some(SocketAddr::new(Ipv4Addr::from(ip).into(), port))
+        }
+        // AF_INET6, src(16) + dst(16) + src_port(2) + dst_port(2)
+        0x2 if addrs.len() >= 36 => {
+            let ip: [u8; 16] = addrs[..16].try_into().unwrap();
+            let port = u16::from_be_bytes([addrs[32], addrs[33]]);
+
+            Some(SocketAddr::new(Ipv6Addr::from(ip).into(), port))
+        }
+        0x1 | 0x2 => return Err(invalid("PROXY protocol address block too short")),
+        _ => None, // AF_UNSPEC or AF_UNIX
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::AsyncWriteExt;
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    async fn accept_with(preamble: &[u8]) -> io::Result<(Option<SocketAddr>, Vec<u8>)> {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        client.write_all(preamble).await.unwrap();
+        client.write_all(b"GET / HTTP/1.1\r\n").await.unwrap();
+        client.shutdown().await.unwrap();
+
+        let (stream, _) = listener.accept().await.unwrap();
+
+        let (mut stream, service) = ProxyProtocolAcceptor(DefaultAcceptor).accept(stream, ()).await?;
+
+        let mut rest = Vec::new();
+        stream.read_to_end(&mut rest).await.unwrap();
+
+        Ok((service.source(), rest))
+    }
+
+    #[tokio::test]
+    async fn test_proxy_v1() {
+        let (source, rest) = accept_with(b"PROXY TCP4 192.0.2.1 198.51.100.1 56324 443\r\n").await.unwrap();
+
+        assert_eq!(source, Some("192.0.2.1:56324".parse().unwrap()));
+        assert_eq!(rest, b"GET / HTTP/1.1\r\n");
+
+        let (source, rest) = accept_with(b"PROXY UNKNOWN\r\n").await.unwrap();
+
+        assert_eq!(source, None);
+        assert_eq!(rest, b"GET / HTTP/1.1\r\n");
+
+        assert!(accept_with(b"").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_proxy_v2() {
+        let mut preamble = V2_SIGNATURE.to_vec();
+        preamble.extend_from_slice(&[0x21, 0x21, 0, 36]); // PROXY, TCP over IPv6
+        preamble.extend_from_slice(&"2001:db8::1".parse::<Ipv6Addr>().unwrap().octets());
+        preamble.extend_from_slice(&"2001:db8::2".parse::<Ipv6Addr>().unwrap().octets());
+        preamble.extend_from_slice(&[0xDC, 0x04, 0x01, 0xBB]);
+
+        let (source, rest) = accept_with(&preamble).await.unwrap();
+
+        assert_eq!(source, Some("[2001:db8::1]:56324".parse().unwrap()));
+        assert_eq!(rest, b"GET / HTTP/1.1\r\n");
+    }
+}
@@ -0,0 +1,100 @@
+//! Response body wrapper that aborts streaming bodies once the
+//! [streaming grace](super::Handle::set_streaming_grace) has elapsed during shutdown.
+
+use std::{
+    error::Error,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::{future::BoxFuture, FutureExt};
+use http_body::{Body, Frame, SizeHint};
+
+use super::Handle;
+use crate::body::BodyError;
+
+#[pin_project::pin_project]
+pub(super) struct DrainBody<B> {
+    #[pin]
+    inner: B,
+
+    /// `None` if no streaming grace is configured.
+    handle: Option<Handle>,
+
+    /// Created the first time the body is pending, so bodies that
+    /// complete without waiting never allocate it.
+    abort: Option<BoxFuture<'static, ()>>,
+
+    aborted: bool,
+}
+
+impl<B> DrainBody<B> {
+    /// Wraps the body, given the server handle only if a streaming grace is configured.
+    pub(super) fn new(inner: B, handle: Option<Handle>) -> Self {
+        DrainBody {
+            inner,
+            handle,
+            abort: None,
+            aborted: false,
+        }
+    }
+}
+
+impl<B> Body for DrainBody<B>
+where
+    B: Body<Error: Into<Box<dyn Error + Send + Sync>>>,
+{
+    type Data = B::Data;
+    type Error = Box<dyn Error + Send + Sync>;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.project();
+
+        if *this.aborted {
+            return Poll::Ready(None);
+        }
+
+        if let Poll::Ready(frame) = this.inner.poll_frame(cx) {
+            return Poll::Ready(frame.map(|frame| frame.map_err(Into::into)));
+        }
+
+        let Some(ref handle) = this.handle else {
+            return Poll::Pending;
+        };
+
+        let abort = this.abort.get_or_insert_with(|| {
+            let handle = handle.clone();
+            async move { handle.0.abort_streams.notified().await }.boxed()
+        });
+
+        match abort.poll_unpin(cx) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(()) => {
+                log::debug!("aborting streaming response body after the streaming grace period");
+
+                *this.aborted = true;
+                *this.abort = None;
+
+                // an error rather than the end of the body, so the client doesn't mistake
+                // a truncated response for a complete one
+                Poll::Ready(Some(Err(BodyError::StreamAborted.into())))
+            }
+        }
+    }
+
+    #[inline]
+    fn is_end_stream(&self) -> bool {
+        self.aborted || self.inner.is_end_stream()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> SizeHint {
+        match self.aborted {
+            true => SizeHint::with_exact(0),
+            false => self.inner.size_hint(),
+        }
+    }
+}
@@ -0,0 +1,143 @@
+//! Reporting of TLS handshake outcomes, shared by the TLS acceptors.
+
+use std::{fmt, io, net::SocketAddr, sync::Arc, time::Duration};
+
+/// The outcome of a TLS handshake, as categorized for metrics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum HandshakeOutcome {
+    /// The handshake completed successfully.
+    Success,
+
+    /// The handshake did not complete within the handshake timeout.
+    TimedOut,
+
+    /// The client closed or reset the connection during the handshake.
+    ClientAborted,
+
+    /// The client does not support any offered protocol version or cipher suite,
+    /// such as clients limited to old TLS versions.
+    Incompatible,
+
+    /// The client rejected the server certificate, or presented an invalid certificate.
+    Certificate,
+
+    /// Any other violation of the TLS protocol, such as plain HTTP sent to a TLS port.
+    Protocol,
+
+    /// Any other I/O error.
+    Io,
+}
+
+impl HandshakeOutcome {
+    /// Returns a short, static label for this outcome, suitable for metrics.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            HandshakeOutcome::Success => "success",
+            HandshakeOutcome::TimedOut => "timed_out",
+            HandshakeOutcome::ClientAborted => "client_aborted",
+            HandshakeOutcome::Incompatible => "incompatible",
+            HandshakeOutcome::Certificate => "certificate",
+            HandshakeOutcome::Protocol => "protocol",
+            HandshakeOutcome::Io => "io",
+        }
+    }
+
+    /// Categorizes a plain I/O error from the underlying stream.
+    pub(crate) fn from_io_kind(kind: io::ErrorKind) -> Self {
+        use io::ErrorKind;
+
+        match kind {
+            ErrorKind::UnexpectedEof
+            | ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted
+            | ErrorKind::BrokenPipe => HandshakeOutcome::ClientAborted,
+            ErrorKind::TimedOut => HandshakeOutcome::TimedOut,
+            _ => HandshakeOutcome::Io,
+        }
+    }
+
+    /// The [`io::ErrorKind`] used for the error returned by the acceptor on this outcome.
+    ///
+    /// Handshake timeouts are [`TimedOut`](io::ErrorKind::TimedOut), whereas TLS protocol errors
+    /// are [`InvalidData`](io::ErrorKind::InvalidData), so the two can be told apart.
+    pub(crate) fn error_kind(self, original: io::ErrorKind) -> io::ErrorKind {
+        match self {
+            HandshakeOutcome::TimedOut => io::ErrorKind::TimedOut,
+            HandshakeOutcome::Incompatible | HandshakeOutcome::Certificate | HandshakeOutcome::Protocol => {
+                io::ErrorKind::InvalidData
+            }
+            HandshakeOutcome::ClientAborted if original == io::ErrorKind::Other => io::ErrorKind::UnexpectedEof,
+            _ => original,
+        }
+    }
+}
+
+impl fmt::Display for HandshakeOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A completed TLS handshake attempt, as given to the `on_handshake` callback of the TLS acceptors.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct HandshakeEvent<'a> {
+    /// The address of the peer, if known, as given by [`peer_addr`](super::accept::peer_addr).
+    ///
+    /// When behind a [`ProxyProtocolAcceptor`](super::accept::ProxyProtocolAcceptor),
+    /// this is the address of the load balancer rather than the client.
+    pub peer: Option<SocketAddr>,
+
+    /// The categorized outcome of the handshake.
+    pub outcome: HandshakeOutcome,
+
+    /// How long the handshake took, or until it failed.
+    pub duration: Duration,
+
+    /// The error returned by the acceptor, if the handshake failed.
+    pub error: Option<&'a io::Error>,
+}
+
+/// Changes the kind of the error, keeping the original error as the source.
+fn with_kind(error: io::Error, kind: io::ErrorKind) -> io::Error {
+    if error.kind() == kind {
+        return error;
+    }
+
+    match error.into_inner() {
+        Some(inner) => io::Error::new(kind, inner),
+        None => io::Error::from(kind),
+    }
+}
+
+pub(crate) type OnHandshake = Arc<dyn Fn(&HandshakeEvent<'_>) + Send + Sync>;
+
+/// Reports the handshake to the callback, if any, and passes the result through
+/// with the error kind adjusted to the outcome.
+pub(crate) fn report<T>(
+    on_handshake: Option<&OnHandshake>,
+    peer: Option<SocketAddr>,
+    duration: Duration,
+    result: Result<T, (HandshakeOutcome, io::Error)>,
+) -> io::Result<T> {
+    let (outcome, result) = match result {
+        Ok(value) => (HandshakeOutcome::Success, Ok(value)),
+        Err((outcome, error)) => {
+            let kind = outcome.error_kind(error.kind());
+            (outcome, Err(with_kind(error, kind)))
+        }
+    };
+
+    if let Some(on_handshake) = on_handshake {
+        on_handshake(&HandshakeEvent {
+            peer,
+            outcome,
+            duration,
+            error: result.as_ref().err(),
+        });
+    }
+
+    result
+}
@@ -0,0 +1,229 @@
+//! Tracking of connection activity for the [idle timeout](super::Server::idle_timeout).
+
+use std::{
+    io,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    time::Instant,
+};
+
+/// Activity of a single connection, shared between its stream, requests and the connection task.
+pub(super) struct IdleState {
+    start: Instant,
+
+    /// Milliseconds since `start` of the last read or write.
+    last_activity: AtomicU64,
+
+    /// Number of requests waiting on a response, which keep the connection active.
+    active_requests: AtomicUsize,
+
+    /// Set once the connection carries a tunnel or WebSocket, which are not subject to the timeout.
+    exempt: AtomicBool,
+}
+
+impl IdleState {
+    pub(super) fn new() -> Arc<Self> {
+        Arc::new(IdleState {
+            start: Instant::now(),
+            last_activity: AtomicU64::new(0),
+            active_requests: AtomicUsize::new(0),
+            exempt: AtomicBool::new(false),
+        })
+    }
+
+    #[inline]
+    fn touch(&self) {
+        let elapsed = self.start.elapsed().as_millis() as u64;
+        self.last_activity.store(elapsed, Ordering::Relaxed);
+    }
+
+    /// Marks the start of a request, which keeps the connection active until the returned guard is dropped.
+    pub(super) fn request<B>(self: &Arc<Self>, req: &http::Request<B>) -> ActiveRequest {
+        self.active_requests.fetch_add(1, Ordering::Relaxed);
+
+        ActiveRequest {
+            state: self.clone(),
+            connect: req.method() == http::Method::CONNECT,
+        }
+    }
+
+    /// Completes once the connection has been idle for the given duration.
+    pub(super) async fn expired(&self, timeout: Duration) {
+        let mut deadline = self.start + timeout;
+
+        loop {
+            tokio::time::sleep_until(deadline).await;
+
+            if self.exempt.load(Ordering::Relaxed) {
+                return std::future::pending().await;
+            }
+
+            let last_activity = self.start + Duration::from_millis(self.last_activity.load(Ordering::Relaxed));
+
+            deadline = match self.active_requests.load(Ordering::Relaxed) {
+                0 if last_activity + timeout <= Instant::now() => return,
+                0 => last_activity + timeout,
+                _ => Instant::now() + timeout,
+            };
+        }
+    }
+}
+
+/// Guard for a request in progress, see [`IdleState::request`].
+pub(super) struct ActiveRequest {
+    state: Arc<IdleState>,
+    connect: bool,
+}
+
+impl ActiveRequest {
+    /// Completes the request with its response.
+    ///
+    /// Responses that upgrade the connection or establish a tunnel, being `101 Switching Protocols`
+    /// or a `2xx` response to `CONNECT` (such as WebSockets over HTTP/2), exempt it from the timeout entirely.
+    pub(super) fn respond<B>(self, resp: &http::Response<B>) {
+        let status = resp.status();
+
+        if status == http::StatusCode::SWITCHING_PROTOCOLS || (self.connect && status.is_success()) {
+            self.state.exempt.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Drop for ActiveRequest {
+    fn drop(&mut self) {
+        self.state.touch();
+        self.state.active_requests.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Stream wrapper recording the time of the last read or write.
+#[pin_project::pin_project]
+pub(super) struct IdleStream<S> {
+    #[pin]
+    inner: S,
+    state: Option<Arc<IdleState>>,
+}
+
+impl<S> IdleStream<S> {
+    pub(super) fn new(inner: S, state: Option<Arc<IdleState>>) -> Self {
+        IdleStream { inner, state }
+    }
+}
+
+impl<S: AsyncRead> AsyncRead for IdleStream<S> {
+    #[inline]
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let this = self.project();
+        let filled = buf.filled().len();
+
+        let res = this.inner.poll_read(cx, buf);
+
+        if let Some(state) = this.state {
+            if buf.filled().len() > filled {
+                state.touch();
+            }
+        }
+
+        res
+    }
+}
+
+impl<S: AsyncWrite> AsyncWrite for IdleStream<S> {
+    #[inline]
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.project();
+        let res = this.inner.poll_write(cx, buf);
+
+        if let (Some(state), Poll::Ready(Ok(n))) = (this.state, &res) {
+            if *n > 0 {
+                state.touch();
+            }
+        }
+
+        res
+    }
+
+    #[inline]
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.project();
+        let res = this.inner.poll_write_vectored(cx, bufs);
+
+        if let (Some(state), Poll::Ready(Ok(n))) = (this.state, &res) {
+            if *n > 0 {
+                state.touch();
+            }
+        }
+
+        res
+    }
+
+    #[inline]
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+
+    #[inline]
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.project().inner.poll_flush(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.project().inner.poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_millis(20);
+
+    /// Completes a request with the given response status, then checks whether the connection expires.
+    async fn expires(req: http::Request<()>, status: http::StatusCode) -> bool {
+        let state = IdleState::new();
+
+        let mut resp = http::Response::new(());
+        *resp.status_mut() = status;
+
+        state.request(&req).respond(&resp);
+
+        tokio::time::timeout(TIMEOUT * 10, state.expired(TIMEOUT)).await.is_ok()
+    }
+
+    #[tokio::test]
+    async fn test_upgrade_exemption() {
+        use http::{Method, Request, StatusCode};
+
+        let ws = || Request::get("/ws").header("upgrade", "websocket").body(()).unwrap();
+        let connect = || Request::builder().method(Method::CONNECT).uri("/ws").body(()).unwrap();
+
+        // only successful upgrades and tunnels are exempt
+        assert!(!expires(ws(), StatusCode::SWITCHING_PROTOCOLS).await);
+        assert!(!expires(connect(), StatusCode::OK).await);
+
+        assert!(expires(ws(), StatusCode::OK).await);
+        assert!(expires(ws(), StatusCode::BAD_REQUEST).await);
+        assert!(expires(connect(), StatusCode::FORBIDDEN).await);
+        assert!(
+            expires(
+                Request::get("/").header("upgrade", "x").body(()).unwrap(),
+                StatusCode::OK
+            )
+            .await
+        );
+    }
+}
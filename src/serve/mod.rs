@@ -6,16 +6,28 @@ pub mod tls_openssl;
 
 pub mod accept;
 
+#[cfg(any(feature = "tls-rustls", feature = "tls-openssl"))]
+pub mod handshake;
+
+mod drain;
+mod idle;
+mod redirect;
+pub use redirect::{redirect_https, HttpsRedirect};
+
 use core::error::Error;
 
-use futures::{stream::FusedStream, FutureExt, Stream, StreamExt};
+use futures::{future::BoxFuture, stream::FusedStream, FutureExt, Stream, StreamExt};
 use hyper::body::Incoming;
 use hyper_util::{
     rt::{TokioExecutor, TokioIo},
-    server::conn::auto::{Builder, Http1Builder, Http2Builder},
+    server::{
+        conn::auto::{Builder, Http1Builder, Http2Builder},
+        graceful::GracefulConnection,
+    },
 };
 
 use std::{
+    convert::Infallible,
     future::Future,
     io::{self},
     net::SocketAddr,
@@ -35,7 +47,20 @@ use tokio::{
 
 use accept::{Accept, DefaultAcceptor};
 
-use crate::service::{MakeService, Service};
+use crate::{
+    error::ClientDisconnected,
+    service::{MakeService, Service, ServiceFuture},
+};
+
+/// Error returned to hyper from the per-request service, which closes the connection.
+#[derive(Debug, thiserror::Error)]
+enum ConnectionError<E> {
+    #[error(transparent)]
+    Service(E),
+
+    #[error("Client Disconnected")]
+    ClientDisconnected,
+}
 
 #[derive(Debug, Default)]
 struct NotifyOnce {
@@ -63,12 +88,20 @@ impl NotifyOnce {
     }
 }
 
+type ShutdownHooks = Mutex<Vec<BoxFuture<'static, ()>>>;
+
 #[derive(Default)]
 struct HandleInner {
     conn_count: AtomicUsize,
+    ready: NotifyOnce,
     shutdown: NotifyOnce,
+    drain: NotifyOnce,
     kill: Notify,
+    abort_streams: NotifyOnce,
     deadline: Mutex<Option<Duration>>,
+    streaming_grace: Mutex<Option<Duration>>,
+    pre_drain: ShutdownHooks,
+    post_drain: ShutdownHooks,
 }
 
 #[derive(Clone, Default)]
@@ -86,9 +119,9 @@ impl Drop for Watcher {
     fn drop(&mut self) {
         let count = self.inner().conn_count.fetch_sub(1, Ordering::SeqCst);
 
-        // if count == 1, the new count is 0, so if shutdown is requested
+        // if count == 1, the new count is 0, so if the server is draining
         // we should kill the server ASAP.
-        if count == 1 && self.inner().shutdown.is_notified() {
+        if count == 1 && self.inner().drain.is_notified() {
             self.inner().kill.notify_waiters();
         }
     }
@@ -103,6 +136,28 @@ impl Handle {
         *self.0.deadline.lock().unwrap() = timeout.into();
     }
 
+    /// Set a grace period for response bodies still streaming once the server begins draining,
+    /// after which they are aborted so their connections can close.
+    ///
+    /// Graceful shutdown lets in-flight responses finish, but long-lived streams such as
+    /// server-sent events may never finish on their own, holding the connection open until the
+    /// [shutdown timeout](Handle::set_shutdown_timeout) forcefully closes it. With a grace period
+    /// shorter than the shutdown timeout, such streams are instead aborted with an error, and the
+    /// shutdown can complete gracefully. Aborted HTTP/1 responses are closed without the final
+    /// chunk and HTTP/2 streams are reset, so clients can tell them apart from complete responses,
+    /// such as a large download that didn't finish in time. Clients of server-sent events
+    /// reconnect as usual.
+    ///
+    /// If set to `None`, streaming bodies are never ended early. This is the default behavior.
+    /// Must be set before the server starts serving.
+    pub fn set_streaming_grace(&self, grace: impl Into<Option<Duration>>) {
+        *self.0.streaming_grace.lock().unwrap() = grace.into();
+    }
+
+    fn streaming_grace(&self) -> Option<Duration> {
+        *self.0.streaming_grace.lock().unwrap()
+    }
+
     /// Initiates a graceful shutdown of the server.
     pub fn shutdown(&self) {
         self.0.shutdown.notify_waiters();
@@ -123,10 +178,47 @@ impl Handle {
         });
     }
 
+    /// Registers a hook to be run once shutdown is requested, but before the server
+    /// stops accepting new connections and begins draining existing ones.
+    ///
+    /// Hooks are run sequentially in the order they were registered. If the handle
+    /// is shared between multiple servers, the hooks are run by the first server to shut down.
+    pub fn on_pre_drain<F>(&self, hook: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        self.0.pre_drain.lock().unwrap().push(Box::pin(hook));
+    }
+
+    /// Registers a hook to be run after all connections have closed (or were killed),
+    /// but before [`Server::serve`] returns.
+    ///
+    /// Hooks are run sequentially in the order they were registered. If the handle
+    /// is shared between multiple servers, the hooks are run by the first server to shut down.
+    pub fn on_post_drain<F>(&self, hook: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        self.0.post_drain.lock().unwrap().push(Box::pin(hook));
+    }
+
     fn shutdown_notified(&self) -> impl Future<Output = ()> + '_ {
         self.0.shutdown.notified()
     }
 
+    fn drain_notified(&self) -> impl Future<Output = ()> + '_ {
+        self.0.drain.notified()
+    }
+
+    async fn run_hooks(hooks: &ShutdownHooks) {
+        // take the hooks so the lock isn't held across await points
+        let hooks = std::mem::take(&mut *hooks.lock().unwrap());
+
+        for hook in hooks {
+            hook.await;
+        }
+    }
+
     fn kill_notified(&self) -> impl Future<Output = ()> + '_ {
         self.0.kill.notified()
     }
@@ -136,33 +228,109 @@ impl Handle {
         Watcher(self.clone())
     }
 
-    async fn wait_internal(&self) {
+    async fn wait_internal(&self) -> ShutdownOutcome {
         if self.0.conn_count.load(Ordering::SeqCst) == 0 {
             self.kill(); // no connections, kill immediately
-            return;
+            return ShutdownOutcome::GRACEFUL;
         }
 
         let deadline = self.0.deadline.lock().unwrap().unwrap_or(Duration::MAX);
 
+        // ends any streaming bodies after the grace period, but never completes itself
+        let abort_streams = async {
+            if let Some(grace) = self.streaming_grace() {
+                tokio::time::sleep(grace).await;
+                self.0.abort_streams.notify_waiters();
+            }
+
+            std::future::pending::<()>().await
+        };
+
         tokio::select! {
             biased;
             _ = self.kill_notified() => {},
-            _ = tokio::time::sleep(deadline) => self.kill(),
+            _ = tokio::time::sleep(deadline) => {},
+            _ = abort_streams => {},
+        }
+
+        // the last `Watcher` decrements the count before notifying, so
+        // any connections remaining at this point are being forcefully closed,
+        // either by the deadline or by an explicit `Handle::kill`
+        let forced_connections = self.0.conn_count.load(Ordering::SeqCst);
+
+        if forced_connections > 0 {
+            self.kill();
+        }
+
+        ShutdownOutcome {
+            graceful: forced_connections == 0,
+            forced_connections,
         }
     }
 
     pub async fn wait(&self) {
         self.kill_notified().await
     }
+
+    /// Waits until the server has bound its listener and is accepting connections.
+    ///
+    /// Resolves immediately if the server is already listening. If binding fails,
+    /// [`Server::serve`] returns the error instead and this never resolves. If the handle
+    /// is shared between multiple servers, this resolves once the first of them is listening.
+    pub async fn wait_ready(&self) {
+        self.0.ready.notified().await
+    }
+}
+
+/// The result of a server shutting down, as returned by [`Server::serve_with_outcome`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShutdownOutcome {
+    /// `true` if all connections closed on their own before the server stopped.
+    pub graceful: bool,
+
+    /// The number of connections that were still open and forcefully closed, either
+    /// due to the [shutdown timeout](Handle::set_shutdown_timeout) or [`Handle::kill`].
+    pub forced_connections: usize,
+}
+
+impl ShutdownOutcome {
+    const GRACEFUL: Self = ShutdownOutcome {
+        graceful: true,
+        forced_connections: 0,
+    };
+}
+
+/// Presets of HTTP/2 settings for common workloads, applied with [`Server::http2_preset`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Http2Preset {
+    /// Hyper's defaults: 1 MiB stream and connection windows, 16 KiB frames,
+    /// 200 concurrent streams and a 400 KiB send buffer per stream.
+    #[default]
+    Default,
+
+    /// For many small, interactive requests such as APIs: the default windows and 16 KiB frames,
+    /// but a 64 KiB send buffer per stream, so responses are flushed to the client sooner
+    /// rather than buffered, and 100 concurrent streams to bound per-connection work.
+    LowLatency,
+
+    /// For large transfers such as file downloads: an adaptive flow control window that grows
+    /// with the measured bandwidth-delay product, 64 KiB frames to reduce framing overhead,
+    /// 400 concurrent streams and a 1 MiB send buffer per stream.
+    HighThroughput,
 }
 
 /// HTTP server.
+///
+/// The executor `E` is used by the HTTP builder to spawn tasks, such as for HTTP/2 streams,
+/// and is a [`LocalExecutor`] for servers created for [`Server::serve_local`].
 #[must_use]
-pub struct Server<A = DefaultAcceptor> {
+pub struct Server<A = DefaultAcceptor, E = TokioExecutor> {
     acceptor: A,
-    builder: Builder<TokioExecutor>,
+    builder: Builder<E>,
     listener: Listener,
     handle: Handle,
+    max_pending_handshakes: usize,
+    idle_timeout: Option<Duration>,
 }
 
 #[derive(Debug)]
@@ -174,28 +342,44 @@ enum Listener {
 impl Server {
     /// Create a server that will bind to provided address.
     pub fn bind(addr: impl IntoIterator<Item = SocketAddr>) -> Self {
-        Self {
-            acceptor: DefaultAcceptor,
-            builder: Builder::new(TokioExecutor::new()),
-            listener: Listener::Bind(addr.into_iter().collect()),
-            handle: Handle::default(),
-        }
+        Server::new(Listener::Bind(addr.into_iter().collect()), TokioExecutor::new())
     }
 
     /// Create a server from existing `std::net::TcpListener`.
     pub fn from_tcp(listener: std::net::TcpListener) -> Self {
+        Server::new(Listener::Std(listener), TokioExecutor::new())
+    }
+}
+
+impl Server<DefaultAcceptor, LocalExecutor> {
+    /// Create a server that will bind to provided address, to be run with [`Server::serve_local`].
+    pub fn bind_local(addr: impl IntoIterator<Item = SocketAddr>) -> Self {
+        Server::new(Listener::Bind(addr.into_iter().collect()), LocalExecutor)
+    }
+
+    /// Create a server from existing `std::net::TcpListener`, to be run with [`Server::serve_local`].
+    pub fn from_tcp_local(listener: std::net::TcpListener) -> Self {
+        Server::new(Listener::Std(listener), LocalExecutor)
+    }
+}
+
+impl<E> Server<DefaultAcceptor, E> {
+    fn new(listener: Listener, executor: E) -> Self {
         Self {
             acceptor: DefaultAcceptor,
-            builder: Builder::new(TokioExecutor::new()),
-            listener: Listener::Std(listener),
+            builder: Builder::new(executor),
+            listener,
             handle: Handle::default(),
+            max_pending_handshakes: usize::MAX,
+            idle_timeout: None,
         }
     }
 }
 
-impl<A> Server<A>
+impl<A, E> Server<A, E>
 where
     A: Clone,
+    E: Clone,
 {
     pub fn rebind(&self, addr: impl IntoIterator<Item = SocketAddr>) -> Self {
         Self {
@@ -203,23 +387,27 @@ where
             builder: self.builder.clone(),
             listener: Listener::Bind(addr.into_iter().collect()),
             handle: self.handle.clone(),
+            max_pending_handshakes: self.max_pending_handshakes,
+            idle_timeout: self.idle_timeout,
         }
     }
 }
 
-impl<A> Server<A> {
+impl<A, E> Server<A, E> {
     /// Overwrite acceptor.
-    pub fn acceptor<Acceptor>(self, acceptor: Acceptor) -> Server<Acceptor> {
+    pub fn acceptor<Acceptor>(self, acceptor: Acceptor) -> Server<Acceptor, E> {
         Server {
             acceptor,
             builder: self.builder,
             listener: self.listener,
             handle: self.handle,
+            max_pending_handshakes: self.max_pending_handshakes,
+            idle_timeout: self.idle_timeout,
         }
     }
 
     /// Map acceptor.
-    pub fn map<Acceptor, F>(self, acceptor: F) -> Server<Acceptor>
+    pub fn map<Acceptor, F>(self, acceptor: F) -> Server<Acceptor, E>
     where
         F: FnOnce(A) -> Acceptor,
     {
@@ -228,6 +416,35 @@ impl<A> Server<A> {
             builder: self.builder,
             listener: self.listener,
             handle: self.handle,
+            max_pending_handshakes: self.max_pending_handshakes,
+            idle_timeout: self.idle_timeout,
+        }
+    }
+
+    /// Binds the server's listener immediately rather than when serving, so that
+    /// [`Server::local_addr`] can be queried beforehand, such as to learn the port
+    /// assigned when binding to port `0`.
+    ///
+    /// Does nothing if the server is already bound, such as with [`Server::from_tcp`].
+    pub fn listen(mut self) -> io::Result<Self> {
+        if let Listener::Bind(ref addr) = self.listener {
+            self.listener = Listener::Std(std::net::TcpListener::bind(&**addr)?);
+        }
+
+        Ok(self)
+    }
+
+    /// Returns the local address the server is bound to.
+    ///
+    /// Servers created with [`Server::bind`] are not bound until serving,
+    /// so [`Server::listen`] must be called first, otherwise this returns an error.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        match self.listener {
+            Listener::Std(ref listener) => listener.local_addr(),
+            Listener::Bind(_) => Err(io::Error::new(
+                io::ErrorKind::NotConnected,
+                "server is not bound yet, use `Server::listen` first",
+            )),
         }
     }
 
@@ -242,25 +459,113 @@ impl<A> Server<A> {
     }
 
     /// Returns a mutable reference to the Http builder.
-    pub fn http_builder(&mut self) -> &mut Builder<TokioExecutor> {
+    pub fn http_builder(&mut self) -> &mut Builder<E> {
         &mut self.builder
     }
 
-    pub fn http1(&mut self) -> Http1Builder<TokioExecutor> {
+    pub fn http1(&mut self) -> Http1Builder<E> {
         self.builder.http1()
     }
 
-    pub fn http2(&mut self) -> Http2Builder<TokioExecutor> {
+    pub fn http2(&mut self) -> Http2Builder<E> {
         self.builder.http2()
     }
 
     pub fn handle(&self) -> Handle {
         self.handle.clone()
     }
+
+    /// Limits how many connections may be in the accept phase at once, such as while
+    /// performing a TLS handshake. Unlimited by default.
+    ///
+    /// While the limit is reached, no new connections are taken from the listener, leaving them
+    /// in the OS backlog until a pending handshake completes or fails. This bounds the memory
+    /// used by a flood of slow handshakes, and should usually be combined with a
+    /// [`TimeoutAcceptor`](accept::TimeoutAcceptor) so stalled handshakes are eventually dropped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max` is zero, as no connection could ever be accepted.
+    pub fn max_pending_handshakes(&mut self, max: usize) -> &mut Self {
+        assert!(max > 0, "max pending handshakes must be greater than zero");
+
+        self.max_pending_handshakes = max;
+        self
+    }
+
+    /// Closes connections once no data has been read or written for the given duration,
+    /// while no requests are waiting on a response. Disabled by default.
+    ///
+    /// This protects against clients that complete the accept phase but then send nothing,
+    /// or stall partway through a request, holding connections open. Unlike the
+    /// [`TimeoutAcceptor`](accept::TimeoutAcceptor), it applies for the lifetime of the connection.
+    ///
+    /// Responses whose bodies pause for longer than the timeout, such as Server-Sent Events without
+    /// a keep-alive, will also be closed. Connections that are successfully upgraded, such as for WebSockets,
+    /// or carry an established `CONNECT` tunnel, including WebSockets over HTTP/2, are exempt from the timeout
+    /// from then on, and should rely on their own keep-alive mechanisms instead.
+    pub fn idle_timeout(&mut self, timeout: impl Into<Option<Duration>>) -> &mut Self {
+        self.idle_timeout = timeout.into();
+        self
+    }
+
+    /// Enables WebSockets over HTTP/2 using the extended `CONNECT` protocol (RFC 8441).
+    ///
+    /// The HTTP/2 path of [`Ws`](crate::ws::Ws) requires this, as without it clients are never
+    /// told they may use extended `CONNECT`, and hyper will not populate the
+    /// [`hyper::ext::Protocol`] request extension that [`Ws`](crate::ws::Ws) checks for.
+    /// HTTP/1.1 WebSocket upgrades are always supported and do not require this.
+    ///
+    /// Routes should also accept `CONNECT` requests, such as via [`Router::ws`](crate::Router::ws).
+    pub fn enable_http2_websockets(&mut self) -> &mut Self {
+        self.builder.http2().enable_connect_protocol();
+        self
+    }
+
+    /// Applies a set of HTTP/2 settings tuned for the given workload, see [`Http2Preset`]
+    /// for what each preset changes.
+    ///
+    /// Settings can still be adjusted afterwards with [`Server::http2`].
+    pub fn http2_preset(&mut self, preset: Http2Preset) -> &mut Self {
+        let (window, frame_size, streams, send_buf, adaptive) = match preset {
+            Http2Preset::Default => (1024 * 1024, 16 * 1024, 200, 400 * 1024, false),
+            Http2Preset::LowLatency => (1024 * 1024, 16 * 1024, 100, 64 * 1024, false),
+            Http2Preset::HighThroughput => (1024 * 1024, 64 * 1024, 400, 1024 * 1024, true),
+        };
+
+        self.builder
+            .http2()
+            // NOTE: setting the window sizes disables the adaptive window, so set them first
+            .initial_stream_window_size(window)
+            .initial_connection_window_size(window)
+            .adaptive_window(adaptive)
+            .max_frame_size(frame_size)
+            .max_concurrent_streams(streams)
+            .max_send_buf_size(send_buf);
+
+        self
+    }
 }
 
 impl<A> Server<A> {
+    /// Serves the given service until shutdown, see [`Server::serve_with_outcome`]
+    /// to also learn if the shutdown was graceful.
     pub async fn serve<M, B>(self, make_service: M) -> io::Result<()>
+    where
+        M: MakeService<SocketAddr, http::Request<Incoming>>,
+        A: Clone + Accept<TcpStream, M::Service, Stream: 'static>,
+        M::Service: 'static,
+        A::Service: 'static
+            + Clone
+            + Service<http::Request<Incoming>, Response = http::Response<B>, Error: Error + Send + Sync + 'static>,
+        B: http_body::Body<Data: Send, Error: Error + Send + Sync + 'static> + Send + 'static,
+    {
+        self.serve_with_outcome(make_service).await.map(|_| ())
+    }
+
+    /// Serves the given service until shutdown, returning whether the shutdown completed
+    /// gracefully or if connections had to be forcefully closed.
+    pub async fn serve_with_outcome<M, B>(self, make_service: M) -> io::Result<ShutdownOutcome>
     where
         // M "creates" a service under the given client address
         M: MakeService<SocketAddr, http::Request<Incoming>>,
@@ -272,16 +577,121 @@ impl<A> Server<A> {
             + Service<http::Request<Incoming>, Response = http::Response<B>, Error: Error + Send + Sync + 'static>,
         // Body requirements
         B: http_body::Body<Data: Send, Error: Error + Send + Sync + 'static> + Send + 'static,
+    {
+        let builder = Arc::new(self.builder.clone());
+        let handle = self.handle.clone();
+        let idle_timeout = self.idle_timeout;
+
+        let spawn = |stream: A::Stream, service: A::Service, socket_addr: SocketAddr, watcher: Watcher| {
+            let builder = builder.clone();
+            let handle = handle.clone();
+
+            // in practice, this should be a single `Arc` clone,
+            // and it allows us to make `call` non-'static, reducing
+            // the number of clones internally.
+            let call = move |req| {
+                let service = service.clone();
+                async move { service.call(req).await }
+            };
+
+            // spawn new task to handle real HTTP connection
+            tokio::spawn(async move {
+                serve_connection(&builder, stream, call, socket_addr, handle, watcher, idle_timeout).await;
+            });
+        };
+
+        self.run(|socket_addr| make_service.make_service(socket_addr), spawn).await
+    }
+}
+
+impl<A> Server<A, LocalExecutor> {
+    /// Serves a `!Send` service until shutdown, running each connection on the current
+    /// [`LocalSet`](tokio::task::LocalSet) with [`spawn_local`](tokio::task::spawn_local).
+    ///
+    /// This allows handlers to hold `!Send` values such as `Rc`-based state, at the cost of
+    /// all connections being driven by a single thread, so CPU-heavy or blocking handlers
+    /// will stall every other connection. For multithreaded servers, prefer [`Server::serve`]
+    /// and share state with `Arc` instead.
+    ///
+    /// The server must be created with [`Server::bind_local`] or [`Server::from_tcp_local`], so that the
+    /// HTTP builder spawns its tasks locally, and the returned future must be run within a
+    /// [`LocalSet`](tokio::task::LocalSet), otherwise it will panic when accepting the first connection.
+    ///
+    /// Because the service is not an ftl [`Service`], which must be `Send`, it is a plain async function
+    /// of the request. Acceptors are given a [`LocalService`] in its place, so any request extensions
+    /// they add, such as the client address from a [`ProxyProtocolAcceptor`](accept::ProxyProtocolAcceptor),
+    /// are still present, as is the client address extension itself.
+    ///
+    /// ```rust,ignore
+    /// let state = Rc::new(RefCell::new(MyState::default()));
+    ///
+    /// let local = tokio::task::LocalSet::new();
+    ///
+    /// local.run_until(Server::bind_local([addr]).serve_local(move |req| {
+    ///     let state = state.clone();
+    ///     async move { Ok::<_, Infallible>(handle(&state, req).await.into_response()) }
+    /// })).await?;
+    /// ```
+    pub async fn serve_local<F, R, B, E>(self, service: F) -> io::Result<ShutdownOutcome>
+    where
+        A: Accept<TcpStream, LocalService, Stream: 'static>,
+        A::Service:
+            Service<http::Request<Incoming>, Response = http::Request<Incoming>, Error = Infallible> + 'static,
+        F: Fn(http::Request<Incoming>) -> R + Clone + 'static,
+        R: Future<Output = Result<http::Response<B>, E>> + 'static,
+        E: Error + Send + Sync + 'static,
+        B: http_body::Body<Data: Send, Error: Error + Send + Sync + 'static> + 'static,
+    {
+        let builder = std::rc::Rc::new(self.builder.clone());
+        let handle = self.handle.clone();
+        let idle_timeout = self.idle_timeout;
+
+        let spawn = |stream: A::Stream, accepted: A::Service, socket_addr: SocketAddr, watcher: Watcher| {
+            let builder = builder.clone();
+            let handle = handle.clone();
+            let accepted = std::rc::Rc::new(accepted);
+            let service = service.clone();
+
+            let call = move |req| {
+                let accepted = accepted.clone();
+                let service = service.clone();
+
+                async move {
+                    let req = match accepted.call(req).await {
+                        Ok(req) => req,
+                        Err(never) => match never {},
+                    };
+
+                    service(req).await
+                }
+            };
+
+            tokio::task::spawn_local(async move {
+                serve_connection(&builder, stream, call, socket_addr, handle, watcher, idle_timeout).await;
+            });
+        };
+
+        self.run(|_| LocalService, spawn).await
+    }
+}
+
+impl<A, E> Server<A, E> {
+    /// Accepts connections until shutdown, passing each accepted connection to `spawn`,
+    /// then waits for existing connections to drain.
+    async fn run<S, F>(self, make_service: impl Fn(SocketAddr) -> S, mut spawn: F) -> io::Result<ShutdownOutcome>
+    where
+        S: Send,
+        A: Accept<TcpStream, S>,
+        F: FnMut(A::Stream, A::Service, SocketAddr, Watcher),
     {
         let Self {
             acceptor,
-            builder,
             listener,
             handle,
+            max_pending_handshakes,
+            ..
         } = self;
 
-        let builder = Arc::new(builder);
-
         #[pin_project::pin_project]
         struct IncomingThrottle {
             #[pin]
@@ -360,6 +770,8 @@ impl<A> Server<A> {
             throttle: None,
         });
 
+        handle.0.ready.notify_waiters();
+
         #[cfg(feature = "unicycle")]
         type FuturesUnordered<F> = unicycle::Unordered<F, unicycle::Futures>;
 
@@ -371,9 +783,21 @@ impl<A> Server<A> {
         let mut accepting = std::pin::pin!(FuturesUnordered::new());
 
         // since this is fused, create the future ahead of time to simplify polling.
-        let mut shutdown = std::pin::pin!(handle.shutdown_notified().fuse());
+        // Pre-drain hooks are run as part of this future, so new connections
+        // are still accepted while they run.
+        let mut shutdown = std::pin::pin!(async {
+            handle.shutdown_notified().await;
+            Handle::run_hooks(&handle.0.pre_drain).await;
+        }
+        .fuse());
 
         loop {
+            // apply backpressure by not polling the listener while too many connections are being accepted.
+            // A `None` future is terminated, so the select will skip it.
+            let mut next = futures::future::OptionFuture::from(
+                (accepting.len() < max_pending_handshakes).then(|| incoming.next()),
+            );
+
             // futures::select! is required over tokio::select! due to the `accepting` stream,
             // which may be empty, but not terminated.
             futures::select_biased! {
@@ -382,82 +806,249 @@ impl<A> Server<A> {
 
                 // NOTE: This needs to come before the `accepting.select_next_some()` branch
                 // to avoid it polling a `None` and being less efficient.
-                res = incoming.next() => match res {
+                res = next => match res {
+                    // skipped while at the limit
+                    None => continue,
+
                     // NOTE: This `None` branch is technically unreachable due to the current implementation.
                     // However, I'd rather keep it around for future-proofing.
-                    None => break,
+                    Some(None) => break,
 
                     // TODO: Add rate limiting of some kind here, or potentially defer that to eBPF.
                     #[allow(clippy::let_unit_value)]
-                    Some((stream, socket_addr)) => _ = accepting.push(FutureWithAssociatedData {
-                        future: acceptor.accept(stream, make_service.make_service(socket_addr)),
+                    Some(Some((stream, socket_addr))) => _ = accepting.push(FutureWithAssociatedData {
+                        future: accept::with_peer_addr(socket_addr, acceptor.accept(stream, make_service(socket_addr))),
                         data: Some((socket_addr, handle.watcher())), // increments the conn count
                     }),
                 },
 
                 accepted = accepting.select_next_some() => match accepted {
-                    (Ok((stream, service)), (socket_addr, watcher)) => {
-                        let builder = builder.clone();
-
-                        // spawn new task to handle real HTTP connection
-                        tokio::spawn(async move {
-                            // NOTE: `conn` technically encompasses a physical connection but can handle multiple HTTP requests, especially
-                            // with HTTP/2. Therefore, you don't have to feel bad if a service spawns its own tasks.
-                            let mut conn = std::pin::pin!(builder.serve_connection_with_upgrades(
-                                TokioIo::new(stream),
-                                hyper::service::service_fn(move |mut req| {
-                                    req.extensions_mut().insert(socket_addr);
-
-                                    // in practice, this should be a single `Arc` clone,
-                                    // and it allows us to make `call` non-'static, reducing
-                                    // the number of clones internally.
-                                    let service = service.clone();
-                                    async move { service.call(req).await }
-                                }),
-                            ));
-
-                            let mut kill = std::pin::pin!(watcher.0.kill_notified());
-
-                            loop {
-                                tokio::select! {
-                                    biased;
-
-                                    _ = &mut kill => break,
-
-                                    res = &mut conn => {
-                                        if let Err(err) = res {
-                                            // honestly, ignore logging hyper errors, so only log if it's not hyper
-                                            if let Err(err) = err.downcast::<hyper::Error>() {
-                                                log::error!("server error: {err:?}");
-                                            }
-                                        }
-
-                                        break; // connection has completed
-                                    },
-
-                                    _ = watcher.0.shutdown_notified() => {
-                                        // tell the connection to shutdown gracefully, then continue
-                                        conn.as_mut().graceful_shutdown();
-
-                                        continue;
-                                    }
-                                }
-                            }
-                        });
-                    },
+                    (Ok((stream, service)), (socket_addr, watcher)) => spawn(stream, service, socket_addr, watcher),
                     _ => continue,
                 },
             }
         }
 
-        handle.wait_internal().await;
+        // tell existing connections to shutdown gracefully
+        handle.0.drain.notify_waiters();
+
+        let outcome = handle.wait_internal().await;
+
+        if !outcome.graceful {
+            log::warn!(
+                "server shutdown forcefully closed {} connections",
+                outcome.forced_connections
+            );
+        }
+
+        Handle::run_hooks(&handle.0.post_drain).await;
+
+        Ok(outcome)
+    }
+}
+
+/// Executor for [`Server::serve_local`], spawning `!Send` tasks onto the current `LocalSet`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LocalExecutor;
+
+impl<F> hyper::rt::Executor<F> for LocalExecutor
+where
+    F: Future + 'static,
+{
+    fn execute(&self, fut: F) {
+        tokio::task::spawn_local(fut);
+    }
+}
+
+/// The service given to acceptors by [`Server::serve_local`] in place of the `!Send` service,
+/// which returns each request as given to it, after any service wrappers from the acceptor
+/// have inserted their request extensions.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LocalService;
+
+impl Service<http::Request<Incoming>> for LocalService {
+    type Response = http::Request<Incoming>;
+    type Error = Infallible;
+
+    #[inline]
+    fn call(&self, req: http::Request<Incoming>) -> impl ServiceFuture<Self::Response, Self::Error> {
+        std::future::ready(Ok(req))
+    }
+}
+
+/// Serves a single connection with the given per-request `call`, until it completes.
+///
+/// `conn` technically encompasses a physical connection but can handle multiple HTTP requests, especially
+/// with HTTP/2. Therefore, you don't have to feel bad if a service spawns its own tasks.
+async fn serve_connection<X, S, F, R, B, E>(
+    builder: &Builder<X>,
+    stream: S,
+    call: F,
+    socket_addr: SocketAddr,
+    handle: Handle,
+    watcher: Watcher,
+    idle_timeout: Option<Duration>,
+) where
+    X: hyper::rt::bounds::Http2ServerConnExec<ConnectionFuture<R>, drain::DrainBody<B>>,
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + 'static,
+    F: Fn(http::Request<Incoming>) -> R,
+    R: Future<Output = Result<http::Response<B>, E>> + 'static,
+    E: Error + Send + Sync + 'static,
+    B: http_body::Body<Data: Send, Error: Error + Send + Sync + 'static> + 'static,
+{
+    let idle = idle_timeout.map(|_| idle::IdleState::new());
+
+    let conn = builder.serve_connection_with_upgrades(
+        TokioIo::new(idle::IdleStream::new(stream, idle.clone())),
+        ConnectionService {
+            call,
+            socket_addr,
+            // read once per connection rather than for every response
+            drain: handle.streaming_grace().is_some().then_some(handle),
+            idle: idle.clone(),
+        },
+    );
+
+    drive_connection(conn, watcher, idle.zip(idle_timeout)).await;
+}
+
+/// The per-request service given to hyper by [`serve_connection`].
+struct ConnectionService<F> {
+    call: F,
+    socket_addr: SocketAddr,
+    /// The server handle, if a [streaming grace](Handle::set_streaming_grace) is configured.
+    drain: Option<Handle>,
+    idle: Option<Arc<idle::IdleState>>,
+}
+
+impl<F, R, B, E> hyper::service::Service<http::Request<Incoming>> for ConnectionService<F>
+where
+    F: Fn(http::Request<Incoming>) -> R,
+    R: Future<Output = Result<http::Response<B>, E>>,
+{
+    type Response = http::Response<drain::DrainBody<B>>;
+    type Error = ConnectionError<E>;
+    type Future = ConnectionFuture<R>;
+
+    fn call(&self, mut req: http::Request<Incoming>) -> Self::Future {
+        req.extensions_mut().insert(self.socket_addr);
+
+        ConnectionFuture {
+            active: self.idle.as_ref().map(|idle| idle.request(&req)),
+            future: (self.call)(req),
+            drain: self.drain.clone(),
+        }
+    }
+}
+
+#[pin_project::pin_project]
+struct ConnectionFuture<R> {
+    #[pin]
+    future: R,
+    drain: Option<Handle>,
+    /// Keeps the connection from being idle until the response is ready.
+    active: Option<idle::ActiveRequest>,
+}
+
+impl<R, B, E> Future for ConnectionFuture<R>
+where
+    R: Future<Output = Result<http::Response<B>, E>>,
+{
+    type Output = Result<http::Response<drain::DrainBody<B>>, ConnectionError<E>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        let res = std::task::ready!(this.future.poll(cx));
+
+        if let (Some(active), Ok(resp)) = (this.active.take(), &res) {
+            active.respond(resp);
+        }
+
+        Poll::Ready(check_response(res, this.drain))
+    }
+}
+
+/// Converts the service result for hyper, closing the connection if the client has gone away.
+fn check_response<B, E>(
+    res: Result<http::Response<B>, E>,
+    drain: &Option<Handle>,
+) -> Result<http::Response<drain::DrainBody<B>>, ConnectionError<E>> {
+    match res {
+        // the client has gone away, so drop the response and close the connection
+        Ok(resp) if resp.extensions().get::<ClientDisconnected>().is_some() => {
+            Err(ConnectionError::ClientDisconnected)
+        }
+        Ok(resp) => Ok(resp.map(|body| drain::DrainBody::new(body, drain.clone()))),
+        Err(err) => Err(ConnectionError::Service(err)),
+    }
+}
+
+/// Drives the connection to completion, shutting it down gracefully when the server is draining,
+/// or closing it once idle for the [idle timeout](Server::idle_timeout).
+async fn drive_connection<C>(conn: C, watcher: Watcher, idle: Option<(Arc<idle::IdleState>, Duration)>)
+where
+    C: GracefulConnection<Error = Box<dyn Error + Send + Sync>>,
+{
+    let mut conn = std::pin::pin!(conn);
+    let mut kill = std::pin::pin!(watcher.0.kill_notified());
+
+    let mut idle = std::pin::pin!(async {
+        match idle {
+            Some((ref state, timeout)) => state.expired(timeout).await,
+            None => std::future::pending().await,
+        }
+    });
+
+    // the drain notification persists, so only act on it once
+    let mut draining = false;
+
+    loop {
+        tokio::select! {
+            biased;
+
+            _ = &mut kill => break,
+
+            _ = &mut idle => {
+                log::debug!("closing idle connection");
+                break;
+            },
+
+            res = &mut conn => {
+                if let Err(err) = res {
+                    // honestly, ignore logging hyper errors, so only log if it's not hyper
+                    if let Err(err) = err.downcast::<hyper::Error>() {
+                        match err.downcast::<io::Error>() {
+                            Ok(err) if crate::error::is_io_disconnect(&err) => {
+                                log::debug!("client disconnected: {err}");
+                            }
+                            Ok(err) => log::error!("server error: {err:?}"),
+                            Err(err) => log::error!("server error: {err:?}"),
+                        }
+                    }
+                }
+
+                break; // connection has completed
+            },
+
+            _ = watcher.0.drain_notified(), if !draining => {
+                // tell the connection to shutdown gracefully, then continue
+                conn.as_mut().graceful_shutdown();
+                draining = true;
 
-        Ok(())
+                continue;
+            }
+        }
     }
 }
 
 use std::path::Path;
 
+/// Common interface for TLS configurations.
+///
+/// Certificates are always treated as a full chain, with the leaf certificate first,
+/// followed by any intermediate certificates in order towards the root. This applies to
+/// both DER chains and PEM data or files containing multiple certificates.
 #[allow(async_fn_in_trait)]
 pub trait TlsConfig: Sized + core::fmt::Debug {
     type Error;
@@ -466,7 +1057,7 @@ pub trait TlsConfig: Sized + core::fmt::Debug {
 
     /// Create config from DER-encoded data.
     ///
-    /// The certificate must be DER-encoded X.509.
+    /// The certificate chain must be DER-encoded X.509, leaf first.
     ///
     /// The private key must be DER-encoded ASN.1 in either PKCS#8 or PKCS#1 format.
     async fn from_der(cert: Self::DerCertChain, key: Vec<u8>) -> Result<Self, Self::Error>;
@@ -483,7 +1074,7 @@ pub trait TlsConfig: Sized + core::fmt::Debug {
 
     /// Reload config from DER-encoded data.
     ///
-    /// The certificate must be DER-encoded X.509.
+    /// The certificate chain must be DER-encoded X.509, leaf first.
     ///
     /// The private key must be DER-encoded ASN.1 in either PKCS#8 or PKCS#1 format.
     async fn reload_from_der(&self, cert: Self::DerCertChain, key: Vec<u8>) -> Result<(), Self::Error>;
@@ -512,3 +1103,444 @@ pub trait TlsConfig: Sized + core::fmt::Debug {
         key: impl AsRef<Path>,
     ) -> Result<(), Self::Error>;
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use crate::{
+        extract::State,
+        layers::{cloneable::Cloneable, convert_body::ConvertBody},
+        Layer, Router,
+    };
+
+    use super::{Server, ShutdownOutcome};
+
+    #[tokio::test]
+    async fn test_shutdown_hook_ordering() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let server = Server::from_tcp(listener);
+        let handle = server.handle();
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+
+        for (name, post) in [("pre1", false), ("post1", true), ("pre2", false), ("post2", true)] {
+            let events = events.clone();
+            let hook = async move { events.lock().unwrap().push(name) };
+
+            match post {
+                false => handle.on_pre_drain(hook),
+                true => handle.on_post_drain(hook),
+            }
+        }
+
+        let mut router = Router::<(), crate::Response>::with_state(());
+        router.get("/", || async { "Hello" });
+
+        let service = Cloneable::default().layer(ConvertBody::default().layer(router));
+
+        let serving = tokio::spawn(server.serve(service));
+
+        handle.shutdown();
+        serving.await.unwrap().unwrap();
+
+        events.lock().unwrap().push("done");
+
+        assert_eq!(*events.lock().unwrap(), ["pre1", "pre2", "post1", "post2", "done"]);
+    }
+
+    #[tokio::test]
+    async fn test_wait_ready() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let server = Server::bind(["127.0.0.1:0".parse().unwrap()]).listen().unwrap();
+        let addr = server.local_addr().unwrap();
+        let handle = server.handle();
+
+        let mut router = Router::<(), crate::Response>::with_state(());
+        router.get("/", || async { "Hello" });
+
+        let service = Cloneable::default().layer(ConvertBody::default().layer(router));
+
+        let serving = tokio::spawn(server.serve(service));
+
+        handle.wait_ready().await;
+        handle.wait_ready().await; // already ready
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream.write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n").await.unwrap();
+
+        let mut resp = String::new();
+        stream.read_to_string(&mut resp).await.unwrap();
+
+        assert!(resp.starts_with("HTTP/1.1 200 OK"));
+
+        handle.shutdown();
+        serving.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_outcome() {
+        use tokio::io::AsyncWriteExt;
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = Server::from_tcp(listener);
+        let handle = server.handle();
+
+        handle.set_shutdown_timeout(Duration::from_millis(50));
+
+        let started = Arc::new(tokio::sync::Notify::new());
+
+        let mut router = Router::<_, crate::Response>::with_state(started.clone());
+        router.get("/slow", |State(started): State<Arc<tokio::sync::Notify>>| async move {
+            started.notify_one();
+            tokio::time::sleep(Duration::from_secs(60)).await;
+        });
+
+        let service = Cloneable::default().layer(ConvertBody::default().layer(router));
+
+        let serving = tokio::spawn(server.serve_with_outcome(service));
+
+        // keep a request in-flight past the shutdown timeout
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream.write_all(b"GET /slow HTTP/1.1\r\nHost: localhost\r\n\r\n").await.unwrap();
+        started.notified().await;
+
+        handle.shutdown();
+
+        let outcome = serving.await.unwrap().unwrap();
+
+        assert_eq!(
+            outcome,
+            ShutdownOutcome {
+                graceful: false,
+                forced_connections: 1
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_streaming_grace() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let server = Server::bind(["127.0.0.1:0".parse().unwrap()]).listen().unwrap();
+        let addr = server.local_addr().unwrap();
+        let handle = server.handle();
+
+        handle.set_shutdown_timeout(Duration::from_secs(5));
+        handle.set_streaming_grace(Duration::from_millis(50));
+
+        let mut router = Router::<(), crate::Response>::with_state(());
+        router.get("/events", || async {
+            // an endless stream, such as server-sent events
+            crate::body::Body::stream(futures::stream::unfold((), |()| async {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+                Some((Ok(http_body::Frame::data(bytes::Bytes::from_static(b"tick\n"))), ()))
+            }))
+        });
+
+        let service = Cloneable::default().layer(ConvertBody::default().layer(router));
+
+        let serving = tokio::spawn(server.serve_with_outcome(service));
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream.write_all(b"GET /events HTTP/1.1\r\nHost: localhost\r\n\r\n").await.unwrap();
+
+        let mut buf = [0; 256];
+        let n = stream.read(&mut buf).await.unwrap();
+        assert!(buf[..n].starts_with(b"HTTP/1.1 200 OK"));
+
+        let started = std::time::Instant::now();
+        handle.shutdown();
+
+        // the stream is ended after the grace period, well before the shutdown timeout
+        let outcome = tokio::time::timeout(Duration::from_secs(2), serving).await.unwrap().unwrap().unwrap();
+        assert_eq!(outcome, ShutdownOutcome::GRACEFUL);
+        assert!(started.elapsed() >= Duration::from_millis(50));
+
+        // and aborted without the final chunk, so it isn't mistaken for a complete response
+        let mut rest = Vec::new();
+        _ = stream.read_to_end(&mut rest).await;
+        assert!(!rest.ends_with(b"0\r\n\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_listen_local_addr() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let server = Server::bind(["127.0.0.1:0".parse().unwrap()]);
+        assert!(server.local_addr().is_err());
+
+        let server = server.listen().unwrap();
+        let addr = server.local_addr().unwrap();
+        assert_ne!(addr.port(), 0);
+
+        let handle = server.handle();
+
+        let mut router = Router::<(), crate::Response>::with_state(());
+        router.get("/", || async { "Hello" });
+
+        let service = Cloneable::default().layer(ConvertBody::default().layer(router));
+
+        let serving = tokio::spawn(server.serve(service));
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream.write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n").await.unwrap();
+
+        let mut resp = String::new();
+        stream.read_to_string(&mut resp).await.unwrap();
+        assert!(resp.starts_with("HTTP/1.1 200 OK"));
+        assert!(resp.ends_with("Hello"));
+
+        handle.shutdown();
+        serving.await.unwrap().unwrap();
+    }
+
+    #[test]
+    #[should_panic = "max pending handshakes must be greater than zero"]
+    fn test_zero_pending_handshakes() {
+        Server::bind(["127.0.0.1:0".parse().unwrap()]).max_pending_handshakes(0);
+    }
+
+    #[tokio::test]
+    async fn test_max_pending_handshakes() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio::net::TcpStream;
+
+        use crate::serve::accept::Accept;
+
+        /// Simulates a slow TLS handshake, tracking the peak number of concurrent handshakes.
+        #[derive(Clone, Default)]
+        struct SlowAcceptor {
+            pending: Arc<AtomicUsize>,
+            peak: Arc<AtomicUsize>,
+        }
+
+        impl<S: Send> Accept<TcpStream, S> for SlowAcceptor {
+            type Stream = TcpStream;
+            type Service = S;
+
+            fn accept(
+                &self,
+                stream: TcpStream,
+                service: S,
+            ) -> impl std::future::Future<Output = std::io::Result<(TcpStream, S)>> + Send {
+                async move {
+                    let pending = self.pending.fetch_add(1, Ordering::SeqCst) + 1;
+                    self.peak.fetch_max(pending, Ordering::SeqCst);
+
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+
+                    self.pending.fetch_sub(1, Ordering::SeqCst);
+                    Ok((stream, service))
+                }
+            }
+        }
+
+        let acceptor = SlowAcceptor::default();
+        let peak = acceptor.peak.clone();
+
+        let mut server = Server::bind(["127.0.0.1:0".parse().unwrap()]).listen().unwrap().acceptor(acceptor);
+        server.max_pending_handshakes(2);
+
+        let addr = server.local_addr().unwrap();
+        let handle = server.handle();
+
+        let mut router = Router::<(), crate::Response>::with_state(());
+        router.get("/", || async { "Hello" });
+
+        let service = Cloneable::default().layer(ConvertBody::default().layer(router));
+
+        let serving = tokio::spawn(server.serve(service));
+
+        let clients = (0..10).map(|_| async move {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream.write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n").await.unwrap();
+
+            let mut resp = String::new();
+            stream.read_to_string(&mut resp).await.unwrap();
+            assert!(resp.ends_with("Hello"));
+        });
+
+        futures::future::join_all(clients).await;
+
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+
+        handle.shutdown();
+        serving.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_serve_local() {
+        use std::{cell::Cell, convert::Infallible, net::SocketAddr, rc::Rc};
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        use crate::IntoResponse;
+
+        let mut server = Server::bind_local(["127.0.0.1:0".parse().unwrap()])
+            .acceptor(super::accept::ProxyProtocolAcceptor(super::DefaultAcceptor))
+            .listen()
+            .unwrap();
+        let addr = server.local_addr().unwrap();
+        let handle = server.handle();
+
+        // the configured builder is used
+        server.http1().title_case_headers(true);
+
+        // `Rc<Cell<_>>` is neither `Send` nor `Sync`
+        let hits = Rc::new(Cell::new(0u32));
+
+        let local = tokio::task::LocalSet::new();
+
+        let serving = local.spawn_local(server.serve_local({
+            let hits = hits.clone();
+
+            move |req: http::Request<hyper::body::Incoming>| {
+                let hits = hits.clone();
+                let client = req.extensions().get::<SocketAddr>().copied();
+
+                async move {
+                    tokio::task::yield_now().await;
+                    hits.set(hits.get() + 1);
+
+                    Ok::<_, Infallible>(format!("hit {} from {}", hits.get(), client.unwrap()).into_response())
+                }
+            }
+        }));
+
+        local
+            .run_until(async move {
+                for i in 1..=3 {
+                    let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+                    stream.write_all(b"PROXY TCP4 192.0.2.1 198.51.100.1 56324 443\r\n").await.unwrap();
+                    stream
+                        .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+                        .await
+                        .unwrap();
+
+                    let mut resp = String::new();
+                    stream.read_to_string(&mut resp).await.unwrap();
+                    assert!(resp.contains("\r\nContent-Length: "), "{resp}");
+                    assert!(resp.ends_with(&format!("hit {i} from 192.0.2.1:56324")));
+                }
+
+                handle.shutdown();
+                assert!(serving.await.unwrap().unwrap().graceful);
+            })
+            .await;
+
+        assert_eq!(hits.get(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_idle_timeout() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let mut server = Server::bind(["127.0.0.1:0".parse().unwrap()]).listen().unwrap();
+        let addr = server.local_addr().unwrap();
+        let handle = server.handle();
+
+        server.idle_timeout(Duration::from_millis(100));
+
+        let mut router = Router::<(), crate::Response>::with_state(());
+        router.get("/slow", || async {
+            // longer than the idle timeout, but the request is in-flight
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            "Hello"
+        });
+
+        let service = Cloneable::default().layer(ConvertBody::default().layer(router));
+
+        let serving = tokio::spawn(server.serve(service));
+
+        handle.wait_ready().await;
+
+        // connections that never send anything are closed
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let mut buf = Vec::new();
+        let read = tokio::time::timeout(Duration::from_secs(5), stream.read_to_end(&mut buf)).await;
+        assert!(matches!(read, Ok(Ok(0))));
+
+        // keep-alive connections are closed once idle after responding
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream.write_all(b"GET /slow HTTP/1.1\r\nHost: localhost\r\n\r\n").await.unwrap();
+
+        let mut resp = String::new();
+        let read = tokio::time::timeout(Duration::from_secs(5), stream.read_to_string(&mut resp)).await;
+        assert!(matches!(read, Ok(Ok(_))));
+        assert!(resp.starts_with("HTTP/1.1 200 OK"));
+        assert!(resp.ends_with("Hello"));
+
+        handle.shutdown();
+        serving.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_idle_timeout_upgrades() {
+        use futures::StreamExt;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let mut server = Server::bind(["127.0.0.1:0".parse().unwrap()]).listen().unwrap();
+        let addr = server.local_addr().unwrap();
+        let handle = server.handle();
+
+        server.idle_timeout(Duration::from_millis(100));
+
+        let mut router = Router::<(), crate::Response>::with_state(());
+        router.get("/", || async { "Hello" });
+        router.get("/ws", |ws: crate::ws::Ws| async move {
+            ws.on_upgrade(|socket| async move {
+                if let Ok(mut socket) = socket {
+                    while let Some(Ok(_)) = socket.next().await {}
+                }
+            })
+        });
+
+        let service = Cloneable::default().layer(ConvertBody::default().layer(router));
+
+        let serving = tokio::spawn(server.serve(service));
+
+        handle.wait_ready().await;
+
+        // an `Upgrade` header alone does not exempt the connection
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream.write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nUpgrade: x\r\n\r\n").await.unwrap();
+
+        let mut resp = String::new();
+        let read = tokio::time::timeout(Duration::from_secs(5), stream.read_to_string(&mut resp)).await;
+        assert!(matches!(read, Ok(Ok(_))));
+        assert!(resp.starts_with("HTTP/1.1 200 OK"), "{resp}");
+
+        // upgraded WebSocket connections are kept open, see `idle::tests` for the exemption itself,
+        // as HTTP/1.1 connections are handed off once upgraded
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(
+                b"GET /ws HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+                  Sec-WebSocket-Version: 13\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\r\n",
+            )
+            .await
+            .unwrap();
+
+        let mut resp = Vec::new();
+        while !resp.ends_with(b"\r\n\r\n") {
+            assert_ne!(stream.read_buf(&mut resp).await.unwrap(), 0);
+        }
+        assert!(resp.starts_with(b"HTTP/1.1 101 Switching Protocols"));
+
+        tokio::time::sleep(Duration::from_millis(300)).await;
+
+        let mut buf = [0; 1];
+        let read = tokio::time::timeout(Duration::from_millis(50), stream.read(&mut buf)).await;
+        assert!(read.is_err(), "upgraded connection was closed: {read:?}");
+
+        drop(stream);
+        handle.shutdown();
+        serving.await.unwrap().unwrap();
+    }
+}
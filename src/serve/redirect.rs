@@ -0,0 +1,243 @@
+use std::{convert::Infallible, fmt::Write, future::Future, io, net::SocketAddr, str::FromStr};
+
+use bytes::Bytes;
+use futures::future::Either;
+use http::{uri::Authority, HeaderMap, HeaderName, StatusCode};
+use http_body_util::Empty;
+use hyper_util::{rt::TokioExecutor, server::conn::auto::Builder};
+
+use super::{accept::DefaultAcceptor, Handle, Listener, Server};
+use crate::{rewrite::RewriteService, service::ServiceFuture, RequestParts, Service};
+
+const X_FORWARDED_HOST: HeaderName = HeaderName::from_static("x-forwarded-host");
+const X_FORWARDED_PROTO: HeaderName = HeaderName::from_static("x-forwarded-proto");
+const X_FORWARDED_PORT: HeaderName = HeaderName::from_static("x-forwarded-port");
+
+/// Serves plain HTTP on the given address, redirecting every request to the same
+/// path and query on the HTTPS origin at `https_port` with a `308 Permanent Redirect`.
+///
+/// The host is taken from the `Host` header or request URI, and any port given with it
+/// is replaced by `https_port`, which is omitted from the redirect if it's `443`.
+/// Forwarded headers are ignored, see [`HttpsRedirect`] to trust them when behind a reverse proxy.
+///
+/// The redirect server shares the given [`Handle`], so it shuts down alongside the HTTPS server.
+pub fn redirect_https(
+    addr: impl IntoIterator<Item = SocketAddr>,
+    https_port: u16,
+    handle: &Handle,
+) -> impl Future<Output = io::Result<()>> + Send + 'static {
+    HttpsRedirect::new(https_port).serve(addr, handle)
+}
+
+/// Configurable HTTP to HTTPS redirect server, see [`redirect_https`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[must_use]
+pub struct HttpsRedirect {
+    https_port: u16,
+    trust_forwarded: bool,
+}
+
+impl HttpsRedirect {
+    /// Creates a new redirect server configuration, redirecting to the HTTPS origin at `https_port`.
+    pub const fn new(https_port: u16) -> Self {
+        HttpsRedirect {
+            https_port,
+            trust_forwarded: false,
+        }
+    }
+
+    /// Sets whether to trust the `X-Forwarded-Host`, `X-Forwarded-Proto` and `X-Forwarded-Port`
+    /// headers, `false` by default.
+    ///
+    /// When enabled, the host is taken from `X-Forwarded-Host` if present, and requests that the proxy
+    /// already received over HTTPS, by `X-Forwarded-Proto: https` or an `X-Forwarded-Port` equal to the
+    /// HTTPS port, are answered with `421 Misdirected Request` rather than redirecting them in a loop.
+    ///
+    /// # Security
+    ///
+    /// Only enable this when behind a reverse proxy that sets or strips these headers, otherwise
+    /// any client can redirect users to an arbitrary host through links to this server.
+    pub const fn trust_forwarded_headers(mut self, trust: bool) -> Self {
+        self.trust_forwarded = trust;
+        self
+    }
+
+    /// Serves plain HTTP on the given address with this configuration, sharing the given [`Handle`].
+    pub fn serve(
+        self,
+        addr: impl IntoIterator<Item = SocketAddr>,
+        handle: &Handle,
+    ) -> impl Future<Output = io::Result<()>> + Send + 'static {
+        let server = Server {
+            acceptor: DefaultAcceptor,
+            builder: Builder::new(TokioExecutor::new()),
+            listener: Listener::Bind(addr.into_iter().collect()),
+            handle: handle.clone(),
+            max_pending_handshakes: usize::MAX,
+            idle_timeout: None,
+        };
+
+        server.serve(self.service())
+    }
+
+    fn service(self) -> RedirectService<impl Fn(&RequestParts) -> String + Clone + Send + Sync + 'static> {
+        RedirectService {
+            config: self,
+            rewrite: RewriteService::permanent_redirect(move |parts| self.location(parts)),
+        }
+    }
+
+    /// Whether the proxy received the request over HTTPS already, as per the forwarded headers.
+    fn is_forwarded_https(&self, headers: &HeaderMap) -> bool {
+        let first = |name| {
+            let value = headers.get(name)?.to_str().ok()?;
+            Some(value.split(',').next()?.trim())
+        };
+
+        let proto = first(X_FORWARDED_PROTO).is_some_and(|proto| proto.eq_ignore_ascii_case("https"));
+        let port = first(X_FORWARDED_PORT).and_then(|port| port.parse::<u16>().ok()) == Some(self.https_port);
+
+        proto || port
+    }
+
+    fn location(&self, parts: &RequestParts) -> String {
+        let forwarded_host = match self.trust_forwarded {
+            true => parts
+                .headers
+                .get(X_FORWARDED_HOST)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| Authority::from_str(value.split(',').next()?.trim()).ok()),
+            false => None,
+        };
+
+        // `RewriteService` inserts the authority parsed from the `Host` header or URI
+        let authority = forwarded_host.as_ref().or_else(|| parts.extensions.get::<Authority>());
+
+        // HTTP/1.1 requires a `Host` header, so this fallback is only for very old or broken clients
+        let host = authority.map_or("localhost", Authority::host);
+
+        let path_and_query = parts.uri.path_and_query().map_or("/", |pq| pq.as_str());
+
+        let mut location = String::with_capacity(16 + host.len() + path_and_query.len());
+
+        location.push_str("https://");
+        location.push_str(host);
+
+        if self.https_port != 443 {
+            _ = write!(location, ":{}", self.https_port);
+        }
+
+        location.push_str(path_and_query);
+
+        location
+    }
+}
+
+/// Redirects with the [`RewriteService`], except for requests already received over HTTPS.
+#[derive(Clone)]
+struct RedirectService<F> {
+    config: HttpsRedirect,
+    rewrite: RewriteService<F>,
+}
+
+impl<F, B> Service<http::Request<B>> for RedirectService<F>
+where
+    F: Fn(&RequestParts) -> String + Clone + Send + Sync + 'static,
+{
+    type Response = http::Response<Empty<Bytes>>;
+    type Error = Infallible;
+
+    #[inline]
+    fn call(&self, req: http::Request<B>) -> impl ServiceFuture<Self::Response, Self::Error> {
+        if self.config.trust_forwarded && self.config.is_forwarded_https(req.headers()) {
+            let mut resp = http::Response::new(Empty::new());
+            *resp.status_mut() = StatusCode::MISDIRECTED_REQUEST;
+
+            return Either::Left(std::future::ready(Ok(resp)));
+        }
+
+        Either::Right(self.rewrite.call(req))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::header::LOCATION;
+
+    use super::*;
+
+    async fn redirect(config: HttpsRedirect, headers: &[(&'static str, &'static str)]) -> (StatusCode, String) {
+        let mut req = http::Request::get("/path?query=1");
+
+        for (name, value) in headers {
+            req = req.header(*name, *value);
+        }
+
+        let resp = config.service().call(req.body(()).unwrap()).await.unwrap();
+        let location = resp.headers().get(LOCATION).map(|l| l.to_str().unwrap().to_owned());
+
+        (resp.status(), location.unwrap_or_default())
+    }
+
+    #[tokio::test]
+    async fn test_redirect() {
+        let config = HttpsRedirect::new(443);
+
+        assert_eq!(
+            redirect(config, &[("host", "example.com:80")]).await,
+            (
+                StatusCode::PERMANENT_REDIRECT,
+                "https://example.com/path?query=1".to_owned()
+            )
+        );
+
+        let config = HttpsRedirect::new(8443);
+
+        assert_eq!(
+            redirect(config, &[("host", "example.com")]).await,
+            (
+                StatusCode::PERMANENT_REDIRECT,
+                "https://example.com:8443/path?query=1".to_owned()
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_redirect_forwarded() {
+        // attacker-supplied forwarded headers are ignored by default
+        let headers = [
+            ("host", "example.com"),
+            ("x-forwarded-host", "evil.example"),
+            ("x-forwarded-proto", "https"),
+        ];
+
+        assert_eq!(
+            redirect(HttpsRedirect::new(443), &headers).await,
+            (
+                StatusCode::PERMANENT_REDIRECT,
+                "https://example.com/path?query=1".to_owned()
+            )
+        );
+
+        let config = HttpsRedirect::new(443).trust_forwarded_headers(true);
+
+        assert_eq!(
+            redirect(
+                config,
+                &[("host", "internal:8080"), ("x-forwarded-host", "example.com")]
+            )
+            .await,
+            (
+                StatusCode::PERMANENT_REDIRECT,
+                "https://example.com/path?query=1".to_owned()
+            )
+        );
+
+        // already HTTPS at the proxy, so redirecting would loop
+        assert_eq!(redirect(config, &headers).await.0, StatusCode::MISDIRECTED_REQUEST);
+        assert_eq!(
+            redirect(config, &[("host", "example.com"), ("x-forwarded-port", "443")]).await.0,
+            StatusCode::MISDIRECTED_REQUEST
+        );
+    }
+}
@@ -1,19 +1,20 @@
-use super::accept::{Accept, DefaultAcceptor};
+use super::accept::{self, Accept, DefaultAcceptor};
+use super::handshake::{self, HandshakeEvent, HandshakeOutcome, OnHandshake};
 use crate::error::io_other;
 
 use arc_swap::ArcSwap;
 use std::future::{poll_fn, Future};
 use std::io::ErrorKind;
 use std::pin::Pin;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use std::{fmt, io, path::Path, sync::Arc};
 use tokio::io::{AsyncRead, AsyncWrite};
 
 use openssl::{
-    pkey::PKey,
+    pkey::{PKey, Private},
     ssl::{
-        self, AlpnError, Error as OpenSSLError, Ssl, SslAcceptor, SslAcceptorBuilder, SslFiletype, SslMethod,
-        SslRef,
+        self, AlpnError, Error as OpenSSLError, ErrorCode, Ssl, SslAcceptor, SslAcceptorBuilder, SslFiletype,
+        SslMethod, SslRef,
     },
     x509::X509,
 };
@@ -25,6 +26,7 @@ pub struct OpenSSLAcceptor<A = DefaultAcceptor> {
     inner: A,
     config: OpenSSLConfig,
     handshake_timeout: Duration,
+    on_handshake: Option<OnHandshake>,
 }
 
 impl OpenSSLAcceptor {
@@ -43,23 +45,35 @@ impl OpenSSLAcceptor {
             inner: DefaultAcceptor,
             config,
             handshake_timeout,
+            on_handshake: None,
         }
     }
+}
 
+impl<A> OpenSSLAcceptor<A> {
     /// Override the default TLS handshake timeout of 10 seconds.
     pub fn handshake_timeout(mut self, val: Duration) -> Self {
         self.handshake_timeout = val;
         self
     }
-}
 
-impl<A> OpenSSLAcceptor<A> {
+    /// Calls `on_handshake` with the outcome of every handshake, such as for metrics
+    /// on failed handshakes by [category](HandshakeOutcome).
+    pub fn on_handshake<F>(mut self, on_handshake: F) -> Self
+    where
+        F: Fn(&HandshakeEvent<'_>) + Send + Sync + 'static,
+    {
+        self.on_handshake = Some(Arc::new(on_handshake));
+        self
+    }
+
     /// Overwrite inner acceptor.
     pub fn acceptor<Acceptor>(self, acceptor: Acceptor) -> OpenSSLAcceptor<Acceptor> {
         OpenSSLAcceptor {
             inner: acceptor,
             config: self.config,
             handshake_timeout: self.handshake_timeout,
+            on_handshake: self.on_handshake,
         }
     }
 }
@@ -78,26 +92,62 @@ where
         service: S,
     ) -> impl Future<Output = io::Result<(Self::Stream, Self::Service)>> + Send {
         async move {
+            let peer = accept::peer_addr();
+
             let (stream, service) = self.inner.accept(stream, service).await?;
 
+            let start = Instant::now();
+
             let handshake = tokio::time::timeout(self.handshake_timeout, async {
                 let acceptor = self.config.get_inner();
                 let ssl = Ssl::new(acceptor.context()).unwrap();
 
-                let mut tls_stream = SslStream::new(ssl, stream).map_err(io_other)?;
+                let mut tls_stream =
+                    SslStream::new(ssl, stream).map_err(|e| (HandshakeOutcome::Io, io_other(e)))?;
 
-                poll_fn(|cx| Pin::new(&mut tls_stream).poll_accept(cx)).await.map_err(io_other)?;
+                if let Err(e) = poll_fn(|cx| Pin::new(&mut tls_stream).poll_accept(cx)).await {
+                    return Err((categorize(&e), e.into_io_error().unwrap_or_else(io_other)));
+                }
 
                 Ok(tls_stream)
             });
 
-            match handshake.await {
+            let res = match handshake.await {
                 Ok(Ok(stream)) => Ok((stream, service)),
                 Ok(Err(e)) => Err(e),
-                Err(timeout) => Err(io::Error::new(ErrorKind::TimedOut, timeout)),
-            }
+                Err(timeout) => Err((HandshakeOutcome::TimedOut, io::Error::new(ErrorKind::TimedOut, timeout))),
+            };
+
+            handshake::report(self.on_handshake.as_ref(), peer, start.elapsed(), res)
+        }
+    }
+}
+
+fn categorize(e: &OpenSSLError) -> HandshakeOutcome {
+    if let Some(e) = e.io_error() {
+        return HandshakeOutcome::from_io_kind(e.kind());
+    }
+
+    let Some(stack) = e.ssl_error() else {
+        // a syscall error without an I/O error is an unexpected EOF
+        return match e.code() {
+            ErrorCode::SYSCALL | ErrorCode::ZERO_RETURN => HandshakeOutcome::ClientAborted,
+            _ => HandshakeOutcome::Protocol,
+        };
+    };
+
+    // OpenSSL only exposes reasons as strings, such as "unsupported protocol" or "tlsv1 alert unknown ca"
+    for reason in stack.errors().iter().filter_map(|e| e.reason()) {
+        if reason.contains("version") || reason.contains("no shared cipher") || reason.contains("no suitable") {
+            return HandshakeOutcome::Incompatible;
+        }
+
+        if reason.contains("certificate") || reason.contains("unknown ca") {
+            return HandshakeOutcome::Certificate;
         }
     }
+
+    HandshakeOutcome::Protocol
 }
 
 impl<A> fmt::Debug for OpenSSLAcceptor<A> {
@@ -136,12 +186,12 @@ impl OpenSSLConfig {
 impl super::TlsConfig for OpenSSLConfig {
     type Error = OpenSSLError;
     type DerCert = Vec<u8>;
-    type DerCertChain = Vec<u8>;
+    type DerCertChain = Vec<Self::DerCert>;
 
     /// This helper will establish a TLS server based on strong cipher suites
-    /// from a DER-encoded certificate and key.
+    /// from a DER-encoded certificate chain and key.
     async fn from_der(cert: Self::DerCertChain, key: Vec<u8>) -> Result<Self, OpenSSLError> {
-        let acceptor = Arc::new(ArcSwap::from_pointee(config_from_der(cert.as_ref(), key.as_ref())?));
+        let acceptor = Arc::new(ArcSwap::from_pointee(config_from_der(&cert, key.as_ref())?));
 
         Ok(OpenSSLConfig { acceptor })
     }
@@ -170,9 +220,9 @@ impl super::TlsConfig for OpenSSLConfig {
         Ok(OpenSSLConfig { acceptor })
     }
 
-    /// Reload acceptor from a DER-encoded certificate and key.
+    /// Reload acceptor from a DER-encoded certificate chain and key.
     async fn reload_from_der(&self, cert: Self::DerCertChain, key: Vec<u8>) -> Result<(), OpenSSLError> {
-        let acceptor = Arc::new(config_from_der(cert.as_ref(), key.as_ref())?);
+        let acceptor = Arc::new(config_from_der(&cert, key.as_ref())?);
         self.acceptor.store(acceptor);
 
         Ok(())
@@ -255,12 +305,21 @@ fn alpn_select<'a>(_tls: &mut SslRef, client: &'a [u8]) -> Result<&'a [u8], Alpn
     ssl::select_next_proto(b"\x02h2\x08http/1.1", client).ok_or(AlpnError::NOACK)
 }
 
-fn config_from_der(cert: &[u8], key: &[u8]) -> Result<SslAcceptor, OpenSSLError> {
-    let cert = X509::from_der(cert)?;
-    let key = PKey::private_key_from_der(key)?;
+/// Builds an acceptor from a certificate chain, leaf certificate first followed by any intermediates.
+fn config_from_chain(chain: Vec<X509>, key: PKey<Private>) -> Result<SslAcceptor, OpenSSLError> {
+    let mut chain = chain.into_iter();
 
+    // an empty chain will fail the private key check below
     let mut tls_builder = SslAcceptor::mozilla_modern_v5(SslMethod::tls())?;
-    tls_builder.set_certificate(&cert)?;
+
+    if let Some(leaf) = chain.next() {
+        tls_builder.set_certificate(&leaf)?;
+    }
+
+    for intermediate in chain {
+        tls_builder.add_extra_chain_cert(intermediate)?;
+    }
+
     tls_builder.set_private_key(&key)?;
     tls_builder.check_private_key()?;
     tls_builder.set_alpn_select_callback(alpn_select);
@@ -269,29 +328,23 @@ fn config_from_der(cert: &[u8], key: &[u8]) -> Result<SslAcceptor, OpenSSLError>
     Ok(acceptor)
 }
 
+fn config_from_der(cert: &[Vec<u8>], key: &[u8]) -> Result<SslAcceptor, OpenSSLError> {
+    let chain = cert.iter().map(|cert| X509::from_der(cert)).collect::<Result<Vec<_>, _>>()?;
+    let key = PKey::private_key_from_der(key)?;
+
+    config_from_chain(chain, key)
+}
+
 fn config_from_pem(cert: &[u8], key: &[u8]) -> Result<SslAcceptor, OpenSSLError> {
-    let cert = X509::from_pem(cert)?;
+    let chain = X509::stack_from_pem(cert)?;
     let key = PKey::private_key_from_pem(key)?;
 
-    let mut tls_builder = SslAcceptor::mozilla_modern_v5(SslMethod::tls())?;
-    tls_builder.set_certificate(&cert)?;
-    tls_builder.set_private_key(&key)?;
-    tls_builder.check_private_key()?;
-    tls_builder.set_alpn_select_callback(alpn_select);
-
-    let acceptor = tls_builder.build();
-    Ok(acceptor)
+    config_from_chain(chain, key)
 }
 
 fn config_from_pem_file(cert: impl AsRef<Path>, key: impl AsRef<Path>) -> Result<SslAcceptor, OpenSSLError> {
-    let mut tls_builder = SslAcceptor::mozilla_modern_v5(SslMethod::tls())?;
-    tls_builder.set_certificate_file(cert, SslFiletype::PEM)?;
-    tls_builder.set_private_key_file(key, SslFiletype::PEM)?;
-    tls_builder.check_private_key()?;
-    tls_builder.set_alpn_select_callback(alpn_select);
-
-    let acceptor = tls_builder.build();
-    Ok(acceptor)
+    // `set_certificate_file` only loads the first certificate, so always load the full chain
+    config_from_pem_chain_file(cert, key)
 }
 
 fn config_from_pem_chain_file(
@@ -307,3 +360,142 @@ fn config_from_pem_chain_file(
     let acceptor = tls_builder.build();
     Ok(acceptor)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{future::poll_fn, pin::Pin};
+
+    use openssl::{
+        asn1::Asn1Time,
+        bn::BigNum,
+        ec::{EcGroup, EcKey},
+        hash::MessageDigest,
+        nid::Nid,
+        pkey::{PKey, Private},
+        ssl::{SslConnector, SslMethod, SslVerifyMode},
+        x509::{extension::BasicConstraints, X509NameBuilder, X509},
+    };
+    use tokio_openssl::SslStream;
+
+    use super::{OpenSSLAcceptor, OpenSSLConfig};
+    use crate::serve::{accept::Accept, TlsConfig};
+
+    fn make_cert(cn: &str, issuer: Option<(&X509, &PKey<Private>)>) -> (X509, PKey<Private>) {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        let key = PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap();
+
+        let mut name = X509NameBuilder::new().unwrap();
+        name.append_entry_by_nid(Nid::COMMONNAME, cn).unwrap();
+        let name = name.build();
+
+        let mut builder = X509::builder().unwrap();
+        builder.set_version(2).unwrap();
+        builder.set_serial_number(&BigNum::from_u32(1).unwrap().to_asn1_integer().unwrap()).unwrap();
+        builder.set_subject_name(&name).unwrap();
+        builder.set_not_before(&Asn1Time::days_from_now(0).unwrap()).unwrap();
+        builder.set_not_after(&Asn1Time::days_from_now(1).unwrap()).unwrap();
+        builder.set_pubkey(&key).unwrap();
+
+        match issuer {
+            Some((ca, ca_key)) => {
+                builder.set_issuer_name(ca.subject_name()).unwrap();
+                builder.sign(ca_key, MessageDigest::sha256()).unwrap();
+            }
+            None => {
+                builder.append_extension(BasicConstraints::new().critical().ca().build().unwrap()).unwrap();
+                builder.set_issuer_name(&name).unwrap();
+                builder.sign(&key, MessageDigest::sha256()).unwrap();
+            }
+        }
+
+        (builder.build(), key)
+    }
+
+    #[tokio::test]
+    async fn test_presents_full_chain() {
+        let (ca, ca_key) = make_cert("intermediate", None);
+        let (leaf, leaf_key) = make_cert("localhost", Some((&ca, &ca_key)));
+
+        let chain = [leaf.to_pem().unwrap(), ca.to_pem().unwrap()].concat();
+        let key = leaf_key.private_key_to_pem_pkcs8().unwrap();
+
+        let config = OpenSSLConfig::from_pem(String::from_utf8(chain).unwrap(), String::from_utf8(key).unwrap())
+            .await
+            .unwrap();
+
+        let (client_io, server_io) = tokio::io::duplex(16 * 1024);
+
+        let acceptor = OpenSSLAcceptor::new(config);
+
+        let mut connector = SslConnector::builder(SslMethod::tls()).unwrap();
+        connector.set_verify(SslVerifyMode::NONE);
+        let ssl = connector.build().configure().unwrap().into_ssl("localhost").unwrap();
+        let mut client = SslStream::new(ssl, client_io).unwrap();
+
+        let (server, client_res) = tokio::join!(
+            acceptor.accept(server_io, ()),
+            poll_fn(|cx| Pin::new(&mut client).poll_connect(cx))
+        );
+
+        server.unwrap();
+        client_res.unwrap();
+
+        let presented = client.ssl().peer_cert_chain().expect("server presented no chain");
+
+        assert_eq!(presented.len(), 2);
+        assert_eq!(presented[0].to_der().unwrap(), leaf.to_der().unwrap());
+        assert_eq!(presented[1].to_der().unwrap(), ca.to_der().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_handshake_failures() {
+        use std::sync::{Arc, Mutex};
+
+        use tokio::io::AsyncWriteExt;
+
+        use crate::serve::handshake::HandshakeOutcome;
+
+        let (leaf, key) = make_cert("localhost", None);
+
+        let config = OpenSSLConfig::from_pem(
+            String::from_utf8(leaf.to_pem().unwrap()).unwrap(),
+            String::from_utf8(key.private_key_to_pem_pkcs8().unwrap()).unwrap(),
+        )
+        .await
+        .unwrap();
+
+        let outcomes = Arc::new(Mutex::new(Vec::new()));
+
+        let acceptor =
+            OpenSSLAcceptor::new(config).handshake_timeout(std::time::Duration::from_millis(50)).on_handshake({
+                let outcomes = outcomes.clone();
+                move |event| outcomes.lock().unwrap().push(event.outcome)
+            });
+
+        // plain HTTP sent to a TLS port
+        let (mut client_io, server_io) = tokio::io::duplex(16 * 1024);
+        client_io.write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n").await.unwrap();
+        let err = acceptor.accept(server_io, ()).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+        // client disconnects without a handshake
+        let (client_io, server_io) = tokio::io::duplex(16 * 1024);
+        drop(client_io);
+        let err = acceptor.accept(server_io, ()).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+
+        // client never speaks
+        let (_client_io, server_io) = tokio::io::duplex(16 * 1024);
+        let err = acceptor.accept(server_io, ()).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+
+        assert_eq!(
+            *outcomes.lock().unwrap(),
+            [
+                HandshakeOutcome::Protocol,
+                HandshakeOutcome::ClientAborted,
+                HandshakeOutcome::TimedOut
+            ]
+        );
+    }
+}
@@ -1,4 +1,5 @@
-use super::accept::{Accept, DefaultAcceptor};
+use super::accept::{self, Accept, DefaultAcceptor};
+use super::handshake::{self, HandshakeEvent, HandshakeOutcome, OnHandshake};
 use crate::error::io_other;
 
 use arc_swap::ArcSwap;
@@ -7,8 +8,12 @@ use rustls_pemfile::Item;
 use rustls_pki_types::{CertificateDer, PrivateKeyDer};
 use std::future::Future;
 use std::io::ErrorKind;
-use std::time::Duration;
-use std::{fmt, io, path::Path, sync::Arc};
+use std::time::{Duration, Instant};
+use std::{
+    fmt, io,
+    path::Path,
+    sync::{Arc, Mutex},
+};
 use tokio::task::spawn_blocking;
 use tokio_rustls::server::TlsStream;
 use tokio_rustls::TlsAcceptor;
@@ -20,6 +25,7 @@ pub struct RustlsAcceptor<A = DefaultAcceptor> {
     inner: A,
     config: RustlsConfig,
     handshake_timeout: Duration,
+    on_handshake: Option<OnHandshake>,
 }
 
 impl RustlsAcceptor {
@@ -36,14 +42,27 @@ impl RustlsAcceptor {
             inner: DefaultAcceptor,
             config,
             handshake_timeout,
+            on_handshake: None,
         }
     }
+}
 
+impl<A> RustlsAcceptor<A> {
     /// Override the default TLS handshake timeout of 10 seconds, except during testing.
     pub fn handshake_timeout(mut self, val: Duration) -> Self {
         self.handshake_timeout = val;
         self
     }
+
+    /// Calls `on_handshake` with the outcome of every handshake, such as for metrics
+    /// on failed handshakes by [category](HandshakeOutcome).
+    pub fn on_handshake<F>(mut self, on_handshake: F) -> Self
+    where
+        F: Fn(&HandshakeEvent<'_>) + Send + Sync + 'static,
+    {
+        self.on_handshake = Some(Arc::new(on_handshake));
+        self
+    }
 }
 
 impl<A> RustlsAcceptor<A> {
@@ -53,6 +72,7 @@ impl<A> RustlsAcceptor<A> {
             inner: acceptor,
             config: self.config,
             handshake_timeout: self.handshake_timeout,
+            on_handshake: self.on_handshake,
         }
     }
 }
@@ -70,22 +90,54 @@ where
         service: S,
     ) -> impl Future<Output = io::Result<(Self::Stream, Self::Service)>> + Send {
         async move {
+            let peer = accept::peer_addr();
+
             let (stream, service) = self.inner.accept(stream, service).await?;
 
+            let start = Instant::now();
+
             let handshake = tokio::time::timeout(
                 self.handshake_timeout,
                 TlsAcceptor::from(self.config.get_inner()).accept(stream),
             );
 
-            match handshake.await {
+            let res = match handshake.await {
                 Ok(Ok(stream)) => Ok((stream, service)),
-                Ok(Err(e)) => Err(e),
-                Err(timeout) => Err(io::Error::new(ErrorKind::TimedOut, timeout)),
-            }
+                Ok(Err(e)) => Err((categorize(&e), e)),
+                Err(timeout) => Err((HandshakeOutcome::TimedOut, io::Error::new(ErrorKind::TimedOut, timeout))),
+            };
+
+            handshake::report(self.on_handshake.as_ref(), peer, start.elapsed(), res)
         }
     }
 }
 
+fn categorize(e: &io::Error) -> HandshakeOutcome {
+    use rustls::{AlertDescription as Alert, Error as TlsError};
+
+    let Some(e) = e.get_ref().and_then(|e| e.downcast_ref::<TlsError>()) else {
+        return HandshakeOutcome::from_io_kind(e.kind());
+    };
+
+    match e {
+        TlsError::PeerIncompatible(_) => HandshakeOutcome::Incompatible,
+        TlsError::InvalidCertificate(_) | TlsError::NoCertificatesPresented => HandshakeOutcome::Certificate,
+        TlsError::AlertReceived(alert) => match alert {
+            Alert::ProtocolVersion | Alert::HandshakeFailure | Alert::InsufficientSecurity => {
+                HandshakeOutcome::Incompatible
+            }
+            Alert::BadCertificate
+            | Alert::UnsupportedCertificate
+            | Alert::CertificateRevoked
+            | Alert::CertificateExpired
+            | Alert::CertificateUnknown
+            | Alert::UnknownCA => HandshakeOutcome::Certificate,
+            _ => HandshakeOutcome::Protocol,
+        },
+        _ => HandshakeOutcome::Protocol,
+    }
+}
+
 impl<A> fmt::Debug for RustlsAcceptor<A> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("RustlsAcceptor").finish()
@@ -97,16 +149,76 @@ impl<A> fmt::Debug for RustlsAcceptor<A> {
 #[must_use]
 pub struct RustlsConfig {
     inner: Arc<ArcSwap<ServerConfig>>,
+    options: Arc<Mutex<RustlsOptions>>,
+}
+
+/// Extra options applied whenever the certificate is (re)loaded.
+#[derive(Debug, Default, Clone)]
+struct RustlsOptions {
+    ocsp_response: Option<Vec<u8>>,
+    key_log: bool,
 }
 
 impl RustlsConfig {
+    fn new(config: Arc<ServerConfig>) -> Self {
+        Self {
+            inner: Arc::new(ArcSwap::new(config)),
+            options: Arc::default(),
+        }
+    }
+
+    fn options(&self) -> RustlsOptions {
+        self.options.lock().unwrap().clone()
+    }
+
     /// Create config from `Arc<`[`ServerConfig`]`>`.
     ///
     /// NOTE: You need to set ALPN protocols (like `http/1.1` or `h2`) manually.
     pub fn from_config(config: Arc<ServerConfig>) -> Self {
-        let inner = Arc::new(ArcSwap::new(config));
+        Self::new(config)
+    }
 
-        Self { inner }
+    /// Set a DER-encoded OCSP response to staple to the certificate during handshakes,
+    /// or `None` to disable stapling.
+    ///
+    /// This takes effect the next time the certificate is reloaded, such as with
+    /// [`reload_from_pem_file`](TlsConfig::reload_from_pem_file). OCSP responses expire,
+    /// so a fresh response should be set and the certificate reloaded periodically.
+    ///
+    /// This has no effect on configs given via [`from_config`](Self::from_config)
+    /// or [`reload_from_config`](Self::reload_from_config).
+    pub fn set_ocsp_response(&self, ocsp_response: Option<Vec<u8>>) {
+        self.options.lock().unwrap().ocsp_response = ocsp_response;
+    }
+
+    /// Enable or disable logging of TLS session secrets to the file named by the
+    /// `SSLKEYLOGFILE` environment variable, for debugging with tools such as Wireshark.
+    /// If the environment variable is not set, nothing is logged.
+    ///
+    /// This takes effect immediately, and persists across reloads of the certificate,
+    /// except with [`reload_from_config`](Self::reload_from_config).
+    ///
+    /// # Security
+    ///
+    /// Anyone with access to the key log file can decrypt all traffic of the logged sessions.
+    /// This should never be enabled in production.
+    pub fn set_key_log(&self, enable: bool) {
+        let mut options = self.options.lock().unwrap();
+        options.key_log = enable;
+
+        let mut config = ServerConfig::clone(&self.get_inner());
+        config.key_log = key_log(enable);
+        self.inner.store(Arc::new(config));
+    }
+
+    /// Stores a reloaded config, applying the current key log setting.
+    ///
+    /// This holds the options lock while storing, so a concurrent [`set_key_log`](Self::set_key_log)
+    /// is never overwritten by a config built before it.
+    fn store(&self, mut config: ServerConfig) {
+        let options = self.options.lock().unwrap();
+        config.key_log = key_log(options.key_log);
+        self.inner.store(Arc::new(config));
     }
 
     /// Get  inner `Arc<`[`ServerConfig`]`>`.
@@ -129,65 +241,57 @@ impl TlsConfig for RustlsConfig {
     type DerCertChain = Vec<Self::DerCert>;
 
     async fn from_der(cert: Self::DerCertChain, key: Vec<u8>) -> io::Result<Self> {
-        let server_config = spawn_blocking(|| config_from_der(cert, key)).await.unwrap()?;
-        let inner = Arc::new(ArcSwap::from_pointee(server_config));
+        let options = RustlsOptions::default();
+        let server_config = spawn_blocking(move || config_from_der(cert, key, &options)).await.unwrap()?;
 
-        Ok(Self { inner })
+        Ok(Self::new(Arc::new(server_config)))
     }
 
     async fn from_pem(cert: String, key: String) -> io::Result<Self> {
-        let server_config = spawn_blocking(|| config_from_pem(cert, key)).await.unwrap()?;
-        let inner = Arc::new(ArcSwap::from_pointee(server_config));
+        let options = RustlsOptions::default();
+        let server_config = spawn_blocking(move || config_from_pem(cert, key, &options)).await.unwrap()?;
 
-        Ok(Self { inner })
+        Ok(Self::new(Arc::new(server_config)))
     }
 
     async fn from_pem_file(cert: impl AsRef<Path>, key: impl AsRef<Path>) -> io::Result<Self> {
-        let server_config = config_from_pem_file(cert, key).await?;
-        let inner = Arc::new(ArcSwap::from_pointee(server_config));
+        let server_config = config_from_pem_file(cert, key, &RustlsOptions::default()).await?;
 
-        Ok(Self { inner })
+        Ok(Self::new(Arc::new(server_config)))
     }
 
     async fn reload_from_der(&self, cert: Self::DerCertChain, key: Vec<u8>) -> io::Result<()> {
-        let server_config = spawn_blocking(|| config_from_der(cert, key)).await.unwrap()?;
-        let inner = Arc::new(server_config);
-
-        self.inner.store(inner);
+        let options = self.options();
+        let server_config = spawn_blocking(move || config_from_der(cert, key, &options)).await.unwrap()?;
+        self.store(server_config);
 
         Ok(())
     }
 
     async fn from_pem_chain_file(chain: impl AsRef<Path>, key: impl AsRef<Path>) -> io::Result<Self> {
-        let server_config = config_from_pem_chain_file(chain, key).await?;
-        let inner = Arc::new(ArcSwap::from_pointee(server_config));
+        let server_config = config_from_pem_chain_file(chain, key, &RustlsOptions::default()).await?;
 
-        Ok(Self { inner })
+        Ok(Self::new(Arc::new(server_config)))
     }
 
     async fn reload_from_pem(&self, cert: String, key: String) -> io::Result<()> {
-        let server_config = spawn_blocking(|| config_from_pem(cert, key)).await.unwrap()?;
-        let inner = Arc::new(server_config);
-
-        self.inner.store(inner);
+        let options = self.options();
+        let server_config = spawn_blocking(move || config_from_pem(cert, key, &options)).await.unwrap()?;
+        self.store(server_config);
 
         Ok(())
     }
 
     async fn reload_from_pem_file(&self, cert: impl AsRef<Path>, key: impl AsRef<Path>) -> io::Result<()> {
-        let server_config = config_from_pem_file(cert, key).await?;
-        let inner = Arc::new(server_config);
-
-        self.inner.store(inner);
+        let server_config = config_from_pem_file(cert, key, &self.options()).await?;
+        self.store(server_config);
 
         Ok(())
     }
 
     async fn reload_from_pem_chain_file(&self, chain: impl AsRef<Path>, key: impl AsRef<Path>) -> io::Result<()> {
-        let server_config = config_from_pem_chain_file(chain, key).await?;
-        let inner = Arc::new(server_config);
-
-        self.inner.store(inner);
+        let server_config = config_from_pem_chain_file(chain, key, &self.options()).await?;
+        self.store(server_config);
 
         Ok(())
     }
@@ -199,19 +303,38 @@ impl fmt::Debug for RustlsConfig {
     }
 }
 
-fn config_from_der(cert: Vec<Vec<u8>>, key: Vec<u8>) -> io::Result<ServerConfig> {
+fn key_log(enable: bool) -> Arc<dyn rustls::KeyLog> {
+    match enable {
+        true => Arc::new(rustls::KeyLogFile::new()),
+        false => Arc::new(rustls::NoKeyLog),
+    }
+}
+
+fn build_config(
+    cert: Vec<CertificateDer<'static>>,
+    key: PrivateKeyDer<'static>,
+    options: &RustlsOptions,
+) -> Result<ServerConfig, rustls::Error> {
+    let builder = ServerConfig::builder().with_no_client_auth();
+
+    match options.ocsp_response {
+        Some(ref ocsp) => builder.with_single_cert_with_ocsp(cert, key, ocsp.clone()),
+        None => builder.with_single_cert(cert, key),
+    }
+}
+
+fn config_from_der(cert: Vec<Vec<u8>>, key: Vec<u8>, options: &RustlsOptions) -> io::Result<ServerConfig> {
     let cert = cert.into_iter().map(CertificateDer::from).collect();
     let key = PrivateKeyDer::try_from(key).map_err(io_other)?;
 
-    let mut config =
-        ServerConfig::builder().with_no_client_auth().with_single_cert(cert, key).map_err(io_other)?;
+    let mut config = build_config(cert, key, options).map_err(io_other)?;
 
     config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
 
     Ok(config)
 }
 
-fn config_from_pem(cert: String, key: String) -> io::Result<ServerConfig> {
+fn config_from_pem(cert: String, key: String, options: &RustlsOptions) -> io::Result<ServerConfig> {
     let cert = rustls_pemfile::certs(&mut cert.as_ref())
         .map(|it| it.map(|it| it.to_vec()))
         .collect::<Result<Vec<_>, _>>()?;
@@ -234,17 +357,25 @@ fn config_from_pem(cert: String, key: String) -> io::Result<ServerConfig> {
         ));
     }
 
-    config_from_der(cert, key)
+    config_from_der(cert, key, options)
 }
 
-async fn config_from_pem_file(cert: impl AsRef<Path>, key: impl AsRef<Path>) -> io::Result<ServerConfig> {
+async fn config_from_pem_file(
+    cert: impl AsRef<Path>,
+    key: impl AsRef<Path>,
+    options: &RustlsOptions,
+) -> io::Result<ServerConfig> {
     let cert = tokio::fs::read_to_string(cert.as_ref()).await?;
     let key = tokio::fs::read_to_string(key.as_ref()).await?;
 
-    config_from_pem(cert, key)
+    config_from_pem(cert, key, options)
 }
 
-async fn config_from_pem_chain_file(cert: impl AsRef<Path>, chain: impl AsRef<Path>) -> io::Result<ServerConfig> {
+async fn config_from_pem_chain_file(
+    cert: impl AsRef<Path>,
+    chain: impl AsRef<Path>,
+    options: &RustlsOptions,
+) -> io::Result<ServerConfig> {
     let cert = tokio::fs::read_to_string(cert.as_ref()).await?;
     let key = tokio::fs::read_to_string(chain.as_ref()).await?;
 
@@ -259,8 +390,131 @@ async fn config_from_pem_chain_file(cert: impl AsRef<Path>, chain: impl AsRef<Pa
             x => Err(io_other(format!("invalid certificate format, received: {x:?}"))),
         }?;
 
-    ServerConfig::builder()
-        .with_no_client_auth()
-        .with_single_cert(cert, key_cert)
-        .map_err(|_| io_other("invalid certificate"))
+    build_config(cert, key_cert, options).map_err(|_| io_other("invalid certificate"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use rustls::{pki_types::ServerName, ClientConfig, RootCertStore};
+    use tokio_rustls::TlsConnector;
+
+    use super::{RustlsAcceptor, RustlsConfig};
+    use crate::serve::{accept::Accept, TlsConfig};
+
+    const CA: &str = include_str!("testdata/ca.pem");
+    const CERT: &str = include_str!("testdata/leaf.pem");
+    const KEY: &str = include_str!("testdata/leaf.key");
+
+    fn certs(pem: &str) -> Vec<rustls::pki_types::CertificateDer<'static>> {
+        rustls_pemfile::certs(&mut pem.as_bytes()).collect::<Result<_, _>>().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_presents_full_chain() {
+        let config = RustlsConfig::from_pem(format!("{CERT}{CA}"), KEY.to_owned()).await.unwrap();
+
+        let mut roots = RootCertStore::empty();
+        roots.add_parsable_certificates(certs(CA));
+
+        let connector = TlsConnector::from(Arc::new(
+            ClientConfig::builder().with_root_certificates(roots).with_no_client_auth(),
+        ));
+
+        let (client_io, server_io) = tokio::io::duplex(16 * 1024);
+
+        let acceptor = RustlsAcceptor::new(config);
+
+        let (server, client) = tokio::join!(
+            acceptor.accept(server_io, ()),
+            connector.connect(ServerName::try_from("localhost").unwrap(), client_io)
+        );
+
+        server.unwrap();
+        let client = client.unwrap();
+
+        let presented = client.get_ref().1.peer_certificates().expect("server presented no chain");
+
+        assert_eq!(presented, [certs(CERT), certs(CA)].concat());
+    }
+
+    #[tokio::test]
+    async fn test_key_log_reload() {
+        let config = RustlsConfig::from_pem(CERT.to_owned(), KEY.to_owned()).await.unwrap();
+        let logs = |config: &RustlsConfig| config.get_inner().key_log.will_log("CLIENT_RANDOM");
+
+        assert!(!logs(&config));
+
+        config.set_key_log(true);
+        assert!(logs(&config));
+
+        // reloading the certificate keeps the key log
+        config.reload_from_pem(CERT.to_owned(), KEY.to_owned()).await.unwrap();
+        assert!(logs(&config));
+
+        config.set_key_log(false);
+        config.reload_from_pem(CERT.to_owned(), KEY.to_owned()).await.unwrap();
+        assert!(!logs(&config));
+    }
+
+    #[tokio::test]
+    async fn test_handshake_failures() {
+        use std::{io::ErrorKind, net::SocketAddr, sync::Mutex, time::Duration};
+
+        use tokio::io::AsyncWriteExt;
+
+        use crate::serve::{accept, handshake::HandshakeOutcome};
+
+        let config = RustlsConfig::from_pem(CERT.to_owned(), KEY.to_owned()).await.unwrap();
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+
+        let acceptor = RustlsAcceptor::new(config).handshake_timeout(Duration::from_millis(50)).on_handshake({
+            let events = events.clone();
+            move |event| events.lock().unwrap().push((event.outcome, event.peer))
+        });
+
+        let peer: SocketAddr = "192.0.2.1:4321".parse().unwrap();
+
+        // plain HTTP sent to a TLS port, with the peer address given by the server
+        let (mut client_io, server_io) = tokio::io::duplex(16 * 1024);
+        client_io.write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n").await.unwrap();
+        let err = accept::with_peer_addr(peer, acceptor.accept(server_io, ())).await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+        // client disconnects without a handshake
+        let (client_io, server_io) = tokio::io::duplex(16 * 1024);
+        drop(client_io);
+        let err = acceptor.accept(server_io, ()).await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+
+        // client never speaks
+        let (_client_io, server_io) = tokio::io::duplex(16 * 1024);
+        let err = acceptor.accept(server_io, ()).await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::TimedOut);
+
+        // client doesn't trust the certificate
+        let connector = TlsConnector::from(Arc::new(
+            ClientConfig::builder().with_root_certificates(RootCertStore::empty()).with_no_client_auth(),
+        ));
+
+        let (client_io, server_io) = tokio::io::duplex(16 * 1024);
+        let (server, client) = tokio::join!(
+            acceptor.accept(server_io, ()),
+            connector.connect(ServerName::try_from("localhost").unwrap(), client_io)
+        );
+        assert!(client.is_err());
+        assert_eq!(server.unwrap_err().kind(), ErrorKind::InvalidData);
+
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                (HandshakeOutcome::Protocol, Some(peer)),
+                (HandshakeOutcome::ClientAborted, None),
+                (HandshakeOutcome::TimedOut, None),
+                (HandshakeOutcome::Certificate, None),
+            ]
+        );
+    }
 }
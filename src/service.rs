@@ -1,5 +1,7 @@
 use std::{error::Error, future::Future, ops::Deref};
 
+use futures::TryFutureExt as _;
+
 pub trait ServiceFuture<R, E>: Future<Output = Result<R, E>> + Send {}
 
 impl<T, R, E> ServiceFuture<R, E> for T where T: Future<Output = Result<R, E>> + Send {}
@@ -86,6 +88,87 @@ impl<S, F> MapServiceRequest<S, F> {
     }
 }
 
+pub struct MapServiceResponse<S, F> {
+    service: S,
+    f: F,
+}
+
+impl<S, F, Req, Res> Service<Req> for MapServiceResponse<S, F>
+where
+    S: Service<Req>,
+    F: Fn(S::Response) -> Res + Send + Sync,
+{
+    type Response = Res;
+    type Error = S::Error;
+
+    #[inline]
+    fn call(&self, req: Req) -> impl ServiceFuture<Self::Response, Self::Error> {
+        self.service.call(req).map_ok(&self.f)
+    }
+}
+
+impl<S, F> MapServiceResponse<S, F> {
+    pub fn new(service: S, f: F) -> Self {
+        Self { service, f }
+    }
+}
+
+pub struct MapServiceError<S, F> {
+    service: S,
+    f: F,
+}
+
+impl<S, F, Req, E> Service<Req> for MapServiceError<S, F>
+where
+    S: Service<Req>,
+    F: Fn(S::Error) -> E + Send + Sync,
+    E: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = E;
+
+    #[inline]
+    fn call(&self, req: Req) -> impl ServiceFuture<Self::Response, Self::Error> {
+        self.service.call(req).map_err(&self.f)
+    }
+}
+
+impl<S, F> MapServiceError<S, F> {
+    pub fn new(service: S, f: F) -> Self {
+        Self { service, f }
+    }
+}
+
+/// Combinators for adjusting a [`Service`] inline, without writing a full [`Layer`](crate::Layer).
+pub trait ServiceExt<Req>: Service<Req> + Sized {
+    /// Maps the request before it is passed to this service.
+    fn map_request<F, R>(self, f: F) -> MapServiceRequest<Self, F>
+    where
+        F: Fn(R) -> Req + Send + Sync,
+    {
+        MapServiceRequest::new(self, f)
+    }
+
+    /// Maps the successful response of this service.
+    fn map_response<F, Res>(self, f: F) -> MapServiceResponse<Self, F>
+    where
+        F: Fn(Self::Response) -> Res + Send + Sync,
+    {
+        MapServiceResponse::new(self, f)
+    }
+
+    /// Maps the error of this service.
+    fn map_err<F, E>(self, f: F) -> MapServiceError<Self, F>
+    where
+        F: Fn(Self::Error) -> E + Send + Sync,
+        E: Send + 'static,
+    {
+        MapServiceError::new(self, f)
+    }
+}
+
+impl<S, Req> ServiceExt<Req> for S where S: Service<Req> {}
+
 impl<S, T> MakeService<T, http::Request<hyper::body::Incoming>> for S
 where
     S: Service<http::Request<hyper::body::Incoming>, Error: Error + Send + Sync + 'static>
@@ -100,3 +183,41 @@ where
         self.clone()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use http::StatusCode;
+
+    use super::{Service, ServiceExt};
+    use crate::{body::Body, IntoResponse, Request, Response, Router};
+
+    fn request(path: &'static str) -> Request {
+        http::Request::get(path).body(Body::empty()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_map_response() {
+        let mut router = Router::<(), Response>::with_state(());
+        router.get("/", || async { Bytes::from_static(b"Hello") });
+
+        let service = router.map_response(|resp: Response| resp.with_status(StatusCode::ACCEPTED).into_response());
+
+        let resp = service.call(request("/")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::ACCEPTED);
+    }
+
+    #[tokio::test]
+    async fn test_map_err_into_response() {
+        let router = Router::<(), Response>::with_state(());
+
+        let service = router.map_err(|err: crate::Error| err.into_response());
+
+        let resp = match service.call(request("/missing")).await {
+            Ok(_) => panic!("expected error"),
+            Err(resp) => resp,
+        };
+
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+}
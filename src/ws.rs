@@ -1,4 +1,6 @@
 use std::borrow::Cow;
+use std::net::SocketAddr;
+use std::sync::Arc;
 
 use crate::headers::Header;
 use crate::{FromRequest, IntoResponse, Request, Response};
@@ -32,6 +34,10 @@ pub enum WsError {
     InvalidProtocolPsuedoHeader,
     #[error("Missing Sec-WebSocket-Key header")]
     MissingWebSocketKey,
+    #[error("Unexpected request body")]
+    UnexpectedBody,
+    #[error("Forbidden Origin")]
+    ForbiddenOrigin,
 }
 
 impl IntoResponse for WsError {
@@ -44,16 +50,121 @@ impl IntoResponse for WsError {
             WsError::IncorrectWebSocketVersion => ("Incorrect WebSocket version", StatusCode::BAD_REQUEST),
             WsError::InvalidProtocolPsuedoHeader => ("Invalid protocol psuedo-header", StatusCode::BAD_REQUEST),
             WsError::MissingWebSocketKey => ("Missing Sec-WebSocket-Key header", StatusCode::BAD_REQUEST),
+            WsError::UnexpectedBody => ("Unexpected request body", StatusCode::BAD_REQUEST),
+            WsError::ForbiddenOrigin => ("Forbidden Origin", StatusCode::FORBIDDEN),
         })
     }
 }
 
+/// Restricts the `Origin` of WebSocket upgrade requests, to guard against cross-site WebSocket hijacking.
+///
+/// Browsers always send the `Origin` header with WebSocket handshakes, but do not
+/// otherwise restrict cross-origin WebSocket connections, which include cookies. Without
+/// an origin check, any website can open a WebSocket to your endpoint as the visiting user.
+///
+/// When inserted into the request extensions, such as by a middleware, [`Ws`] rejects
+/// upgrades from disallowed origins during extraction with [`WsError::ForbiddenOrigin`].
+/// It can also be checked per-route with [`Ws::allowed_origins`]. Requests without an
+/// `Origin` header, which are not from browsers, are always allowed.
+///
+/// By default, no origin check is performed, which is insecure for endpoints relying on cookies.
+#[derive(Clone)]
+pub struct AllowedOrigins(Arc<dyn Fn(&str) -> bool + Send + Sync>);
+
+impl AllowedOrigins {
+    /// Allows only the given origins, e.g. `https://example.com`, compared case-insensitively.
+    pub fn list<I>(origins: I) -> Self
+    where
+        I: IntoIterator<Item: Into<String>>,
+    {
+        let origins: Vec<String> = origins.into_iter().map(Into::into).collect();
+
+        AllowedOrigins(Arc::new(move |origin| {
+            origins.iter().any(|o| o.eq_ignore_ascii_case(origin))
+        }))
+    }
+
+    /// Allows origins for which the predicate returns `true`.
+    pub fn predicate<F>(f: F) -> Self
+    where
+        F: Fn(&str) -> bool + Send + Sync + 'static,
+    {
+        AllowedOrigins(Arc::new(f))
+    }
+
+    /// Returns true if the given origin is allowed.
+    #[must_use]
+    pub fn is_allowed(&self, origin: &str) -> bool {
+        (self.0)(origin)
+    }
+
+    fn check(&self, origin: Option<&HeaderValue>) -> Result<(), WsError> {
+        match origin {
+            None => Ok(()),
+            Some(origin) => match origin.to_str() {
+                Ok(origin) if self.is_allowed(origin) => Ok(()),
+                _ => Err(WsError::ForbiddenOrigin),
+            },
+        }
+    }
+}
+
+impl std::fmt::Debug for AllowedOrigins {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AllowedOrigins").finish_non_exhaustive()
+    }
+}
+
+/// Details of a WebSocket upgrade that failed after the handshake response was sent,
+/// given to [`OnFailedUpgrade`] callbacks.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct UpgradeFailure<'a> {
+    /// The address of the peer, if known.
+    pub peer: Option<SocketAddr>,
+
+    /// The error from the underlying connection.
+    pub error: &'a hyper::Error,
+}
+
+/// A callback for WebSocket upgrades that fail after the handshake response was sent,
+/// such as to record metrics.
+///
+/// By the time the connection is upgraded, the `101 Switching Protocols` (or `200 OK` for HTTP/2)
+/// response has already been sent, so the client only sees a broken connection. This
+/// callback allows the failure to be observed server-side, in addition to the error given
+/// to the [`Ws::on_upgrade`] callback.
+///
+/// When inserted into the request extensions, such as by a middleware, it applies to all
+/// [`Ws`] extracted from those requests. It can also be set per-route with [`Ws::on_failed_upgrade`].
+#[derive(Clone)]
+pub struct OnFailedUpgrade(Arc<dyn Fn(&UpgradeFailure<'_>) + Send + Sync>);
+
+impl OnFailedUpgrade {
+    /// Creates a new callback from the given function.
+    pub fn new<F>(f: F) -> Self
+    where
+        F: Fn(&UpgradeFailure<'_>) + Send + Sync + 'static,
+    {
+        OnFailedUpgrade(Arc::new(f))
+    }
+}
+
+impl std::fmt::Debug for OnFailedUpgrade {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OnFailedUpgrade").finish_non_exhaustive()
+    }
+}
+
 pub struct Ws {
     /// `None` if HTTP/2
     key: Option<SecWebsocketKey>,
+    origin: Option<HeaderValue>,
     sec_websocket_protocol: Option<HeaderValue>,
     config: protocol::WebSocketConfig,
     on_upgrade: Option<OnUpgrade>,
+    peer: Option<SocketAddr>,
+    on_failed_upgrade: Option<OnFailedUpgrade>,
 }
 
 impl<S> FromRequest<S> for Ws {
@@ -81,6 +192,14 @@ impl<S> FromRequest<S> for Ws {
                     _ => return Err(WsError::IncorrectUpgrade),
                 }
 
+                // the handshake is a GET request, so any body is unexpected and
+                // would otherwise be misinterpreted as WebSocket frames after the upgrade
+                if headers.contains_key(hyper::header::TRANSFER_ENCODING)
+                    || headers.typed_get::<headers::ContentLength>().is_some_and(|len| len.0 > 0)
+                {
+                    return Err(WsError::UnexpectedBody);
+                }
+
                 match headers.typed_get() {
                     Some(key) => Some(key),
                     None => return Err(WsError::MissingWebSocketKey),
@@ -102,21 +221,57 @@ impl<S> FromRequest<S> for Ws {
                 _ => return Err(WsError::IncorrectWebSocketVersion),
             }
 
+            let origin = req.headers().get(hyper::header::ORIGIN).cloned();
+
+            if let Some(allowed) = req.extensions().get::<AllowedOrigins>() {
+                allowed.check(origin.as_ref())?;
+            }
+
             let sec_websocket_protocol = req.headers().get(hyper::header::SEC_WEBSOCKET_PROTOCOL).cloned();
 
             let on_upgrade = req.extensions_mut().remove::<OnUpgrade>();
+            let peer = req.extensions().get::<SocketAddr>().copied();
+            let on_failed_upgrade = req.extensions().get::<OnFailedUpgrade>().cloned();
 
             Ok(Ws {
                 key,
+                origin,
                 sec_websocket_protocol,
                 config: Default::default(),
                 on_upgrade,
+                peer,
+                on_failed_upgrade,
             })
         }
     }
 }
 
 impl Ws {
+    /// Rejects the upgrade with [`WsError::ForbiddenOrigin`] if the request's `Origin` is not allowed.
+    ///
+    /// See [`AllowedOrigins`] for details.
+    pub fn allowed_origins(self, allowed: &AllowedOrigins) -> Result<Self, WsError> {
+        allowed.check(self.origin.as_ref())?;
+        Ok(self)
+    }
+
+    /// Returns the address of the peer, if known.
+    #[must_use]
+    pub fn peer_addr(&self) -> Option<SocketAddr> {
+        self.peer
+    }
+
+    /// Sets a callback for when the upgrade fails after the handshake response was sent,
+    /// overriding any [`OnFailedUpgrade`] from the request extensions.
+    #[must_use]
+    pub fn on_failed_upgrade<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&UpgradeFailure<'_>) + Send + Sync + 'static,
+    {
+        self.on_failed_upgrade = Some(OnFailedUpgrade::new(f));
+        self
+    }
+
     /// See [WebSocketConfig](protocol::WebSocketConfig)
     #[must_use]
     pub fn write_buffer_size(mut self, size: usize) -> Self {
@@ -139,12 +294,21 @@ impl Ws {
     }
 
     /// Set the maximum frame size (defaults to 16 megabytes)
+    ///
+    /// This only applies to frames received from the client. Fragmented messages
+    /// are limited by [`max_message_size`](Ws::max_message_size) as a whole, regardless
+    /// of the number of frames they are sent in.
     #[must_use]
     pub fn max_frame_size(mut self, max: usize) -> Self {
         self.config.max_frame_size = Some(max);
         self
     }
 
+    /// Responds with the handshake, then calls `func` with the upgraded connection.
+    ///
+    /// If the upgrade fails, `func` is given the error, but the handshake response has
+    /// already been sent, so the failure is also logged with the peer address and passed
+    /// to any [`OnFailedUpgrade`] callback.
     #[must_use]
     pub fn on_upgrade<F, Fut>(self, func: F) -> impl IntoResponse
     where
@@ -169,21 +333,28 @@ where
     Fut: Future<Output = ()> + Send,
 {
     fn into_response(self) -> Response {
+        let peer = self.ws.peer;
+
         let Some(on_upgrade) = self.ws.on_upgrade else {
-            log::warn!("ws couldn't be upgraded since no upgrade state was present");
+            log::warn!(peer = ?peer, "ws couldn't be upgraded since no upgrade state was present");
 
             return IntoResponse::into_response(StatusCode::BAD_REQUEST);
         };
 
         let on_upgrade_cb = self.on_upgrade;
+        let on_failed_upgrade = self.ws.on_failed_upgrade;
         let config = self.ws.config;
 
         tokio::spawn(async move {
             let ws = match on_upgrade.await {
-                Err(e) => {
-                    log::error!("ws upgrade error: {e}");
+                Err(error) => {
+                    log::error!(peer = ?peer, %error, "ws upgrade error");
+
+                    if let Some(OnFailedUpgrade(cb)) = on_failed_upgrade {
+                        cb(&UpgradeFailure { peer, error: &error });
+                    }
 
-                    Err(e)
+                    Err(error)
                 }
                 Ok(upgraded) => {
                     log::trace!("websocket upgrade complete");
@@ -234,6 +405,53 @@ impl WebSocket {
     pub async fn close(mut self) -> Result<(), tungstenite::Error> {
         future::poll_fn(|cx| Pin::new(&mut self).poll_close(cx)).await
     }
+
+    /// Send a single binary message as a sequence of fragmented frames, one frame per item
+    /// of the given stream, so the whole message never has to be held in memory at once.
+    ///
+    /// Each item is sent as-is in its own frame, so items should be kept below the
+    /// maximum frame size accepted by the peer, and the total length below its maximum
+    /// message size. An empty stream sends an empty binary message.
+    ///
+    /// Because the final frame must be marked as such, each frame is only sent
+    /// once the next item has been received from the stream.
+    pub async fn send_fragmented<S>(&mut self, fragments: S) -> Result<(), SinkError>
+    where
+        S: Stream<Item: Into<Vec<u8>>>,
+    {
+        use futures::{SinkExt, StreamExt};
+        use protocol::frame::{
+            coding::{Data, OpCode},
+            Frame,
+        };
+
+        let mut fragments = std::pin::pin!(fragments);
+
+        let Some(mut current) = fragments.next().await else {
+            return self.send(Message::binary(Vec::new())).await;
+        };
+
+        let mut opcode = OpCode::Data(Data::Binary);
+
+        loop {
+            let next = fragments.next().await;
+            let frame = Frame::message(current.into(), opcode, next.is_none());
+
+            self.feed(Message {
+                inner: protocol::Message::Frame(frame),
+            })
+            .await?;
+
+            match next {
+                Some(next) => current = next,
+                None => break,
+            }
+
+            opcode = OpCode::Data(Data::Continue);
+        }
+
+        self.flush().await
+    }
 }
 
 impl Stream for WebSocket {
@@ -354,6 +572,18 @@ impl Message {
         }
     }
 
+    /// Returns the length of the message payload in bytes.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns true if the message payload is empty.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
     /// Returns true if this message is a Text message.
     #[must_use]
     pub fn is_text(&self) -> bool {
@@ -435,3 +665,140 @@ impl From<Message> for Vec<u8> {
         m.into_bytes()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::body::Body;
+
+    fn upgrade_request(origin: &str) -> Request {
+        http::Request::get("/ws")
+            .header("connection", "upgrade")
+            .header("upgrade", "websocket")
+            .header("sec-websocket-version", "13")
+            .header("sec-websocket-key", "dGhlIHNhbXBsZSBub25jZQ==")
+            .header("origin", origin)
+            .extension(AllowedOrigins::list(["https://example.com"]))
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_failed_upgrade() {
+        let peer: SocketAddr = "127.0.0.1:4000".parse().unwrap();
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let tx = std::sync::Mutex::new(Some(tx));
+
+        let mut req = upgrade_request("https://example.com");
+        req.extensions_mut().insert(peer);
+        req.extensions_mut().insert(OnFailedUpgrade::new(move |failure| {
+            if let Some(tx) = tx.lock().unwrap().take() {
+                _ = tx.send(failure.peer);
+            }
+        }));
+
+        // a request that was never upgraded, so the upgrade fails
+        req.extensions_mut().insert(hyper::upgrade::on(http::Request::new(())));
+
+        let (done_tx, done_rx) = tokio::sync::oneshot::channel();
+
+        let ws = Ws::from_request(req, &()).await.unwrap();
+        assert_eq!(ws.peer_addr(), Some(peer));
+
+        let resp = ws
+            .on_upgrade(move |ws| async move {
+                _ = done_tx.send(ws.is_err());
+            })
+            .into_response();
+
+        // the handshake response is still sent
+        assert_eq!(resp.status(), StatusCode::SWITCHING_PROTOCOLS);
+
+        assert_eq!(rx.await.unwrap(), Some(peer));
+        assert!(done_rx.await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_allowed_origins() {
+        assert!(Ws::from_request(upgrade_request("https://EXAMPLE.com"), &()).await.is_ok());
+
+        assert!(matches!(
+            Ws::from_request(upgrade_request("https://evil.example"), &()).await,
+            Err(WsError::ForbiddenOrigin)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_unexpected_body() {
+        let with_header = |name: &'static str, value: &'static str| {
+            let mut req = upgrade_request("https://example.com");
+            req.headers_mut().insert(name, HeaderValue::from_static(value));
+            req
+        };
+
+        for req in [
+            with_header("content-length", "5"),
+            with_header("transfer-encoding", "chunked"),
+        ] {
+            let err = Ws::from_request(req, &()).await.err().unwrap();
+            assert!(matches!(err, WsError::UnexpectedBody));
+            assert_eq!(err.into_response().status(), StatusCode::BAD_REQUEST);
+        }
+
+        // an explicitly empty body is fine
+        assert!(Ws::from_request(with_header("content-length", "0"), &()).await.is_ok());
+    }
+
+    #[test]
+    fn test_message_len() {
+        assert_eq!(Message::text("héllo").len(), 6);
+        assert_eq!(Message::binary(vec![0; 4]).len(), 4);
+        assert!(Message::binary(Vec::new()).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_send_fragmented() {
+        use futures::StreamExt;
+
+        use crate::{
+            layers::{cloneable::Cloneable, convert_body::ConvertBody},
+            serve::Server,
+            Layer, Router,
+        };
+
+        let server = Server::bind(["127.0.0.1:0".parse().unwrap()]).listen().unwrap();
+        let addr = server.local_addr().unwrap();
+        let handle = server.handle();
+
+        let mut router = Router::<(), Response>::with_state(());
+        router.get("/ws", |ws: Ws| async move {
+            ws.on_upgrade(|ws| async move {
+                let mut ws = ws.unwrap();
+
+                let fragments = ["hello", ", ", "world"].map(|part| part.as_bytes().to_vec());
+                ws.send_fragmented(futures::stream::iter(fragments)).await.unwrap();
+                ws.send_fragmented(futures::stream::empty::<Vec<u8>>()).await.unwrap();
+
+                ws.close().await.unwrap();
+            })
+        });
+
+        tokio::spawn(server.serve(Cloneable::default().layer(ConvertBody::default().layer(router))));
+        handle.wait_ready().await;
+
+        let stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let (mut client, _) = tokio_tungstenite::client_async(format!("ws://{addr}/ws"), stream).await.unwrap();
+
+        // the fragments are reassembled into a single message by the client
+        let message = client.next().await.unwrap().unwrap();
+        assert_eq!(message, tungstenite::Message::binary(b"hello, world".to_vec()));
+
+        let message = client.next().await.unwrap().unwrap();
+        assert_eq!(message, tungstenite::Message::binary(Vec::new()));
+
+        assert!(client.next().await.unwrap().unwrap().is_close());
+
+        handle.shutdown();
+    }
+}